@@ -0,0 +1,313 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use hex2d::{Coordinate, ToCoordinate};
+use hex2d::Angle::{Left, Right, Forward, Back, LeftBack};
+use game;
+use game::actor;
+use util::GameRng;
+
+pub trait Ai {
+    fn action(&mut self,
+              id: actor::Id,
+              loc: &game::Location,
+              rng: &mut GameRng)
+              -> game::Action;
+}
+
+/// Why a brain came up with no action
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Error {
+    /// It thought for longer than it was given
+    Timeout,
+}
+
+/// Ask `ai` what actor `id` does, allowing it `budget` to think. A
+/// brain can't be stopped midway, so a late answer is thrown away.
+pub fn think(ai: &mut Ai,
+             id: actor::Id,
+             loc: &game::Location,
+             rng: &mut GameRng,
+             budget: Duration)
+             -> Result<game::Action, Error> {
+    let started = Instant::now();
+    let action = ai.action(id, loc, rng);
+    if started.elapsed() > budget {
+        Err(Error::Timeout)
+    } else {
+        Ok(action)
+    }
+}
+
+pub struct Simple;
+
+impl Ai for Simple {
+    fn action(&mut self,
+              id: actor::Id,
+              loc: &game::Location,
+              rng: &mut GameRng)
+              -> game::Action {
+        let astate = &loc.actors_byid[&id];
+        let action = if astate.companion {
+            follow(id, astate, loc, rng)
+        } else if astate.race == actor::Race::Shopkeeper {
+            shopkeeper(id, astate, loc, rng)
+        } else {
+            grue(id, astate, loc, rng)
+        };
+        debug!(target: "ai",
+               "turn {}: {} {} at {:?} facing {:?}: {:?}",
+               loc.turn,
+               astate.race.description(),
+               id,
+               astate.pos.coord,
+               astate.pos.dir,
+               action);
+        action
+    }
+}
+
+/// Brain for those who can't think straight: just wanders about
+pub struct Roam;
+
+impl Ai for Roam {
+    fn action(&mut self,
+              _id: actor::Id,
+              _loc: &game::Location,
+              rng: &mut GameRng)
+              -> game::Action {
+        roam(rng)
+    }
+}
+
+/// How worth checking out a noise is; `None` for the ones to stay away from
+fn noise_interest(noise: game::Noise) -> Option<u32> {
+    match noise {
+        game::Noise::Fire => None,
+        game::Noise::Creature(_) => Some(0),
+        game::Noise::Combat | game::Noise::Alarm => Some(2),
+        _ => Some(1),
+    }
+}
+
+fn roam(rng: &mut GameRng) -> game::Action {
+    match rng.gen_range(0, 10) {
+        0 => game::Action::Turn(Right),
+        1 => game::Action::Turn(Left),
+        2 => game::Action::Move(Forward),
+        _ => game::Action::Wait,
+    }
+}
+
+/// How monsters get around: not through others, nor fire and traps
+fn path_opts(max_cost: i32) -> game::PathOpts<'static> {
+    game::PathOpts {
+        avoid_hazards: true,
+        max_cost: max_cost,
+        occupied_blocks: true,
+        ..Default::default()
+    }
+}
+
+/// Closest coordinate `cond` holds for, and the first step towards it
+fn closest_reachable<F>(gstate: &game::Location,
+                        start: Coordinate,
+                        max_cost: i32,
+                        cond: F)
+                        -> Option<(Coordinate, Coordinate)>
+    where F: Fn(Coordinate) -> bool
+{
+    gstate.nearest_matching(start, cond, &path_opts(max_cost))
+          .and_then(|path| path.first().map(|&step| (*path.last().unwrap(), step)))
+}
+
+fn grue(id: actor::Id,
+        astate: &actor::Actor,
+        gstate: &game::Location,
+        rng: &mut GameRng)
+        -> game::Action {
+
+    for other_id in gstate.actors_visible_by(id) {
+        let other = &gstate.actors_byid[&other_id];
+        if other.is_player() {
+            trace!(target: "ai", "{} chases the player at {:?}", id, other.pos.coord);
+            return go_to(other.pos.coord, astate, gstate, rng);
+        }
+    }
+
+    if gstate.at(astate.head()).item_map_or(false, |_| true) {
+        return game::Action::Pick;
+    }
+
+    if gstate.at(astate.pos.coord).item_map_or(false, |_| true) {
+        return game::Action::Move(Back);
+    }
+
+    for &visible_coord in &astate.perception.visible {
+        if gstate.at(visible_coord).item_map_or(false, |_| true) {
+            trace!(target: "ai", "{} goes for the item at {:?}", id, visible_coord);
+            return go_to(visible_coord, astate, gstate, rng);
+        }
+    }
+
+    // fights and alarms first; of the same kind, the loudest noise is
+    // likely the closest one
+    let loudest = astate.heard
+                        .iter()
+                        .filter(|&(&coord, _)| coord != astate.pos.coord)
+                        .filter_map(|(&coord, heard)| {
+                            noise_interest(heard.noise).map(|i| (coord, (i, heard.loudness)))
+                        })
+                        .max_by_key(|&(_, key)| key);
+    if let Some((coord, _)) = loudest {
+        trace!(target: "ai",
+               "{} checks out {} at {:?}",
+               id,
+               astate.heard[&coord].noise.description(),
+               coord);
+        return go_to(coord, astate, gstate, rng);
+    }
+
+    match rng.gen_range(0, 5) {
+        0 => roam(rng),
+        _ => game::Action::Wait,
+    }
+}
+
+/// Minds the shop until somebody steals from it
+fn shopkeeper(id: actor::Id,
+              astate: &actor::Actor,
+              gstate: &game::Location,
+              rng: &mut GameRng)
+              -> game::Action {
+    let home = match gstate.shop {
+        Some(ref shop) if !shop.angry => shop.area.center,
+        _ => return grue(id, astate, gstate, rng),
+    };
+
+    if astate.pos.coord != home && !gstate.at(home).is_occupied() {
+        go_to(home, astate, gstate, rng)
+    } else {
+        game::Action::Wait
+    }
+}
+
+fn go_to(c: Coordinate,
+         astate: &actor::Actor,
+         gstate: &game::Location,
+         rng: &mut GameRng)
+         -> game::Action {
+    let ndir = match astate.pos.coord.direction_to_cw(c) {
+        None => return game::Action::Wait,
+        Some(dir) => dir,
+    };
+
+    let n_pos = astate.pos + ndir.to_coordinate();
+    if gstate.at(n_pos.coord).tile().type_.is_passable() {
+        if ndir == astate.pos.dir {
+            if gstate.at(n_pos.coord).tile().door_state() == Some(false) &&
+               !astate.race.can_open_doors() {
+                return if astate.race.bashes_doors() {
+                    game::Action::Bash(ndir)
+                } else {
+                    roam(rng)
+                };
+            }
+
+            return game::Action::Move(Forward);
+        } else {
+            let rdir = ndir - astate.pos.dir;
+            let rdir = match rdir {
+                Left | LeftBack => Left,
+                Back => {
+                    if astate.pos.coord.x & 1 == 0 {
+                        Left
+                    } else {
+                        Right
+                    }
+                }
+                _ => Right,
+            };
+            return game::Action::Turn(rdir);
+        }
+    }
+    let path = gstate.find_path(astate.pos.coord, c, &path_opts(10));
+    trace!(target: "ai",
+           "path from {:?} to {:?}: {}",
+           astate.pos.coord,
+           c,
+           path.as_ref().map_or("none".to_string(), |p| format!("{} steps", p.len())));
+
+    if let Some(&n) = path.as_ref().and_then(|path| path.first()) {
+        return go_to(n, astate, gstate, rng);
+    }
+
+    // portals are shortcuts
+    let start = astate.pos.coord;
+    let portal = closest_reachable(gstate, start, 10, |pos| {
+        gstate.at(pos).tile().portal().map_or(false, |twin| twin.distance(c) < start.distance(c))
+    });
+
+    if let Some((portal, n)) = portal {
+        trace!(target: "ai", "taking the portal at {:?} towards {:?}", portal, c);
+        go_to(n, astate, gstate, rng)
+    } else {
+        roam(rng)
+    }
+}
+
+/// Keeps next to the player, so as to come along on the stairs
+fn follow(id: actor::Id,
+          astate: &actor::Actor,
+          gstate: &game::Location,
+          rng: &mut GameRng)
+          -> game::Action {
+    let start = astate.pos.coord;
+    let player = gstate.player().pos.coord;
+    if start.distance(player) <= 1 {
+        return game::Action::Wait;
+    }
+
+    let spot = closest_reachable(gstate,
+                                 start,
+                                 10,
+                                 |pos| pos.distance(player) == 1 && gstate.at(pos).is_passable());
+    if let Some((_, step)) = spot {
+        trace!(target: "ai", "{} follows the player to {:?}", id, step);
+        go_to(step, astate, gstate, rng)
+    } else {
+        roam(rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex2d::{Coordinate, Direction, Position};
+
+    use game::Location;
+    use game::actor::{Actor, Race};
+    use util;
+    use super::go_to;
+
+    #[test]
+    fn heading_off_the_map_stays_on_it() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["....",
+                                            "...."]);
+        let pos = Position::new(Coordinate::new(0, 0), Direction::XY);
+        let id = loc.spawn(Actor::new(Race::Goblin, pos), &mut rng);
+
+        let targets = [Coordinate::new(-1, 0),
+                       Coordinate::new(-50, 20),
+                       Coordinate::new(1000, -1000)];
+        for &far in &targets {
+            for _ in 0..20 {
+                let action = go_to(far, &loc.actors_byid[&id], &loc, &mut rng);
+                loc.act(id, action, &mut rng);
+                let coord = loc.actors_byid[&id].coord();
+                assert!(loc.in_bounds(coord), "{:?} heading for {:?}", coord, far);
+            }
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use hex2d::{Angle, Coordinate, Direction, Left, Right, Forward};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Action {
+    Wait,
+    Turn(Angle),
+    Move(Angle),
+    Charge,
+    Spin(Angle),
+    Equip(char),
+    Drop_(char),
+    Ranged(Coordinate),
+    /// Throw the item with that letter; it lands where a shot at the
+    /// coordinate would stop
+    Throw(char, Coordinate),
+    Pick,
+    Descend,
+    Ascend,
+    Interact,
+    /// Close the door next to the actor
+    Close(Direction),
+    /// Try to break down the door next to the actor
+    Bash(Direction),
+}
+
@@ -0,0 +1,1888 @@
+use std::ops::{Add, Sub};
+use std::cmp;
+use std::sync::Arc;
+
+use hex2d::{Coordinate, Angle, Position, ToCoordinate, Direction, Left, Right, Forward};
+
+use game::{Action, LevelId, tile};
+use util::{self, GameRng};
+use super::item::{self, Item};
+
+use self::Race::*;
+use std::iter::Iterator;
+
+use rand::Rng;
+
+use super::conts::*;
+use super::{FnvHashMap, FnvHashSet, NoiseMap};
+use super::{decode_coords, encode_coords};
+use super::perception::{Eye, Perception};
+
+use super::{Bones, Event, Heard, Location, Noise};
+
+pub type Id = u32;
+
+/// Strength needed to push a boulder
+const BOULDER_STR_REQ: i32 = 3;
+/// Stamina spent on each push
+const BOULDER_SP_COST: i32 = 2;
+/// Furthest a ranged attack reaches
+pub const RANGED_RANGE: i32 = 8;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Race {
+    Human,
+    Elf,
+    Dwarf,
+    Rat,
+    Goblin,
+    Troll,
+    Shopkeeper,
+    /// The player's companion
+    Pony,
+    /// What's left of a player from another game
+    Ghost,
+}
+
+/// Every race there is, to read them back
+const RACES: [Race; 9] = [Human, Elf, Dwarf, Rat, Goblin, Troll, Shopkeeper, Pony, Ghost];
+
+impl Race {
+    pub fn description(&self) -> String {
+        match *self {
+            Race::Human => "human",
+            Race::Elf => "elf",
+            Race::Dwarf => "dwarf",
+            Race::Rat => "rat",
+            Race::Goblin => "goblin",
+            Race::Troll => "troll",
+            Race::Shopkeeper => "shopkeeper",
+            Race::Pony => "pony",
+            Race::Ghost => "ghost",
+        }
+        .to_string()
+    }
+
+    /// Animals and trolls don't know how to use a door handle
+    pub fn can_open_doors(&self) -> bool {
+        match *self {
+            Race::Rat | Race::Troll | Race::Pony => false,
+            _ => true,
+        }
+    }
+
+    /// Strong enough to try to break a door it can't open
+    pub fn bashes_doors(&self) -> bool {
+        *self == Race::Troll
+    }
+
+    /// What a player of the race starts with
+    pub fn starting_items(&self) -> Vec<item::Type> {
+        match *self {
+            Race::Dwarf => vec![item::Type::Pickaxe],
+            Race::Elf => vec![item::Type::Bow],
+            _ => vec![],
+        }
+    }
+
+    /// Shallowest level the race normally appears at
+    pub fn native_depth(&self) -> u32 {
+        match *self {
+            Race::Goblin => 2,
+            Race::Troll => 4,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Stats {
+    pub int: i32,
+    pub dex: i32,
+    pub str_: i32,
+    pub max_hp: i32,
+    pub max_mp: i32,
+    pub max_sp: i32,
+    pub ac: i32,
+    pub ev: i32,
+    pub infravision: i32,
+    pub vision: i32,
+    pub regeneration: u32,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct EffectiveStats {
+    pub base: Stats,
+    pub melee_dmg: i32,
+    pub melee_acc: i32,
+    pub melee_str_req: i32,
+    pub light_emision: u32,
+}
+
+impl Stats {
+    pub fn new(race: Race) -> Stats {
+        match race {
+            Goblin => GOBLIN_STATS,
+            Rat => RAT_STATS,
+            Elf => ELF_STATS,
+            Human => HUMAN_STATS,
+            Dwarf => DWARF_STATS,
+            Troll => TROLL_STATS,
+            Shopkeeper => SHOPKEEPER_STATS,
+            Pony => PONY_STATS,
+            Ghost => GHOST_STATS,
+        }
+    }
+
+    pub fn to_effective(&self) -> EffectiveStats {
+        let mut efs = EffectiveStats::default();
+        efs.base = *self;
+        efs
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            int: 0,
+            dex: 0,
+            str_: 0,
+            max_hp: 0,
+            max_mp: 0,
+            max_sp: 0,
+            ac: 0,
+            ev: 0,
+            infravision: 0,
+            vision: 0,
+            regeneration: 0,
+        }
+    }
+}
+
+impl Default for EffectiveStats {
+    fn default() -> Self {
+        EffectiveStats {
+            base: Default::default(),
+            melee_dmg: 0,
+            melee_acc: 0,
+            melee_str_req: 0,
+            light_emision: 0,
+        }
+    }
+}
+
+impl Add for Stats {
+    type Output = Stats;
+
+    fn add(self, s: Self) -> Self {
+        Stats {
+            int: self.int + s.int,
+            dex: self.dex + s.dex,
+            str_: self.str_ + s.str_,
+            max_hp: self.max_hp + s.max_hp,
+            max_mp: self.max_mp + s.max_mp,
+            max_sp: self.max_sp + s.max_sp,
+            ac: self.ac + s.ac,
+            ev: self.ev + s.ev,
+            infravision: self.infravision + s.infravision,
+            vision: self.vision + s.vision,
+            regeneration: self.regeneration + s.regeneration,
+        }
+    }
+}
+
+impl Add for EffectiveStats {
+    type Output = EffectiveStats;
+
+    fn add(self, s: Self) -> Self {
+        EffectiveStats {
+            base: self.base + s.base,
+            melee_dmg: self.melee_dmg + s.melee_dmg,
+            melee_acc: self.melee_acc + s.melee_acc,
+            melee_str_req: self.melee_str_req + s.melee_str_req,
+            light_emision: self.light_emision + s.light_emision,
+        }
+    }
+}
+
+impl Sub for Stats {
+    type Output = Stats;
+
+    fn sub(self, s: Self) -> Self {
+        Stats {
+            int: self.int - s.int,
+            dex: self.dex - s.dex,
+            str_: self.str_ - s.str_,
+            max_hp: self.max_hp - s.max_hp,
+            max_mp: self.max_mp - s.max_mp,
+            max_sp: self.max_sp - s.max_sp,
+            ac: self.ac - s.ac,
+            ev: self.ev - s.ev,
+            infravision: self.infravision - s.infravision,
+            vision: self.vision - s.vision,
+            regeneration: self.regeneration - self.regeneration,
+        }
+    }
+}
+
+impl Sub for EffectiveStats {
+    type Output = EffectiveStats;
+
+    fn sub(self, s: Self) -> Self {
+        EffectiveStats {
+            base: self.base - s.base,
+            melee_dmg: self.melee_dmg - s.melee_dmg,
+            melee_acc: self.melee_acc - s.melee_acc,
+            melee_str_req: self.melee_str_req - s.melee_str_req,
+            light_emision: self.light_emision - s.light_emision,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Ord, PartialOrd)]
+pub enum Slot {
+    Head,
+    Feet,
+    LHand,
+    RHand,
+    Body,
+    Cloak,
+    Quick,
+}
+
+/// How much a status should worry the player
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum Severity {
+    Mild,
+    Bad,
+    Severe,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct AttackResult {
+    pub success: bool,
+    pub dmg: i32,
+    pub who: String,
+    pub behind: bool,
+}
+
+/// Outcome of `Action::Interact`, `Action::Close` and stepping on traps
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum InteractResult {
+    FountainHeal,
+    FountainRefresh,
+    FountainPoison,
+    FountainDriedUp,
+    FountainDry,
+    AltarIdentify,
+    AltarUncurse,
+    AltarNoItem,
+    AltarUsed,
+    BrazierLit,
+    BrazierDoused,
+    BrazierNoFire,
+    BrazierKnockedOver,
+    TrapDart,
+    TrapAlarm,
+    DoorNotOpen,
+    DoorBlocked,
+    DoorBashed,
+    DoorBroken,
+    NoDoor,
+    Teleported,
+    PortalBlocked,
+    TrapNeedle,
+    ChestLocked,
+    ChestUnlocked,
+    ChestOpened,
+    ChestEmpty,
+    ChestDisarmed,
+    ChestForced,
+    ChestHolds,
+    ChestContentsBroken,
+    BoulderTooHeavy,
+    BoulderStuck,
+    ShopPaid,
+    ShopNoGold,
+    ShopThief,
+    Burned,
+}
+
+#[derive(Clone, Debug)]
+pub struct Actor {
+    pub hp: i32,
+    pub mp: i32,
+    pub sp: i32,
+    pub saved_hp: i32,
+    pub saved_mp: i32,
+    pub saved_sp: i32,
+
+    pub player: bool,
+    /// Follows the player to other levels
+    pub companion: bool,
+    pub pre_pos: Option<Position>,
+    pub pos: Position,
+    pub acted: bool,
+    /// Level the actor has just taken the stairs to
+    travel: Option<LevelId>,
+
+    pub race: Race,
+    /// Who the actor is, if it's somebody in particular
+    pub name: Option<String>,
+    pub base_stats: Stats,
+    pub mod_stats: EffectiveStats,
+    pub stats: EffectiveStats,
+
+    /// What the actor sees and knows of the level; worked out by the
+    /// level at the end of each tick
+    pub perception: Perception,
+
+    pub heard: NoiseMap,
+    /// Loudest noise made during the current tick
+    pub noise_emision: Option<Noise>,
+
+    pub action_cd: i32,
+
+    /// Turns of poison left
+    pub poisoned: i32,
+    pub gold: u32,
+    /// Was ever attacked by the player
+    pub provoked: bool,
+
+    pub items_letters: FnvHashSet<char>,
+    pub items_equipped: FnvHashMap<Slot, (char, Arc<Item>)>,
+    pub items_backpack: FnvHashMap<char, Arc<Item>>,
+
+    pub was_attacked_by: Vec<AttackResult>,
+    pub did_attack: Vec<AttackResult>,
+    pub did_interact: Vec<InteractResult>,
+}
+
+impl Actor {
+    pub fn new(race: Race, pos: Position) -> Self {
+        let stats = Stats::new(race);
+
+        Actor {
+            race: race,
+            name: None,
+            player: false,
+            companion: false,
+            pos: pos,
+            pre_pos: None,
+            base_stats: stats, // base stats
+            mod_stats: Default::default(), // from items etc.
+            stats: Default::default(), // effective stats
+            perception: Perception::new(),
+            heard: Default::default(),
+            noise_emision: None,
+            items_backpack: Default::default(),
+            items_equipped: Default::default(),
+            items_letters: Default::default(),
+            action_cd: 0,
+            poisoned: 0,
+            gold: 0,
+            provoked: false,
+            was_attacked_by: Vec::new(),
+            did_attack: Vec::new(),
+            did_interact: Vec::new(),
+            hp: stats.max_hp,
+            mp: stats.max_mp,
+            sp: stats.max_sp,
+            saved_hp: stats.max_hp,
+            saved_mp: stats.max_mp,
+            saved_sp: stats.max_sp,
+            acted: false,
+            travel: None,
+        }
+    }
+
+    /// The actor as lines of text, `parse` reads them back: who it is,
+    /// where, how it's doing, what it has and what it sees and knows.
+    /// What lasts a tick only isn't kept, nor are the caches.
+    pub fn lines(&self) -> Vec<String> {
+        let pos = |pos: Position| {
+            let dir = Direction::all().iter().position(|&d| d == pos.dir).unwrap();
+            format!("{} {} {}", pos.coord.x, pos.coord.y, dir)
+        };
+        let stats = |s: Stats| {
+            format!("{} {} {} {} {} {} {} {} {} {} {}",
+                    s.int,
+                    s.dex,
+                    s.str_,
+                    s.max_hp,
+                    s.max_mp,
+                    s.max_sp,
+                    s.ac,
+                    s.ev,
+                    s.infravision,
+                    s.vision,
+                    s.regeneration)
+        };
+        let m = self.mod_stats;
+
+        let mut lines = vec![format!("race {}", self.race.description())];
+        if let Some(ref name) = self.name {
+            lines.push(format!("name {}", name.replace(|c: char| c.is_control(), " ")));
+        }
+        lines.push(format!("pos {}", pos(self.pos)));
+        if let Some(pre_pos) = self.pre_pos {
+            lines.push(format!("pre_pos {}", pos(pre_pos)));
+        }
+        lines.push(format!("points {} {} {} {} {} {}",
+                           self.hp,
+                           self.mp,
+                           self.sp,
+                           self.saved_hp,
+                           self.saved_mp,
+                           self.saved_sp));
+        lines.push(format!("base {}", stats(self.base_stats)));
+        lines.push(format!("mod {} {} {} {} {}",
+                           stats(m.base),
+                           m.melee_dmg,
+                           m.melee_acc,
+                           m.melee_str_req,
+                           m.light_emision));
+        lines.push(format!("state {} {} {} {} {}",
+                           self.action_cd,
+                           self.poisoned,
+                           self.gold,
+                           self.provoked as u8,
+                           self.companion as u8));
+
+        let mut backpack: Vec<_> = self.items_backpack.iter().collect();
+        backpack.sort_by_key(|&(&ch, _)| ch);
+        for (ch, item) in backpack {
+            lines.push(format!("carry {} {}", ch, item.encode_priced()));
+        }
+        let mut equipped: Vec<_> = self.items_equipped.values().collect();
+        equipped.sort_by_key(|&&(ch, _)| ch);
+        for &&(ch, ref item) in &equipped {
+            lines.push(format!("wear {} {}", ch, item.encode_priced()));
+        }
+
+        let p = &self.perception;
+        lines.push(format!("in_los {}", encode_coords(&p.in_los)));
+        lines.push(format!("visible {}", encode_coords(&p.visible)));
+        lines.push(format!("known {}", encode_coords(&p.known)));
+        lines.push(format!("known_areas {}", encode_coords(&p.known_areas)));
+        lines
+    }
+
+    /// An actor as written by `lines`; keys this version doesn't know
+    /// are skipped
+    ///
+    /// Returns: `None` if anything else doesn't parse.
+    pub fn parse<'a, I>(lines: I) -> Option<Actor>
+        where I: IntoIterator<Item = &'a str>
+    {
+        let numbers = |value: &str, n: usize| -> Option<Vec<i32>> {
+            let numbers: Vec<i32> = match value.split(' ').map(|w| w.parse()).collect() {
+                Ok(numbers) => numbers,
+                Err(_) => return None,
+            };
+            if numbers.len() == n {
+                Some(numbers)
+            } else {
+                None
+            }
+        };
+        let pos = |value: &str| {
+            numbers(value, 3).and_then(|n| {
+                Direction::all()
+                    .get(n[2] as usize)
+                    .map(|&dir| Position::new(Coordinate::new(n[0], n[1]), dir))
+            })
+        };
+        let stats = |n: &[i32]| {
+            Stats {
+                int: n[0],
+                dex: n[1],
+                str_: n[2],
+                max_hp: n[3],
+                max_mp: n[4],
+                max_sp: n[5],
+                ac: n[6],
+                ev: n[7],
+                infravision: n[8],
+                vision: n[9],
+                regeneration: n[10] as u32,
+            }
+        };
+        let item = |value: &str| {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), Some(' ')) => {
+                    Item::decode_priced(&value[ch.len_utf8() + 1..]).map(|item| (ch, item))
+                }
+                _ => None,
+            }
+        };
+
+        let mut lines = lines.into_iter();
+        let race = match lines.next() {
+            Some(line) if line.starts_with("race ") => {
+                match RACES.iter().find(|r| r.description() == line["race ".len()..]) {
+                    Some(&race) => race,
+                    None => return None,
+                }
+            }
+            _ => return None,
+        };
+        let mut actor = Actor::new(race, Position::new(Coordinate::new(0, 0), Direction::YZ));
+
+        for line in lines {
+            let (key, value) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => (line, ""),
+            };
+            let parsed = match key {
+                "name" => {
+                    actor.name = Some(value.to_string());
+                    Some(())
+                }
+                "pos" => pos(value).map(|p| actor.pos = p),
+                "pre_pos" => pos(value).map(|p| actor.pre_pos = Some(p)),
+                "points" => {
+                    numbers(value, 6).map(|n| {
+                        actor.hp = n[0];
+                        actor.mp = n[1];
+                        actor.sp = n[2];
+                        actor.saved_hp = n[3];
+                        actor.saved_mp = n[4];
+                        actor.saved_sp = n[5];
+                    })
+                }
+                "base" => numbers(value, 11).map(|n| actor.base_stats = stats(&n[..])),
+                "mod" => {
+                    numbers(value, 15).map(|n| {
+                        actor.mod_stats = EffectiveStats {
+                            base: stats(&n[..]),
+                            melee_dmg: n[11],
+                            melee_acc: n[12],
+                            melee_str_req: n[13],
+                            light_emision: n[14] as u32,
+                        }
+                    })
+                }
+                "state" => {
+                    numbers(value, 5).map(|n| {
+                        actor.action_cd = n[0];
+                        actor.poisoned = n[1];
+                        actor.gold = n[2] as u32;
+                        actor.provoked = n[3] != 0;
+                        actor.companion = n[4] != 0;
+                    })
+                }
+                "carry" => {
+                    item(value).map(|(ch, item)| {
+                        actor.items_backpack.insert(ch, Arc::new(item));
+                    })
+                }
+                "wear" => {
+                    item(value).and_then(|(ch, item)| {
+                        item.slot().map(|slot| {
+                            actor.items_equipped.insert(slot, (ch, Arc::new(item)));
+                        })
+                    })
+                }
+                "in_los" => decode_coords(value).map(|v| actor.perception.in_los = v),
+                "visible" => decode_coords(value).map(|v| actor.perception.visible = v),
+                "known" => decode_coords(value).map(|v| actor.perception.known = v),
+                "known_areas" => decode_coords(value).map(|v| actor.perception.known_areas = v),
+                _ => Some(()),
+            };
+            if parsed.is_none() {
+                return None;
+            }
+        }
+        actor.recalculate_stats();
+        Some(actor)
+    }
+
+    /// Ghost of the player the `bones` are of: as strong as they were,
+    /// wearing what they wore and carrying the rest. Bones that don't
+    /// say what was worn get it put on as it fits.
+    pub fn ghost(bones: Bones, pos: Position) -> Self {
+        let stats = bones.stats.unwrap_or_else(|| Stats::new(bones.race));
+        let mut ghost = Actor::new(bones.race, pos);
+        ghost.race = Ghost;
+        ghost.name = Some(bones.name);
+        ghost.base_stats = stats;
+        ghost.hp = stats.max_hp;
+        ghost.mp = stats.max_mp;
+        ghost.sp = stats.max_sp;
+
+        let dress = bones.worn.is_empty();
+        for item in bones.worn {
+            ghost.pick_item(item);
+        }
+        ghost.equip_free_slots();
+        for item in bones.items {
+            ghost.pick_item(item);
+        }
+        if dress {
+            ghost.equip_free_slots();
+        }
+        ghost.recalculate_stats();
+        ghost
+    }
+
+    pub fn sees(&self, pos: Coordinate) -> bool {
+        self.perception.visible.contains(&pos)
+    }
+
+    pub fn in_los(&self, pos: Coordinate) -> bool {
+        self.perception.in_los.contains(&pos)
+    }
+
+    pub fn knows(&self, pos: Coordinate) -> bool {
+        self.perception.known.contains(&pos)
+    }
+
+    /// What the actor sees from
+    pub fn eye(&self) -> Eye {
+        Eye {
+            pos: self.pos,
+            vision: self.stats.base.vision,
+            infravision: self.stats.base.infravision,
+        }
+    }
+
+    pub fn hears(&self, coord: Coordinate) -> bool {
+        self.heard.contains_key(&coord)
+    }
+
+    pub fn coord(&self) -> Coordinate {
+        self.pos.coord
+    }
+
+    /// "head" - The coordinate that is in front of an actor
+    pub fn head(&self) -> Coordinate {
+        self.pos.coord + self.pos.dir
+    }
+
+    /// relating to "head" - The coordinate that is next to actor, relative
+    /// to head by a given angle.
+    pub fn head_rel(&self, angle : Angle) -> Coordinate {
+        self.pos.coord + (self.pos.dir + angle).to_coordinate()
+    }
+
+    pub fn pos_after_action(&self, action: Action) -> Vec<Position> {
+        let pos = self.pos;
+        match action {
+            Action::Wait |
+            Action::Pick |
+            Action::Equip(_) |
+            Action::Descend |
+            Action::Ascend |
+            Action::Interact |
+            Action::Close(_) |
+            Action::Bash(_) |
+            Action::Ranged(_) |
+            Action::Throw(..) |
+            Action::Drop_(_) => vec![pos],
+            Action::Turn(a) => vec![pos + a],
+            Action::Move(a) => vec![pos + (pos.dir + a).to_coordinate()],
+            Action::Charge => {
+                if self.can_charge_sp() {
+                    vec![pos + pos.dir.to_coordinate(),
+                         pos + pos.dir.to_coordinate() + pos.dir.to_coordinate()]
+                } else {
+                    vec![pos + pos.dir.to_coordinate()]
+                }
+            }
+            Action::Spin(a) => {
+                vec![pos + (pos.dir + a).to_coordinate() +
+                     match a {
+                         Angle::Right => Angle::Left,
+                         Angle::Left => Angle::Right,
+                         _ => return vec![pos],
+                     }]
+            }
+        }
+    }
+
+    fn substract_melee_sp_cost(&mut self) {
+        self.sp = cmp::max(0, self.sp - self.melee_sp_cost());
+    }
+
+    pub fn attack_ranged(&mut self,
+                         loc : &mut Location,
+                         target_coord : Coordinate,
+                         rng: &mut GameRng) {
+
+        let target_id = match loc.actors_coord_to_id.get(&target_coord) {
+            None => return,
+            Some(&id) => id
+        };
+        let attacker_id = loc.actors_coord_to_id[&self.pos.coord];
+
+        let (success, dmg, from_behind) = {
+            let target = match loc.actors_byid.get_mut(&target_id) {
+                None => return,
+                Some(target) => target,
+            };
+            if self.is_player() {
+                target.provoked = true;
+            }
+            let mut acc = self.stats.melee_acc;
+            let mut dmg = self.stats.melee_dmg;
+
+            let (ac, ev) = (target.stats.base.ac, target.stats.base.ev);
+
+            let from_behind = match self.pos.dir - target.pos.dir {
+                Angle::Forward | Angle::Left | Angle::Right => true,
+                _ => false,
+            };
+
+            if from_behind {
+                acc *= 2;
+                dmg *= 2;
+            }
+
+            if !self.can_attack_sp() {
+                acc /= 2;
+                dmg /= 2;
+                self.sp = 0;
+            } else {
+                self.substract_melee_sp_cost();
+            }
+
+            let success = util::roll(acc, ev, rng);
+
+            let rand_ac = cmp::max(rng.gen_range(0, ac + 1),
+            rng.gen_range(0, ac + 1));
+
+            debug!(target: "game::act",
+                   "{} shoots {}: acc {} against ev {}, {}; dmg {} less {} of ac {}",
+                   self.description(),
+                   target.description(),
+                   acc,
+                   ev,
+                   if success { "hit" } else { "missed" },
+                   dmg,
+                   rand_ac,
+                   ac);
+            let dmg = cmp::max(0, dmg - rand_ac);
+
+            if success {
+                target.hp -= dmg;
+                target.noise_makes(Noise::Combat);
+            }
+
+            target.was_attacked_by.push(AttackResult {
+                success: success,
+                dmg: dmg,
+                who: self.description(),
+                behind: from_behind,
+            });
+
+            self.did_attack.push(AttackResult {
+                success: success,
+                dmg: dmg,
+                who: target.description(),
+                behind: from_behind,
+            });
+
+            (success, dmg, from_behind)
+        };
+        if success {
+            loc.made_noise(target_id);
+        }
+
+        loc.event(target_coord,
+                  Event::Attack {
+                      attacker: attacker_id,
+                      target: target_id,
+                      success: success,
+                      dmg: dmg,
+                      behind: from_behind,
+                  });
+    }
+
+    /// Shoot at `target_coord`, hitting whatever is first on the way
+    ///
+    /// Returns: false if it's out of range or there's nothing to shoot
+    /// with.
+    pub fn try_attack_ranged(&mut self,
+                         loc : &mut Location,
+                         target_coord : Coordinate,
+                         rng: &mut GameRng) -> bool {
+
+        if !self.can_attack_ranged() || self.coord().distance(target_coord) > RANGED_RANGE {
+            return false;
+        }
+        let path = loc.shot_path(self.coord(), target_coord);
+        let hit = path.last().cloned();
+        let id = loc.actors_coord_to_id[&self.coord()];
+        loc.event(self.coord(),
+                  Event::Projectile {
+                      id: id,
+                      path: path,
+                  });
+        if let Some(hit) = hit {
+            self.attack_ranged(loc, hit, rng);
+        }
+        true
+    }
+
+    pub fn post_action(&mut self, action: Action) {
+        match action {
+            Action::Charge => {
+                self.sp = cmp::max(0, self.sp - self.charge_sp_cost());
+                self.acted = true;
+            }
+            _ => {
+                self.acted = true;
+            }
+        }
+    }
+
+    pub fn can_attack_at_angle(&self, angle : Angle) -> bool {
+        match angle {
+            Forward|Left|Right => true,
+            _ => false,
+        }
+    }
+
+    pub fn can_dig_at_angle(&self, angle : Angle) -> bool {
+        match angle {
+            Forward|Left|Right => true,
+            _ => false,
+        }
+    }
+
+    pub fn acted_last_tick(&self) -> bool {
+        self.acted
+    }
+
+    // Could this actor have seen action/movement
+    // of another actor (given by id)
+    pub fn could_have_seen(&self, actor: &Actor) -> bool {
+        self.sees(actor.pos.coord) || self.sees(actor.pre_pos.unwrap().coord)
+    }
+
+    // Save some stats for a reference
+    pub fn save_stats(&mut self) {
+        self.saved_hp = self.hp;
+        self.saved_mp = self.mp;
+        self.saved_sp = self.sp;
+    }
+
+
+    pub fn noise_makes(&mut self, noise: Noise) {
+        if self.noise_emision.map_or(true, |n| n.loudness() < noise.loudness()) {
+            self.noise_emision = Some(noise);
+        }
+    }
+
+    /// Remember the loudest of the noises coming from `coord`
+    pub fn noise_hears(&mut self, coord: Coordinate, heard: Heard) {
+        if self.heard.get(&coord).map_or(true, |h| h.loudness < heard.loudness) {
+            self.heard.insert(coord, heard);
+        }
+    }
+
+    pub fn pre_any_tick(&mut self) {
+        self.pre_pos = Some(self.pos);
+        self.did_attack = Vec::new();
+        self.was_attacked_by = Vec::new();
+        self.did_interact = Vec::new();
+        self.perception.pre_any_tick();
+
+        self.noise_emision = None;
+        self.heard = Default::default();
+
+        self.acted = false;
+        self.travel = None;
+    }
+
+    pub fn pre_own_tick(&mut self) {
+        if self.action_cd > 0 {
+            self.action_cd -= 1;
+        }
+        if self.can_perform_action() {
+            self.save_stats();
+        }
+    }
+
+    /// Once a turn: poison wears off, hurting meanwhile
+    pub fn update_effects(&mut self, rng: &mut GameRng) {
+        if self.poisoned > 0 {
+            self.poisoned -= 1;
+            if rng.gen_weighted_bool(2) {
+                self.hp -= 1
+            }
+        }
+    }
+
+    /// What `turns` turns of `update_effects` and `regenerate` do on
+    /// average, without the RNG; nobody dies of it
+    pub fn fast_forward(&mut self, turns: u64) {
+        let poisoned = cmp::min(turns, self.poisoned as u64);
+        self.poisoned -= poisoned as i32;
+        self.hp = cmp::max(cmp::min(self.hp, 1), self.hp - (poisoned / 2) as i32);
+
+        let healthy = turns - poisoned;
+        let sp = self.sp as i64 + (turns / 10) as i64;
+        self.sp = cmp::max(self.sp, cmp::min(self.stats.base.max_sp as i64, sp) as i32);
+        let hp = self.hp as i64 + (healthy * self.stats.base.regeneration as u64 / 50) as i64;
+        self.hp = cmp::max(self.hp, cmp::min(self.stats.base.max_hp as i64, hp) as i32);
+    }
+
+    /// Once a turn: get some SP back, and HP unless poisoned
+    pub fn regenerate(&mut self, rng: &mut GameRng) {
+        if self.sp < self.stats.base.max_sp {
+            if rng.gen_weighted_bool(10) {
+                self.sp += 1
+            }
+        }
+
+        if self.poisoned == 0 && self.hp < self.stats.base.max_hp {
+            if rng.gen_range(0, 50) < self.stats.base.regeneration {
+                self.hp += 1
+            }
+        }
+    }
+
+    pub fn post_any_tick(&mut self, _loc: &Location) {
+        self.recalculate_stats();
+    }
+
+    /// Forget everything specific to the previous level
+    pub fn changed_level(&mut self) {
+        self.perception.changed_level();
+        self.heard = Default::default();
+    }
+
+    pub fn post_spawn(&mut self, loc: &Location) {
+        // TODO: save & restore, using global location uuids ?
+        self.changed_level();
+        let pos = self.pos;
+        self.moved(loc, pos)
+    }
+
+    pub fn pick_item(&mut self, item: Arc<Item>) -> Option<Arc<Item>> {
+        if let Some(gold) = item.gold() {
+            self.gold += gold;
+            return None;
+        }
+
+        for ch in ('a' as u8..'z' as u8).chain('A' as u8..'Z' as u8) {
+            let ch = ch as char;
+            if !self.item_letter_taken(ch) {
+                assert!(!self.items_backpack.contains_key(&ch));
+                self.items_backpack.insert(ch, item);
+                return None;
+            }
+        }
+        Some(item)
+    }
+
+    pub fn item_letter_taken(&self, ch: char) -> bool {
+        if self.items_backpack.contains_key(&ch) {
+            return true;
+        }
+
+        for (&_, &(ref item_ch, _)) in &self.items_equipped {
+            if *item_ch == ch {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn equip_switch(&mut self, ch: char) {
+        if self.items_backpack.contains_key(&ch) {
+            if let Some(item) = self.items_backpack.remove(&ch) {
+                if item.is_usable() {
+                    if !item.use_(self) {
+                        self.items_backpack.insert(ch, item);
+                    }
+                    self.action_cd += 2;
+                } else {
+                    self.equip(item, ch);
+                }
+            }
+
+        } else {
+            self.unequip(ch);
+        }
+    }
+
+    /// Put on whatever fits a slot still free, taking no time; for
+    /// actors yet to be spawned
+    pub fn equip_free_slots(&mut self) {
+        let mut letters: Vec<char> = self.items_backpack.keys().cloned().collect();
+        letters.sort();
+        for ch in letters {
+            let slot = self.items_backpack[&ch].slot();
+            if slot.map_or(false, |slot| self.equipped_in_slot(slot).is_none()) {
+                self.equip_switch(ch);
+            }
+        }
+        self.action_cd = 0;
+    }
+
+    pub fn equip_drop(&mut self, ch: char) -> Option<Arc<Item>> {
+        self.unequip(ch);
+        self.items_backpack.remove(&ch)
+    }
+
+    pub fn equip(&mut self, item: Arc<Item>, ch: char) {
+        if let Some(slot) = item.slot() {
+            if self.equipped_in_slot(slot).map(|i| i.is_cursed()).unwrap_or(false) {
+                self.items_backpack.insert(ch, item);
+                return;
+            }
+            self.unequip_slot(slot);
+            self.mod_stats = self.mod_stats + item.stats();
+            self.items_equipped.insert(slot, (ch, item));
+            self.action_cd += if slot == Slot::Body {
+                4
+            } else {
+                2
+            }
+        } else {
+            self.items_backpack.insert(ch, item);
+        }
+    }
+
+    pub fn unequip_slot(&mut self, slot: Slot) {
+        if self.equipped_in_slot(slot).map(|i| i.is_cursed()).unwrap_or(false) {
+            return;
+        }
+        if let Some((ch, item)) = self.items_equipped.remove(&slot) {
+            self.mod_stats = self.mod_stats - item.stats();
+            self.items_backpack.insert(ch, item);
+            self.action_cd += if slot == Slot::Body {
+                4
+            } else {
+                2
+            }
+        }
+    }
+
+    pub fn unequip(&mut self, ch: char) {
+        let mut found_slot = None;
+        for (&slot, &(ref item_ch, _)) in &self.items_equipped {
+            if ch == *item_ch {
+                found_slot = Some(slot);
+                break;
+            }
+        }
+        if let Some(slot) = found_slot {
+            self.unequip_slot(slot);
+        }
+    }
+
+    pub fn recalculate_stats(&mut self) {
+        self.stats = self.base_stats.to_effective() + self.mod_stats;
+
+        // Add attributes to derived stats
+        self.stats.melee_dmg += self.stats.base.str_;
+        self.stats.melee_acc += self.stats.base.dex;
+        self.stats.base.ac += self.stats.base.str_ / 2;
+        self.stats.base.ev += self.stats.base.dex / 2;
+        self.stats.base.max_sp += self.stats.base.str_ * 2;
+        self.stats.base.max_mp += self.stats.base.int * 2;
+    }
+
+    pub fn attacks(&mut self, dir: Direction, target: &mut Actor, rng: &mut GameRng) {
+        if self.is_player() {
+            target.provoked = true;
+        }
+
+        let mut acc = self.stats.melee_acc;
+        let mut dmg = self.stats.melee_dmg;
+
+        let (ac, ev) = (target.stats.base.ac, target.stats.base.ev);
+
+        let from_behind = match dir - target.pos.dir {
+            Angle::Forward | Angle::Left | Angle::Right => true,
+            _ => false,
+        };
+
+        if from_behind {
+            acc *= 2;
+            dmg *= 2;
+        }
+
+        if !self.can_attack_sp() {
+            acc /= 2;
+            dmg /= 2;
+            self.sp = 0;
+        } else {
+            self.sp -= self.melee_sp_cost();
+        }
+
+        let success = util::roll(acc, ev, rng);
+
+        let rand_ac = cmp::max(rng.gen_range(0, ac + 1), rng.gen_range(0, ac + 1));
+
+        debug!(target: "game::act",
+               "{} attacks {}: acc {} against ev {}, {}; dmg {} less {} of ac {}",
+               self.description(),
+               target.description(),
+               acc,
+               ev,
+               if success { "hit" } else { "missed" },
+               dmg,
+               rand_ac,
+               ac);
+        let dmg = cmp::max(0, dmg - rand_ac);
+
+        if success {
+            target.hp -= dmg;
+            target.noise_makes(Noise::Combat);
+        }
+
+        target.was_attacked_by.push(AttackResult {
+            success: success,
+            dmg: dmg,
+            who: self.description(),
+            behind: from_behind,
+        });
+
+        self.did_attack.push(AttackResult {
+            success: success,
+            dmg: dmg,
+            who: target.description(),
+            behind: from_behind,
+        });
+    }
+
+    pub fn discovered_stairs(&self, loc: &Location) -> bool {
+        self.perception.discovered.iter().any(|c| loc.at(*c).tile().has_stairs())
+    }
+
+    pub fn set_player(&mut self) {
+        self.player = true;
+    }
+
+    pub fn melee_sp_cost(&self) -> i32 {
+        cmp::max(0, self.stats.melee_str_req - self.stats.base.str_)
+    }
+
+    pub fn charge_sp_cost(&self) -> i32 {
+        cmp::max(0, 10 - self.stats.base.str_)
+    }
+
+    // Can attack considering only sp?
+    pub fn can_attack_sp(&self) -> bool {
+        self.sp >= self.melee_sp_cost()
+    }
+
+    // Can attack considering only sp?
+    pub fn can_charge_sp(&self) -> bool {
+        self.sp >= self.charge_sp_cost()
+    }
+
+    pub fn can_act(&self) -> bool {
+        self.action_cd == 0 && !self.is_dead()
+    }
+
+    pub fn can_attack(&self) -> bool {
+        self.can_act()
+    }
+
+    /// Throw item `ch` at `target_coord`, to land where a shot would stop
+    ///
+    /// Returns: false if it's out of range or can't be let go of.
+    pub fn throw(&mut self, loc: &mut Location, ch: char, target_coord: Coordinate) -> bool {
+        if self.coord().distance(target_coord) > RANGED_RANGE {
+            return false;
+        }
+        let item = match self.equip_drop(ch) {
+            Some(item) => item,
+            None => return false,
+        };
+
+        let from = self.coord();
+        let path = loc.shot_path(from, target_coord);
+        let lands = path.last().cloned().unwrap_or(from);
+        if let Some(item) = loc.at_mut(lands).drop_item(item) {
+            // nowhere to put it
+            self.items_backpack.insert(ch, item);
+            return false;
+        }
+        let id = loc.actors_coord_to_id[&from];
+        loc.event(from,
+                  Event::Projectile {
+                      id: id,
+                      path: path,
+                  });
+        true
+    }
+
+    /// Out to get the player
+    pub fn is_hostile(&self) -> bool {
+        !self.player && !self.companion && (self.race != Shopkeeper || self.provoked)
+    }
+
+    pub fn can_attack_ranged(&self) -> bool {
+       self.can_act() && self.is_holding_ranged_weapon()
+    }
+
+    pub fn is_holding_lit_torch(&self) -> bool {
+        self.equipped_in_slot(Slot::LHand).map(|i| i.is_torch()).unwrap_or(false)
+    }
+
+    pub fn is_holding_ranged_weapon(&self) -> bool {
+        if let Some(&(_, ref box_item)) = self.items_equipped.get(&Slot::RHand) {
+            box_item.is_ranged_weapon()
+        } else {
+            false
+        }
+    }
+
+    pub fn moved(&mut self, loc: &Location, new_pos: Position) {
+        self.pos = new_pos;
+        self.perception.look(self.eye(), loc);
+        let race = self.race;
+        self.noise_makes(Noise::Creature(race));
+    }
+
+    /// Returns: false if there was no open, unobstructed door at `coord`.
+    fn close_door(&mut self, loc: &mut Location, coord: Coordinate) -> bool {
+        let tile = *loc.at(coord).tile();
+        if tile.door_state() != Some(true) || tile.is_door_broken() {
+            self.did_interact.push(InteractResult::DoorNotOpen);
+            return false;
+        }
+
+        if loc.actors_coord_to_id.contains_key(&coord) || loc.items.contains_key(&coord) {
+            self.did_interact.push(InteractResult::DoorBlocked);
+            return false;
+        }
+
+        loc.tile_mut(coord).set_door(false);
+        self.noise_makes(Noise::DoorCreak);
+        let id = loc.actors_coord_to_id[&self.pos.coord];
+        loc.event(coord,
+                  Event::DoorClosed {
+                      id: id,
+                      coord: coord,
+                  });
+        true
+    }
+
+    fn push_boulder(&mut self, loc: &mut Location, from: Coordinate, to: Coordinate) -> bool {
+        if self.stats.base.str_ < BOULDER_STR_REQ || self.sp < BOULDER_SP_COST {
+            self.did_interact.push(InteractResult::BoulderTooHeavy);
+            return false;
+        }
+
+        if !loc.push_boulder(from, to) {
+            self.did_interact.push(InteractResult::BoulderStuck);
+            return false;
+        }
+
+        self.sp -= BOULDER_SP_COST;
+        self.noise_makes(Noise::Rumble);
+        true
+    }
+
+    /// Total price of unpaid shop items carried
+    pub fn debt(&self) -> u32 {
+        self.items_backpack
+            .values()
+            .chain(self.items_equipped.values().map(|&(_, ref item)| item))
+            .filter_map(|item| item.price())
+            .fold(0, |sum, p| sum + p)
+    }
+
+    /// Pay for all the carried shop items
+    ///
+    /// Returns: false if there was not enough gold.
+    pub fn pay_debt(&mut self) -> bool {
+        let debt = self.debt();
+        if debt > self.gold {
+            return false;
+        }
+
+        self.gold -= debt;
+        for item in self.items_backpack.values_mut() {
+            if item.price().is_some() {
+                Arc::make_mut(item).set_price(None);
+            }
+        }
+        for &mut (_, ref mut item) in self.items_equipped.values_mut() {
+            if item.price().is_some() {
+                Arc::make_mut(item).set_price(None);
+            }
+        }
+        true
+    }
+
+    /// Use up a key from the backpack
+    ///
+    /// Returns: false if there was none.
+    pub fn use_key(&mut self) -> bool {
+        let ch = self.items_backpack
+                     .iter()
+                     .find(|&(_, item)| item.is_key())
+                     .map(|(&ch, _)| ch);
+
+        match ch {
+            Some(ch) => {
+                self.items_backpack.remove(&ch);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns: false if there was no intact door, locked chest or lit brazier at `coord`.
+    fn bash(&mut self, loc: &mut Location, coord: Coordinate, rng: &mut GameRng) -> bool {
+        if let Some(tile::Chest(chest)) = loc.at(coord).tile().feature_on(tile::Layer::Fixture) {
+            if chest.locked {
+                loc.force_chest(self, coord, rng);
+                self.noise_makes(Noise::Crash);
+                return true;
+            }
+        }
+
+        if loc.at(coord).tile().has_feature(tile::Brazier(true)) {
+            loc.tile_mut(coord).add_light(0).remove_feature(tile::Layer::Fixture);
+            loc.set_on_fire(coord, tile::BRAZIER_FIRE_TURNS);
+            self.did_interact.push(InteractResult::BrazierKnockedOver);
+            self.noise_makes(Noise::Crash);
+            return true;
+        }
+
+        let tile = *loc.at(coord).tile();
+        if !tile.has_door() || tile.is_door_broken() {
+            self.did_interact.push(InteractResult::NoDoor);
+            return false;
+        }
+
+        let dmg = rng.gen_range(0, cmp::max(1, self.stats.melee_dmg) + 1);
+        if loc.tile_mut(coord).bash_door(dmg) {
+            self.did_interact.push(InteractResult::DoorBroken);
+        } else {
+            self.did_interact.push(InteractResult::DoorBashed);
+        }
+        self.noise_makes(Noise::DoorSlam);
+        true
+    }
+
+    /// Move to `to`, or next to it if it's taken
+    ///
+    /// Returns: false if there was no room at the destination.
+    pub fn teleport(&mut self, loc: &mut Location, to: Coordinate) -> bool {
+        let dest = if loc.at(to).is_passable() {
+            Some(to)
+        } else {
+            loc.free_coord_near(to)
+        };
+
+        let dest = match dest {
+            Some(dest) => dest,
+            None => {
+                self.did_interact.push(InteractResult::PortalBlocked);
+                return false;
+            }
+        };
+
+        let id = loc.actors_coord_to_id.remove(&self.pos.coord).unwrap();
+        let new_pos = Position::new(dest, self.pos.dir);
+        self.moved(loc, new_pos);
+        loc.actors_coord_to_id.insert(dest, id);
+        self.did_interact.push(InteractResult::Teleported);
+        true
+    }
+
+    pub fn triggers(&mut self, trap: tile::Trap, rng: &mut GameRng) {
+        match trap {
+            tile::Trap::Dart => {
+                self.hp -= rng.gen_range(1, 4);
+                self.did_interact.push(InteractResult::TrapDart);
+            }
+            tile::Trap::Alarm => {
+                self.noise_makes(Noise::Alarm);
+                self.did_interact.push(InteractResult::TrapAlarm);
+            }
+            tile::Trap::Needle => {
+                self.poisoned += 5;
+                self.did_interact.push(InteractResult::TrapNeedle);
+            }
+        }
+    }
+
+    pub fn is_player(&self) -> bool {
+        self.player
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.hp <= 0
+    }
+
+    /// Whatever affects the actor now: name, how bad it is and the
+    /// turns it has left, if it wears off on its own
+    pub fn active_statuses(&self) -> Vec<(&'static str, Severity, Option<i32>)> {
+        let mut statuses = vec![];
+        if self.poisoned > 0 {
+            // could take the last of its health
+            let severity = if self.poisoned / 2 >= self.hp {
+                Severity::Severe
+            } else {
+                Severity::Bad
+            };
+            statuses.push(("Poisoned", severity, Some(self.poisoned)));
+        }
+        if self.debt() > 0 {
+            statuses.push(("Indebted", Severity::Bad, None));
+        }
+        if self.sp == 0 && self.stats.base.max_sp > 0 {
+            statuses.push(("Exhausted", Severity::Mild, None));
+        }
+        if self.melee_sp_cost() > 0 {
+            statuses.push(("Strained", Severity::Mild, None));
+        }
+        statuses
+    }
+
+    pub fn can_perform_action(&self) -> bool {
+        !self.is_dead() && self.action_cd == 0
+    }
+
+    pub fn description(&self) -> String {
+        match self.name {
+            Some(ref name) if self.race == Ghost => format!("ghost of {}", name),
+            Some(ref name) => name.clone(),
+            None => self.race.description(),
+        }
+    }
+
+    pub fn travel_to(&mut self, level: LevelId) {
+        self.travel = Some(level);
+    }
+
+    pub fn traveled(&self) -> Option<LevelId> {
+        self.travel
+    }
+
+    pub fn light_emision(&self) -> u32 {
+        self.stats.light_emision
+    }
+
+    /// Perform an action
+    ///
+    /// Returns: false if the action was rejected and no time was spent.
+    pub fn act(&mut self, loc : &mut Location, action: Action, rng: &mut GameRng) -> bool {
+        let new_pos = self.pos_after_action(action);
+        let start_pos = self.pos;
+
+        for &new_pos in &new_pos {
+            let old_pos = self.pos;
+
+            if old_pos == new_pos {
+                // no movement
+                match action {
+                    Action::Pick => {
+                        let head = self.head();
+                        if loc.at(head).tile().closed_chest().is_some() {
+                            return loc.interact(self, rng);
+                        }
+                        let item = loc.at_mut(head).pick_item();
+
+                        match item {
+                            Some(item) => {
+                                let descr = item.description();
+                                if let Some(item) = self.pick_item(item) {
+                                    loc.place_item(head, item);
+                                } else {
+                                    let id = loc.actors_coord_to_id[&self.pos.coord];
+                                    loc.event(head,
+                                              Event::PickedUp {
+                                                  id: id,
+                                                  item: descr,
+                                              });
+                                }
+                            }
+                            None => {}
+                        }
+                    }
+                    Action::Equip(ch) => {
+                        self.equip_switch(ch);
+                    }
+                    Action::Drop_(ch) => {
+                        if let Some(item) = self.equip_drop(ch) {
+                            if let Some(item) = loc.at_mut(self.pos.coord).drop_item(item) {
+                                // nowhere to put it
+                                self.items_backpack.insert(ch, item);
+                                return false;
+                            }
+                        }
+                    }
+                    Action::Descend => {
+                        if let Some(to) = loc.at(self.coord()).tile().stairs_down() {
+                            self.travel_to(to);
+                        }
+                    }
+                    Action::Ascend => {
+                        if let Some(to) = loc.at(self.coord()).tile().stairs_up() {
+                            self.travel_to(to);
+                        }
+                    }
+                    Action::Ranged(target_coord) => {
+                        if !self.try_attack_ranged(loc, target_coord, rng) {
+                            return false;
+                        }
+                    },
+                    Action::Throw(ch, target_coord) => {
+                        if !self.throw(loc, ch, target_coord) {
+                            return false;
+                        }
+                    }
+                    Action::Interact => {
+                        if !loc.interact(self, rng) {
+                            return false;
+                        }
+                    }
+                    Action::Close(dir) => {
+                        let coord = self.coord() + dir.to_coordinate();
+                        if !self.close_door(loc, coord) {
+                            return false;
+                        }
+                    }
+                    Action::Bash(dir) => {
+                        let coord = self.coord() + dir.to_coordinate();
+                        if !self.bash(loc, coord, rng) {
+                            return false;
+                        }
+                    }
+                    _ => {}
+                }
+            } else if self.could_be_attack(action) &&
+                old_pos.coord != new_pos.coord &&
+                loc.actors_coord_to_id.contains_key(&new_pos.coord) {
+                    // we've tried to move into self; attack?
+                    if !self.can_attack() {
+                        break;
+                    }
+                    let dir = match action {
+                        Action::Move(dir) => old_pos.dir + dir,
+                        _ => old_pos.dir,
+                    };
+
+                    let target_id = loc.actors_coord_to_id[&new_pos.coord];
+                    if self.player && loc.actors_byid[&target_id].companion {
+                        // the pony makes way instead
+                        let id = loc.actors_coord_to_id[&old_pos.coord];
+                        let mut pony = loc.actors_byid.remove(&target_id).unwrap();
+                        let pony_pos = Position::new(old_pos.coord, pony.pos.dir);
+                        pony.moved(loc, pony_pos);
+                        loc.actors_byid.insert(target_id, pony);
+                        self.moved(loc, new_pos);
+                        loc.actors_coord_to_id.insert(old_pos.coord, target_id);
+                        loc.actors_coord_to_id.insert(new_pos.coord, id);
+                        break;
+                    }
+
+                    self.attacks(dir, loc.actors_byid.get_mut(&target_id).unwrap(), rng);
+                    loc.made_noise(target_id);
+
+                    let attacker_id = loc.actors_coord_to_id[&self.pos.coord];
+                    let res = self.did_attack.last().unwrap().clone();
+                    loc.event(new_pos.coord,
+                              Event::Attack {
+                                  attacker: attacker_id,
+                                  target: target_id,
+                                  success: res.success,
+                                  dmg: res.dmg,
+                                  behind: res.behind,
+                              });
+                    // Can't attack twice
+                    break;
+                } else if loc.at(new_pos.coord).tile().door_state() == Some(false) {
+                    // walked into door: open it
+                    if self.race.can_open_doors() {
+                        loc.tile_mut(new_pos.coord).set_door(true);
+                        let id = loc.actors_coord_to_id[&self.pos.coord];
+                        loc.event(new_pos.coord,
+                                  Event::DoorOpened {
+                                      id: id,
+                                      coord: new_pos.coord,
+                                  });
+                    }
+                    // Can't charge through the doors
+                    break;
+                } else if loc.at(new_pos.coord).tile().has_feature(tile::Boulder) {
+                    // walked into a boulder: push it and follow
+                    if let Action::Move(_) = action {
+                        let behind = new_pos.coord + (new_pos.coord - old_pos.coord);
+                        if self.push_boulder(loc, new_pos.coord, behind) {
+                            let id = loc.actors_coord_to_id.remove(&self.pos.coord).unwrap();
+                            self.moved(loc, new_pos);
+                            loc.actors_coord_to_id.insert(new_pos.coord, id);
+                        }
+                    }
+                    break;
+                } else if old_pos.coord == new_pos.coord && old_pos.dir != new_pos.dir {
+                    // we've rotated
+                    self.moved(loc, new_pos);
+                } else if old_pos.coord != new_pos.coord && loc.at(new_pos.coord).is_passable() &&
+                    !loc.actors_coord_to_id.contains_key(&new_pos.coord) {
+                        // for the rest of this turn this self can be found through both new
+                        // and old coor
+                        debug_assert!(!loc.actors_coord_to_id.contains_key(&new_pos.coord));
+                        let id = loc.actors_coord_to_id.remove(&self.pos.coord).unwrap();
+                        // we've moved
+                        self.moved(loc, new_pos);
+                        loc.actors_coord_to_id.insert(new_pos.coord, id);
+                        if let Some(trap) = loc.at(new_pos.coord).tile().trap() {
+                            self.triggers(trap, rng);
+                        }
+                        if let Some(twin) = loc.at(new_pos.coord).tile().portal() {
+                            self.teleport(loc, twin);
+                            // Portal ends the movement
+                            break;
+                        }
+                    } else {
+                        // we hit the wall or something
+                        let dug = match action {
+                            Action::Move(angle) => {
+                                if self.can_dig() &&
+                                    self.can_dig_at_angle(angle) &&
+                                    loc.at(new_pos.coord)
+                                       .tile_opt()
+                                       .map_or(false, |t| t.can_dig_through()) {
+                                    self.dig(angle, loc, rng);
+                                    true
+                                } else {
+                                    false
+                                }
+                            },
+                            _ => false,
+                        };
+                        if !dug && self.pos == start_pos {
+                            // walking into a wall takes no time
+                            return false;
+                        }
+                    }
+        }
+
+        true
+    }
+
+    // Item equipped in a given slot
+    pub fn equipped_in_slot<'a>(&'a self, slot : Slot) -> Option<&'a Item> {
+        self.items_equipped.get(&slot).map(|&(_, ref item)| &**item)
+    }
+
+    pub fn can_dig(&self) -> bool {
+        self.can_attack_sp() &&
+            self.equipped_in_slot(Slot::RHand)
+            .map(|item| item.can_dig())
+            .unwrap_or(false)
+    }
+
+    pub fn dig(&mut self, angle : Angle, loc : &mut Location, rng: &mut GameRng) {
+        assert!(self.can_dig_at_angle(angle));
+        let target_coord = self.head_rel(angle);
+        loc.at_mut(target_coord).tile().dig(rng);
+        self.substract_melee_sp_cost();
+        self.noise_makes(Noise::Digging);
+    }
+
+
+    pub fn could_be_attack(&self, action : Action) -> bool {
+        match action {
+            Action::Charge => true,
+            Action::Move(angle) => {
+                match angle {
+                    Left | Right | Forward => true,
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hex2d::{Angle, Coordinate, Direction, Position};
+
+    use game::{Action, Bones, Event, LevelId, Location};
+    use game::item::{self, Item};
+    use util;
+    use super::{Actor, Race, Slot, Stats};
+
+    /// A goblin at `from` looking at `to`, spawned on `loc`, with one
+    /// tick behind it for the light of the level to settle
+    fn goblin_looking(loc: &mut Location, from: Coordinate, to: Coordinate) -> u32 {
+        let mut rng = util::game_rng(0);
+        let pos = Position::new(from, from.direction_to_cw(to).unwrap());
+        let id = loc.spawn(Actor::new(Race::Goblin, pos), &mut rng);
+        assert!(loc.act(id, Action::Wait, &mut rng));
+        id
+    }
+
+    #[test]
+    fn standing_still_takes_no_los_work() {
+        let mut rng = util::game_rng(1);
+        let mut loc = Location::from_rows(&["############",
+                                            "#..........#",
+                                            "#..........#",
+                                            "#..........#",
+                                            "############"]);
+        let id = goblin_looking(&mut loc, Coordinate::new(2, 2), Coordinate::new(3, 2));
+        let updates = loc.actors_byid[&id].perception.los_updates;
+
+        for _ in 0..100 {
+            assert!(loc.act(id, Action::Wait, &mut rng));
+        }
+        assert_eq!(loc.actors_byid[&id].perception.los_updates, updates);
+    }
+
+    #[test]
+    fn opening_a_door_in_sight_updates_los() {
+        let mut rng = util::game_rng(1);
+        let mut loc = Location::from_rows(&["###########",
+                                            "#....+....#",
+                                            "###########"]);
+        let id = goblin_looking(&mut loc, Coordinate::new(1, 1), Coordinate::new(2, 1));
+        let behind = Coordinate::new(7, 1);
+        assert!(!loc.actors_byid[&id].in_los(behind));
+        let updates = loc.actors_byid[&id].perception.los_updates;
+
+        loc.tile_mut(Coordinate::new(5, 1)).set_door(true);
+        assert!(loc.act(id, Action::Wait, &mut rng));
+
+        assert!(loc.actors_byid[&id].in_los(behind));
+        assert!(loc.actors_byid[&id].perception.los_updates > updates);
+    }
+
+    #[test]
+    fn walking_into_a_closed_door_opens_it_for_those_who_can() {
+        let mut rng = util::game_rng(1);
+        let mut loc = Location::from_rows(&["#######",
+                                            "#..+..#",
+                                            "#######"]);
+        let door = Coordinate::new(3, 1);
+        let (west, east) = (Coordinate::new(2, 1), Coordinate::new(4, 1));
+        let rat = loc.spawn(Actor::new(Race::Rat,
+                                       Position::new(east, east.direction_to_cw(door).unwrap())),
+                            &mut rng);
+        loc.act(rat, Action::Move(Angle::Forward), &mut rng);
+        assert_eq!(loc.at(door).tile().door_state(), Some(false));
+        assert_eq!(loc.actors_byid[&rat].coord(), east);
+
+        let id = goblin_looking(&mut loc, west, door);
+        assert!(loc.act(id, Action::Move(Angle::Forward), &mut rng));
+        assert_eq!(loc.at(door).tile().door_state(), Some(true));
+        // opening it takes the step
+        assert_eq!(loc.actors_byid[&id].coord(), west);
+        assert!(loc.events_for(id).contains(&Event::DoorOpened {
+            id: id,
+            coord: door,
+        }));
+    }
+
+    #[test]
+    fn walking_into_somebody_attacks_them() {
+        let mut hits = 0;
+        for seed in 0..20 {
+            let mut rng = util::game_rng(seed);
+            let mut loc = Location::from_rows(&["#####", "#...#", "#####"]);
+            let (at, ahead) = (Coordinate::new(1, 1), Coordinate::new(2, 1));
+            let attacker = goblin_looking(&mut loc, at, ahead);
+            // looking the same way, so it's hit from behind
+            let pos = Position::new(ahead, at.direction_to_cw(ahead).unwrap());
+            let target = loc.spawn(Actor::new(Race::Troll, pos), &mut rng);
+            let hp = loc.actors_byid[&target].hp;
+
+            assert!(loc.act(attacker, Action::Move(Angle::Forward), &mut rng));
+
+            assert_eq!(loc.actors_byid[&attacker].coord(), at);
+            assert_eq!(loc.actors_byid[&target].coord(), ahead);
+            let res = loc.actors_byid[&attacker].did_attack.clone();
+            assert_eq!(res.len(), 1);
+            let res = &res[0];
+            assert!(res.behind);
+            assert!(res.dmg >= 0);
+            let taken = &loc.actors_byid[&target].was_attacked_by;
+            assert_eq!(taken.len(), 1);
+            assert_eq!((taken[0].success, taken[0].dmg, taken[0].behind),
+                       (res.success, res.dmg, res.behind));
+            let lost = if res.success { res.dmg } else { 0 };
+            assert_eq!(loc.actors_byid[&target].hp, hp - lost);
+            assert!(loc.events_for(attacker).contains(&Event::Attack {
+                attacker: attacker,
+                target: target,
+                success: res.success,
+                dmg: res.dmg,
+                behind: true,
+            }));
+            if res.success {
+                hits += 1;
+            }
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn picked_up_and_dropped_items_are_shared_not_copied() {
+        let mut rng = util::game_rng(1);
+        let mut loc = Location::from_rows(&["#####", "#...#", "#####"]);
+        let (at, ahead) = (Coordinate::new(1, 1), Coordinate::new(2, 1));
+        let knife = Arc::new(Item::new(item::Type::Knife, vec![]));
+        loc.place_item(ahead, knife.clone());
+        assert_eq!(Arc::strong_count(&knife), 2);
+
+        let pos = Position::new(at, at.direction_to_cw(ahead).unwrap());
+        let id = loc.spawn(Actor::new(Race::Human, pos), &mut rng);
+        assert!(loc.act(id, Action::Pick, &mut rng));
+        assert!(loc.items.get(&ahead).is_none());
+        let ch = {
+            let (&ch, carried) = loc.actors_byid[&id].items_backpack.iter().next().unwrap();
+            assert!(Arc::ptr_eq(carried, &knife));
+            ch
+        };
+        assert_eq!(Arc::strong_count(&knife), 2);
+
+        assert!(loc.act(id, Action::Drop_(ch), &mut rng));
+        assert!(loc.actors_byid[&id].items_backpack.is_empty());
+        assert!(Arc::ptr_eq(&loc.items[&at], &knife));
+        assert_eq!(Arc::strong_count(&knife), 2);
+
+        // a copy of the level shares them too
+        let copy = loc.clone();
+        assert_eq!(Arc::strong_count(&knife), 3);
+        drop(copy);
+        assert_eq!(Arc::strong_count(&knife), 2);
+    }
+
+    #[test]
+    fn ghost_is_the_dead_player_as_they_were() {
+        let stats = Stats { str_: 9, max_hp: 40, ..Stats::new(Race::Dwarf) };
+        let bones = Bones {
+            level: LevelId::start(),
+            name: "Urist".to_string(),
+            race: Race::Dwarf,
+            stats: Some(stats),
+            worn: vec![Arc::new(Item::new(item::Type::Helmet, vec![]))],
+            items: vec![Arc::new(Item::new(item::Type::Knife, vec![])),
+                        Arc::new(Item::new(item::Type::HealthPotion, vec![]))],
+            site: Coordinate::new(0, 0),
+            fragment: vec![],
+        };
+        let ghost = Actor::ghost(bones, Position::new(Coordinate::new(0, 0), Direction::XZ));
+
+        assert_eq!(ghost.race, Race::Ghost);
+        assert_eq!(ghost.base_stats, stats);
+        assert_eq!(ghost.hp, 40);
+        assert_eq!(ghost.stats.base.str_, 9);
+        assert_eq!(ghost.equipped_in_slot(Slot::Head).map(|i| i.description()),
+                   Some("helmet".to_string()));
+        // the knife was carried, not wielded
+        assert_eq!(ghost.items_equipped.len(), 1);
+        assert_eq!(ghost.items_backpack.len(), 2);
+    }
+}
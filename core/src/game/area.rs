@@ -4,7 +4,7 @@ use std::fmt;
 
 pub use self::Type::*;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum Type {
     Room(u32),
 }
@@ -26,7 +26,7 @@ impl fmt::Display for Type {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub struct Area {
     pub center: h2d::Coordinate,
     pub type_: Type,
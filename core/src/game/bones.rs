@@ -0,0 +1,320 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use hex2d::Coordinate;
+use rand::Rng;
+
+use super::LevelId;
+use super::actor::{Race, Stats};
+use super::format::{FileFormat, Refusal};
+use super::item::Item;
+use super::level::Branch;
+use super::tile::Tile;
+
+pub const FORMAT: FileFormat = FileFormat {
+    kind: "bones",
+    version: 1,
+    migrations: &[],
+};
+
+/// Races a player can have died as
+const PLAYABLE: [Race; 3] = [Race::Human, Race::Elf, Race::Dwarf];
+
+const BRANCHES: [Branch; 2] = [Branch::Main, Branch::Warrens];
+
+/// One in that many bones files gets used by a new game
+const CLAIM_CHANCE: u32 = 3;
+
+/// What's left of a player who died on a level, for the same level of a
+/// later game to be haunted by
+#[derive(Clone, Debug)]
+pub struct Bones {
+    pub level: LevelId,
+    pub name: String,
+    /// Race of the player
+    pub race: Race,
+    /// The player's stats when they died, for the ghost to have; `None`
+    /// in bones from before they were kept, for the race's to be used
+    pub stats: Option<Stats>,
+    /// What the player wore, for the ghost to wear
+    pub worn: Vec<Arc<Item>>,
+    /// Everything else the player had, for the ghost to carry
+    pub items: Vec<Arc<Item>>,
+    /// Where the player died
+    pub site: Coordinate,
+    /// The map around `site`, each tile by its offset from it, with
+    /// nothing that leads elsewhere or holds loot
+    pub fragment: Vec<(Coordinate, Tile)>,
+}
+
+impl Bones {
+    /// The bones as lines of text, the first one saying which level
+    /// they are for; `parse` reads them back
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("level {} {}", self.level.branch.name(), self.level.depth),
+                             format!("race {}", self.race.description()),
+                             format!("name {}", self.name.replace(|c: char| c.is_control(), " ")),
+                             format!("site {} {}", self.site.x, self.site.y)];
+        if let Some(s) = self.stats {
+            lines.push(format!("stats {} {} {} {} {} {} {} {} {} {} {}",
+                               s.int,
+                               s.dex,
+                               s.str_,
+                               s.max_hp,
+                               s.max_mp,
+                               s.max_sp,
+                               s.ac,
+                               s.ev,
+                               s.infravision,
+                               s.vision,
+                               s.regeneration));
+        }
+        lines.extend(self.worn.iter().map(|item| format!("wear {}", item.encode())));
+        lines.extend(self.items.iter().map(|item| format!("item {}", item.encode())));
+        lines.extend(self.fragment
+                         .iter()
+                         .map(|&(d, ref tile)| format!("tile {} {} {}", d.x, d.y, tile.encode())));
+        lines
+    }
+
+    /// Every bones in `lines`, as written by `lines`; keys this version
+    /// doesn't know are skipped
+    ///
+    /// Returns: `None` if anything else doesn't parse.
+    pub fn parse<'a, I>(lines: I) -> Option<Vec<Bones>>
+        where I: IntoIterator<Item = &'a str>
+    {
+        let mut all: Vec<Bones> = vec![];
+        for line in lines {
+            let (key, value) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => return None,
+            };
+
+            if key == "level" {
+                let words: Vec<&str> = value.split(' ').collect();
+                let branch = BRANCHES.iter().cloned().find(|b| Some(&b.name()) == words.get(0));
+                let depth = words.get(1).and_then(|d| d.parse().ok());
+                match (words.len(), branch, depth) {
+                    (2, Some(branch), Some(depth)) => {
+                        all.push(Bones {
+                            level: LevelId::new(branch, depth),
+                            name: String::new(),
+                            race: Race::Human,
+                            stats: None,
+                            worn: vec![],
+                            items: vec![],
+                            site: Coordinate::new(0, 0),
+                            fragment: vec![],
+                        })
+                    }
+                    _ => return None,
+                }
+                continue;
+            }
+
+            let bones = match all.last_mut() {
+                Some(bones) => bones,
+                None => return None,
+            };
+            match key {
+                "race" => {
+                    match PLAYABLE.iter().cloned().find(|r| r.description() == value) {
+                        Some(race) => bones.race = race,
+                        None => return None,
+                    }
+                }
+                "name" => bones.name = value.to_string(),
+                "site" => {
+                    let n: Vec<i32> = match value.split(' ').map(|w| w.parse()).collect() {
+                        Ok(n) => n,
+                        Err(_) => return None,
+                    };
+                    if n.len() != 2 {
+                        return None;
+                    }
+                    bones.site = Coordinate::new(n[0], n[1]);
+                }
+                "stats" => {
+                    let n: Vec<i32> = match value.split(' ').map(|w| w.parse()).collect() {
+                        Ok(n) => n,
+                        Err(_) => return None,
+                    };
+                    if n.len() != 11 || n[10] < 0 {
+                        return None;
+                    }
+                    bones.stats = Some(Stats {
+                        int: n[0],
+                        dex: n[1],
+                        str_: n[2],
+                        max_hp: n[3],
+                        max_mp: n[4],
+                        max_sp: n[5],
+                        ac: n[6],
+                        ev: n[7],
+                        infravision: n[8],
+                        vision: n[9],
+                        regeneration: n[10] as u32,
+                    });
+                }
+                "item" | "wear" => {
+                    match Item::decode(value) {
+                        Some(item) if key == "wear" => bones.worn.push(Arc::new(item)),
+                        Some(item) => bones.items.push(Arc::new(item)),
+                        None => return None,
+                    }
+                }
+                "tile" => {
+                    let words: Vec<&str> = value.splitn(3, ' ').collect();
+                    let dx = words.get(0).and_then(|w| w.parse().ok());
+                    let dy = words.get(1).and_then(|w| w.parse().ok());
+                    let tile = words.get(2).and_then(|w| Tile::decode(w));
+                    match (dx, dy, tile) {
+                        (Some(dx), Some(dy), Some(tile)) => {
+                            bones.fragment.push((Coordinate::new(dx, dy), tile))
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(all)
+    }
+
+    /// File in `dir` the bones for `level` go to
+    fn path(dir: &Path, level: LevelId) -> PathBuf {
+        dir.join(format!("{}-{}", level.branch.name(), level.depth))
+    }
+
+    /// Leave the bones in `dir`, unless there are some for the level
+    /// already
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let path = Bones::path(dir, self.level);
+        if path.exists() {
+            return Ok(());
+        }
+        try!(fs::create_dir_all(dir));
+
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = io::BufWriter::new(try!(fs::File::create(&tmp)));
+            try!(writeln!(file, "{}", FORMAT.header()));
+            for line in self.lines() {
+                try!(writeln!(file, "{}", line));
+            }
+            try!(file.flush());
+            try!(file.get_ref().sync_all());
+        }
+        fs::rename(&tmp, path)
+    }
+
+    /// Returns: `None` if the bones are no use to anyone; an error if
+    /// they can't be read, or only by a newer rhex.
+    fn load(path: &Path) -> io::Result<Option<Bones>> {
+        let file = io::BufReader::new(try!(fs::File::open(path)));
+        let mut lines: Vec<String> = try!(file.lines().collect());
+        if lines.is_empty() {
+            return Ok(None);
+        }
+        let rest = lines.split_off(1);
+        let lines = match FORMAT.check(&lines[0]).and_then(|v| FORMAT.migrate(v, rest)) {
+            Ok(lines) => lines,
+            Err(refusal @ Refusal::TooNew(..)) => return Err(FORMAT.refuse(refusal)),
+            Err(_) => return Ok(None),
+        };
+
+        let all = Bones::parse(lines.iter().map(|l| &l[..]));
+        Ok(match all {
+            Some(ref all) if all.len() == 1 => Some(all[0].clone()),
+            _ => None,
+        })
+    }
+
+    /// Now and then take bones out of `dir` for a new game. Each is only
+    /// ever used once; bad files are thrown away, and those of a newer
+    /// rhex left for it.
+    pub fn claim<R: Rng>(dir: &Path, rng: &mut R) -> Vec<Bones> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+
+        let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path()))
+                                             .filter(|p| p.extension().is_none())
+                                             .collect();
+        paths.sort();
+
+        let mut claimed = vec![];
+        for path in paths {
+            if rng.gen_range(0, CLAIM_CHANCE) != 0 {
+                continue;
+            }
+            match Bones::load(&path) {
+                Ok(Some(bones)) => claimed.push(bones),
+                Ok(None) => warn!("Throwing away bad bones {}", path.display()),
+                Err(e) => {
+                    warn!("Can't read bones {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Can't remove bones {}: {}", path.display(), e);
+            }
+        }
+        claimed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hex2d::Coordinate;
+
+    use game::{Branch, LevelId};
+    use game::actor::{Race, Stats};
+    use game::item::{self, Item};
+    use game::tile::{self, Tile};
+    use super::Bones;
+
+    #[test]
+    fn lines_read_back() {
+        let mut door = Tile::new(tile::Empty);
+        door.add_feature(tile::Door(true, 3));
+        let bones = Bones {
+            level: LevelId::new(Branch::Warrens, 1),
+            name: "Lorien".to_string(),
+            race: Race::Elf,
+            stats: Some(Stats { dex: 7, ..Stats::new(Race::Elf) }),
+            worn: vec![Arc::new(Item::new(item::Type::Cloak, vec![]))],
+            items: vec![Arc::new(Item::new(item::Type::Bow, vec![]))],
+            site: Coordinate::new(-4, 12),
+            fragment: vec![(Coordinate::new(0, 0), Tile::new(tile::Empty)),
+                           (Coordinate::new(1, -1), door)],
+        };
+        let lines = bones.lines();
+        let read = Bones::parse(lines.iter().map(|l| &l[..])).unwrap();
+
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].lines(), lines);
+        assert_eq!(read[0].stats, bones.stats);
+        assert_eq!(read[0].worn.len(), 1);
+        assert_eq!(read[0].items.len(), 1);
+        assert_eq!(read[0].site, bones.site);
+        assert_eq!(read[0].fragment, bones.fragment);
+    }
+
+    #[test]
+    fn bones_without_stats_still_parse() {
+        let lines = ["level Dungeon 3", "race human", "name Old", "item knife"];
+        let read = Bones::parse(lines.iter().cloned()).unwrap();
+
+        assert_eq!(read[0].stats, None);
+        assert!(read[0].worn.is_empty());
+        assert_eq!(read[0].items.len(), 1);
+        assert!(read[0].fragment.is_empty());
+    }
+}
@@ -11,7 +11,7 @@ pub const ELF_STATS: actor::Stats = actor::Stats {
     ev: 2,
     infravision: 3,
     vision: 15,
-    regeneration: 0,
+    regeneration: 2,
 };
 
 pub const HUMAN_STATS: actor::Stats = actor::Stats {
@@ -25,7 +25,7 @@ pub const HUMAN_STATS: actor::Stats = actor::Stats {
     ev: 1,
     infravision: 1,
     vision: 10,
-    regeneration: 0,
+    regeneration: 3,
 };
 
 
@@ -40,7 +40,7 @@ pub const DWARF_STATS: actor::Stats = actor::Stats {
     ev: 0,
     infravision: 2,
     vision: 10,
-    regeneration: 0,
+    regeneration: 4,
 };
 
 
@@ -85,3 +85,47 @@ pub const TROLL_STATS: actor::Stats = actor::Stats {
     vision: 10,
     regeneration: 10,
 };
+
+pub const SHOPKEEPER_STATS: actor::Stats = actor::Stats {
+    int: 5,
+    dex: 5,
+    str_: 8,
+    max_hp: 80,
+    max_mp: 10,
+    max_sp: 20,
+    ac: 5,
+    ev: 3,
+    infravision: 2,
+    vision: 10,
+    regeneration: 10,
+};
+
+pub const PONY_STATS: actor::Stats = actor::Stats {
+    int: 1,
+    dex: 3,
+    str_: 4,
+    max_hp: 20,
+    max_mp: 0,
+    max_sp: 20,
+    ac: 1,
+    ev: 2,
+    infravision: 1,
+    vision: 8,
+    regeneration: 2,
+};
+
+/// Only used as is by wizards; a ghost from bones gets the stats the
+/// player had
+pub const GHOST_STATS: actor::Stats = actor::Stats {
+    int: 3,
+    dex: 3,
+    str_: 3,
+    max_hp: 20,
+    max_mp: 15,
+    max_sp: 15,
+    ac: 1,
+    ev: 2,
+    infravision: 5,
+    vision: 10,
+    regeneration: 0,
+};
@@ -0,0 +1,1529 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use fnv::FnvHasher;
+use hex2d::Coordinate;
+use log::LogLevel;
+use rand::Rng;
+
+use super::{Location, Action, Bones, Event, Feeling, FnvHashMap, FnvHashSet, LevelId, Replay};
+use super::actor::{self, Actor};
+use super::location::DeadActorRecord;
+use super::{item, score};
+use super::timings::{self, Stopwatch};
+use util::{self, GameRng};
+use ai::{self, Ai};
+
+/// RNG for one part of the game with `seed`, independent of the others
+fn derived_rng<T: Hash>(seed: u32, part: T) -> GameRng {
+    let mut hasher = FnvHasher::default();
+    (seed, part).hash(&mut hasher);
+    util::game_rng(hasher.finish() as u32)
+}
+
+/// Level `id` as a game with `seed` generates it, lit but not yet
+/// visited, without playing up to it
+pub fn generate_level(seed: u32, id: LevelId) -> Location {
+    let mut loc = Location::new(id, &mut derived_rng(seed, id));
+    loc.recalculate_light_map(&mut derived_rng(seed, (id, "export")));
+    loc
+}
+
+/// Player actions that can be undone
+const UNDO_STEPS: usize = 20;
+
+/// Turns between checkpoints
+const AUTOSAVE_TURNS: u64 = 50;
+/// Turns between state hashes recorded in the replay
+const HASH_TURNS: u64 = 10;
+/// How long a monster may think before it has to wait instead
+const AI_BUDGET_MS: u64 = 100;
+/// Timeouts in a row after which a monster's brain is swapped for
+/// `ai::Roam`
+const AI_TIMEOUTS: u32 = 3;
+
+/// A replay stopped playing out the way it was recorded
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Divergence {
+    /// First turn known to differ
+    pub turn: u64,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "replay diverged at turn {}", self.turn)
+    }
+}
+
+/// How the game ended
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum GameResult {
+    /// What killed the player, where and when
+    Died { cause: String },
+    Quit,
+}
+
+/// The game as it was before a player action, to go back to. The
+/// location's map is shared with the live one until either changes it;
+/// the rest is a copy, mostly made of the actors' visibility sets and
+/// the light map, which grow with the part of the level explored and lit.
+struct Snapshot {
+    location: Location,
+    turn: u64,
+    rng: GameRng,
+    ai_rng: GameRng,
+    queue: VecDeque<actor::Id>,
+    acted: FnvHashSet<actor::Id>,
+    /// Lengths of the replay's action and hash lists
+    replay_len: (usize, usize),
+}
+
+/// Where a level is kept. Only the current level and those its stairs
+/// lead to are sure to be in memory; the others go to disk, if there's
+/// somewhere to put them.
+enum LevelSlot {
+    Loaded(Location),
+    /// Written to a file, read back when the player returns
+    OnDisk(PathBuf),
+    /// Known of, but made only once the player gets there
+    Ungenerated,
+}
+
+pub struct Engine {
+    turn: u64,
+    location_cur: LevelId,
+    locations: FnvHashMap<LevelId, LevelSlot>,
+    /// Where to write the levels the player is away from, if anywhere
+    spill_dir: Option<PathBuf>,
+    /// Kill logs of the levels on disk, for the score to count
+    kill_logs: FnvHashMap<LevelId, Vec<DeadActorRecord>>,
+    visited: FnvHashSet<LevelId>,
+    feelings: Vec<Feeling>,
+    /// Drives the random outcomes of actions and turns
+    rng: GameRng,
+    /// Drives the monsters' decisions
+    ai_rng: GameRng,
+    /// What it takes to get the game back to where it is
+    replay: Replay,
+    /// Where to keep checkpoints, if anywhere
+    autosave: Option<PathBuf>,
+    /// Wizard commands were used; the game no longer counts
+    wizard: bool,
+    /// How long a monster may think; the player may take as long as
+    /// they like
+    ai_budget: Duration,
+    /// Brains other than `ai::Simple`, by level and actor
+    brains: FnvHashMap<(LevelId, actor::Id), Box<Ai>>,
+    /// Timeouts in a row, by level and actor
+    ai_timeouts: FnvHashMap<(LevelId, actor::Id), u32>,
+    /// Keep snapshots to undo player actions, for wizard mode
+    undo_enabled: bool,
+    /// Most recent last; only from the current level
+    undo: VecDeque<Snapshot>,
+    /// What the player noticed on levels since left
+    events: Vec<Event>,
+    /// Set once the game is over; no more actions are taken
+    result: Option<GameResult>,
+    /// Absolute depth of the deepest level visited
+    deepest: u32,
+    /// Bones of other games, for levels yet to be generated
+    bones: Vec<Bones>,
+
+    /// Who is still to act this round, in order; the player, while in
+    /// it, comes first
+    queue: VecDeque<actor::Id>,
+    /// Who already acted this round
+    acted: FnvHashSet<actor::Id>,
+    /// When the work on the current turn started, if turns are timed for
+    /// the log; waiting for the player doesn't count
+    turn_started: Option<Instant>,
+}
+
+impl Engine {
+    /// Two engines with the same `seed`, given the same actions, play
+    /// out the same game; and the levels come out the same whatever the
+    /// actions
+    pub fn new(seed: u32) -> Self {
+        let start = LevelId::start();
+        let mut locations = FnvHashMap::default();
+        locations.insert(start,
+                         LevelSlot::Loaded(Location::new(start, &mut derived_rng(seed, start))));
+        Engine {
+            rng: util::game_rng(seed),
+            ai_rng: derived_rng(seed, "ai"),
+            replay: Replay::new(seed),
+            autosave: None,
+            wizard: false,
+            ai_budget: Duration::from_millis(AI_BUDGET_MS),
+            brains: FnvHashMap::default(),
+            ai_timeouts: FnvHashMap::default(),
+            undo_enabled: false,
+            undo: VecDeque::new(),
+            events: vec![],
+            result: None,
+            deepest: 0,
+            bones: vec![],
+            location_cur: start,
+            locations: locations,
+            spill_dir: None,
+            kill_logs: FnvHashMap::default(),
+            visited: FnvHashSet::default(),
+            feelings: vec![],
+            queue: VecDeque::new(),
+            acted: Default::default(),
+            turn: 0,
+            turn_started: None,
+        }
+    }
+
+    /// Play out `replay` again, checking that it goes the same way
+    pub fn resume(replay: &Replay) -> Result<Self, Divergence> {
+        let mut engine = Engine::new(replay.seed);
+        for bones in &replay.bones {
+            engine.add_bones(bones.clone());
+        }
+        if let Some(race) = replay.race {
+            engine.initial_spawn(race, &replay.name);
+        }
+
+        for &(turn, action) in &replay.actions {
+            if !engine.needs_player_input() || engine.turn != turn {
+                return Err(Divergence { turn: cmp::min(engine.turn, turn) });
+            }
+            engine.player_act(action);
+            engine.finish_round();
+        }
+
+        let hashes = replay.hashes.iter().zip(engine.replay.hashes.iter());
+        for (&(turn, expected), &(_, actual)) in hashes {
+            if expected != actual {
+                return Err(Divergence { turn: turn });
+            }
+        }
+        if replay.hashes.len() > engine.replay.hashes.len() {
+            return Err(Divergence { turn: engine.turn });
+        }
+
+        if let Some((turn, expected)) = replay.end {
+            if turn != engine.turn || expected != engine.state_hash() {
+                return Err(Divergence { turn: cmp::min(engine.turn, turn) });
+            }
+        }
+
+        Ok(engine)
+    }
+
+    /// Let everybody else act, until it's the player's turn again
+    pub fn finish_round(&mut self) {
+        while !self.needs_player_input() && !self.queue.is_empty() {
+            self.one_actor_tick();
+        }
+    }
+
+    /// Digest of the game state; two games that went the same way have
+    /// the same one
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        self.turn.hash(&mut hasher);
+        self.location_cur.hash(&mut hasher);
+        // whatever happened, it shows in what comes out of the RNGs next
+        self.rng.clone().next_u32().hash(&mut hasher);
+        self.ai_rng.clone().next_u32().hash(&mut hasher);
+
+        let loc = self.current_location();
+        let mut ids = loc.actors_ids();
+        ids.sort();
+        for id in ids {
+            let actor = &loc.actors_byid[&id];
+            id.hash(&mut hasher);
+            actor.pos.coord.hash(&mut hasher);
+            (actor.hp, actor.mp, actor.sp).hash(&mut hasher);
+        }
+
+        let mut items: Vec<_> = loc.items.iter().collect();
+        items.sort_by_key(|&(c, _)| (c.x, c.y));
+        for (coord, item) in items {
+            coord.hash(&mut hasher);
+            item.description().hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+
+    /// Everything played so far
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Keep the game saved in `path` from now on
+    pub fn set_autosave(&mut self, path: &Path) {
+        self.autosave = Some(path.to_owned());
+        self.checkpoint();
+    }
+
+    /// Write the autosave, if there's one; a dead player's game can't
+    /// be resumed, and neither can one changed by wizard commands
+    pub fn checkpoint(&mut self) {
+        if self.autosave.is_none() {
+            return;
+        }
+        self.replay.end = Some((self.turn, self.state_hash()));
+        let path = self.autosave.as_ref().unwrap();
+
+        let res = if self.wizard || self.replay.race.is_some() && self.player().is_dead() {
+            match fs::remove_file(path) {
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                res => res,
+            }
+        } else {
+            self.replay.save(path)
+        };
+
+        if let Err(e) = res {
+            warn!("Autosave to {} failed: {}", path.display(), e);
+        }
+    }
+
+    /// Whether the game counts, i.e. no wizard commands were used
+    pub fn is_scored(&self) -> bool {
+        !self.wizard
+    }
+
+    /// Every wizard command goes through here first
+    fn wizard_used(&mut self) {
+        assert!(self.needs_player_input());
+        if !self.wizard {
+            self.wizard = true;
+            // the replay can't reproduce what follows
+            self.checkpoint();
+        }
+    }
+
+    /// Wizard commands take no time, but must leave everything consistent
+    fn wizard_done(&mut self) {
+        self.current_location().check_invariants();
+        if self.queue.is_empty() {
+            // after a level change
+            self.start_round();
+        } else {
+            self.update_queue();
+        }
+    }
+
+    /// Wizard: let the player know the whole level
+    pub fn debug_reveal(&mut self) {
+        self.wizard_used();
+        {
+            let loc = self.current_location_mut();
+            let player_id = loc.player_id();
+            let coords: Vec<_> = loc.map.iter().map(|(&c, _)| c).collect();
+            let player = loc.actors_byid.get_mut(&player_id).unwrap();
+            for coord in coords {
+                if player.perception.known.insert(coord) {
+                    player.perception.discovered.insert(coord);
+                }
+            }
+        }
+        self.wizard_done();
+    }
+
+    /// Wizard: move the player to `coord`, or as close as there's room
+    pub fn debug_teleport(&mut self, coord: Coordinate) {
+        self.wizard_used();
+        {
+            let (loc, rng) = self.current_location_rng();
+            let player_id = loc.player_id();
+            loc.teleport(player_id, coord, rng);
+        }
+        self.wizard_done();
+    }
+
+    /// Wizard: put a new `race` actor at `coord`, or as close as there's
+    /// room
+    pub fn debug_spawn_monster(&mut self, race: actor::Race, coord: Coordinate) {
+        self.wizard_used();
+        {
+            let (loc, rng) = self.current_location_rng();
+            if let Some(coord) = loc.free_coord_near(coord) {
+                let pos = util::random_pos(coord.x, coord.y, rng);
+                loc.spawn(Actor::new(race, pos), rng);
+            }
+        }
+        self.wizard_done();
+    }
+
+    /// Wizard: drop `item` at `coord`, or a random one fitting the level
+    pub fn debug_spawn_item(&mut self, coord: Coordinate, item: Option<item::Item>) {
+        self.wizard_used();
+        {
+            let (loc, rng) = self.current_location_rng();
+            let level = loc.id.absolute_depth() as i32;
+            let item = match item {
+                Some(item) => Arc::new(item),
+                None => item::random(level, rng),
+            };
+            loc.place_item(coord, item);
+        }
+        self.wizard_done();
+    }
+
+    /// Wizard: restore the player's HP, MP and SP and cure poison
+    pub fn debug_heal(&mut self) {
+        self.wizard_used();
+        {
+            let loc = self.current_location_mut();
+            let player_id = loc.player_id();
+            let player = loc.actors_byid.get_mut(&player_id).unwrap();
+            player.hp = player.stats.base.max_hp;
+            player.mp = player.stats.base.max_mp;
+            player.sp = player.stats.base.max_sp;
+            player.poisoned = 0;
+        }
+        self.wizard_done();
+    }
+
+    /// Wizard: remember the game before each player action, so it can
+    /// be undone
+    pub fn enable_undo(&mut self) {
+        self.undo_enabled = true;
+    }
+
+    /// Wizard: go back to before the last player action on this level
+    ///
+    /// Returns: false if there's nothing to go back to.
+    pub fn debug_undo(&mut self) -> bool {
+        let snapshot = match self.undo.pop_back() {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+        self.wizard_used();
+
+        self.locations.insert(self.location_cur, LevelSlot::Loaded(snapshot.location));
+        self.turn = snapshot.turn;
+        self.rng = snapshot.rng;
+        self.ai_rng = snapshot.ai_rng;
+        self.queue = snapshot.queue;
+        self.acted = snapshot.acted;
+        self.replay.actions.truncate(snapshot.replay_len.0);
+        self.replay.hashes.truncate(snapshot.replay_len.1);
+        self.events.clear();
+        self.current_location_mut().take_player_events();
+
+        self.wizard_done();
+        true
+    }
+
+    /// The game as it is now, to go back to
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            location: self.current_location().clone(),
+            turn: self.turn,
+            rng: self.rng.clone(),
+            ai_rng: self.ai_rng.clone(),
+            queue: self.queue.clone(),
+            acted: self.acted.clone(),
+            replay_len: (self.replay.actions.len(), self.replay.hashes.len()),
+        }
+    }
+
+    fn save_undo(&mut self, snapshot: Snapshot) {
+        if self.undo.len() == UNDO_STEPS {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(snapshot);
+    }
+
+    /// Wizard: go one level down without looking for stairs
+    pub fn debug_descend(&mut self) {
+        self.wizard_used();
+        if let Some(to) = self.location_cur.below() {
+            self.travel(to);
+        }
+        self.wizard_done();
+    }
+
+    pub fn current_location(&self) -> &Location {
+        match self.locations[&self.location_cur] {
+            LevelSlot::Loaded(ref loc) => loc,
+            _ => panic!("current level {} isn't loaded", self.location_cur),
+        }
+    }
+
+    pub fn current_location_mut(&mut self) -> &mut Location {
+        match self.locations.get_mut(&self.location_cur) {
+            Some(&mut LevelSlot::Loaded(ref mut loc)) => loc,
+            _ => panic!("current level {} isn't loaded", self.location_cur),
+        }
+    }
+
+    /// Current location along with the RNG to drive it
+    fn current_location_rng(&mut self) -> (&mut Location, &mut GameRng) {
+        match self.locations.get_mut(&self.location_cur) {
+            Some(&mut LevelSlot::Loaded(ref mut loc)) => (loc, &mut self.rng),
+            _ => panic!("current level {} isn't loaded", self.location_cur),
+        }
+    }
+
+    /// Write the levels the player is away from to files in `dir`, to
+    /// keep memory in check on long games, and read them back on return
+    pub fn spill_levels_to(&mut self, dir: &Path) {
+        self.spill_dir = Some(dir.to_owned());
+        self.spill_cold_levels();
+    }
+
+    /// Levels in memory
+    pub fn levels_loaded(&self) -> usize {
+        self.locations
+            .values()
+            .filter(|slot| {
+                match **slot {
+                    LevelSlot::Loaded(_) => true,
+                    _ => false,
+                }
+            })
+            .count()
+    }
+
+    fn is_generated(&self, id: LevelId) -> bool {
+        match self.locations.get(&id) {
+            Some(&LevelSlot::Loaded(_)) | Some(&LevelSlot::OnDisk(_)) => true,
+            Some(&LevelSlot::Ungenerated) | None => false,
+        }
+    }
+
+    /// Level `id` as it would have been made in the first place
+    fn generate(&self, id: LevelId) -> Location {
+        Location::new(id, &mut derived_rng(self.replay.seed, id))
+    }
+
+    /// Have level `id` in memory: read back from disk, or made anew if
+    /// it never was or can't be read
+    fn load(&mut self, id: LevelId) {
+        let seed = self.replay.seed;
+        let loc = match self.locations.remove(&id) {
+            Some(LevelSlot::Loaded(loc)) => loc,
+            Some(LevelSlot::OnDisk(path)) => {
+                let kill_log = self.kill_logs.remove(&id).unwrap_or_default();
+                let read = fs::File::open(&path)
+                               .and_then(|f| Location::read_from(io::BufReader::new(f),
+                                                                 self.generate(id)));
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!("Can't remove {}: {}", path.display(), e);
+                }
+                let mut loc = match read {
+                    Ok(loc) => loc,
+                    Err(e) => {
+                        warn!("Can't read level {} back from {}, making it anew: {}",
+                              id,
+                              path.display(),
+                              e);
+                        let mut loc = self.generate(id);
+                        loc.turn = self.turn;
+                        loc
+                    }
+                };
+                loc.kill_log = kill_log;
+                loc
+            }
+            Some(LevelSlot::Ungenerated) | None => {
+                let mut loc = self.generate(id);
+                loc.turn = self.turn;
+                if let Some(i) = self.bones.iter().position(|b| b.level == id) {
+                    loc.haunt(self.bones.remove(i), &mut derived_rng(seed, (id, "bones")));
+                }
+                loc
+            }
+        };
+        self.locations.insert(id, LevelSlot::Loaded(loc));
+    }
+
+    /// Write out every level in memory other than the current one and
+    /// those its stairs lead to. A level that can't be written stays
+    /// in memory.
+    fn spill_cold_levels(&mut self) {
+        let dir = match self.spill_dir {
+            Some(ref dir) => dir.clone(),
+            None => return,
+        };
+
+        let near: Vec<LevelId> = {
+            let loc = self.current_location();
+            Some(loc.id)
+                .into_iter()
+                .chain(loc.map.iter().filter_map(|(_, t)| t.stairs_down().or(t.stairs_up())))
+                .collect()
+        };
+        let cold: Vec<LevelId> = self.locations
+                                     .iter()
+                                     .filter(|&(id, slot)| {
+                                         match *slot {
+                                             LevelSlot::Loaded(_) => !near.contains(id),
+                                             _ => false,
+                                         }
+                                     })
+                                     .map(|(&id, _)| id)
+                                     .collect();
+        if !cold.is_empty() {
+            if let Err(e) = fs::create_dir_all(&dir) {
+                warn!("Can't create {}, keeping every level in memory: {}", dir.display(), e);
+                return;
+            }
+        }
+
+        for id in cold {
+            let path = dir.join(format!("{}-{}", id.branch.name(), id.depth));
+            let mut loc = match self.locations.remove(&id) {
+                Some(LevelSlot::Loaded(loc)) => loc,
+                _ => unreachable!(),
+            };
+            let written = fs::File::create(&path).and_then(|f| {
+                let mut file = io::BufWriter::new(f);
+                try!(loc.write_to(&mut file, &self.generate(id)));
+                file.flush()
+            });
+            match written {
+                Ok(()) => {
+                    self.kill_logs.insert(id, ::std::mem::replace(&mut loc.kill_log, vec![]));
+                    self.locations.insert(id, LevelSlot::OnDisk(path));
+                }
+                Err(e) => {
+                    warn!("Can't write level {} to {}, keeping it in memory: {}",
+                          id,
+                          path.display(),
+                          e);
+                    let _ = fs::remove_file(&path);
+                    self.locations.insert(id, LevelSlot::Loaded(loc));
+                }
+            }
+        }
+    }
+
+    /// Move the player to another level, generating it if needed
+    fn travel(&mut self, to: LevelId) {
+        let from = self.location_cur;
+        let turn = self.turn;
+        self.current_location_mut().left_at = Some(turn);
+        let events = self.current_location_mut().take_player_events();
+        self.events.extend(events);
+
+        self.load(to);
+
+        let party = self.current_location_mut().take_party();
+        self.location_cur = to;
+        let left = {
+            let (loc, rng) = self.current_location_rng();
+            loc.catch_up(turn);
+            loc.place_party(party, from, rng)
+        };
+        // no room for them there; they stay where they were
+        for companion in left {
+            match self.locations.get_mut(&from) {
+                Some(&mut LevelSlot::Loaded(ref mut loc)) => loc.spawn(companion, &mut self.rng),
+                _ => unreachable!(),
+            };
+        }
+        // Actors left behind don't get to finish the turn
+        self.queue.clear();
+        self.acted.clear();
+        // no going back to another level
+        self.undo.clear();
+
+        self.arrived();
+        self.spill_cold_levels();
+        self.checkpoint();
+    }
+
+    /// Note the first visit to the current level, and the levels its
+    /// stairs lead to
+    fn arrived(&mut self) {
+        self.deepest = cmp::max(self.deepest, self.location_cur.absolute_depth());
+        let ahead: Vec<LevelId> = self.current_location()
+                                      .map
+                                      .iter()
+                                      .filter_map(|(_, t)| t.stairs_down().or(t.stairs_up()))
+                                      .collect();
+        for id in ahead {
+            self.locations.entry(id).or_insert(LevelSlot::Ungenerated);
+        }
+        if self.visited.insert(self.location_cur) {
+            let coord = self.player().pos.coord;
+            self.feelings = self.current_location().feelings(coord);
+        }
+    }
+
+    /// Everything the player noticed since last asked, oldest first
+    pub fn take_events_for_player(&mut self) -> Vec<Event> {
+        let mut events = ::std::mem::replace(&mut self.events, vec![]);
+        events.extend(self.current_location_mut().take_player_events());
+        events
+    }
+
+    /// Level feelings not yet shown to the player
+    pub fn take_feelings(&mut self) -> Vec<Feeling> {
+        ::std::mem::replace(&mut self.feelings, vec![])
+    }
+
+    /// Global clock, advanced once all the actors on the current
+    /// level have acted
+    pub fn turn(&self) -> u64 {
+        self.turn
+    }
+
+    /// Haunt a level with `bones` from another game; only before the
+    /// game starts
+    pub fn add_bones(&mut self, bones: Bones) {
+        assert!(self.replay.race.is_none());
+        if self.is_generated(bones.level) {
+            return;
+        }
+        self.replay.bones.push(bones.clone());
+        self.bones.push(bones);
+    }
+
+    /// What the dead player leaves for later games; nothing from the
+    /// first level, nor from games that don't count
+    pub fn bones(&self) -> Option<Bones> {
+        match self.result {
+            Some(GameResult::Died { .. }) if self.is_scored() => {}
+            _ => return None,
+        }
+        let loc = self.current_location();
+        if loc.id == LevelId::start() {
+            return None;
+        }
+
+        // the kill log knows what the player wore; the rest lies around
+        // the body
+        let player = self.player();
+        let worn = loc.kill_log
+                      .iter()
+                      .rev()
+                      .find(|r| r.id == loc.player_id())
+                      .map_or(vec![], |r| r.worn.clone());
+        let coord = player.pos.coord;
+        let mut items: Vec<_> = loc.items
+                                   .iter()
+                                   .filter(|&(&c, _)| c.distance(coord) <= 2)
+                                   .collect();
+        items.sort_by_key(|&(c, _)| (c.x, c.y));
+        let items = items.into_iter()
+                         .map(|(_, item)| item.clone())
+                         .filter(|item| !worn.iter().any(|w| Arc::ptr_eq(w, item)))
+                         .collect();
+        Some(Bones {
+            level: loc.id,
+            name: self.replay.name.clone(),
+            race: self.replay.race.unwrap(),
+            stats: Some(player.base_stats),
+            worn: worn,
+            items: items,
+            site: coord,
+            fragment: loc.fragment(coord),
+        })
+    }
+
+    /// Start the game with a player of `race` called `name`, and their
+    /// pony next to them
+    pub fn initial_spawn(&mut self, race: actor::Race, name: &str) {
+        let start = self.current_location().start;
+        let pos = util::random_pos(start.x, start.y, &mut self.rng);
+        let mut player = Actor::new(race, pos);
+        player.set_player();
+        player.name = Some(name.to_string());
+        for &type_ in &race.starting_items() {
+            player.pick_item(Arc::new(item::Item::new(type_, vec![])));
+        }
+        player.equip_free_slots();
+        self.replay.race = Some(race);
+        self.replay.name = name.to_string();
+
+        let (loc, rng) = self.current_location_rng();
+        loc.spawn_player(player, rng);
+        let spot = loc.companion_spots.first().cloned();
+        if let Some(spot) = spot {
+            let mut pony = Actor::new(actor::Race::Pony, util::random_pos(spot.x, spot.y, rng));
+            pony.companion = true;
+            loc.spawn(pony, rng);
+        }
+        self.arrived();
+        self.start_round();
+    }
+
+    pub fn needs_player_input(&self) -> bool {
+        let player_id = self.current_location().player_id();
+        self.result.is_none() && self.queue.front() == Some(&player_id) &&
+        !self.player().is_dead()
+    }
+
+    /// How the game ended, if it did
+    pub fn result(&self) -> Option<&GameResult> {
+        self.result.as_ref()
+    }
+
+    /// End the game at the player's request; it's kept for resuming
+    pub fn quit(&mut self) {
+        if self.result.is_none() {
+            self.result = Some(GameResult::Quit);
+            self.checkpoint();
+        }
+    }
+
+    /// Absolute depth of the deepest level the player got to
+    pub fn deepest(&self) -> u32 {
+        self.deepest
+    }
+
+    /// How many of each race the player killed, sorted by race name
+    pub fn kills(&self) -> Vec<(actor::Race, u32)> {
+        let mut kills: Vec<(actor::Race, u32)> = vec![];
+        let loaded = self.locations.values().filter_map(|slot| {
+            match *slot {
+                LevelSlot::Loaded(ref loc) => Some(&loc.kill_log),
+                _ => None,
+            }
+        });
+        for kill_log in loaded.chain(self.kill_logs.values()) {
+            for record in kill_log.iter().filter(|r| r.by_player) {
+                match kills.iter().position(|&(race, _)| race == record.race) {
+                    Some(i) => kills[i].1 += 1,
+                    None => kills.push((record.race, 1)),
+                }
+            }
+        }
+        kills.sort_by_key(|&(race, _)| race.description());
+        kills
+    }
+
+    /// Points the game is worth so far
+    pub fn score(&self) -> u64 {
+        score::score(self.deepest, self.turn, &self.kills(), self.player().gold)
+    }
+
+    /// Game over once the player is dead
+    fn check_result(&mut self) {
+        if self.result.is_some() || !self.player().is_dead() {
+            return;
+        }
+
+        let cause = {
+            let loc = self.current_location();
+            let player_id = loc.player_id();
+            let record = loc.kill_log.iter().find(|r| r.id == player_id);
+            let how = match record.and_then(|r| r.killer.as_ref()) {
+                Some(killer) => format!("killed by a {}", killer),
+                None => "died".to_string(),
+            };
+            let turn = record.map_or(self.turn, |r| r.killed_on_turn);
+            format!("{} on level {}, turn {}", how, loc.id, turn)
+        };
+        self.result = Some(GameResult::Died { cause: cause });
+    }
+
+    pub fn player(&self) -> &Actor {
+        self.current_location().player()
+    }
+
+    pub fn checks_after_act(&mut self, actor_id: actor::Id) {
+        if actor_id == self.current_location().player_id() {
+            if let Some(to) = self.current_location().player().traveled() {
+                self.travel(to);
+            }
+        }
+
+        // every round that everybody got through ends the turn, including
+        // the ones the player sat out with nobody else around
+        while self.queue.is_empty() {
+            self.end_turn();
+            self.start_round();
+            if self.player().can_act() {
+                break;
+            }
+            let player_id = self.current_location().player_id();
+            {
+                let (loc, rng) = self.current_location_rng();
+                loc.skip_act(player_id, rng);
+            }
+            if self.queue.front() == Some(&player_id) {
+                self.queue.pop_front();
+            }
+            self.took_turn(player_id);
+            if self.player().is_dead() {
+                break;
+            }
+        }
+
+        self.check_result();
+    }
+
+    /// Queue up everybody alive, the player first; once the player is
+    /// dead, there are no more rounds
+    fn start_round(&mut self) {
+        self.acted.clear();
+        self.queue.clear();
+        if self.player().is_dead() {
+            return;
+        }
+
+        let player_id = self.current_location().player_id();
+        let mut ids = self.current_location().actors_alive_ids();
+        ids.sort();
+        self.queue.push_back(player_id);
+        self.queue.extend(ids.into_iter().filter(|&id| id != player_id));
+    }
+
+    /// Note that `id` is done for this round
+    fn took_turn(&mut self, id: actor::Id) {
+        let first = self.acted.insert(id);
+        debug_assert!(first, "actor {} acted twice in a round", id);
+        self.update_queue();
+    }
+
+    /// Drop whoever died from the queue, and add whoever appeared, so they
+    /// still act this round
+    fn update_queue(&mut self) {
+        if self.player().is_dead() {
+            // game over
+            self.queue.clear();
+            return;
+        }
+
+        // the level alone, so the queue can change alongside
+        let loc = match self.locations[&self.location_cur] {
+            LevelSlot::Loaded(ref loc) => loc,
+            _ => panic!("current level {} isn't loaded", self.location_cur),
+        };
+        let alive = |id: &actor::Id| loc.actors_byid.get(id).map_or(false, |a| !a.is_dead());
+
+        self.queue.retain(|id| alive(id));
+
+        let mut new: Vec<actor::Id> = loc.actors_byid
+                                         .keys()
+                                         .filter(|id| alive(id))
+                                         .filter(|id| !self.acted.contains(id))
+                                         .filter(|id| !self.queue.contains(id))
+                                         .cloned()
+                                         .collect();
+        new.sort();
+        self.queue.extend(new);
+
+        debug_assert!(loc.actors_byid
+                         .keys()
+                         .filter(|id| alive(id))
+                         .all(|id| self.acted.contains(id) != self.queue.contains(id)),
+                      "every living actor acts exactly once a round");
+    }
+
+    /// Player's move, always the first one of a round
+    ///
+    /// Returns: false if the action was rejected and no time was spent.
+    pub fn player_act(&mut self, action: Action) -> bool {
+        assert!(self.needs_player_input());
+
+        // taken before acting, but only kept if the action goes through
+        let snapshot = if self.undo_enabled {
+            Some(self.snapshot())
+        } else {
+            None
+        };
+
+        if log_enabled!(target: "perf", LogLevel::Debug) {
+            self.turn_started = Some(Instant::now());
+        }
+        let player_id = self.current_location().player_id();
+        let turn = self.turn;
+
+        let acted = {
+            let (loc, rng) = self.current_location_rng();
+            loc.act(player_id, action, rng)
+        };
+        if !acted {
+            return false;
+        }
+        if let Some(snapshot) = snapshot {
+            self.save_undo(snapshot);
+        }
+        self.replay.actions.push((turn, action));
+
+        self.queue.pop_front();
+        self.took_turn(player_id);
+
+        self.checks_after_act(player_id);
+        true
+    }
+
+    // then everybody else one by one
+    pub fn one_actor_tick(&mut self) -> actor::Id {
+        assert!(!self.needs_player_input());
+
+        let actor_id = self.queue.pop_front().unwrap();
+
+        let player_id = self.current_location().player_id();
+        assert!(actor_id != player_id);
+
+        let can_act = self.current_location().actors_byid[&actor_id].can_act();
+        if can_act {
+            let think = Stopwatch::start();
+            let action = self.think(actor_id);
+            let (loc, rng) = self.current_location_rng();
+            think.stop(&mut loc.timings.ai);
+            loc.act(actor_id, action, rng);
+        } else {
+            let (loc, rng) = self.current_location_rng();
+            loc.skip_act(actor_id, rng);
+        }
+        self.took_turn(actor_id);
+
+        self.checks_after_act(actor_id);
+
+        actor_id
+    }
+
+    /// What monster `id` does next: `Wait` if its brain takes longer
+    /// than `ai_budget`, and roaming about from then on if that keeps
+    /// happening
+    fn think(&mut self, id: actor::Id) -> Action {
+        let key = (self.location_cur, id);
+        let reply = {
+            let loc = match self.locations[&self.location_cur] {
+                LevelSlot::Loaded(ref loc) => loc,
+                _ => panic!("current level {} isn't loaded", self.location_cur),
+            };
+            let rng = &mut self.ai_rng;
+            match self.brains.get_mut(&key) {
+                Some(brain) => ai::think(&mut **brain, id, loc, rng, self.ai_budget),
+                None => ai::think(&mut ai::Simple, id, loc, rng, self.ai_budget),
+            }
+        };
+
+        match reply {
+            Ok(action) => {
+                self.ai_timeouts.remove(&key);
+                action
+            }
+            Err(ai::Error::Timeout) => {
+                warn!("Actor {} on {} thought for over {:?}, waiting instead",
+                      id,
+                      self.location_cur,
+                      self.ai_budget);
+                let timeouts = {
+                    let timeouts = self.ai_timeouts.entry(key).or_insert(0);
+                    *timeouts += 1;
+                    *timeouts
+                };
+                if timeouts >= AI_TIMEOUTS {
+                    warn!("Actor {} on {} keeps timing out, it only roams from now on",
+                          id,
+                          self.location_cur);
+                    self.ai_timeouts.remove(&key);
+                    self.brains.insert(key, Box::new(ai::Roam));
+                }
+                Action::Wait
+            }
+        }
+    }
+
+    pub fn end_turn(&mut self) {
+        self.turn += 1;
+        let turn = self.turn;
+        {
+            let (loc, rng) = self.current_location_rng();
+            loc.post_turn(turn, rng);
+        }
+        if turn % HASH_TURNS == 0 {
+            let hash = self.state_hash();
+            self.replay.hashes.push((turn, hash));
+        }
+        if turn % AUTOSAVE_TURNS == 0 || self.player().is_dead() {
+            self.checkpoint();
+        }
+
+        if log_enabled!(target: "perf", LogLevel::Debug) {
+            let now = Instant::now();
+            if let Some(started) = self.turn_started {
+                debug!(target: "perf",
+                       "turn {}: {} us, {} actors",
+                       turn,
+                       timings::micros(now - started),
+                       self.current_location().actors_coord_to_id.len());
+            }
+            self.turn_started = Some(now);
+        }
+    }
+}
+
+impl Drop for Engine {
+    /// The levels on disk are no use to another game
+    fn drop(&mut self) {
+        for slot in self.locations.values() {
+            if let LevelSlot::OnDisk(ref path) = *slot {
+                if let Err(e) = fs::remove_file(path) {
+                    warn!("Can't remove {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use hex2d::{Angle, Coordinate, Direction, Position, ToCoordinate};
+    use rand::Rng;
+
+    use ai::Ai;
+    use game::{Action, Bones, LevelId, Location, PathOpts};
+    use game::actor::{self, Actor, Race};
+    use game::item::{self, Item};
+    use util::{self, GameRng};
+    use super::{Engine, LevelSlot, AI_TIMEOUTS};
+
+    /// A game on an open floor, with the player in its corner and
+    /// goblins at `goblins`
+    fn in_a_room(goblins: &[Coordinate]) -> Engine {
+        let mut engine = Engine::new(3);
+        {
+            let mut loc = Location::from_rows(&["##########",
+                                                "#........#",
+                                                "#........#",
+                                                "#........#",
+                                                "##########"]);
+            let rng = &mut engine.rng;
+            let mut player = Actor::new(Race::Human, Position::new(Coordinate::new(1, 1),
+                                                                   Direction::XZ));
+            player.set_player();
+            loc.spawn_player(player, rng);
+            for &coord in goblins {
+                loc.spawn(Actor::new(Race::Goblin, Position::new(coord, Direction::XZ)), rng);
+            }
+            engine.locations.insert(loc.id, LevelSlot::Loaded(loc));
+        }
+        engine.location_cur = LevelId::start();
+        engine.start_round();
+        engine
+    }
+
+    /// Everybody who got to act until it's the player's turn again
+    fn round(engine: &mut Engine) -> Vec<actor::Id> {
+        let mut ids = vec![];
+        while !engine.needs_player_input() {
+            ids.push(engine.one_actor_tick());
+        }
+        ids
+    }
+
+    /// Wait on an open floor, with two goblins that can't act and a
+    /// fire that can't spread, everybody poisoned
+    #[test]
+    fn every_turn_runs_each_subsystem_once() {
+        const TURNS: i32 = 20;
+        const LONG: i32 = 1000;
+
+        let mut engine = in_a_room(&[Coordinate::new(2, 2), Coordinate::new(3, 2)]);
+        let fire = Coordinate::new(5, 1);
+        {
+            let loc = engine.current_location_mut();
+            for actor in loc.actors_byid.values_mut() {
+                if !actor.is_player() {
+                    actor.action_cd = LONG;
+                }
+                actor.poisoned = LONG;
+                actor.hp = LONG;
+            }
+            loc.set_on_fire(fire, LONG as u32);
+        }
+
+        for _ in 0..TURNS {
+            engine.player_act(Action::Wait);
+            round(&mut engine);
+        }
+
+        let loc = engine.current_location();
+        assert_eq!(engine.turn(), TURNS as u64);
+        assert_eq!(loc.turn, TURNS as u64);
+        assert_eq!(loc.burning[&fire], (LONG - TURNS) as u32);
+        for actor in loc.actors_byid.values() {
+            assert_eq!(actor.poisoned, LONG - TURNS, "{:?}", actor.race);
+        }
+
+        // the rounds the player sits out count just the same: the one
+        // the wait was too early for, and the five after it
+        {
+            let loc = engine.current_location_mut();
+            let id = loc.player_id();
+            loc.actors_byid.get_mut(&id).unwrap().action_cd = 5;
+        }
+        engine.player_act(Action::Wait);
+        round(&mut engine);
+        assert_eq!(engine.turn(), TURNS as u64 + 6);
+        assert_eq!(engine.player().poisoned, LONG - TURNS - 6);
+        assert!(engine.player().can_act());
+    }
+
+    #[test]
+    fn somebody_appearing_mid_round_still_acts_in_it() {
+        let mut engine = in_a_room(&[Coordinate::new(7, 3)]);
+        let goblin = engine.queue[1];
+
+        engine.player_act(Action::Wait);
+        let rat = {
+            let (loc, rng) = engine.current_location_rng();
+            let pos = Position::new(Coordinate::new(7, 1), Direction::XZ);
+            loc.spawn(Actor::new(Race::Rat, pos), rng)
+        };
+        assert_eq!(round(&mut engine), vec![goblin, rat]);
+
+        engine.player_act(Action::Wait);
+        assert_eq!(round(&mut engine), vec![goblin, rat]);
+    }
+
+    #[test]
+    fn dying_mid_round_loses_the_turn() {
+        let mut engine = in_a_room(&[Coordinate::new(7, 3), Coordinate::new(7, 1)]);
+        let (first, second) = (engine.queue[1], engine.queue[2]);
+
+        engine.player_act(Action::Wait);
+        engine.current_location_mut().actors_byid.get_mut(&second).unwrap().hp = 0;
+        assert_eq!(round(&mut engine), vec![first]);
+
+        engine.player_act(Action::Wait);
+        assert_eq!(round(&mut engine), vec![first]);
+    }
+
+    /// Brain that always wants to walk, but takes its time deciding;
+    /// counts how often it's asked
+    struct Sleepy(Rc<Cell<u32>>);
+
+    impl Ai for Sleepy {
+        fn action(&mut self, _id: actor::Id, _loc: &Location, _rng: &mut GameRng) -> Action {
+            self.0.set(self.0.get() + 1);
+            thread::sleep(Duration::from_millis(20));
+            Action::Move(Angle::Forward)
+        }
+    }
+
+    #[test]
+    fn a_brain_that_thinks_too_long_waits_and_is_replaced() {
+        let mut engine = in_a_room(&[Coordinate::new(5, 3)]);
+        let goblin = engine.queue[1];
+        let asked = Rc::new(Cell::new(0));
+        engine.ai_budget = Duration::from_millis(5);
+        engine.brains.insert((engine.location_cur, goblin), Box::new(Sleepy(asked.clone())));
+        let pos = engine.current_location().actors_byid[&goblin].pos;
+
+        for _ in 0..AI_TIMEOUTS {
+            engine.player_act(Action::Wait);
+            assert_eq!(round(&mut engine), vec![goblin]);
+            assert_eq!(engine.current_location().actors_byid[&goblin].pos, pos);
+        }
+        assert_eq!(asked.get(), AI_TIMEOUTS);
+
+        // roaming about now, without asking the slow brain anymore
+        for _ in 0..5 {
+            engine.player_act(Action::Wait);
+            assert_eq!(round(&mut engine), vec![goblin]);
+        }
+        assert_eq!(asked.get(), AI_TIMEOUTS);
+    }
+
+    #[test]
+    fn rejected_actions_leave_nothing_to_undo() {
+        let mut engine = in_a_room(&[]);
+        engine.enable_undo();
+        engine.player_act(Action::Wait);
+        assert_eq!(engine.undo.len(), 1);
+
+        // no door behind the player to close
+        assert!(!engine.player_act(Action::Close(Direction::ZX)));
+        assert_eq!(engine.undo.len(), 1);
+
+        assert!(engine.debug_undo());
+        assert_eq!(engine.turn(), 0);
+        assert!(!engine.debug_undo());
+    }
+
+    #[test]
+    fn rejected_actions_are_not_recorded() {
+        let mut engine = Engine::new(5);
+        engine.initial_spawn(Race::Human, "test");
+        engine.player_act(Action::Wait);
+        engine.finish_round();
+
+        let coord = engine.player().coord();
+        let closed = *Direction::all()
+                          .iter()
+                          .find(|&&d| {
+                              let tile = *engine.current_location()
+                                                .at(coord + d.to_coordinate())
+                                                .tile();
+                              tile.door_state() != Some(true)
+                          })
+                          .unwrap();
+        let actions = engine.replay().actions.clone();
+        let state = engine.state_hash();
+
+        engine.player_act(Action::Close(closed));
+
+        assert!(engine.needs_player_input());
+        assert_eq!(engine.replay().actions, actions);
+        assert_eq!(engine.state_hash(), state);
+    }
+
+    #[test]
+    fn a_thousand_teleports_keep_everything_consistent() {
+        let mut engine = Engine::new(31);
+        engine.initial_spawn(Race::Human, "test");
+        let mut rng = util::game_rng(32);
+        let (min, max) = engine.current_location().bounds();
+
+        for i in 0..1000 {
+            let to = Coordinate::new(rng.gen_range(min.x, max.x + 1),
+                                     rng.gen_range(min.y, max.y + 1));
+            let from = engine.player().coord();
+            let updates = engine.player().perception.los_updates;
+            engine.debug_teleport(to);
+
+            let loc = engine.current_location();
+            loc.check_invariants();
+            let player = loc.player();
+            assert!(loc.at(player.coord()).tile().is_passable(), "teleport {}", i);
+            assert_eq!(loc.actors_coord_to_id.get(&player.coord()),
+                       Some(&loc.player_id()));
+            assert!(player.in_los(player.coord()), "teleport {}", i);
+            if player.coord() != from {
+                assert!(player.perception.los_updates > updates, "teleport {}", i);
+            }
+        }
+        assert!(!engine.is_scored());
+    }
+
+    /// What the player carries: letters and descriptions, in order
+    fn inventory(engine: &Engine) -> Vec<(char, String)> {
+        let player = engine.player();
+        let mut items: Vec<(char, String)> = player.items_backpack
+                                                   .iter()
+                                                   .map(|(&ch, item)| (ch, item.description()))
+                                                   .chain(player.items_equipped
+                                                                .values()
+                                                                .map(|&(ch, ref item)| {
+                                                                    (ch, item.description())
+                                                                }))
+                                                   .collect();
+        items.sort();
+        items
+    }
+
+    /// Put the player on the stairs to the level below, moving anybody
+    /// standing there out of the way
+    fn to_stairs_down(engine: &mut Engine) -> Coordinate {
+        let below = engine.location_cur.below().expect("no level below");
+        let (loc, rng) = engine.current_location_rng();
+        let stairs = loc.map
+                        .iter()
+                        .find(|&(_, tile)| tile.stairs_down() == Some(below))
+                        .map(|(&coord, _)| coord)
+                        .expect("no stairs down");
+
+        let id = loc.player_id();
+        let mut player = loc.remove(id).unwrap();
+        if let Some(&other) = loc.actors_coord_to_id.get(&stairs) {
+            loc.remove(other);
+        }
+        player.pos.coord = stairs;
+        loc.spawn_player(player, rng);
+        stairs
+    }
+
+    #[test]
+    fn five_levels_down_keep_inventory_and_hp() {
+        let mut engine = Engine::new(77);
+        engine.initial_spawn(Race::Dwarf, "test");
+        {
+            let loc = engine.current_location_mut();
+            let id = loc.player_id();
+            let player = loc.actors_byid.get_mut(&id).unwrap();
+            for &type_ in &[item::Type::Knife, item::Type::HealthPotion, item::Type::Helmet] {
+                assert!(player.pick_item(Arc::new(Item::new(type_, vec![]))).is_none());
+            }
+            player.hp -= 5;
+        }
+
+        for _ in 0..5 {
+            let depth = engine.current_location().id.absolute_depth();
+            let stairs = to_stairs_down(&mut engine);
+            assert_eq!(engine.player().pos.coord, stairs);
+            let items = inventory(&engine);
+            let hp = engine.player().hp;
+
+            // right after the turn the stairs were taken on, before anybody
+            // new gets to act
+            engine.player_act(Action::Descend);
+
+            assert_eq!(engine.current_location().id.absolute_depth(), depth + 1);
+            assert_eq!(inventory(&engine), items);
+            // a turn went by, to regenerate in
+            assert!(engine.player().hp == hp || engine.player().hp == hp + 1,
+                    "{} HP before, {} after",
+                    hp,
+                    engine.player().hp);
+        }
+    }
+
+    #[test]
+    fn the_pony_comes_along() {
+        let mut engine = Engine::new(77);
+        engine.initial_spawn(Race::Human, "test");
+        let pony = |engine: &Engine| {
+            let loc = engine.current_location();
+            loc.actors_byid
+               .values()
+               .find(|a| a.race == Race::Pony)
+               .map(|a| (a.companion, a.coord().distance(loc.player().coord())))
+        };
+        let pony_items = |engine: &Engine| {
+            let pony = engine.current_location()
+                             .actors_byid
+                             .values()
+                             .find(|a| a.race == Race::Pony)
+                             .unwrap();
+            let mut items: Vec<_> = pony.items_backpack
+                                        .iter()
+                                        .map(|(&ch, item)| (ch, item.description()))
+                                        .collect();
+            items.sort();
+            items
+        };
+        assert_eq!(pony(&engine), Some((true, 1)));
+
+        // a goblin well away from the player and the stairs stays behind,
+        // and the pony carries a knife
+        let start = engine.location_cur;
+        let coord = {
+            let loc = engine.current_location();
+            let player = loc.player().coord();
+            let stairs = loc.arrival_coord(start.below().unwrap());
+            let mut free: Vec<Coordinate> = loc.map
+                                               .iter()
+                                               .map(|(&c, _)| c)
+                                               .filter(|&c| {
+                                                   loc.at(c).is_passable() &&
+                                                   c.distance(player) > 3 &&
+                                                   c.distance(stairs) > 3
+                                               })
+                                               .collect();
+            free.sort_by_key(|c| (c.x, c.y));
+            free[0]
+        };
+        let goblin = {
+            let (loc, rng) = engine.current_location_rng();
+            loc.spawn(Actor::new(Race::Goblin, Position::new(coord, Direction::XZ)), rng)
+        };
+        let goblin_pos = engine.current_location().actors_byid[&goblin].pos;
+        {
+            let loc = engine.current_location_mut();
+            let pony = loc.actors_byid.values_mut().find(|a| a.race == Race::Pony).unwrap();
+            assert!(pony.pick_item(Arc::new(Item::new(item::Type::Knife, vec![]))).is_none());
+        }
+        let items = pony_items(&engine);
+
+        for _ in 0..3 {
+            let below = engine.location_cur.below().unwrap();
+            engine.travel(below);
+            assert_eq!(pony(&engine), Some((true, 1)));
+        }
+        for _ in 0..3 {
+            let above = engine.location_cur.above().unwrap();
+            engine.travel(above);
+            assert_eq!(pony(&engine), Some((true, 1)));
+        }
+
+        assert_eq!(engine.location_cur, start);
+        let goblin = &engine.current_location().actors_byid[&goblin];
+        assert!(!goblin.is_dead());
+        assert_eq!(goblin.pos, goblin_pos);
+        assert_eq!(pony_items(&engine), items);
+    }
+
+    #[test]
+    fn bones_bring_the_death_site_into_another_game() {
+        let mut dead = Engine::new(31);
+        dead.initial_spawn(Race::Human, "Urist");
+        let level = dead.location_cur.below().unwrap();
+        dead.travel(level);
+        dead.start_round();
+        {
+            let loc = dead.current_location_mut();
+            let id = loc.player_id();
+            let player = loc.actors_byid.get_mut(&id).unwrap();
+            for &type_ in &[item::Type::Helmet, item::Type::HealthPotion] {
+                assert!(player.pick_item(Arc::new(Item::new(type_, vec![]))).is_none());
+            }
+            player.equip_free_slots();
+            player.hp = 1;
+            player.poisoned = 1000;
+        }
+        for _ in 0..200 {
+            if dead.result().is_some() {
+                break;
+            }
+            assert!(dead.player_act(Action::Wait));
+            dead.finish_round();
+        }
+        assert!(dead.player().is_dead());
+
+        let bones = dead.bones().unwrap();
+        assert_eq!(bones.name, "Urist");
+        assert_eq!(bones.worn.len(), 1);
+        let lines = bones.lines();
+        let bones = Bones::parse(lines.iter().map(|l| &l[..])).unwrap().pop().unwrap();
+        assert_eq!(bones.lines(), lines);
+        let fragment = bones.fragment.clone();
+        assert!(fragment.iter().any(|&(d, _)| d == Coordinate::new(0, 0)));
+
+        let mut haunted = Engine::new(32);
+        haunted.add_bones(bones);
+        haunted.initial_spawn(Race::Human, "test");
+        haunted.travel(level);
+
+        let loc = haunted.current_location();
+        let corpse = Item::new(item::Type::Corpse, vec![]);
+        let graves: Vec<Coordinate> = loc.items
+                                         .iter()
+                                         .filter(|&(_, item)| **item == corpse)
+                                         .map(|(&c, _)| c)
+                                         .collect();
+        assert_eq!(graves.len(), 1);
+        let grave = graves[0];
+        assert!(loc.actors_byid.values().any(|a| a.race == Race::Ghost));
+
+        // the corpse lies on the spliced fragment, or right next to it if
+        // something was there already
+        let spliced = |center: Coordinate| {
+            fragment.iter().all(|&(d, ref tile)| {
+                let coord = Coordinate::new(center.x + d.x, center.y + d.y);
+                !loc.in_bounds(coord) || !tile.is_passable() ||
+                loc.at(coord).tile().is_passable()
+            }) &&
+            fragment.iter().any(|&(d, ref tile)| {
+                let mut here = *loc.at(center).tile();
+                here.area = None;
+                d == Coordinate::new(0, 0) && here == *tile
+            })
+        };
+        assert!(loc.map.iter().any(|(&c, _)| c.distance(grave) <= 1 && spliced(c)),
+                "no fragment around the corpse at {:?}",
+                grave);
+        assert!(loc.find_path(loc.start, grave, &PathOpts::default()).is_some());
+    }
+}
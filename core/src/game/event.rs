@@ -0,0 +1,97 @@
+use hex2d::Coordinate;
+
+use super::{actor, area, Heard};
+
+/// Something the player came across for the first time
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Discovery {
+    Areas(Vec<area::Type>),
+    Item(String),
+    /// Actors in sight for the first time, by race and name, if any
+    Actors(Vec<(actor::Race, Option<String>)>),
+    /// Item the player just stepped onto
+    Underfoot(String),
+    Stairs,
+    Fountain,
+    Altar,
+}
+
+/// Why an action came to nothing
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Rejection {
+    /// Still busy with the last action, for that many more turns
+    Recovering(i32),
+}
+
+/// Something that happened on a level, for observers to report
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Event {
+    Attack {
+        attacker: actor::Id,
+        target: actor::Id,
+        success: bool,
+        dmg: i32,
+        behind: bool,
+    },
+    Died {
+        id: actor::Id,
+    },
+    PickedUp {
+        id: actor::Id,
+        item: String,
+    },
+    DoorOpened {
+        id: actor::Id,
+        coord: Coordinate,
+    },
+    DoorClosed {
+        id: actor::Id,
+        coord: Coordinate,
+    },
+    /// The sun rose over the surface
+    Daybreak,
+    /// The sun set over the surface
+    Nightfall,
+    /// What came into the player's view
+    Discovered(Discovery),
+    /// Outcome of something actor `id` tried
+    Interacted {
+        id: actor::Id,
+        result: actor::InteractResult,
+    },
+    /// Noise the player heard from `coord` without seeing its source
+    Heard {
+        coord: Coordinate,
+        heard: Heard,
+    },
+    /// Actor `id` shot or threw something over `path`, in order
+    Projectile {
+        id: actor::Id,
+        path: Vec<Coordinate>,
+    },
+    /// Actor `id` tried to act, but couldn't
+    Rejected {
+        id: actor::Id,
+        reason: Rejection,
+    },
+}
+
+impl Event {
+    /// Is actor `id` taking part in it
+    pub fn involves(&self, id: actor::Id) -> bool {
+        match *self {
+            Event::Attack { attacker, target, .. } => attacker == id || target == id,
+            Event::Died { id: who } |
+            Event::PickedUp { id: who, .. } |
+            Event::DoorOpened { id: who, .. } |
+            Event::DoorClosed { id: who, .. } |
+            Event::Interacted { id: who, .. } |
+            Event::Projectile { id: who, .. } |
+            Event::Rejected { id: who, .. } => who == id,
+            // nobody misses that
+            Event::Daybreak | Event::Nightfall => true,
+            // only ever made for whoever noticed it
+            Event::Discovered(_) | Event::Heard { .. } => true,
+        }
+    }
+}
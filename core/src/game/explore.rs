@@ -0,0 +1,416 @@
+use hex2d::{Angle, Coordinate, Direction, Position};
+
+use super::{tile, Action, CoordSet, PathOpts};
+use super::actor::{Actor, Race};
+use super::location::Location;
+
+/// Why exploring stopped
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Stop {
+    /// Everything reachable is known
+    Done,
+    /// The rest can't be reached, or somebody stands in the way
+    Blocked,
+    /// A hostile came into view
+    Hostile(Race),
+    /// A trap turned up next to the way ahead
+    Trap,
+    /// Fire next to the way ahead
+    Fire,
+    /// The explorer lost health on the way
+    Hurt,
+}
+
+/// What to do next
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Step {
+    Act(Action),
+    Stop(Stop),
+}
+
+/// Walks an actor to what it doesn't know yet, picking up the items it
+/// sees on the way, until something calls for the player's attention.
+/// Lives as long as one go at exploring, to tell what's new.
+#[derive(Clone, Debug)]
+pub struct Explorer {
+    /// Health on the previous step
+    hp: i32,
+    /// Hazards seen already; only new ones are worth stopping for
+    hazards: CoordSet,
+    /// Items tried to pick up already, whether it worked or not
+    tried: CoordSet,
+    /// Which tiles around were passable after the last step of a run,
+    /// relative to the way it goes
+    pattern: Option<[bool; 6]>,
+    /// Items and features seen next to the way run so far
+    sights: CoordSet,
+    /// Steps run so far
+    ran: usize,
+}
+
+impl Explorer {
+    pub fn new(loc: &Location, actor: &Actor) -> Self {
+        let start = actor.pos.coord;
+        Explorer {
+            hp: actor.hp,
+            hazards: actor.perception
+                          .known
+                          .iter()
+                          .cloned()
+                          .filter(|&c| is_hazard(loc, actor, c))
+                          .collect(),
+            tried: Default::default(),
+            pattern: None,
+            sights: start.neighbors()
+                         .iter()
+                         .chain(Some(&start))
+                         .cloned()
+                         .filter(|&c| is_sight(loc, actor, c))
+                         .collect(),
+            ran: 0,
+        }
+    }
+
+    /// Next step for `actor` on `loc`
+    pub fn step(&mut self, loc: &Location, actor: &Actor) -> Step {
+        if let Some(stop) = self.interruption(loc, actor) {
+            return Step::Stop(stop);
+        }
+
+        let opts = path_opts(actor);
+        let start = actor.pos.coord;
+
+        // an item in sight first, then the nearest unknown tile
+        let items: CoordSet = actor.perception.visible
+                                                 .iter()
+                                                 .cloned()
+                                                 .filter(|&c| self.is_worth_picking(loc, c))
+                                                 .collect();
+        let to_item = if items.is_empty() {
+            None
+        } else {
+            loc.nearest_matching(start, |c| items.contains(&c), &opts)
+        };
+        let (path, to_item) = match to_item {
+            Some(path) => (path, true),
+            None => {
+                let path = loc.nearest_matching(start,
+                                                |c| {
+                                                    !actor.knows(c) &&
+                                                    loc.at(c).tile().is_passable()
+                                                },
+                                                &opts);
+                match path {
+                    Some(path) => (path, false),
+                    None => return Step::Stop(Stop::Done),
+                }
+            }
+        };
+
+        self.follow(loc, actor, &path, to_item)
+    }
+
+    /// Next step for `actor` on the way to `dest`, stopping for the same
+    /// reasons exploring does; `Stop::Done` once there
+    pub fn travel(&mut self, loc: &Location, actor: &Actor, dest: Coordinate) -> Step {
+        if let Some(stop) = self.interruption(loc, actor) {
+            return Step::Stop(stop);
+        }
+        match route(loc, actor, dest) {
+            Some(path) => self.follow(loc, actor, &path, false),
+            None => Step::Stop(Stop::Blocked),
+        }
+    }
+
+    /// Next turn of `actor` resting, stopping for the same reasons
+    /// exploring does; `Stop::Done` once healed, `Stop::Blocked` if
+    /// waiting won't heal what's left
+    pub fn rest(&mut self, loc: &Location, actor: &Actor) -> Step {
+        if let Some(stop) = self.interruption(loc, actor) {
+            return Step::Stop(stop);
+        }
+        let base = &actor.stats.base;
+        let hurt = actor.hp < base.max_hp;
+        if actor.sp < base.max_sp || (hurt && base.regeneration > 0) {
+            Step::Act(Action::Wait)
+        } else if hurt {
+            Step::Stop(Stop::Blocked)
+        } else {
+            Step::Stop(Stop::Done)
+        }
+    }
+
+    /// Next step of `actor` running straight ahead, stopping for the
+    /// same reasons exploring does, and where the way changes: a side
+    /// passage opens or closes, something new turns up next to it, or a
+    /// door was just passed; `Stop::Blocked` where it can't go on
+    pub fn run(&mut self, loc: &Location, actor: &Actor) -> Step {
+        if let Some(stop) = self.interruption(loc, actor) {
+            return Step::Stop(stop);
+        }
+
+        let start = actor.pos.coord;
+        let pattern = passable_around(loc, actor.pos);
+        let junction = self.pattern.map_or(false, |p| p != pattern);
+        self.pattern = Some(pattern);
+
+        let behind = start + (actor.pos.dir + Angle::Back);
+        let past_door = self.ran > 0 && loc.at(behind).tile().has_door();
+
+        let mut new_sight = false;
+        for &c in start.neighbors().iter().chain(Some(&start)) {
+            if is_sight(loc, actor, c) && self.sights.insert(c) {
+                new_sight = true;
+            }
+        }
+        if junction || past_door || new_sight {
+            return Step::Stop(Stop::Done);
+        }
+
+        let next = actor.head();
+        if let Some(stop) = self.new_hazard(loc, actor, &[next]) {
+            return Step::Stop(stop);
+        }
+        if !loc.at(next).tile().is_passable() || loc.at(next).is_occupied() {
+            return Step::Stop(Stop::Blocked);
+        }
+        self.ran += 1;
+        Step::Act(Action::Move(Angle::Forward))
+    }
+
+    /// Anything that should stop the walk before the next step
+    fn interruption(&mut self, loc: &Location, actor: &Actor) -> Option<Stop> {
+        if actor.hp < self.hp {
+            return Some(Stop::Hurt);
+        }
+        self.hp = actor.hp;
+
+        for &coord in &actor.perception.visible {
+            let hostile = loc.at(coord).actor_map_or(None, |a| {
+                if a.is_hostile() && !a.is_dead() {
+                    Some(a.race)
+                } else {
+                    None
+                }
+            });
+            if let Some(race) = hostile {
+                return Some(Stop::Hostile(race));
+            }
+        }
+        None
+    }
+
+    /// First step along `path`; picking up the item at its end if
+    /// `to_item`
+    fn follow(&mut self,
+              loc: &Location,
+              actor: &Actor,
+              path: &[Coordinate],
+              to_item: bool)
+              -> Step {
+        if let Some(stop) = self.new_hazard(loc, actor, path) {
+            return Step::Stop(stop);
+        }
+
+        let start = actor.pos.coord;
+        let next = match path.first() {
+            Some(&next) => next,
+            None => return Step::Stop(Stop::Done),
+        };
+        let dir = match start.direction_to_cw(next) {
+            Some(dir) => dir,
+            None => return Step::Stop(Stop::Blocked),
+        };
+
+        if dir != actor.pos.dir {
+            Step::Act(Action::Turn(dir - actor.pos.dir))
+        } else if to_item && path.len() == 1 {
+            self.tried.insert(next);
+            Step::Act(Action::Pick)
+        } else if path.len() == 1 && !loc.at(next).tile().is_passable() {
+            // next to it is as close as it gets
+            Step::Stop(Stop::Done)
+        } else if loc.at(next).is_occupied() {
+            Step::Stop(Stop::Blocked)
+        } else {
+            Step::Act(Action::Move(Angle::Forward))
+        }
+    }
+
+    /// Items that don't belong to a shop and weren't tried already
+    fn is_worth_picking(&self, loc: &Location, coord: Coordinate) -> bool {
+        let in_shop = loc.shop.as_ref().map_or(false, |s| s.contains(coord));
+        loc.at(coord).item().is_some() && !in_shop && !self.tried.contains(&coord) &&
+        !loc.at(coord).is_occupied()
+    }
+
+    /// A hazard next to `path` not seen before; from then on, it's seen
+    fn new_hazard(&mut self, loc: &Location, actor: &Actor, path: &[Coordinate]) -> Option<Stop> {
+        let mut found = None;
+        for &coord in path {
+            for &c in coord.neighbors().iter().chain(Some(&coord)) {
+                if is_hazard(loc, actor, c) && self.hazards.insert(c) && found.is_none() {
+                    found = Some(if loc.is_burning(c) {
+                        Stop::Fire
+                    } else {
+                        Stop::Trap
+                    });
+                }
+            }
+        }
+        found
+    }
+}
+
+/// How `actor` walks on its own: only where it's been, around hazards
+fn path_opts(actor: &Actor) -> PathOpts {
+    PathOpts {
+        avoid_hazards: true,
+        known_by: Some(actor),
+        ..Default::default()
+    }
+}
+
+/// The way `actor` knows to `dest`, in steps; `None` if it knows none
+pub fn route(loc: &Location, actor: &Actor, dest: Coordinate) -> Option<Vec<Coordinate>> {
+    loc.nearest_matching(actor.pos.coord, |c| c == dest, &path_opts(actor))
+}
+
+/// Which of the tiles around `pos` are passable, starting with the one
+/// ahead
+fn passable_around(loc: &Location, pos: Position) -> [bool; 6] {
+    let mut passable = [false; 6];
+    let turns = Direction::all().iter().position(|&d| d == pos.dir).unwrap();
+    for (i, &dir) in Direction::all().iter().cycle().skip(turns).take(6).enumerate() {
+        passable[i] = loc.at(pos.coord + dir).tile().is_passable();
+    }
+    passable
+}
+
+/// An item or a fixture `actor` sees; doors and traps are left to the
+/// run and hazard checks
+fn is_sight(loc: &Location, actor: &Actor, coord: Coordinate) -> bool {
+    actor.sees(coord) &&
+    (loc.at(coord).item().is_some() ||
+     loc.at(coord).tile().feature_on(tile::Layer::Fixture).is_some())
+}
+
+/// A trap `actor` knows of, or fire it sees
+fn is_hazard(loc: &Location, actor: &Actor, coord: Coordinate) -> bool {
+    let trap = match loc.at(coord).tile().feature_on(tile::Layer::Trap) {
+        Some(tile::Trap(_)) => actor.knows(coord),
+        _ => false,
+    };
+    trap || (actor.sees(coord) && loc.is_burning(coord))
+}
+
+#[cfg(test)]
+mod tests {
+    use hex2d::{Angle, Coordinate, Position};
+
+    use game::{tile, Action, Location};
+    use game::actor::{Actor, Race};
+    use util;
+    use super::{Explorer, Step, Stop};
+
+    /// A corridor along `y = 1`, with a dead end below its middle, at
+    /// `(3, 2)`, drawn by `below`
+    fn corridor(below: char) -> Location {
+        let below = format!("###{}###", below);
+        Location::from_rows(&["#######", "#.....#", &below[..], "#######"])
+    }
+
+    /// The player at the west end of the corridor facing east, knowing and
+    /// seeing the `known` tiles
+    fn explorer(known: &[Coordinate]) -> Actor {
+        let from = Coordinate::new(1, 1);
+        let dir = from.direction_to_cw(Coordinate::new(2, 1)).unwrap();
+        let mut actor = Actor::new(Race::Human, Position::new(from, dir));
+        actor.set_player();
+        actor.perception.known = known.iter().cloned().collect();
+        actor.perception.visible = actor.perception.known.clone();
+        actor
+    }
+
+    /// The corridor up to `x`
+    fn up_to(x: i32) -> Vec<Coordinate> {
+        (1..x + 1).map(|x| Coordinate::new(x, 1)).collect()
+    }
+
+    #[test]
+    fn walks_to_what_it_does_not_know() {
+        let loc = corridor('#');
+        let actor = explorer(&up_to(3));
+        let mut explorer = Explorer::new(&loc, &actor);
+        assert_eq!(explorer.step(&loc, &actor), Step::Act(Action::Move(Angle::Forward)));
+    }
+
+    #[test]
+    fn stops_once_everything_is_known() {
+        let loc = corridor('.');
+        let known: Vec<Coordinate> = loc.map.iter().map(|(&c, _)| c).collect();
+        let actor = explorer(&known);
+        let mut explorer = Explorer::new(&loc, &actor);
+        assert_eq!(explorer.step(&loc, &actor), Step::Stop(Stop::Done));
+    }
+
+    #[test]
+    fn stops_when_hurt() {
+        let loc = corridor('#');
+        let mut actor = explorer(&up_to(3));
+        let mut explorer = Explorer::new(&loc, &actor);
+        actor.hp -= 1;
+        assert_eq!(explorer.step(&loc, &actor), Step::Stop(Stop::Hurt));
+    }
+
+    #[test]
+    fn stops_on_seeing_a_hostile() {
+        let mut loc = corridor('#');
+        let goblin = Coordinate::new(4, 1);
+        let dir = goblin.direction_to_cw(Coordinate::new(3, 1)).unwrap();
+        loc.spawn(Actor::new(Race::Goblin, Position::new(goblin, dir)),
+                  &mut util::game_rng(0));
+        let actor = explorer(&up_to(4));
+        let mut explorer = Explorer::new(&loc, &actor);
+        assert_eq!(explorer.step(&loc, &actor), Step::Stop(Stop::Hostile(Race::Goblin)));
+    }
+
+    #[test]
+    fn stops_when_somebody_stands_in_the_way() {
+        let mut loc = corridor('#');
+        let keeper = Coordinate::new(2, 1);
+        let dir = keeper.direction_to_cw(Coordinate::new(1, 1)).unwrap();
+        loc.spawn(Actor::new(Race::Shopkeeper, Position::new(keeper, dir)),
+                  &mut util::game_rng(0));
+        let actor = explorer(&up_to(3));
+        let mut explorer = Explorer::new(&loc, &actor);
+        assert_eq!(explorer.step(&loc, &actor), Step::Stop(Stop::Blocked));
+    }
+
+    #[test]
+    fn stops_once_for_a_trap_found_next_to_the_way() {
+        let mut loc = corridor('.');
+        let trap = Coordinate::new(3, 2);
+        loc.tile_mut(trap).add_feature(tile::Trap(tile::Trap::Dart));
+        let mut actor = explorer(&up_to(4));
+        let mut explorer = Explorer::new(&loc, &actor);
+
+        actor.perception.known.insert(trap);
+        assert_eq!(explorer.step(&loc, &actor), Step::Stop(Stop::Trap));
+        // seen already; on with exploring
+        assert_eq!(explorer.step(&loc, &actor), Step::Act(Action::Move(Angle::Forward)));
+    }
+
+    #[test]
+    fn stops_for_fire_next_to_the_way() {
+        let mut loc = corridor('\'');
+        let door = Coordinate::new(3, 2);
+        let mut known = up_to(4);
+        known.push(door);
+        let actor = explorer(&known);
+        let mut explorer = Explorer::new(&loc, &actor);
+
+        assert!(loc.ignite(door));
+        assert_eq!(explorer.step(&loc, &actor), Step::Stop(Stop::Fire));
+    }
+}
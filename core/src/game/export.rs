@@ -0,0 +1,166 @@
+//! A level written out whole, to look at what the generator made or
+//! to pass an interesting one on.
+//!
+//! The JSON is one object, one tile, actor or item to a line so two
+//! dumps compare line by line:
+//!
+//! ```text
+//! {"schema": 1,
+//!  "level": "Dungeon:1",
+//!  "tiles": [
+//!   {"q": 0, "r": 0, "type": "empty", "features": ["open door"], "light": 3},
+//!   ...],
+//!  "actors": [
+//!   {"id": 1, "q": 4, "r": -2, "race": "rat", "hp": 5},
+//!   ...],
+//!  "items": [
+//!   {"q": 2, "r": 7, "category": "weapon", "description": "knife"},
+//!   ...]}
+//! ```
+//!
+//! Coordinates are axial: `q` is the hex2d `x` and `r` its `y`. Tiles go
+//! row by row, by `r` then `q`; actors by id; items as tiles do.
+
+use hex2d::Coordinate;
+
+use super::Location;
+
+/// Bumped when the JSON changes in a way readers would notice
+pub const SCHEMA: u32 = 1;
+
+impl Location {
+    /// The whole map with what's on it as JSON, whatever anyone knows of it
+    pub fn to_json(&self) -> String {
+        let mut coords: Vec<Coordinate> = self.map.iter().map(|(&c, _)| c).collect();
+        coords.sort_by_key(|c| (c.y, c.x));
+
+        let tiles: Vec<String> =
+            coords.iter()
+                  .map(|&c| {
+                      let at = self.at(c);
+                      let tile = at.tile();
+                      let features: Vec<String> = tile.features()
+                                                      .iter()
+                                                      .map(|f| string(f.description()))
+                                                      .collect();
+                      format!("  {{\"q\": {}, \"r\": {}, \"type\": {}, \"features\": [{}], \
+                               \"light\": {}}}",
+                              c.x,
+                              c.y,
+                              string(&format!("{:?}", tile.type_).to_lowercase()),
+                              features.join(", "),
+                              at.light())
+                  })
+                  .collect();
+
+        let mut ids: Vec<_> = self.actors_byid.keys().cloned().collect();
+        ids.sort();
+        let actors: Vec<String> = ids.iter()
+                                     .map(|id| {
+                                         let a = &self.actors_byid[id];
+                                         format!("  {{\"id\": {}, \"q\": {}, \"r\": {}, \
+                                                  \"race\": {}, \"hp\": {}}}",
+                                                 id,
+                                                 a.pos.coord.x,
+                                                 a.pos.coord.y,
+                                                 string(&a.race.description()),
+                                                 a.hp)
+                                     })
+                                     .collect();
+
+        let mut item_coords: Vec<Coordinate> = self.items.keys().cloned().collect();
+        item_coords.sort_by_key(|c| (c.y, c.x));
+        let items: Vec<String> = item_coords.iter()
+                                            .map(|c| {
+                                                let item = &self.items[c];
+                                                let category = format!("{:?}", item.category());
+                                                format!("  {{\"q\": {}, \"r\": {}, \
+                                                         \"category\": {}, \
+                                                         \"description\": {}}}",
+                                                        c.x,
+                                                        c.y,
+                                                        string(&category.to_lowercase()),
+                                                        string(&item.description()))
+                                            })
+                                            .collect();
+
+        format!("{{\"schema\": {},\n \"level\": {},\n \"tiles\": [\n{}],\n \"actors\": [\n{}],\n \
+                 \"items\": [\n{}]}}\n",
+                SCHEMA,
+                string(&self.id.to_string()),
+                tiles.join(",\n"),
+                actors.join(",\n"),
+                items.join(",\n"))
+    }
+}
+
+/// `s` as a JSON string, quotes and all
+fn string(s: &str) -> String {
+    let mut out = "\"".to_string();
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hex2d::{Coordinate, Direction, Position};
+
+    use game::actor::{Actor, Race};
+    use game::item::{self, Item};
+    use game::{LightMap, Location};
+    use util;
+    use super::{string, SCHEMA};
+
+    /// A small level as schema 1 has it; a change here is a change for
+    /// whoever reads the dumps
+    const SNAPSHOT: &'static str = r#"{"schema": 1,
+ "level": "Dungeon:1",
+ "tiles": [
+  {"q": 0, "r": 0, "type": "wall", "features": [], "light": 1},
+  {"q": 1, "r": 0, "type": "empty", "features": [], "light": 1},
+  {"q": 2, "r": 0, "type": "empty", "features": ["open door"], "light": 1},
+  {"q": 0, "r": 1, "type": "wall", "features": [], "light": 1},
+  {"q": 1, "r": 1, "type": "empty", "features": [], "light": 5},
+  {"q": 2, "r": 1, "type": "empty", "features": [], "light": 1}],
+ "actors": [
+  {"id": 0, "q": 1, "r": 0, "race": "rat", "hp": 5}],
+ "items": [
+  {"q": 1, "r": 1, "category": "weapon", "description": "knife"}]}
+"#;
+
+    #[test]
+    fn json_matches_the_snapshot() {
+        let mut loc = Location::from_rows(&["#.'",
+                                            "#.."]);
+        loc.spawn(Actor::new(Race::Rat, Position::new(Coordinate::new(1, 0), Direction::XY)),
+                  &mut util::game_rng(0));
+        loc.place_item(Coordinate::new(1, 1), Arc::new(Item::new(item::Type::Knife, vec![])));
+        let mut light = LightMap::new();
+        for (&c, _) in loc.map.iter() {
+            light[c] = 0;
+        }
+        light[Coordinate::new(1, 1)] = 5;
+        loc.light_map = light;
+        loc.ambient_light = 1;
+
+        assert_eq!(SCHEMA, 1);
+        assert_eq!(loc.to_json(), SNAPSHOT);
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(string("plain"), "\"plain\"");
+        assert_eq!(string("a \"b\"\\c\nd\u{1}"), r#""a \"b\"\\c\nd\u0001""#);
+    }
+}
@@ -0,0 +1,119 @@
+//! The first line of every file the game keeps: what's in it, the
+//! version of the format and the rhex that wrote it.
+//!
+//! ```text
+//! rhex replay 5 0.0.3
+//! ```
+//!
+//! A reader takes its own version, and older ones it has a migration
+//! for, and refuses anything else with a message saying why. The rest
+//! of the file is keyed lines, and lines with a key the reader doesn't
+//! know are skipped, so a new line doesn't need a new version; a line
+//! that changes meaning does.
+
+use std::io::{self, BufRead};
+
+/// Rewrites the lines after the header from one version to the next;
+/// `None` if they can't be
+pub type Migration = fn(Vec<String>) -> Option<Vec<String>>;
+
+/// One kind of file the game writes
+pub struct FileFormat {
+    /// The second word of the header
+    pub kind: &'static str,
+    /// Version written
+    pub version: u32,
+    /// `migrations[i]` takes `version - migrations.len() + i` to the
+    /// next version
+    pub migrations: &'static [Migration],
+}
+
+/// Why a file wasn't read
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Refusal {
+    /// Not this kind of file, or no header at all
+    Foreign,
+    /// From before the oldest version there's a migration for
+    TooOld(u32),
+    /// From a later rhex, given with the version it said it was
+    TooNew(u32, Option<String>),
+    /// A migration gave up on it
+    Unmigrated(u32),
+}
+
+impl FileFormat {
+    pub fn header(&self) -> String {
+        format!("rhex {} {} {}", self.kind, self.version, env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Oldest version that can still be read
+    pub fn oldest(&self) -> u32 {
+        self.version - self.migrations.len() as u32
+    }
+
+    /// Returns: the version of the file `header` starts; headers written
+    /// before the rhex version was added to them do too.
+    pub fn check(&self, header: &str) -> Result<u32, Refusal> {
+        let words: Vec<&str> = header.split(' ').collect();
+        if words.len() < 3 || words.len() > 4 || words[0] != "rhex" || words[1] != self.kind {
+            return Err(Refusal::Foreign);
+        }
+        let version: u32 = try!(words[2].parse().map_err(|_| Refusal::Foreign));
+        if version > self.version {
+            Err(Refusal::TooNew(version, words.get(3).map(|w| w.to_string())))
+        } else if version < self.oldest() {
+            Err(Refusal::TooOld(version))
+        } else {
+            Ok(version)
+        }
+    }
+
+    /// Bring `lines`, from after a header of `version`, up to date
+    pub fn migrate(&self, version: u32, lines: Vec<String>) -> Result<Vec<String>, Refusal> {
+        let mut lines = lines;
+        for v in version..self.version {
+            let migration = self.migrations[(v - self.oldest()) as usize];
+            lines = try!(migration(lines).ok_or(Refusal::Unmigrated(v)));
+        }
+        Ok(lines)
+    }
+
+    /// The lines after the header of `r`, as the current version has
+    /// them. Nothing is written, whatever the outcome.
+    pub fn read<R: BufRead>(&self, r: R) -> io::Result<Vec<String>> {
+        let mut lines: Vec<String> = try!(r.lines().collect());
+        if lines.is_empty() {
+            return Err(self.refuse(Refusal::Foreign));
+        }
+        let rest = lines.split_off(1);
+        let version = try!(self.check(&lines[0]).map_err(|r| self.refuse(r)));
+        self.migrate(version, rest).map_err(|r| self.refuse(r))
+    }
+
+    /// `refusal` as the error to report
+    pub fn refuse(&self, refusal: Refusal) -> io::Error {
+        let (kind, version) = (self.kind, self.version);
+        let message = match refusal {
+            Refusal::Foreign => format!("not a {} file", kind),
+            Refusal::TooOld(v) => {
+                format!("{} format {} is too old; this rhex reads {} to {}",
+                        kind,
+                        v,
+                        self.oldest(),
+                        version)
+            }
+            Refusal::TooNew(v, by) => {
+                format!("{} format {} is from a newer rhex ({}); this one ({}) reads up to {}",
+                        kind,
+                        v,
+                        by.unwrap_or_else(|| "unknown".to_string()),
+                        env!("CARGO_PKG_VERSION"),
+                        version)
+            }
+            Refusal::Unmigrated(v) => {
+                format!("{} can't be brought from format {} to {}", kind, v, version)
+            }
+        };
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+}
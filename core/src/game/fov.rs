@@ -0,0 +1,346 @@
+//! What can be seen, or lit, from a tile: shadowcasting on the hex grid.
+//!
+//! Around the origin the grid splits into six sextants, each between two
+//! neighboring directions. Ring `k` of a sextant is `k + 1` tiles, the
+//! `i`-th of them `i / k` of the way from one side to the other, and so
+//! spanning from `(2i - 1) / 2k` to `(2i + 1) / 2k` of it. A tile is in
+//! sight if the line between its center and the origin's only crosses
+//! tiles that don't block (through the corner of two tiles, either one
+//! will do), or if it blocks and some of it is in sight.
+//!
+//! That line is the same seen from either end, so sight is symmetric:
+//! of two tiles that don't block, within the same range of each other,
+//! either each sees the other or neither does.
+//!
+//! Tiles that dim rather than block, like statues, take as much off the
+//! range past them as they are more opaque than the floor, counted along
+//! the same line.
+//!
+//! Built with the `sweep_los` feature, the sweep of `hex2d-dpcext` is
+//! used instead, to compare the two.
+
+use std::cmp;
+
+use hex2d::{Angle, Coordinate, Direction, ToCoordinate};
+#[cfg(feature = "sweep_los")]
+use hex2dext::algo;
+use num::integer::Integer;
+
+/// Tiles more opaque than this hide what's behind them; the others
+/// only dim it
+pub const BLOCKING: i32 = 10;
+
+/// What someone at `pos` facing `dir` has in sight, up to `range`:
+/// `visible` gets every tile in sight, with how much of the range is
+/// left there
+#[cfg(not(feature = "sweep_los"))]
+pub fn ahead<O, V>(opaque: &O, visible: &mut V, range: i32, pos: Coordinate, dir: Direction)
+    where O: Fn(Coordinate) -> i32,
+          V: FnMut(Coordinate, i32)
+{
+    los(opaque, visible, range, pos, &[dir]);
+}
+
+#[cfg(feature = "sweep_los")]
+pub fn ahead<O, V>(opaque: &O, visible: &mut V, range: i32, pos: Coordinate, dir: Direction)
+    where O: Fn(Coordinate) -> i32,
+          V: FnMut(Coordinate, i32)
+{
+    algo::los2::los(opaque, visible, range, pos, &[dir]);
+}
+
+/// What a light of `range` at `pos` reaches, in every direction
+#[cfg(not(feature = "sweep_los"))]
+pub fn around<O, V>(opaque: &O, visible: &mut V, range: i32, pos: Coordinate)
+    where O: Fn(Coordinate) -> i32,
+          V: FnMut(Coordinate, i32)
+{
+    los(opaque, visible, range, pos, Direction::all());
+}
+
+#[cfg(feature = "sweep_los")]
+pub fn around<O, V>(opaque: &O, visible: &mut V, range: i32, pos: Coordinate)
+    where O: Fn(Coordinate) -> i32,
+          V: FnMut(Coordinate, i32)
+{
+    algo::los::los(opaque, visible, range, pos, Direction::all());
+}
+
+/// Shadowcast from `pos` over the sextants on either side of each of
+/// `dirs`, up to `range`; `visible` gets the tiles in sight, some more
+/// than once, with the range left there, never below zero
+pub fn los<O, V>(opaque: &O, visible: &mut V, range: i32, pos: Coordinate, dirs: &[Direction])
+    where O: Fn(Coordinate) -> i32,
+          V: FnMut(Coordinate, i32)
+{
+    if range < 0 {
+        return;
+    }
+    visible(pos, range);
+
+    let mut sides: Vec<Direction> = vec![];
+    for &dir in dirs {
+        for &side in &[dir + Angle::Left, dir] {
+            if !sides.contains(&side) {
+                sides.push(side);
+            }
+        }
+    }
+    for side in sides {
+        Sextant::new(pos, side).cast(opaque, visible, range);
+    }
+}
+
+/// How far across a sextant, as a fraction
+#[derive(Copy, Clone, Debug)]
+struct Slope {
+    num: i32,
+    den: i32,
+}
+
+impl Slope {
+    fn new(num: i32, den: i32) -> Slope {
+        Slope { num: num, den: den }
+    }
+
+    /// First tile of ring `k` whose center isn't short of the slope,
+    /// rounding halves up
+    fn first(&self, k: i32) -> i32 {
+        (2 * k * self.num + self.den).div_floor(&(2 * self.den))
+    }
+
+    /// Last tile of ring `k` whose center isn't past the slope, rounding
+    /// halves down
+    fn last(&self, k: i32) -> i32 {
+        -(self.den - 2 * k * self.num).div_floor(&(2 * self.den))
+    }
+
+    /// The `i`-th tile of ring `k` has its center at or past the slope
+    fn below(&self, k: i32, i: i32) -> bool {
+        i * self.den >= k * self.num
+    }
+
+    /// The `i`-th tile of ring `k` has its center at or short of the slope
+    fn above(&self, k: i32, i: i32) -> bool {
+        i * self.den <= k * self.num
+    }
+}
+
+/// The part of the grid between two neighboring directions from `pos`
+struct Sextant {
+    pos: Coordinate,
+    /// To the first tile of the next ring
+    out: Coordinate,
+    /// To the next tile of the same ring
+    across: Coordinate,
+}
+
+impl Sextant {
+    /// The one starting from `side`, clockwise
+    fn new(pos: Coordinate, side: Direction) -> Sextant {
+        let out = side.to_coordinate();
+        let next = (side + Angle::Right).to_coordinate();
+        Sextant {
+            pos: pos,
+            out: out,
+            across: Coordinate::new(next.x - out.x, next.y - out.y),
+        }
+    }
+
+    /// The `i`-th tile of ring `k`
+    fn at(&self, k: i32, i: i32) -> Coordinate {
+        Coordinate::new(self.pos.x + k * self.out.x + i * self.across.x,
+                        self.pos.y + k * self.out.y + i * self.across.y)
+    }
+
+    fn cast<O, V>(&self, opaque: &O, visible: &mut V, range: i32)
+        where O: Fn(Coordinate) -> i32,
+              V: FnMut(Coordinate, i32)
+    {
+        // parts of the ring in sight, as slopes
+        let mut rows = vec![(Slope::new(0, 1), Slope::new(1, 1))];
+        // whether any tile passed might dim what's behind it
+        let mut dim = false;
+
+        for k in 1..range + 1 {
+            let mut next = vec![];
+            for &(start, end) in &rows {
+                let mut start = start;
+                let mut prev_blocks = None;
+                for i in start.first(k)..end.last(k) + 1 {
+                    let c = self.at(k, i);
+                    let opaqueness = opaque(c);
+                    let blocks = opaqueness > BLOCKING;
+
+                    if blocks || (start.below(k, i) && end.above(k, i)) {
+                        let dimmed = if dim {
+                            self.dimming(opaque, k, i)
+                        } else {
+                            0
+                        };
+                        if range - k - dimmed >= 0 {
+                            visible(c, range - k - dimmed);
+                        }
+                    }
+                    if !blocks && opaqueness > 1 {
+                        dim = true;
+                    }
+
+                    match (prev_blocks, blocks) {
+                        (Some(true), false) => start = Slope::new(2 * i - 1, 2 * k),
+                        (Some(false), true) => next.push((start, Slope::new(2 * i - 1, 2 * k))),
+                        _ => {}
+                    }
+                    prev_blocks = Some(blocks);
+                }
+                if prev_blocks == Some(false) {
+                    next.push((start, end));
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            rows = next;
+        }
+    }
+
+    /// Range taken off by the tiles on the line to the `i`-th tile of
+    /// ring `k`; through a corner, the clearer side counts
+    fn dimming<O>(&self, opaque: &O, k: i32, i: i32) -> i32
+        where O: Fn(Coordinate) -> i32
+    {
+        let cost = |c: Coordinate| {
+            let opaqueness = opaque(c);
+            if opaqueness > BLOCKING {
+                0
+            } else {
+                cmp::max(opaqueness - 1, 0)
+            }
+        };
+        let mut total = 0;
+        for j in 1..k {
+            let (q, r) = (i * j / k, i * j % k);
+            total += if 2 * r == k {
+                cmp::min(cost(self.at(j, q)), cost(self.at(j, q + 1)))
+            } else if 2 * r < k {
+                cost(self.at(j, q))
+            } else {
+                cost(self.at(j, q + 1))
+            };
+        }
+        total
+    }
+}
+
+#[cfg(all(test, not(feature = "sweep_los")))]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use hex2d::Coordinate;
+    use rand::Rng;
+
+    use util;
+    use super::around;
+
+    const WALL: i32 = 100;
+    const FLOOR: i32 = 1;
+
+    /// Every coordinate within `radius` of the origin
+    fn hexagon(radius: i32) -> Vec<Coordinate> {
+        let origin = Coordinate::new(0, 0);
+        let mut coords = vec![];
+        for y in -radius..radius + 1 {
+            for x in -radius..radius + 1 {
+                let c = Coordinate::new(x, y);
+                if origin.distance(c) <= radius {
+                    coords.push(c);
+                }
+            }
+        }
+        coords
+    }
+
+    /// What's in sight from `pos` on `map`, walls all around it
+    fn in_sight(map: &HashMap<Coordinate, i32>,
+                range: i32,
+                pos: Coordinate)
+                -> HashSet<Coordinate> {
+        let mut seen = HashSet::new();
+        around(&|c| map.get(&c).cloned().unwrap_or(WALL),
+               &mut |c, _| {
+                   seen.insert(c);
+               },
+               range,
+               pos);
+        seen
+    }
+
+    #[test]
+    fn sight_is_symmetric_on_random_maps() {
+        const RANGE: i32 = 6;
+        for seed in 1..21 {
+            let mut rng = util::game_rng(seed);
+            let map: HashMap<Coordinate, i32> = hexagon(8)
+                                                    .into_iter()
+                                                    .map(|c| {
+                                                        let wall = rng.gen_weighted_bool(4);
+                                                        (c, if wall { WALL } else { FLOOR })
+                                                    })
+                                                    .collect();
+            let mut floor: Vec<Coordinate> = map.iter()
+                                                .filter(|&(_, &o)| o == FLOOR)
+                                                .map(|(&c, _)| c)
+                                                .collect();
+            floor.sort_by_key(|c| (c.y, c.x));
+            let sights: HashMap<Coordinate, HashSet<Coordinate>> =
+                floor.iter().map(|&c| (c, in_sight(&map, RANGE, c))).collect();
+
+            for &a in &floor {
+                for &b in &floor {
+                    if a.distance(b) > RANGE {
+                        continue;
+                    }
+                    assert_eq!(sights[&a].contains(&b),
+                               sights[&b].contains(&a),
+                               "seed {}: {:?} and {:?}",
+                               seed,
+                               a,
+                               b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn open_room_is_seen_whole_from_anywhere_in_it() {
+        const RADIUS: i32 = 5;
+        let room = hexagon(RADIUS);
+        let mut map: HashMap<Coordinate, i32> = hexagon(RADIUS + 1)
+                                                    .into_iter()
+                                                    .map(|c| (c, WALL))
+                                                    .collect();
+        for &c in &room {
+            map.insert(c, FLOOR);
+        }
+
+        for &pos in &room {
+            let seen = in_sight(&map, 2 * RADIUS, pos);
+            for &c in &room {
+                assert!(seen.contains(&c), "{:?} not seen from {:?}", c, pos);
+            }
+        }
+
+        // and from the middle, the walls around it
+        let seen = in_sight(&map, RADIUS + 1, Coordinate::new(0, 0));
+        assert_eq!(seen.len(), map.len());
+    }
+
+    #[test]
+    fn nothing_is_seen_beyond_range() {
+        let map: HashMap<Coordinate, i32> = hexagon(10).into_iter().map(|c| (c, FLOOR)).collect();
+        let pos = Coordinate::new(0, 0);
+        let seen = in_sight(&map, 4, pos);
+        assert!(seen.iter().all(|c| pos.distance(*c) <= 4));
+        assert_eq!(seen.len(), hexagon(4).len());
+    }
+}
@@ -0,0 +1,476 @@
+use super::actor::{self, Actor, Slot};
+use rand::{self, Rng, Rand};
+use rand::distributions::IndependentSample;
+
+use core::cmp;
+use std::fmt::{self, Write};
+use std::sync::Arc;
+
+use self::Category::*;
+use self::Type::*;
+use self::Feature::*;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Category {
+    Weapon,
+    RangedWeapon,
+    Armor,
+    Misc,
+    Consumable,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Type {
+    Knife,
+    Bow,
+    Sword,
+    Pickaxe,
+    Axe,
+    HealthPotion,
+    Junk,
+    Leather,
+    Plate,
+    Helmet,
+    Boots,
+    Buckler,
+    Cloak,
+    Torch,
+    Key,
+    /// What's left of a player from an earlier game
+    Corpse,
+    /// Pile of a given amount of gold
+    Gold(u32),
+}
+
+/// Every type but gold, which comes in any amount
+const TYPES: [Type; 16] = [Knife, Bow, Sword, Pickaxe, Axe, HealthPotion, Junk, Leather, Plate,
+                           Helmet, Boots, Buckler, Cloak, Torch, Key, Corpse];
+
+const FEATURES: [Feature; 3] = [Infravision, Light, Regeneration];
+
+impl Type {
+    pub fn description(&self) -> &str {
+        match *self {
+            Junk => "junk",
+            Knife => "knife",
+            Bow => "bow",
+            Sword => "sword",
+            Axe => "axe",
+            HealthPotion => "health potion",
+            Plate => "plate armor",
+            Leather => "leather armor",
+            Helmet => "helmet",
+            Boots => "boots",
+            Buckler => "buckler",
+            Cloak => "cloak",
+            Pickaxe => "pickaxe",
+            Torch => "torch",
+            Key => "key",
+            Corpse => "corpse",
+            Gold(_) => "gold",
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Feature {
+    Infravision,
+    Light,
+    Regeneration,
+}
+
+impl Feature {
+    pub fn description(&self) -> &str {
+        match *self {
+            Infravision => "infravision",
+            Light => "light",
+            Regeneration => "regeneration",
+        }
+    }
+
+
+    pub fn stats(&self) -> actor::EffectiveStats {
+        let mut s: actor::EffectiveStats = Default::default();
+
+        match *self {
+            Infravision => s.base.infravision += 1,
+            Light => s.light_emision += 1,
+            Regeneration => s.base.regeneration += 1,
+        }
+
+        s
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl Rand for Feature {
+    fn rand<R: Rng>(rng: &mut R) -> Self {
+        match rng.gen_range(0, 3) {
+            0 => Infravision,
+            1 => Light,
+            2 => Regeneration,
+            _ => panic!(),
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Item {
+    type_: Type,
+    features: Vec<Feature>,
+    identified: bool,
+    cursed: bool,
+    /// Set while the item belongs to a shop
+    price: Option<u32>,
+}
+
+impl Item {
+    pub fn new(t: Type, features: Vec<Feature>) -> Item {
+        Item {
+            type_: t,
+            features: features,
+            identified: true,
+            cursed: false,
+            price: None,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        let mut s = String::new();
+
+        write!(s, "{}", *self).unwrap();
+
+        s
+    }
+
+    pub fn category(&self) -> Category {
+        match self.type_ {
+            Knife | Sword | Axe | Pickaxe => Weapon,
+            Bow => RangedWeapon,
+            Leather | Plate | Helmet | Boots | Buckler | Cloak => Armor,
+            HealthPotion => Consumable,
+            Junk | Torch | Key | Corpse | Gold(_) => Misc,
+        }
+    }
+
+    pub fn can_dig(&self) -> bool {
+        self.type_ == Pickaxe
+    }
+
+    pub fn is_torch(&self) -> bool {
+        self.type_ == Torch
+    }
+
+    pub fn is_key(&self) -> bool {
+        self.type_ == Key
+    }
+
+    /// Amount of gold, if this is a pile of it
+    pub fn gold(&self) -> Option<u32> {
+        match self.type_ {
+            Gold(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// What a shop asks for the item
+    pub fn value(&self) -> u32 {
+        let base = match self.type_ {
+            Junk | Corpse | Gold(_) => 1,
+            Torch | Key | Knife => 5,
+            HealthPotion | Leather | Helmet | Boots | Buckler | Cloak => 15,
+            Bow | Sword | Pickaxe => 25,
+            Axe | Plate => 40,
+        };
+        base + 20 * self.features.len() as u32
+    }
+
+    pub fn price(&self) -> Option<u32> {
+        self.price
+    }
+
+    pub fn set_price(&mut self, price: Option<u32>) {
+        self.price = price;
+    }
+
+    /// Might get destroyed when its container is forced open
+    pub fn is_fragile(&self) -> bool {
+        self.type_ == HealthPotion
+    }
+
+    pub fn is_ranged_weapon(&self) -> bool {
+        self.category() == RangedWeapon
+    }
+
+    pub fn slot(&self) -> Option<Slot> {
+        match self.type_ {
+            Axe | Sword | Knife => Some(Slot::RHand),
+            Pickaxe => Some(Slot::RHand),
+            Bow => Some(Slot::RHand),
+            Leather | Plate => Some(Slot::Body),
+            Helmet => Some(Slot::Head),
+            Boots => Some(Slot::Feet),
+            Buckler | Torch => Some(Slot::LHand),
+            Cloak => Some(Slot::Cloak),
+            _ => None,
+        }
+    }
+
+    pub fn stats(&self) -> actor::EffectiveStats {
+        let mut s: actor::EffectiveStats = Default::default();
+
+        match self.type_ {
+            Plate => {
+                s.base.ac = 4;
+                s.base.ev = -2;
+            }
+            Leather => {
+                s.base.ac = 1;
+            }
+            Helmet => {
+                s.base.ac = 1;
+                s.base.vision = -2;
+                s.base.infravision = -1;
+            }
+            Boots => s.base.ev = 1,
+            Buckler => {
+                s.base.ev = 1;
+                s.base.ac = 1
+            }
+            Cloak => {
+                s.base.ev = 1;
+            }
+            Knife => {
+                s.melee_dmg = 1;
+                s.melee_str_req = 2;
+            }
+            Sword => {
+                s.melee_dmg += 3;
+                s.melee_str_req = 4;
+            }
+            Axe => {
+                s.melee_dmg += 4;
+                s.melee_str_req = 5;
+            }
+            Pickaxe => {
+                s.melee_dmg += 1;
+                s.melee_str_req = 6;
+            }
+            Torch => {
+                s.light_emision += 2;
+            }
+            _ => {}
+        }
+
+        for feature in &self.features {
+            s = s + feature.stats()
+        }
+        s
+    }
+
+    /// Stats as far as anyone can tell: the features' only once identified
+    pub fn known_stats(&self) -> actor::EffectiveStats {
+        if self.identified {
+            self.stats()
+        } else {
+            Item::new(self.type_, vec![]).stats()
+        }
+    }
+
+    pub fn is_identified(&self) -> bool {
+        self.identified
+    }
+
+    pub fn identify(&mut self) {
+        self.identified = true;
+    }
+
+    /// Cursed items can't be taken off once equipped
+    pub fn is_cursed(&self) -> bool {
+        self.cursed
+    }
+
+    pub fn uncurse(&mut self) {
+        self.cursed = false;
+    }
+
+    pub fn is_usable(&self) -> bool {
+        self.category() == Consumable
+    }
+
+    /// One line of words standing for the item, to keep it in a file;
+    /// the price isn't kept
+    pub fn encode(&self) -> String {
+        let mut words = vec![match self.type_ {
+                                 Gold(n) => format!("gold:{}", n),
+                                 t => t.description().replace(' ', "_"),
+                             }];
+        if !self.identified {
+            words.push("unidentified".to_string());
+        }
+        if self.cursed {
+            words.push("cursed".to_string());
+        }
+        words.extend(self.features.iter().map(|f| f.description().to_string()));
+        words.join(" ")
+    }
+
+    /// Reverse of `encode`
+    pub fn decode(line: &str) -> Option<Item> {
+        let mut words = line.split_whitespace();
+        let type_ = match words.next() {
+            Some(word) if word.starts_with("gold:") => {
+                match word["gold:".len()..].parse() {
+                    Ok(n) => Gold(n),
+                    Err(_) => return None,
+                }
+            }
+            Some(word) => {
+                match TYPES.iter().find(|t| t.description().replace(' ', "_") == word) {
+                    Some(&t) => t,
+                    None => return None,
+                }
+            }
+            None => return None,
+        };
+
+        let mut item = Item::new(type_, vec![]);
+        for word in words {
+            match word {
+                "unidentified" => item.identified = false,
+                "cursed" => item.cursed = true,
+                word => {
+                    match FEATURES.iter().find(|f| f.description() == word) {
+                        Some(&f) => item.features.push(f),
+                        None => return None,
+                    }
+                }
+            }
+        }
+        Some(item)
+    }
+
+    /// `encode` with the price first, `-` if there's none, for items
+    /// that may be for sale
+    pub fn encode_priced(&self) -> String {
+        match self.price {
+            Some(price) => format!("{} {}", price, self.encode()),
+            None => format!("- {}", self.encode()),
+        }
+    }
+
+    /// Reverse of `encode_priced`
+    pub fn decode_priced(line: &str) -> Option<Item> {
+        let (price, rest) = match line.find(' ') {
+            Some(i) => (&line[..i], &line[i + 1..]),
+            None => return None,
+        };
+        let price = match price {
+            "-" => None,
+            price => {
+                match price.parse() {
+                    Ok(price) => Some(price),
+                    Err(_) => return None,
+                }
+            }
+        };
+        Item::decode(rest).map(|mut item| {
+            item.price = price;
+            item
+        })
+    }
+
+    /// Use item
+    ///
+    /// Returns: true if the item was consumed in the process.
+    pub fn use_(&self, astate: &mut Actor) -> bool {
+        match self.type_ {
+            HealthPotion => {
+                astate.hp += 5;
+                astate.hp = cmp::min(astate.hp, astate.stats.base.max_hp);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.type_ {
+            Gold(n) => try!(write!(f, "{} gold", n)),
+            t => try!(write!(f, "{}", t)),
+        }
+
+        if self.identified {
+            for feature in &self.features {
+                try!(write!(f, " of {}", feature));
+            }
+
+            if self.cursed {
+                try!(write!(f, " (cursed)"));
+            }
+        }
+
+        if let Some(price) = self.price {
+            try!(write!(f, " ({} gold)", price));
+        }
+
+        Ok(())
+    }
+}
+
+
+pub fn random<R: Rng>(level: i32, rng: &mut R) -> Arc<Item> {
+
+    let a = -(level / 2);
+    let b = level + 2;
+    let lvrange = rand::distributions::Range::new(a, b);
+    let r = lvrange.ind_sample(rng) + lvrange.ind_sample(rng) + lvrange.ind_sample(rng);
+
+    let mut features = vec![];
+    let mut chance = level;
+    const PER_LOOP: i32 = 30;
+    let looprange = rand::distributions::Range::new(0, PER_LOOP);
+    while looprange.ind_sample(rng) < chance {
+        features.push(rng.gen::<Feature>());
+        chance = cmp::max(0, chance - PER_LOOP);
+    }
+
+    let gold = rng.gen_range(5, 20 + 5 * cmp::max(level, 0) as u32);
+
+    let mut item = Item::new(match r {
+                                 1 => *rng.choose(&[Knife, Pickaxe]).unwrap(),
+                                 2 => HealthPotion,
+                                 3 => *rng.choose(&[Bow, Cloak]).unwrap(),
+                                 4 => Torch,
+                                 5 => *rng.choose(&[Helmet, Sword]).unwrap(),
+                                 6 => Leather,
+                                 7 => Key,
+                                 8 => *rng.choose(&[Boots, Buckler]).unwrap(),
+                                 9 => Gold(gold),
+                                 10 => *rng.choose(&[Plate, Axe]).unwrap(),
+                                 _ => Junk,
+                             },
+                             features);
+
+    if item.gold().is_some() {
+        item.features.clear();
+    }
+
+    item.cursed = item.slot().is_some() && rng.gen_weighted_bool(8);
+    item.identified = item.features.is_empty() && !item.cursed;
+
+    Arc::new(item)
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use util::Compass;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Branch {
+    Main,
+    Warrens,
+}
+
+impl Branch {
+    pub fn name(&self) -> &str {
+        match *self {
+            Branch::Main => "Dungeon",
+            Branch::Warrens => "Warrens",
+        }
+    }
+
+    /// Reverse of `name`
+    pub fn from_name(name: &str) -> Option<Branch> {
+        [Branch::Main, Branch::Warrens].iter().cloned().find(|b| b.name() == name)
+    }
+
+    /// Level the branch is entered from
+    pub fn parent(&self) -> Option<LevelId> {
+        match *self {
+            Branch::Main => None,
+            Branch::Warrens => Some(LevelId::new(Branch::Main, 2)),
+        }
+    }
+
+    /// Number of levels in a branch (`None` for bottomless)
+    pub fn levels(&self) -> Option<u32> {
+        match *self {
+            Branch::Main => None,
+            Branch::Warrens => Some(3),
+        }
+    }
+}
+
+/// Identifies a level in the dungeon
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct LevelId {
+    pub branch: Branch,
+    /// Depth inside the branch, starting from 0
+    pub depth: u32,
+}
+
+impl LevelId {
+    pub fn new(branch: Branch, depth: u32) -> LevelId {
+        LevelId {
+            branch: branch,
+            depth: depth,
+        }
+    }
+
+    pub fn start() -> LevelId {
+        LevelId::new(Branch::Main, 0)
+    }
+
+    /// Depth counting from the surface, used for difficulty
+    pub fn absolute_depth(&self) -> u32 {
+        match self.branch.parent() {
+            Some(parent) => parent.absolute_depth() + 1 + self.depth,
+            None => self.depth,
+        }
+    }
+
+    pub fn below(&self) -> Option<LevelId> {
+        let depth = self.depth + 1;
+        match self.branch.levels() {
+            Some(levels) if depth >= levels => None,
+            _ => Some(LevelId::new(self.branch, depth)),
+        }
+    }
+
+    pub fn above(&self) -> Option<LevelId> {
+        if self.depth > 0 {
+            Some(LevelId::new(self.branch, self.depth - 1))
+        } else {
+            self.branch.parent()
+        }
+    }
+
+    /// Side branch that can be entered from this level
+    pub fn side_branch(&self) -> Option<LevelId> {
+        for &branch in &[Branch::Warrens] {
+            if branch.parent() == Some(*self) {
+                return Some(LevelId::new(branch, 0));
+            }
+        }
+        None
+    }
+
+    /// Last level of a branch
+    pub fn is_bottom(&self) -> bool {
+        self.below().is_none()
+    }
+}
+
+impl fmt::Display for LevelId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.branch.name(), self.depth + 1)
+    }
+}
+
+/// Rough time of day on the surface
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Daytime {
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
+
+impl Daytime {
+    pub fn description(&self) -> &str {
+        match *self {
+            Daytime::Morning => "morning",
+            Daytime::Afternoon => "afternoon",
+            Daytime::Evening => "evening",
+            Daytime::Night => "night",
+        }
+    }
+}
+
+/// Impression the player gets when first entering a level
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Feeling {
+    /// Plenty of rats around
+    Chittering,
+    /// Big lit area in a given direction
+    Glow(Compass),
+    /// Monsters from deeper levels
+    Dangerous,
+}
@@ -0,0 +1,2768 @@
+use std::cmp;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::sync::Arc;
+use rand::Rng;
+use rayon::prelude::*;
+
+use generate;
+
+use hex2d::Coordinate;
+
+use super::{area, fov, path, tile};
+use super::format::FileFormat;
+use super::item::{self, Item};
+use super::{Action, Bones, PathOpts};
+use super::actor::{self, Actor};
+use super::perception::{Eye, Perception};
+use super::{CoordMap, FnvHashMap, FnvHashSet, LightMap, Map, Items, Visibility};
+use super::{decode_coords, encode_coords};
+use super::{Daytime, Discovery, Event, Feeling, Heard, LevelId, Noise, Rejection};
+use super::timings::{Stopwatch, Timings};
+use util::{self, Compass, GameRng};
+
+/// Dexterity needed to usually disarm a chest trap
+const CHEST_TRAP_DIFFICULTY: i32 = 4;
+/// Turns from sunrise to sunset on the surface, and back
+const DAY_LENGTH: u64 = 500;
+/// Most damage fire does in a turn
+const FIRE_DMG: i32 = 3;
+/// One in that many neighbors of a burning tile catches fire each turn
+const FIRE_SPREAD_CHANCE: u32 = 2;
+/// Rats on a level needed to hear them
+const FEELING_RATS: usize = 8;
+/// Lit tiles needed to notice a glow
+const FEELING_GLOW_TILES: usize = 40;
+/// Lit tiles closer to the entry than this don't count
+const FEELING_GLOW_DISTANCE: i32 = 8;
+/// Closest to the start a ghost from bones may appear
+const GHOST_DISTANCE: i32 = 10;
+/// How far around where the player died the map goes into bones
+const BONES_RADIUS: i32 = 3;
+
+/// A level written out while the player is away from it
+pub const FORMAT: FileFormat = FileFormat {
+    kind: "level",
+    version: 1,
+    migrations: &[],
+};
+
+/// What is remembered of an actor after its body is gone
+#[derive(Clone, Debug)]
+pub struct DeadActorRecord {
+    pub id: actor::Id,
+    pub race: actor::Race,
+    pub name: String,
+    pub killed_on_turn: u64,
+    /// Whoever dealt the last blow, if anyone
+    pub killer: Option<String>,
+    /// The last blow was the player's
+    pub by_player: bool,
+    /// What it had equipped, by letter, before it all fell to the floor
+    pub worn: Vec<Arc<Item>>,
+}
+
+/// Room where items are sold
+#[derive(Clone, Debug)]
+pub struct Shop {
+    pub area: area::Area,
+    pub keeper: Option<actor::Id>,
+    /// Shopkeeper is after the player
+    pub angry: bool,
+}
+
+impl Shop {
+    pub fn new(area: area::Area) -> Shop {
+        Shop {
+            area: area,
+            keeper: None,
+            angry: false,
+        }
+    }
+
+    /// Inside the shop or on its threshold
+    pub fn contains(&self, coord: Coordinate) -> bool {
+        let area::Room(r) = self.area.type_;
+        coord.distance(self.area.center) <= r as i32
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Location {
+    pub actors_byid: FnvHashMap<actor::Id, Actor>, // id -> State
+    pub actors_coord_to_id: CoordMap<u32>, // coord -> id
+    /// Dead actors whose bodies are still around
+    pub actors_dead: FnvHashSet<actor::Id>,
+    /// Everyone who died here, in order
+    pub kill_log: Vec<DeadActorRecord>,
+    pub actors_counter: u32,
+    /// Shared with the copies of the location until either changes it
+    pub map: Arc<Map>,
+    /// Smallest and largest coordinates of the generated map
+    bounds: (Coordinate, Coordinate),
+    pub items: Items, // items on the floor
+    pub containers: Items, // contents of closed chests
+    /// Tiles on fire, with the turns they will still burn for
+    pub burning: CoordMap<u32>,
+    /// Items that found no room on the floor yet
+    unplaced: Vec<(Coordinate, Arc<Item>)>,
+    pub light_map: LightMap, // light intensity at a given coordinate
+    /// Least light anywhere on the level; not part of `light_map`
+    pub ambient_light: u32,
+    /// Light generation at which `ambient_light` last changed
+    ambient_changed: u64,
+    /// Bumped on every change to `map`
+    pub map_generation: u64,
+    /// Bumped whenever `light_map` changes
+    pub light_generation: u64,
+    /// Light generation at which each coordinate last got lighter or darker
+    light_changed: CoordMap<u64>,
+    pub id: LevelId,
+    /// Where the player enters the level if not by stairs
+    pub start: Coordinate,
+    /// Free tiles next to `start`
+    pub companion_spots: Vec<Coordinate>,
+    pub shop: Option<Shop>,
+    /// Engine turn this location was last updated at
+    pub turn: u64,
+    /// Engine turn at which the player last left
+    pub left_at: Option<u64>,
+    /// What the player knew of the level when they left it: known
+    /// coordinates and areas
+    player_memory: Option<(Visibility, Visibility)>,
+    /// What happened during the current tick, and where
+    events: Vec<(Coordinate, Event)>,
+    /// What the player noticed, not taken yet
+    player_events: Vec<Event>,
+    /// `player_events` has the current tick already
+    player_observed: bool,
+    /// Actors the player has had in sight already
+    player_sighted: FnvHashSet<actor::Id>,
+    player_id: Option<actor::Id>,
+    /// Where the time running the level goes
+    pub timings: Timings,
+    /// Kept between ticks for `take_alive_ids`, so it's not allocated
+    /// on each
+    scratch_ids: Vec<actor::Id>,
+    /// Actors that may have made noise this tick, for
+    /// `recalculate_noise` to spread and forget
+    noisy: Vec<actor::Id>,
+}
+
+impl Location {
+    pub fn new(id: LevelId, rng: &mut GameRng) -> Location {
+        let params = generate::GenerationParams::for_level(id.absolute_depth());
+        let ambient_light = params.ambient_light;
+
+        let generate::GeneratedLevel {
+            map,
+            actors: gen_actors,
+            items,
+            containers,
+            start,
+            companion_spots,
+            shop,
+            ..
+        } = generate::gen_level(id, params, rng);
+
+        let mut shop = shop.map(Shop::new);
+
+        let mut actors: FnvHashMap<u32, Actor> = Default::default();
+        let mut actors_pos: CoordMap<u32> = Default::default();
+
+        let mut actors_counter = 0u32;
+
+        for (coord, astate) in gen_actors {
+            if astate.race == actor::Race::Shopkeeper {
+                if let Some(ref mut shop) = shop {
+                    shop.keeper = Some(actors_counter);
+                }
+            }
+            actors_pos.insert(coord, actors_counter);
+            actors.insert(actors_counter, astate);
+            actors_counter += 1;
+        }
+
+        let bounds = map_bounds(&map);
+
+        let loc = Location {
+            actors_byid: actors,
+            actors_coord_to_id: actors_pos,
+            actors_counter: actors_counter,
+            actors_dead: Default::default(),
+            kill_log: vec![],
+            items: items,
+            containers: containers,
+            burning: Default::default(),
+            unplaced: vec![],
+            map: Arc::new(map),
+            bounds: bounds,
+            id: id,
+            start: start,
+            companion_spots: companion_spots,
+            shop: shop,
+            turn: 0,
+            left_at: None,
+            player_memory: None,
+            events: vec![],
+            light_map: LightMap::new(),
+            ambient_light: ambient_light,
+            ambient_changed: 0,
+            map_generation: 0,
+            light_generation: 0,
+            light_changed: Default::default(),
+            player_events: vec![],
+            player_observed: false,
+            player_sighted: Default::default(),
+            player_id: None,
+            timings: Default::default(),
+            scratch_ids: vec![],
+            noisy: vec![],
+        };
+
+        loc
+    }
+
+    /// Smallest and largest `x` and `y` a tile can have; anything
+    /// outside is solid rock
+    pub fn bounds(&self) -> (Coordinate, Coordinate) {
+        self.bounds
+    }
+
+    pub fn in_bounds(&self, coord: Coordinate) -> bool {
+        let (min, max) = self.bounds;
+        coord.x >= min.x && coord.x <= max.x && coord.y >= min.y && coord.y <= max.y
+    }
+
+    /// Where an actor coming from `from` level should appear
+    pub fn arrival_coord(&self, from: LevelId) -> Coordinate {
+        let stairs = super::in_order(&self.map).into_iter().find(|&(_, t)| {
+            t.stairs_down() == Some(from) || t.stairs_up() == Some(from)
+        });
+
+        let coord = stairs.map(|(c, _)| c).unwrap_or(self.start);
+        self.free_coord_near(coord).unwrap_or(coord)
+    }
+
+    /// What the player can sense after arriving at `entry`
+    pub fn feelings(&self, entry: Coordinate) -> Vec<Feeling> {
+        let mut feelings = vec![];
+
+        let npcs: Vec<&Actor> = self.actors_byid
+                                    .values()
+                                    .filter(|a| !a.is_player() && !a.is_dead())
+                                    .collect();
+
+        let rats = npcs.iter().filter(|a| a.race == actor::Race::Rat).count();
+        if rats >= FEELING_RATS {
+            feelings.push(Feeling::Chittering);
+        }
+
+        let lit: Vec<Coordinate> = self.light_map
+                                       .iter()
+                                       .filter(|&(&c, &l)| {
+                                           l > 0 && c.distance(entry) > FEELING_GLOW_DISTANCE
+                                       })
+                                       .map(|(&c, _)| c)
+                                       .collect();
+        if lit.len() >= FEELING_GLOW_TILES {
+            let (sx, sy) = lit.iter().fold((0, 0), |(x, y), c| (x + c.x, y + c.y));
+            let n = lit.len() as i32;
+            let center = Coordinate::new(sx / n, sy / n);
+            if let Some(dir) = Compass::between(entry, center) {
+                feelings.push(Feeling::Glow(dir));
+            }
+        }
+
+        let depth = self.id.absolute_depth();
+        if npcs.iter().any(|a| a.race.native_depth() > depth) {
+            feelings.push(Feeling::Dangerous);
+        }
+
+        feelings
+    }
+
+    /// Closest passable, unoccupied coordinate
+    pub fn free_coord_near(&self, coord: Coordinate) -> Option<Coordinate> {
+        self.nearest_matching(coord, |c| self.at(c).is_passable(), &Default::default())
+            .map(|path| path.last().cloned().unwrap_or(coord))
+    }
+
+    /// Steps to take from `from` to get to `to`, the last one being `to`
+    pub fn find_path(&self,
+                     from: Coordinate,
+                     to: Coordinate,
+                     opts: &PathOpts)
+                     -> Option<Vec<Coordinate>> {
+        path::search(self, from, |c| c == to, opts)
+    }
+
+    /// Steps to take from `from` to get to the closest coordinate
+    /// `predicate` holds for; empty if it holds for `from`
+    pub fn nearest_matching<F>(&self,
+                               from: Coordinate,
+                               predicate: F,
+                               opts: &PathOpts)
+                               -> Option<Vec<Coordinate>>
+        where F: Fn(Coordinate) -> bool
+    {
+        path::search(self, from, predicate, opts)
+    }
+
+    pub fn player_id(&self) -> actor::Id {
+        self.player_id.unwrap()
+    }
+
+    pub fn player(&self) -> &Actor {
+        &self.actors_byid[&self.player_id.unwrap()]
+    }
+
+    /// How loud a noise of `emission` made at `source` is wherever it
+    /// reaches. Each tile on the way takes its `noise_cost` off.
+    pub fn noise_spread(&self, source: Coordinate, emission: i32) -> CoordMap<i32> {
+        let mut loudness: CoordMap<i32> = Default::default();
+        // coordinates to spread from, by the loudness lost to get there
+        let mut queue: Vec<Vec<Coordinate>> = vec![vec![]; emission as usize + 1];
+
+        loudness.insert(source, emission);
+        queue[0].push(source);
+
+        for lost in 0..queue.len() {
+            while let Some(coord) = queue[lost].pop() {
+                let left = emission - lost as i32;
+                if loudness[&coord] > left {
+                    // got there some quieter way
+                    continue;
+                }
+
+                for &n in &coord.neighbors() {
+                    let n_left = match self.at(n).tile().noise_cost() {
+                        Some(cost) => left - cost,
+                        None => continue,
+                    };
+
+                    if n_left >= 0 && loudness.get(&n).map_or(true, |&l| l < n_left) {
+                        loudness.insert(n, n_left);
+                        queue[(emission - n_left) as usize].push(n);
+                    }
+                }
+            }
+        }
+
+        loudness
+    }
+
+    /// Spread the noise made this tick; only those `made_noise` tells
+    /// of are asked, as most actors are quiet most of the time
+    pub fn recalculate_noise(&mut self) {
+        let mut sources: Vec<(Coordinate, Noise)> = vec![];
+        self.noisy.sort();
+        self.noisy.dedup();
+        for id in self.noisy.drain(..) {
+            // whoever died this tick was taken away already
+            if let Some(actor) = self.actors_byid.get(&id) {
+                if let Some(noise) = actor.noise_emision {
+                    sources.push((actor.pos.coord, noise));
+                }
+            }
+        }
+        sources.extend(self.burning.keys().map(|&c| (c, Noise::Fire)));
+
+        let turn = self.turn;
+        for (source_coord, noise) in sources {
+            // noise loses at least 1 per hex, so nobody further away can hear it
+            let hearers = self.actors_in_range(source_coord, noise.loudness());
+            if hearers.is_empty() {
+                continue;
+            }
+
+            let spread = self.noise_spread(source_coord, noise.loudness());
+            for target_id in hearers {
+                let target = self.actors_byid.get_mut(&target_id).unwrap();
+                if let Some(&loudness) = spread.get(&target.pos.coord) {
+                    target.noise_hears(source_coord,
+                                       Heard {
+                                           noise: noise,
+                                           loudness: loudness,
+                                           turn: turn,
+                                       });
+                }
+            }
+        }
+    }
+
+    /// Have `recalculate_noise` spread whatever noise actor `id` made
+    /// this tick; every change to `noise_emision` must be followed by it
+    pub fn made_noise(&mut self, id: actor::Id) {
+        self.noisy.push(id);
+    }
+
+    /// Put actor `id` back after its part of the tick
+    fn put_back(&mut self, id: actor::Id, actor: Actor) {
+        if actor.noise_emision.is_some() {
+            self.made_noise(id);
+        }
+        self.actors_byid.insert(id, actor);
+    }
+
+    /// In order, however the actors came to be in the map
+    pub fn actors_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.actors_byid.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// In order, like `actors_ids`
+    pub fn actors_alive_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.actors_byid
+                                    .keys()
+                                    .filter(|&id| !self.actors_byid[id].is_dead())
+                                    .cloned()
+                                    .collect();
+        ids.sort();
+        ids
+    }
+
+    /// `actors_alive_ids`, in the scratch buffer; give it back with
+    /// `return_ids` once done, for the next tick to reuse
+    fn take_alive_ids(&mut self) -> Vec<actor::Id> {
+        let mut ids = mem::replace(&mut self.scratch_ids, vec![]);
+        ids.clear();
+        ids.extend(self.actors_byid.iter().filter(|&(_, a)| !a.is_dead()).map(|(&id, _)| id));
+        ids.sort();
+        ids
+    }
+
+    fn return_ids(&mut self, ids: Vec<actor::Id>) {
+        self.scratch_ids = ids;
+    }
+
+    /// Living actors at most `r` away from `center`
+    pub fn actors_in_range(&self, center: Coordinate, r: i32) -> Vec<actor::Id> {
+        let mut ids = vec![];
+        // probe every hex in range, unless there are fewer actors than hexes
+        if util::hexes_in_range(r) < self.actors_coord_to_id.len() {
+            center.for_each_in_range(r, |c| {
+                if let Some(&id) = self.actors_coord_to_id.get(&c) {
+                    ids.push(id);
+                }
+            });
+        } else {
+            ids.extend(self.actors_coord_to_id
+                           .iter()
+                           .filter(|&(c, _)| c.distance(center) <= r)
+                           .map(|(_, &id)| id));
+        }
+        ids.sort();
+        ids
+    }
+
+    /// Tiles a shot from `from` at `to` flies over, in order: up to `to`,
+    /// the first tile on the way somebody stands on, or the last one
+    /// before something nothing can pass, whichever comes first
+    pub fn shot_path(&self, from: Coordinate, to: Coordinate) -> Vec<Coordinate> {
+        let mut path = vec![];
+        let mut stopped = false;
+        from.for_each_in_line_to(to, |c| {
+            if c == from || stopped {
+                return;
+            }
+            if !self.at(c).tile().is_passable() {
+                stopped = true;
+                return;
+            }
+            path.push(c);
+            stopped = self.at(c).is_occupied();
+        });
+        path
+    }
+
+    /// Living actors, other than itself, that actor `id` currently sees
+    pub fn actors_visible_by(&self, id: actor::Id) -> Vec<actor::Id> {
+        let observer = &self.actors_byid[&id];
+        let mut ids = vec![];
+        let visible = &observer.perception.visible;
+        if visible.len() < self.actors_coord_to_id.len() {
+            ids.extend(visible.iter().filter_map(|c| self.actors_coord_to_id.get(c)).cloned());
+        } else {
+            ids.extend(self.actors_coord_to_id
+                           .iter()
+                           .filter(|&(&c, _)| observer.sees(c))
+                           .map(|(_, &id)| id));
+        }
+        ids.retain(|&other| other != id);
+        ids.sort();
+        ids
+    }
+
+    pub fn recalculate_light_map(&mut self, rng: &mut GameRng) {
+        let mut light_map: LightMap = Default::default();
+
+        let mut sources: Vec<(Coordinate, i32)> = vec![];
+        // in order, so each flicker gets the same roll every game
+        for (pos, tile) in super::in_order(&self.map) {
+            let light = if tile.flickers() {
+                tile.light - rng.gen_range(0, 2)
+            } else {
+                tile.light
+            };
+            if light > 0 {
+                sources.push((pos, light));
+            }
+        }
+        sources.extend(self.burning.keys().map(|&pos| (pos, tile::FIRE_LIGHT)));
+
+        for (pos, light) in sources {
+            fov::around(&|coord| {
+                            if coord == pos {
+                                0
+                            } else {
+                                self.at(coord).tile().opaqueness()
+                            }
+                        },
+                        &mut |coord, light| {
+                            if light_map[coord] < light as u32 {
+                                light_map[coord] = light as u32;
+                            }
+                        },
+                        light,
+                        pos);
+        }
+
+        for (_, id) in &self.actors_coord_to_id {
+            let astate = &self.actors_byid[id];
+            let pos = astate.pos.coord;
+            if astate.light_emision() > 0 {
+                fov::around(&|coord| {
+                                if coord == pos {
+                                    0
+                                } else {
+                                    self.at(coord).tile().opaqueness()
+                                }
+                            },
+                            &mut |coord, light| {
+                                if light_map[coord] < light as u32 {
+                                    light_map[coord] = light as u32;
+                                }
+                            },
+                            astate.light_emision() as i32,
+                            pos);
+            }
+        }
+
+        let mut changed: Vec<Coordinate> = vec![];
+        for (&coord, &light) in light_map.iter() {
+            if self.light_map[coord] != light {
+                changed.push(coord);
+            }
+        }
+        for (&coord, &light) in self.light_map.iter() {
+            if light_map[coord] != light {
+                changed.push(coord);
+            }
+        }
+
+        if !changed.is_empty() {
+            self.light_generation += 1;
+            for coord in changed {
+                self.light_changed.insert(coord, self.light_generation);
+            }
+        }
+
+        self.light_map = light_map;
+    }
+
+    /// Did the light change anywhere in `area` after generation `gen`
+    pub fn light_changed_since(&self, gen: u64, area: &Visibility) -> bool {
+        gen != self.light_generation &&
+        (self.ambient_changed > gen ||
+         area.iter().any(|c| self.light_changed.get(c).map_or(false, |&changed| changed > gen)))
+    }
+
+    /// Tile to modify; anything relying on the map being unchanged
+    /// will notice
+    pub fn tile_mut(&mut self, coord: Coordinate) -> &mut tile::Tile {
+        debug_assert!(self.in_bounds(coord), "{:?} is outside the map", coord);
+        self.map_generation += 1;
+        &mut Arc::make_mut(&mut self.map)[coord]
+    }
+
+    pub fn spawn(&mut self, mut astate: Actor, rng: &mut GameRng) -> actor::Id {
+        if self.actors_coord_to_id.contains_key(&astate.pos.coord) {
+            let coord = self.free_coord_near(astate.pos.coord)
+                            .expect("no room left on the level to spawn in");
+            astate.pos.coord = coord;
+        }
+        self.pre_any_tick();
+        let id = self.actors_counter;
+        self.actors_counter += 1;
+
+        debug_assert!(!self.actors_coord_to_id.contains_key(&astate.pos.coord));
+        self.actors_coord_to_id.insert(astate.pos.coord, id);
+        astate.pre_own_tick();
+        astate.post_spawn(self);
+        self.put_back(id, astate);
+        self.post_any_tick(rng);
+
+        id
+    }
+
+    /// Raise the ghost of a player who died on this level in another
+    /// game, away from the start, and lay their corpse where the map
+    /// around it is put back
+    pub fn haunt(&mut self, bones: Bones, rng: &mut GameRng) {
+        let grave = self.splice(bones.site, &bones.fragment);
+        self.place_item(grave, Arc::new(Item::new(item::Type::Corpse, vec![])));
+
+        let start = self.start;
+        let mut free: Vec<Coordinate> = self.map
+                                            .iter()
+                                            .map(|(&c, _)| c)
+                                            .filter(|&c| {
+                                                c.distance(start) >= GHOST_DISTANCE &&
+                                                self.at(c).is_passable()
+                                            })
+                                            .collect();
+        free.sort_by_key(|c| (c.x, c.y));
+        let coord = match rng.choose(&free) {
+            Some(&coord) => coord,
+            None => return,
+        };
+
+        let pos = util::random_pos(coord.x, coord.y, rng);
+        self.spawn(Actor::ghost(bones, pos), rng);
+    }
+
+    /// The map around `site`, for bones, by offset from it. Stairs,
+    /// portals and chests stay behind, and so does the area.
+    pub fn fragment(&self, site: Coordinate) -> Vec<(Coordinate, tile::Tile)> {
+        let mut fragment: Vec<(Coordinate, tile::Tile)> =
+            self.map
+                .iter()
+                .filter(|&(&c, _)| c.distance(site) <= BONES_RADIUS)
+                .map(|(&c, &tile)| {
+                    let mut tile = tile;
+                    match tile.feature_on(tile::Layer::Fixture) {
+                        Some(tile::StairsDown(_)) |
+                        Some(tile::StairsUp(_)) |
+                        Some(tile::Portal(_)) |
+                        Some(tile::Chest(_)) => {
+                            tile.remove_feature(tile::Layer::Fixture);
+                        }
+                        _ => {}
+                    }
+                    tile.area = None;
+                    (Coordinate::new(c.x - site.x, c.y - site.y), tile)
+                })
+                .collect();
+        fragment.sort_by_key(|&(d, _)| (d.y, d.x));
+        fragment
+    }
+
+    /// Lay a `fragment` of another game's map over this level, centered
+    /// on the passable tile closest to `site` that has no stairs. Tiles
+    /// with stairs, portals or closed chests are left alone, and so are
+    /// passable tiles the fragment would block, for every part of the
+    /// level to stay reachable.
+    ///
+    /// Returns: where the center of the fragment went
+    pub fn splice(&mut self,
+                  site: Coordinate,
+                  fragment: &[(Coordinate, tile::Tile)])
+                  -> Coordinate {
+        let mut spots: Vec<Coordinate> = self.map
+                                             .iter()
+                                             .filter(|&(_, t)| t.is_passable() && !t.has_stairs())
+                                             .map(|(&c, _)| c)
+                                             .collect();
+        spots.sort_by_key(|&c| (c.distance(site), c.y, c.x));
+        let center = match spots.first() {
+            Some(&c) => c,
+            None => return self.start,
+        };
+
+        for &(d, tile) in fragment {
+            let coord = Coordinate::new(center.x + d.x, center.y + d.y);
+            if !self.in_bounds(coord) {
+                continue;
+            }
+            let here = *self.at(coord).tile();
+            if here.has_stairs() || here.portal().is_some() || here.closed_chest().is_some() ||
+               (here.is_passable() && !tile.is_passable()) {
+                continue;
+            }
+            let mut tile = tile;
+            tile.area = here.area;
+            *self.tile_mut(coord) = tile;
+        }
+        center
+    }
+
+    pub fn remove(&mut self, id: actor::Id) -> Option<Actor> {
+        let actor = self.actors_byid.remove(&id);
+
+        let actor = if let Some(actor) = actor {
+            actor
+        } else {
+            return None;
+        };
+
+        self.actors_coord_to_id.remove(&actor.pos.coord);
+        self.check_invariants();
+
+        Some(actor)
+    }
+
+    /// Take the player off the level, along with the companions
+    /// standing next to them. The player goes first.
+    pub fn take_party(&mut self) -> Vec<Actor> {
+        let player_id = self.player_id();
+        let coord = self.player().coord();
+
+        let companions: Vec<actor::Id> = coord.neighbors()
+                                              .iter()
+                                              .filter_map(|c| self.actors_coord_to_id.get(c))
+                                              .cloned()
+                                              .filter(|id| {
+                                                  let a = &self.actors_byid[id];
+                                                  a.companion && !a.is_dead()
+                                              })
+                                              .collect();
+
+        {
+            let p = &self.player().perception;
+            self.player_memory = Some((p.known.clone(), p.known_areas.clone()));
+        }
+        self.player_id = None;
+
+        Some(player_id)
+            .into_iter()
+            .chain(companions)
+            .filter_map(|id| self.remove(id))
+            .collect()
+    }
+
+    /// Put the party taken from level `from` on this one: the player
+    /// where the way from `from` ends, the companions around them
+    ///
+    /// Returns: the companions there was no room for.
+    pub fn place_party(&mut self,
+                       party: Vec<Actor>,
+                       from: LevelId,
+                       rng: &mut GameRng)
+                       -> Vec<Actor> {
+        let mut party = party.into_iter();
+        let mut player = party.next().expect("party without the player");
+
+        let coord = self.arrival_coord(from);
+        player.pos = util::random_pos(coord.x, coord.y, rng);
+        let player_id = self.spawn_player(player, rng);
+
+        if let Some((known, known_areas)) = self.player_memory.take() {
+            let p = &mut self.actors_byid.get_mut(&player_id).unwrap().perception;
+            // nothing new about what was seen before
+            p.discovered = p.discovered.difference(&known).cloned().collect();
+            p.discovered_areas = p.discovered_areas.difference(&known_areas).cloned().collect();
+            p.known.extend(known);
+            p.known_areas.extend(known_areas);
+        }
+
+        let mut left = vec![];
+        for mut companion in party {
+            match self.free_coord_near(coord) {
+                Some(spot) => {
+                    companion.pos = util::random_pos(spot.x, spot.y, rng);
+                    self.spawn(companion, rng);
+                }
+                None => left.push(companion),
+            }
+        }
+        left
+    }
+
+    /// Bring the level up to engine turn `turn`. Levels aren't simulated
+    /// while the player is away; instead, on return, everyone gets what
+    /// the missed turns would have done to them on average, and fires
+    /// that would have burned out are out.
+    pub fn catch_up(&mut self, turn: u64) {
+        let turns = turn.saturating_sub(self.turn);
+        if turns == 0 {
+            return;
+        }
+        self.turn = turn;
+
+        for actor in self.actors_byid.values_mut().filter(|a| !a.is_dead()) {
+            actor.fast_forward(turns);
+        }
+
+        let out: Vec<Coordinate> = self.burning
+                                       .iter()
+                                       .filter(|&(_, &left)| left as u64 <= turns)
+                                       .map(|(&c, _)| c)
+                                       .collect();
+        for coord in out {
+            self.burning.remove(&coord);
+            self.tile_mut(coord).burn_out();
+        }
+        for left in self.burning.values_mut() {
+            *left -= turns as u32;
+        }
+
+        self.update_daylight();
+        self.remove_bodies();
+    }
+
+    /// Move actor `id` to `to`, or as close as there's room, outside of
+    /// its own turn
+    ///
+    /// Returns: false if there was no room.
+    pub fn teleport(&mut self, id: actor::Id, to: Coordinate, rng: &mut GameRng) -> bool {
+        self.pre_any_tick();
+        let mut actor = self.actors_byid.remove(&id).unwrap();
+        let moved = actor.teleport(self, to);
+        self.put_back(id, actor);
+        self.post_any_tick(rng);
+        moved
+    }
+
+    /// In debug builds, panic unless:
+    ///
+    /// * every living actor is where `actors_coord_to_id` says, and the
+    ///   other way around, so no two of them share a coordinate
+    /// * nobody dead is in `actors_coord_to_id`
+    /// * items only lie where one can walk
+    /// * the player, while on the level, is an actor that is the player
+    /// * nowhere is lit brighter than the brightest light on the level
+    ///
+    /// Whatever changes the level is followed by it.
+    pub fn check_invariants(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        for (coord, id) in &self.actors_coord_to_id {
+            let actor = self.actors_byid.get(id);
+            debug_assert!(actor.map_or(false, |a| a.coord() == *coord),
+                          "actor {} isn't at {:?}",
+                          id,
+                          coord);
+            debug_assert!(actor.map_or(true, |a| !a.is_dead()),
+                          "dead actor {} still at {:?}",
+                          id,
+                          coord);
+        }
+        for (id, actor) in &self.actors_byid {
+            debug_assert!(actor.is_dead() ||
+                          self.actors_coord_to_id.get(&actor.coord()) == Some(id),
+                          "actor {} missing from {:?}",
+                          id,
+                          actor.coord());
+        }
+
+        for coord in self.items.keys() {
+            debug_assert!(self.at(*coord).tile().is_passable(),
+                          "item on {:?}, where nobody can walk",
+                          coord);
+        }
+
+        if let Some(id) = self.player_id {
+            debug_assert!(self.actors_byid.get(&id).map_or(false, |a| a.is_player()),
+                          "player {} isn't on the level",
+                          id);
+        }
+
+        let brightest = self.map
+                            .iter()
+                            .map(|(_, t)| cmp::max(t.light, 0) as u32)
+                            .chain(self.burning.keys().map(|_| tile::FIRE_LIGHT as u32))
+                            .chain(self.actors_coord_to_id
+                                       .values()
+                                       .map(|id| self.actors_byid[id].light_emision()))
+                            .max()
+                            .unwrap_or(0);
+        for (coord, &light) in self.light_map.iter() {
+            debug_assert!(light <= brightest,
+                          "light {} at {:?}, brighter than any light there is",
+                          light,
+                          coord);
+        }
+    }
+
+    pub fn spawn_player(&mut self, actor: Actor, rng: &mut GameRng) -> actor::Id {
+        assert!(actor.is_player());
+        self.player_id = Some(self.spawn(actor, rng));
+        self.player_id.unwrap()
+    }
+
+    pub fn skip_act(&mut self, id: u32, rng: &mut GameRng) {
+        self.pre_any_tick();
+        let mut actor = self.actors_byid.remove(&id).unwrap();
+        actor.pre_own_tick();
+        self.put_back(id, actor);
+        self.post_any_tick(rng);
+    }
+
+    /// Returns: false if the action was rejected and no time was spent.
+    pub fn act(&mut self, id: u32, action: Action, rng: &mut GameRng) -> bool {
+        self.pre_any_tick();
+        let mut actor = self.actors_byid.remove(&id).unwrap();
+
+        if !actor.can_perform_action() {
+            // the turn goes by all the same
+            let (coord, reason) = (actor.coord(), Rejection::Recovering(actor.action_cd));
+            debug!(target: "game::act",
+                   "turn {}: {} can't {:?}: {:?}",
+                   self.turn,
+                   id,
+                   action,
+                   reason);
+            self.actors_byid.insert(id, actor);
+            self.event(coord,
+                       Event::Rejected {
+                           id: id,
+                           reason: reason,
+                       });
+            return true;
+        }
+
+        actor.pre_own_tick();
+        let act = Stopwatch::start();
+        let acted = actor.act(self, action, rng);
+        act.stop(&mut self.timings.act);
+        if !acted {
+            debug!(target: "game::act",
+                   "turn {}: {} {} can't {:?}",
+                   self.turn,
+                   actor.description(),
+                   id,
+                   action);
+            // nothing happened, so there's nothing to catch up with
+            self.put_back(id, actor);
+            return false;
+        }
+
+        actor.post_action(action);
+        self.put_back(id, actor);
+        self.post_any_tick(rng);
+        true
+    }
+
+    /// Use the feature that `actor` is standing on, or the
+    /// chest or brazier in front of it
+    ///
+    /// Returns: false if there was nothing to do.
+    pub fn interact(&mut self, actor: &mut Actor, rng: &mut GameRng) -> bool {
+        let coord = actor.coord();
+        let head = actor.head();
+
+        if let Some(tile::Chest(chest)) = self.at(head).tile().feature_on(tile::Layer::Fixture) {
+            return self.open_chest(actor, head, chest, rng);
+        }
+
+        let in_shop = self.shop.as_ref().map_or(false, |s| s.contains(coord));
+        if in_shop && actor.debt() > 0 {
+            if actor.pay_debt() {
+                actor.did_interact.push(actor::InteractResult::ShopPaid);
+                return true;
+            } else {
+                actor.did_interact.push(actor::InteractResult::ShopNoGold);
+                return false;
+            }
+        }
+
+        if let Some(tile::Brazier(lit)) = self.at(head).tile().feature_on(tile::Layer::Fixture) {
+            if lit {
+                self.tile_mut(head).update_feature(tile::Brazier(false)).add_light(0);
+                actor.did_interact.push(actor::InteractResult::BrazierDoused);
+            } else if actor.is_holding_lit_torch() {
+                self.tile_mut(head)
+                    .update_feature(tile::Brazier(true))
+                    .add_light(tile::BRAZIER_LIGHT);
+                actor.did_interact.push(actor::InteractResult::BrazierLit);
+            } else {
+                actor.did_interact.push(actor::InteractResult::BrazierNoFire);
+                return false;
+            }
+            return true;
+        }
+
+        match self.at(coord).tile().feature_on(tile::Layer::Fixture) {
+            Some(tile::Portal(twin)) => actor.teleport(self, twin),
+            Some(tile::Fountain(0)) => {
+                actor.did_interact.push(actor::InteractResult::FountainDry);
+                false
+            }
+            Some(tile::Fountain(uses)) => {
+                let res = match rng.gen_range(0, 3) {
+                    0 => {
+                        actor.hp = cmp::min(actor.hp + 5, actor.stats.base.max_hp);
+                        actor::InteractResult::FountainHeal
+                    }
+                    1 => {
+                        actor.sp = actor.stats.base.max_sp;
+                        actor::InteractResult::FountainRefresh
+                    }
+                    _ => {
+                        actor.poisoned += 5;
+                        actor::InteractResult::FountainPoison
+                    }
+                };
+                actor.did_interact.push(res);
+
+                self.tile_mut(coord).update_feature(tile::Fountain(uses - 1));
+                if uses == 1 {
+                    actor.did_interact.push(actor::InteractResult::FountainDriedUp);
+                }
+                true
+            }
+            Some(tile::Altar(true)) => {
+                actor.did_interact.push(actor::InteractResult::AltarUsed);
+                false
+            }
+            Some(tile::Altar(false)) => {
+                let res = match self.items.get_mut(&coord) {
+                    Some(item) => {
+                        if item.is_cursed() {
+                            let item = Arc::make_mut(item);
+                            item.uncurse();
+                            item.identify();
+                            Some(actor::InteractResult::AltarUncurse)
+                        } else if !item.is_identified() {
+                            Arc::make_mut(item).identify();
+                            Some(actor::InteractResult::AltarIdentify)
+                        } else {
+                            None
+                        }
+                    }
+                    None => None,
+                };
+
+                match res {
+                    Some(res) => {
+                        actor.did_interact.push(res);
+                        self.tile_mut(coord).update_feature(tile::Altar(true));
+                        true
+                    }
+                    None => {
+                        actor.did_interact.push(actor::InteractResult::AltarNoItem);
+                        false
+                    }
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn open_chest(&mut self,
+                  actor: &mut Actor,
+                  coord: Coordinate,
+                  mut chest: tile::Chest,
+                  rng: &mut GameRng)
+                  -> bool {
+        if chest.open {
+            actor.did_interact.push(actor::InteractResult::ChestEmpty);
+            return false;
+        }
+
+        if chest.locked {
+            if !actor.use_key() {
+                actor.did_interact.push(actor::InteractResult::ChestLocked);
+                return false;
+            }
+            chest.locked = false;
+            actor.did_interact.push(actor::InteractResult::ChestUnlocked);
+        }
+
+        if let Some(trap) = chest.trap {
+            if util::roll(actor.stats.base.dex, CHEST_TRAP_DIFFICULTY, rng) {
+                actor.did_interact.push(actor::InteractResult::ChestDisarmed);
+            } else {
+                actor.triggers(trap, rng);
+            }
+            chest.trap = None;
+        }
+
+        chest.open = true;
+        self.tile_mut(coord).update_feature(tile::Chest(chest));
+        actor.did_interact.push(actor::InteractResult::ChestOpened);
+
+        match self.containers.remove(&coord) {
+            Some(item) => self.place_item(coord, item),
+            None => actor.did_interact.push(actor::InteractResult::ChestEmpty),
+        }
+        true
+    }
+
+    /// Roll the boulder at `from` onto `to`. Boulders fill water and
+    /// crush traps.
+    ///
+    /// Returns: false if the way was blocked.
+    pub fn push_boulder(&mut self, from: Coordinate, to: Coordinate) -> bool {
+        if self.at(to).is_occupied() || self.items.contains_key(&to) {
+            return false;
+        }
+
+        let target = *self.at(to).tile();
+        if target.type_ == tile::Water {
+            self.tile_mut(from).remove_feature(tile::Layer::Fixture);
+            self.tile_mut(to).type_ = tile::Empty;
+            return true;
+        }
+
+        if !target.is_passable() || target.feature_on(tile::Layer::Fixture).is_some() ||
+           target.door_state() == Some(false) {
+            return false;
+        }
+
+        self.tile_mut(from).remove_feature(tile::Layer::Fixture);
+        self.tile_mut(to).remove_feature(tile::Layer::Trap);
+        self.tile_mut(to).add_feature(tile::Boulder);
+        true
+    }
+
+    /// Try to break the lock of the chest at `coord`
+    pub fn force_chest(&mut self, actor: &mut Actor, coord: Coordinate, rng: &mut GameRng) {
+        let mut chest = match self.at(coord).tile().closed_chest() {
+            Some(chest) => chest,
+            None => return,
+        };
+
+        if !rng.gen_weighted_bool(2) {
+            actor.did_interact.push(actor::InteractResult::ChestHolds);
+            return;
+        }
+
+        chest.locked = false;
+        self.tile_mut(coord).update_feature(tile::Chest(chest));
+        actor.did_interact.push(actor::InteractResult::ChestForced);
+
+        let fragile = self.containers.get(&coord).map_or(false, |i| i.is_fragile());
+        if fragile && rng.gen_weighted_bool(2) {
+            self.containers.remove(&coord);
+            actor.did_interact.push(actor::InteractResult::ChestContentsBroken);
+        }
+    }
+
+    pub fn event(&mut self, coord: Coordinate, event: Event) {
+        trace!(target: "game::act", "turn {} at {:?}: {:?}", self.turn, coord, event);
+        self.events.push((coord, event));
+    }
+
+    /// Events of the current tick actor `id` took part in or saw; what
+    /// was only heard reaches them as a noise, without names
+    pub fn events_for(&self, id: actor::Id) -> Vec<Event> {
+        let observer = match self.actors_byid.get(&id) {
+            Some(observer) => observer,
+            None => return vec![],
+        };
+
+        self.events
+            .iter()
+            .filter(|&&(coord, ref event)| {
+                event.involves(id) || observer.sees(coord)
+            })
+            .map(|&(_, ref event)| event.clone())
+            .collect()
+    }
+
+    /// Everything the player noticed since last asked, oldest first
+    pub fn take_player_events(&mut self) -> Vec<Event> {
+        self.observe_for_player();
+        ::std::mem::replace(&mut self.player_events, vec![])
+    }
+
+    /// Note what the player noticed during the current tick, once
+    fn observe_for_player(&mut self) {
+        if self.player_observed {
+            return;
+        }
+        let player_id = match self.player_id {
+            Some(id) if self.actors_byid.contains_key(&id) => id,
+            _ => return,
+        };
+        self.player_observed = true;
+
+        let mut events = vec![];
+        let sighted: Vec<actor::Id>;
+        {
+            let player = &self.actors_byid[&player_id];
+
+            let areas: Vec<area::Type> = player.perception
+                                               .discovered_areas
+                                               .iter()
+                                               .filter_map(|c| self.at(*c).tile().area)
+                                               .map(|area| area.type_)
+                                               .collect();
+            if !areas.is_empty() {
+                events.push(Event::Discovered(Discovery::Areas(areas)));
+            }
+
+            for &coord in &player.perception.discovered {
+                if let Some(item) = self.items.get(&coord) {
+                    events.push(Event::Discovered(Discovery::Item(item.description()
+                                                                      .to_string())));
+                }
+            }
+
+            sighted = self.actors_visible_by(player_id)
+                          .into_iter()
+                          .filter(|id| !self.player_sighted.contains(id))
+                          .collect();
+            let actors: Vec<(actor::Race, Option<String>)> =
+                sighted.iter()
+                       .map(|id| &self.actors_byid[id])
+                       .filter(|a| !a.companion)
+                       .map(|a| (a.race, a.name.clone()))
+                       .collect();
+            if !actors.is_empty() {
+                events.push(Event::Discovered(Discovery::Actors(actors)));
+            }
+
+            let moved = player.pre_pos.map_or(false, |pos| pos.coord != player.pos.coord);
+            if let Some(item) = self.items.get(&player.pos.coord) {
+                if moved {
+                    events.push(Event::Discovered(Discovery::Underfoot(item.description()
+                                                                           .to_string())));
+                }
+            }
+
+            if player.discovered_stairs(self) {
+                events.push(Event::Discovered(Discovery::Stairs));
+            }
+
+            for &coord in &player.perception.discovered {
+                match self.at(coord).tile().feature_on(tile::Layer::Fixture) {
+                    Some(tile::Fountain(_)) => events.push(Event::Discovered(Discovery::Fountain)),
+                    Some(tile::Altar(_)) => events.push(Event::Discovered(Discovery::Altar)),
+                    _ => {}
+                }
+            }
+
+            for &result in &player.did_interact {
+                events.push(Event::Interacted {
+                    id: player_id,
+                    result: result,
+                });
+            }
+
+            events.extend(self.events_for(player_id));
+
+            // the player's own noises come from where they stand
+            for (&coord, &heard) in &player.heard {
+                if coord != player.pos.coord && !player.sees(coord) {
+                    events.push(Event::Heard {
+                        coord: coord,
+                        heard: heard,
+                    });
+                }
+            }
+        }
+        self.player_events.extend(events);
+        self.player_sighted.extend(sighted);
+    }
+
+    /// Work out again what each living actor sees, where that could
+    /// have changed: for whoever moved, and wherever the map or the light
+    /// in sight did. Each actor's sight depends on its eye and the level
+    /// as it is, and nothing else, so they're all worked out at once, on
+    /// threads, and the order they get done in makes no difference.
+    fn update_sight(&mut self) {
+        let mut ids = self.actors_alive_ids();
+        ids.sort();
+        let mut looking: Vec<(Eye, Perception)> =
+            ids.iter()
+               .map(|id| {
+                   let actor = self.actors_byid.get_mut(id).unwrap();
+                   (actor.eye(), mem::replace(&mut actor.perception, Perception::new()))
+               })
+               .collect();
+
+        {
+            let loc: &Location = self;
+            looking.par_iter_mut().for_each(|&mut (eye, ref mut perception)| {
+                perception.update(eye, loc);
+            });
+        }
+
+        for (id, (_, perception)) in ids.into_iter().zip(looking) {
+            self.actors_byid.get_mut(&id).unwrap().perception = perception;
+        }
+    }
+
+    pub fn pre_any_tick(&mut self) {
+        // whatever the player noticed is about to be forgotten
+        self.observe_for_player();
+        self.player_observed = false;
+        self.events.clear();
+        self.noisy.clear();
+        for actor in self.actors_byid.values_mut().filter(|a| !a.is_dead()) {
+            actor.pre_any_tick();
+        }
+    }
+
+    pub fn post_any_tick(&mut self, rng: &mut GameRng) {
+        let los = Stopwatch::start();
+        self.update_sight();
+        los.stop(&mut self.timings.los);
+
+        let ids = self.take_alive_ids();
+        for &id in &ids {
+            let mut actor = self.actors_byid.remove(&id).unwrap();
+            actor.post_any_tick(self);
+            self.actors_byid.insert(id, actor);
+        }
+        self.return_ids(ids);
+
+        let mut dying: Vec<actor::Id> = self.actors_byid
+                                            .iter()
+                                            .filter(|&(id, a)| {
+                                                a.is_dead() && !self.actors_dead.contains(id)
+                                            })
+                                            .map(|(&id, _)| id)
+                                            .collect();
+        // in order, as their deaths are told
+        dying.sort();
+        for id in &dying {
+            let mut a = self.actors_byid.remove(&id).unwrap();
+            let coord = a.pos.coord;
+            if self.actors_coord_to_id.get(&coord) == Some(id) {
+                self.actors_coord_to_id.remove(&coord);
+            }
+
+            let mut items: Vec<(char, Arc<Item>)> = a.items_backpack.drain().collect();
+            items.sort_by_key(|&(ch, _)| ch);
+            let mut equipped: Vec<(char, Arc<Item>)> = a.items_equipped
+                                                        .drain()
+                                                        .map(|(_, item)| item)
+                                                        .collect();
+            equipped.sort_by_key(|&(ch, _)| ch);
+            let worn: Vec<Arc<Item>> = equipped.iter()
+                                               .map(|&(_, ref item)| item.clone())
+                                               .collect();
+            for (_, item) in items.into_iter().chain(equipped) {
+                self.place_item(coord, item);
+            }
+
+            let (killer, by_player) = {
+                let killer = self.events
+                                 .iter()
+                                 .filter_map(|&(_, ref event)| {
+                                     match *event {
+                                         Event::Attack { attacker,
+                                                         target,
+                                                         success: true,
+                                                         .. } if target == *id => {
+                                             Some(attacker)
+                                         }
+                                         _ => None,
+                                     }
+                                 })
+                                 .last()
+                                 .and_then(|attacker| self.actors_byid.get(&attacker));
+                (killer.map(|k| k.description()), killer.map_or(false, |k| k.is_player()))
+            };
+            self.kill_log.push(DeadActorRecord {
+                id: *id,
+                race: a.race,
+                name: a.description(),
+                killed_on_turn: self.turn,
+                killer: killer,
+                by_player: by_player,
+                worn: worn,
+            });
+
+            self.actors_byid.insert(*id, a);
+
+            self.actors_dead.insert(*id);
+            self.event(coord, Event::Died { id: *id });
+        }
+
+        self.place_unplaced();
+        let light = Stopwatch::start();
+        self.recalculate_light_map(rng);
+        light.stop(&mut self.timings.light);
+
+        let noise = Stopwatch::start();
+        self.recalculate_noise();
+        noise.stop(&mut self.timings.noise);
+
+        self.check_invariants();
+    }
+
+    /// Drop `item` as close to `coord` as possible; if the floor is
+    /// full, keep it until there's room
+    pub fn place_item(&mut self, coord: Coordinate, item: Arc<Item>) {
+        if let Some(item) = self.at_mut(coord).drop_item(item) {
+            warn!("no room for {} near {:?}, keeping it for later",
+                  item.description(),
+                  coord);
+            self.unplaced.push((coord, item));
+        }
+    }
+
+    /// Try again with the items that found no room before
+    fn place_unplaced(&mut self) {
+        let unplaced = ::std::mem::replace(&mut self.unplaced, vec![]);
+        for (coord, item) in unplaced {
+            if let Some(item) = self.at_mut(coord).drop_item(item) {
+                self.unplaced.push((coord, item));
+            }
+        }
+    }
+
+    /// Everything that happens once per turn, after all the actors acted
+    pub fn post_turn(&mut self, turn: u64, rng: &mut GameRng) {
+        self.turn = turn;
+        self.update_effects(rng);
+        self.regenerate(rng);
+        self.update_shop();
+        self.update_fire(rng);
+        self.update_daylight();
+        self.remove_bodies();
+    }
+
+    /// Wear off the effects the living actors are under
+    fn update_effects(&mut self, rng: &mut GameRng) {
+        // in order, so each gets the same rolls every game
+        let ids = self.take_alive_ids();
+        for id in &ids {
+            self.actors_byid.get_mut(id).unwrap().update_effects(rng);
+        }
+        self.return_ids(ids);
+    }
+
+    fn regenerate(&mut self, rng: &mut GameRng) {
+        let ids = self.take_alive_ids();
+        for id in &ids {
+            self.actors_byid.get_mut(id).unwrap().regenerate(rng);
+        }
+        self.return_ids(ids);
+    }
+
+    /// Change the least light anywhere on the level
+    pub fn set_ambient_light(&mut self, light: u32) {
+        if light != self.ambient_light {
+            self.ambient_light = light;
+            self.light_generation += 1;
+            self.ambient_changed = self.light_generation;
+        }
+    }
+
+    /// What time of day it is; `None` below the surface, where there's
+    /// no telling
+    pub fn daytime(&self) -> Option<Daytime> {
+        if self.id.absolute_depth() != 0 {
+            return None;
+        }
+        // the first half of the day, then of the night
+        let half = self.turn % DAY_LENGTH < DAY_LENGTH / 2;
+        Some(match ((self.turn / DAY_LENGTH) % 2 == 0, half) {
+            (true, true) => Daytime::Morning,
+            (true, false) => Daytime::Afternoon,
+            (false, true) => Daytime::Evening,
+            (false, false) => Daytime::Night,
+        })
+    }
+
+    /// What the part of the level at `coord` is called: the kind of
+    /// room, a shop, or a corridor between them
+    pub fn place_name(&self, coord: Coordinate) -> String {
+        if self.shop.as_ref().map_or(false, |s| s.contains(coord)) {
+            return "shop".to_string();
+        }
+        match self.at(coord).tile().area {
+            Some(ref area) => area.type_.to_string(),
+            None => "corridor".to_string(),
+        }
+    }
+
+    /// Night and day on the surface
+    fn update_daylight(&mut self) {
+        if self.id.absolute_depth() != 0 {
+            return;
+        }
+
+        let day = (self.turn / DAY_LENGTH) % 2 == 0;
+        let light = if day {
+            generate::SURFACE_AMBIENT_LIGHT
+        } else {
+            0
+        };
+
+        if light != self.ambient_light {
+            self.set_ambient_light(light);
+            let start = self.start;
+            self.event(start, if day { Event::Daybreak } else { Event::Nightfall });
+        }
+    }
+
+    pub fn is_burning(&self, coord: Coordinate) -> bool {
+        self.burning.contains_key(&coord)
+    }
+
+    /// Set the tile at `coord` on fire if it can burn
+    ///
+    /// Returns: false if it didn't catch fire.
+    pub fn ignite(&mut self, coord: Coordinate) -> bool {
+        match self.at(coord).tile().burn_time() {
+            Some(turns) if !self.is_burning(coord) => {
+                self.burning.insert(coord, turns);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Set `coord` on fire for at least `turns`, whether or not the tile
+    /// itself can burn
+    pub fn set_on_fire(&mut self, coord: Coordinate, turns: u32) {
+        let left = self.burning.entry(coord).or_insert(0);
+        *left = cmp::max(*left, turns);
+    }
+
+    /// Burn whatever is standing in the fire and let it spread. Only
+    /// the burning tiles are looked at.
+    fn update_fire(&mut self, rng: &mut GameRng) {
+        let mut burning: Vec<(Coordinate, u32)> = self.burning
+                                                      .iter()
+                                                      .map(|(&c, &t)| (c, t))
+                                                      .collect();
+        // spread in the same order for the same game
+        burning.sort_by_key(|&(c, _)| (c.x, c.y));
+
+        for (coord, turns) in burning {
+            if let Some(&id) = self.actors_coord_to_id.get(&coord) {
+                let actor = self.actors_byid.get_mut(&id).unwrap();
+                actor.hp -= rng.gen_range(1, FIRE_DMG + 1);
+                actor.did_interact.push(actor::InteractResult::Burned);
+            }
+
+            for &n in &coord.neighbors() {
+                if rng.gen_weighted_bool(FIRE_SPREAD_CHANCE) {
+                    self.ignite(n);
+                }
+            }
+
+            if turns > 1 {
+                self.burning.insert(coord, turns - 1);
+            } else {
+                self.burning.remove(&coord);
+                self.tile_mut(coord).burn_out();
+            }
+        }
+    }
+
+    /// Forget the bodies of actors that died before the last turn;
+    /// everybody had a chance to see them die, and the kill log
+    /// remembers the rest. The player's body stays.
+    fn remove_bodies(&mut self) {
+        let turn = self.turn;
+        let player_id = self.player_id;
+        let gone: Vec<actor::Id> = self.kill_log
+                                       .iter()
+                                       .filter(|r| r.killed_on_turn + 1 < turn)
+                                       .map(|r| r.id)
+                                       .filter(|&id| Some(id) != player_id)
+                                       .filter(|id| self.actors_dead.contains(id))
+                                       .collect();
+
+        for id in gone {
+            self.actors_dead.remove(&id);
+            self.actors_byid.remove(&id);
+        }
+    }
+
+    /// Anger the shopkeeper if the player attacked it, or walked out
+    /// without paying
+    fn update_shop(&mut self) {
+        let player_id = match self.player_id {
+            Some(id) if self.actors_byid.contains_key(&id) => id,
+            _ => return,
+        };
+
+        let angry = match self.shop {
+            Some(ref shop) if !shop.angry => {
+                let player = &self.actors_byid[&player_id];
+                let provoked = shop.keeper
+                                   .and_then(|id| self.actors_byid.get(&id))
+                                   .map_or(false, |k| k.provoked && !k.is_dead());
+                provoked || (player.debt() > 0 && !shop.contains(player.coord()))
+            }
+            _ => false,
+        };
+
+        if angry {
+            self.shop.as_mut().unwrap().angry = true;
+            self.actors_byid
+                .get_mut(&player_id)
+                .unwrap()
+                .did_interact
+                .push(actor::InteractResult::ShopThief);
+        }
+    }
+
+    pub fn at(&self, coord: Coordinate) -> At {
+        At {
+            coord: coord,
+            state: self,
+        }
+    }
+
+    pub fn at_mut(&mut self, coord: Coordinate) -> AtMut {
+        AtMut {
+            coord: coord,
+            state: self,
+        }
+    }
+}
+
+impl Location {
+    /// Write the level out for `read_from` to read back: what changed
+    /// of the map since it was `generated`, and everything on it, then
+    /// a last line saying that was all. The kill log isn't written; nor
+    /// is what happened during the last tick, which nobody is left to
+    /// notice.
+    pub fn write_to<W: Write>(&self, w: &mut W, generated: &Location) -> io::Result<()> {
+        let priced = |key: &str, items: &Items| -> Vec<String> {
+            let mut items: Vec<_> = items.iter().collect();
+            items.sort_by_key(|&(c, _)| (c.y, c.x));
+            items.into_iter()
+                 .map(|(c, item)| format!("{} {} {} {}", key, c.x, c.y, item.encode_priced()))
+                 .collect()
+        };
+
+        try!(writeln!(w, "{}", FORMAT.header()));
+        try!(writeln!(w, "level {} {}", self.id.branch.name(), self.id.depth));
+        try!(writeln!(w, "turn {}", self.turn));
+        if let Some(left_at) = self.left_at {
+            try!(writeln!(w, "left {}", left_at));
+        }
+        try!(writeln!(w, "counter {}", self.actors_counter));
+        try!(writeln!(w, "ambient {} {}", self.ambient_light, self.ambient_changed));
+        try!(writeln!(w,
+                      "generations {} {}",
+                      self.map_generation,
+                      self.light_generation));
+
+        let mut coords: Vec<Coordinate> = self.map.iter().map(|(&c, _)| c).collect();
+        coords.sort_by_key(|c| (c.y, c.x));
+        for &c in &coords {
+            let tile = self.at(c).tile();
+            if tile != generated.at(c).tile() {
+                try!(writeln!(w, "tile {} {} {}", c.x, c.y, tile.encode()));
+            }
+        }
+        let mut lit: Vec<(Coordinate, u32)> = self.light_map
+                                                  .iter()
+                                                  .filter(|&(_, &light)| light != 0)
+                                                  .map(|(&c, &light)| (c, light))
+                                                  .collect();
+        lit.sort_by_key(|&(c, _)| (c.y, c.x));
+        for (c, light) in lit {
+            try!(writeln!(w, "light {} {} {}", c.x, c.y, light));
+        }
+        let mut changed: Vec<_> = self.light_changed.iter().collect();
+        changed.sort_by_key(|&(c, _)| (c.y, c.x));
+        for (c, gen) in changed {
+            try!(writeln!(w, "light_changed {} {} {}", c.x, c.y, gen));
+        }
+        let mut burning: Vec<_> = self.burning.iter().collect();
+        burning.sort_by_key(|&(c, _)| (c.y, c.x));
+        for (c, turns) in burning {
+            try!(writeln!(w, "burning {} {} {}", c.x, c.y, turns));
+        }
+
+        let mut items = priced("floor", &self.items);
+        items.extend(priced("chest", &self.containers));
+        for line in items {
+            try!(writeln!(w, "{}", line));
+        }
+        for &(c, ref item) in &self.unplaced {
+            try!(writeln!(w, "unplaced {} {} {}", c.x, c.y, item.encode_priced()));
+        }
+
+        if let Some(ref shop) = self.shop {
+            let keeper = shop.keeper.map_or("-".to_string(), |id| id.to_string());
+            try!(writeln!(w, "shop {} {}", keeper, shop.angry as u8));
+        }
+        if let Some((ref known, ref known_areas)) = self.player_memory {
+            try!(writeln!(w, "remembered {}", encode_coords(known)));
+            try!(writeln!(w, "remembered_areas {}", encode_coords(known_areas)));
+        }
+        let mut sighted: Vec<_> = self.player_sighted.iter().map(|id| id.to_string()).collect();
+        sighted.sort();
+        try!(writeln!(w, "sighted {}", sighted.join(" ")));
+
+        for id in self.actors_ids() {
+            for line in self.actors_byid[&id].lines() {
+                try!(writeln!(w, "actor {} {}", id, line));
+            }
+        }
+        writeln!(w, "end")
+    }
+
+    /// The level `write_to` wrote, put together with what it was
+    /// `generated` as; lines with keys this version doesn't know are
+    /// skipped
+    pub fn read_from<R: BufRead>(r: R, generated: Location) -> io::Result<Location> {
+        let invalid = |what: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("corrupted level: {}", what))
+        };
+        let coord_and = |value: &str| -> Option<(Coordinate, String)> {
+            let mut words = value.splitn(3, ' ');
+            match (words.next().map(|x| x.parse()), words.next().map(|y| y.parse())) {
+                (Some(Ok(x)), Some(Ok(y))) => {
+                    Some((Coordinate::new(x, y), words.next().unwrap_or("").to_string()))
+                }
+                _ => None,
+            }
+        };
+
+        let mut lines = try!(FORMAT.read(r)).into_iter();
+        let level = format!("level {} {}", generated.id.branch.name(), generated.id.depth);
+        if lines.next().as_ref() != Some(&level) {
+            return Err(invalid("not the level expected"));
+        }
+
+        let mut loc = generated;
+        loc.actors_byid.clear();
+        loc.actors_coord_to_id.clear();
+        loc.items.clear();
+        loc.containers.clear();
+        loc.light_map = LightMap::new();
+        let mut actors: Vec<(actor::Id, Vec<String>)> = vec![];
+        let mut ended = false;
+
+        for line in lines {
+            if ended {
+                return Err(invalid("lines after the end"));
+            }
+            let (key, value) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => (&line[..], ""),
+            };
+            let parsed = match key {
+                "turn" => value.parse::<u64>().ok().map(|turn| loc.turn = turn),
+                "left" => value.parse::<u64>().ok().map(|turn| loc.left_at = Some(turn)),
+                "counter" => value.parse::<u32>().ok().map(|n| loc.actors_counter = n),
+                "ambient" | "generations" => {
+                    let numbers: Result<Vec<u64>, _> = value.split(' ')
+                                                            .map(|w| w.parse())
+                                                            .collect();
+                    match numbers {
+                        Ok(ref n) if n.len() == 2 && key == "ambient" => {
+                            loc.ambient_light = n[0] as u32;
+                            loc.ambient_changed = n[1];
+                            Some(())
+                        }
+                        Ok(ref n) if n.len() == 2 => {
+                            loc.map_generation = n[0];
+                            loc.light_generation = n[1];
+                            Some(())
+                        }
+                        _ => None,
+                    }
+                }
+                "tile" => {
+                    coord_and(value).and_then(|(c, rest)| {
+                        tile::Tile::decode(&rest).map(|mut tile| {
+                            tile.area = loc.at(c).tile().area;
+                            Arc::make_mut(&mut loc.map)[c] = tile;
+                        })
+                    })
+                }
+                "light" | "light_changed" | "burning" => {
+                    coord_and(value).and_then(|(c, rest)| {
+                        rest.parse().ok().map(|n: u64| {
+                            match key {
+                                "light" => loc.light_map[c] = n as u32,
+                                "light_changed" => {
+                                    loc.light_changed.insert(c, n);
+                                }
+                                _ => {
+                                    loc.burning.insert(c, n as u32);
+                                }
+                            }
+                        })
+                    })
+                }
+                "floor" | "chest" | "unplaced" => {
+                    coord_and(value).and_then(|(c, rest)| {
+                        Item::decode_priced(&rest).map(|item| {
+                            let item = Arc::new(item);
+                            match key {
+                                "floor" => {
+                                    loc.items.insert(c, item);
+                                }
+                                "chest" => {
+                                    loc.containers.insert(c, item);
+                                }
+                                _ => loc.unplaced.push((c, item)),
+                            }
+                        })
+                    })
+                }
+                "shop" => {
+                    let words: Vec<&str> = value.split(' ').collect();
+                    let keeper = match words[0] {
+                        "-" => Some(None),
+                        id => id.parse().ok().map(Some),
+                    };
+                    match (loc.shop.as_mut(), keeper, words.len()) {
+                        (Some(shop), Some(keeper), 2) => {
+                            shop.keeper = keeper;
+                            shop.angry = words[1] == "1";
+                            Some(())
+                        }
+                        _ => None,
+                    }
+                }
+                "remembered" | "remembered_areas" => {
+                    decode_coords(value).map(|coords| {
+                        if loc.player_memory.is_none() {
+                            loc.player_memory = Some(Default::default());
+                        }
+                        let memory = loc.player_memory.as_mut().unwrap();
+                        if key == "remembered" {
+                            memory.0 = coords;
+                        } else {
+                            memory.1 = coords;
+                        }
+                    })
+                }
+                "sighted" => {
+                    let ids: Result<Vec<actor::Id>, _> = value.split_whitespace()
+                                                             .map(|id| id.parse())
+                                                             .collect();
+                    ids.ok().map(|ids| loc.player_sighted = ids.into_iter().collect())
+                }
+                "actor" => {
+                    let mut words = value.splitn(2, ' ');
+                    match (words.next().map(|id| id.parse()), words.next()) {
+                        (Some(Ok(id)), Some(line)) => {
+                            if actors.last().map_or(false, |&(last, _)| last == id) {
+                                actors.last_mut().unwrap().1.push(line.to_string());
+                            } else {
+                                actors.push((id, vec![line.to_string()]));
+                            }
+                            Some(())
+                        }
+                        _ => None,
+                    }
+                }
+                "end" => {
+                    ended = true;
+                    Some(())
+                }
+                _ => Some(()),
+            };
+            if parsed.is_none() {
+                return Err(invalid(&line));
+            }
+        }
+        if !ended {
+            return Err(invalid("cut short"));
+        }
+
+        for (id, lines) in actors {
+            let actor = try!(Actor::parse(lines.iter().map(|l| &l[..]))
+                                 .ok_or_else(|| invalid("bad actor")));
+            if actor.is_dead() {
+                loc.actors_dead.insert(id);
+            } else if loc.actors_coord_to_id.insert(actor.pos.coord, id).is_some() {
+                return Err(invalid("actors on the same tile"));
+            }
+            if loc.actors_byid.insert(id, actor).is_some() {
+                return Err(invalid("actor twice"));
+            }
+        }
+        Ok(loc)
+    }
+}
+
+/// Smallest and largest coordinates of the tiles on `map`
+fn map_bounds(map: &Map) -> (Coordinate, Coordinate) {
+    let mut coords = map.iter().map(|(&c, _)| c);
+    let first = coords.next().unwrap_or(Coordinate::new(0, 0));
+
+    coords.fold((first, first), |(min, max), c| {
+        (Coordinate::new(cmp::min(min.x, c.x), cmp::min(min.y, c.y)),
+         Coordinate::new(cmp::max(max.x, c.x), cmp::max(max.y, c.y)))
+    })
+}
+
+pub struct At<'a> {
+    coord: Coordinate,
+    state: &'a Location,
+}
+
+impl<'a> At<'a> {
+    /// The tile, or solid rock outside of the map
+    pub fn tile(&self) -> &'a tile::Tile {
+        self.tile_opt().unwrap_or(&tile::ROCK)
+    }
+
+    /// `None` outside of the map
+    pub fn tile_opt(&self) -> Option<&'a tile::Tile> {
+        if self.state.in_bounds(self.coord) {
+            Some(&self.state.map[self.coord])
+        } else {
+            None
+        }
+    }
+
+    pub fn actor_map_or<R, F: Fn(&Actor) -> R>(&self, def: R, cond: F) -> R {
+        self.state
+            .actors_coord_to_id
+            .get(&self.coord)
+            .map(|&id| &self.state.actors_byid[&id])
+            .map_or(def, |a| cond(&a))
+    }
+
+    pub fn item_map_or<R, F: Fn(&Arc<Item>) -> R>(&self, def: R, cond: F) -> R {
+        self.state.items.get(&self.coord).map_or(def, |i| cond(i))
+    }
+
+    pub fn is_occupied(&self) -> bool {
+        self.state.actors_coord_to_id.contains_key(&self.coord)
+    }
+
+    pub fn is_passable(&self) -> bool {
+        !self.is_occupied() && self.tile().is_passable()
+    }
+
+    /// Light from light sources, but never less than the ambient light
+    pub fn light(&self) -> u32 {
+        cmp::max(self.state.light_map[self.coord], self.state.ambient_light)
+    }
+
+    pub fn light_as_seen_by(&self, astate: &Actor) -> u32 {
+        self.light_as_seen_from(astate.pos.coord)
+    }
+
+    /// How lit the tile looks from `pl_coord`: an opaque one by the
+    /// light on its sides facing there
+    pub fn light_as_seen_from(&self, pl_coord: Coordinate) -> u32 {
+        let ownlight = self.light();
+        if self.tile().opaqueness() < 20 {
+            ownlight
+        } else {
+            pl_coord.directions_to(self.coord)
+                    .iter()
+                    .map(|&dir| self.coord - dir)
+                    .map(|d_coord| {
+                        if self.state.at(d_coord).tile().opaqueness() < 20 {
+                            self.state.at(d_coord).light()
+                        } else {
+                            0
+                        }
+                    })
+                    .max()
+                    .unwrap_or(0)
+        }
+    }
+
+    pub fn item(&self) -> Option<&'a Item> {
+        self.state.items.get(&self.coord).map(|i| &**i)
+    }
+}
+
+pub struct AtMut<'a> {
+    coord: Coordinate,
+    state: &'a mut Location,
+}
+
+impl<'a> AtMut<'a> {
+    /// Put `item` on the closest free floor tile
+    ///
+    /// Returns: the item back if there was no room for it.
+    pub fn drop_item(&mut self, item: Arc<Item>) -> Option<Arc<Item>> {
+        let coord = {
+            let state = &*self.state;
+            let free = |c| state.at(c).tile().is_passable() && state.items.get(&c).is_none();
+            let start = self.coord;
+            state.nearest_matching(start, free, &Default::default())
+                 .map(|path| path.last().cloned().unwrap_or(start))
+        };
+
+        match coord {
+            None => Some(item),
+            Some(coord) => {
+                self.state.items.insert(coord, item);
+                None
+            }
+        }
+    }
+
+    pub fn pick_item(&mut self) -> Option<Arc<Item>> {
+        if self.state.items.get(&self.coord).is_some() {
+            self.state.items.remove(&self.coord)
+        } else {
+            None
+        }
+    }
+
+    pub fn tile(&'a mut self) -> &'a mut tile::Tile {
+        self.state.tile_mut(self.coord)
+    }
+
+}
+
+#[cfg(test)]
+impl Location {
+    /// A level of nothing but what `rows` draw, one character a tile at
+    /// `(column, row)`: `.` floor, `+` closed door, `'` open door, and
+    /// wall for anything else. Nobody and nothing is on it.
+    pub fn from_rows(rows: &[&str]) -> Location {
+        let mut map = Map::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                let coord = Coordinate::new(x as i32, y as i32);
+                let mut tile = match ch {
+                    '.' | '+' | '\'' => tile::Tile::new(tile::Empty),
+                    _ => tile::Tile::new(tile::Wall),
+                };
+                match ch {
+                    '+' => {
+                        tile.add_feature(tile::Door(false, tile::DOOR_HP));
+                    }
+                    '\'' => {
+                        tile.add_feature(tile::Door(true, tile::DOOR_HP));
+                    }
+                    _ => {}
+                }
+                map[coord] = tile;
+            }
+        }
+
+        let mut loc = Location::new(LevelId::start(), &mut util::game_rng(0));
+        loc.bounds = map_bounds(&map);
+        loc.map = Arc::new(map);
+        loc.actors_byid.clear();
+        loc.actors_coord_to_id.clear();
+        loc.actors_counter = 0;
+        loc.items.clear();
+        loc.containers.clear();
+        loc.shop = None;
+        loc.start = Coordinate::new(1, 1);
+        loc.companion_spots.clear();
+        loc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use hex2d::{Angle, Coordinate, Direction, Position};
+    use rand::Rng;
+
+    use game::{area, in_order, tile, Action, Branch, Daytime, Event, LevelId, Map, Noise};
+    use game::actor::{self, Actor, Race};
+    use game::item::{self, Item};
+    use generate;
+    use util::{self, GameRng};
+    use super::{Location, Shop, DAY_LENGTH};
+
+    /// Facing from `from` to its neighbor `to`
+    fn towards(from: Coordinate, to: Coordinate) -> Direction {
+        *Direction::all().iter().find(|&&d| from + d == to).unwrap()
+    }
+
+    #[test]
+    fn corridor_goes_dark_when_its_brazier_is_doused() {
+        let mut loc = Location::from_rows(&["#########",
+                                            "#.......#",
+                                            "#########"]);
+        let brazier = Coordinate::new(1, 1);
+        loc.tile_mut(brazier).add_feature(tile::Brazier(true)).add_light(tile::BRAZIER_LIGHT);
+        loc.recalculate_light_map(&mut util::game_rng(0));
+        let corridor: Vec<Coordinate> = (2..8).map(|x| Coordinate::new(x, 1)).collect();
+        for &c in &corridor[..3] {
+            assert!(loc.light_map[c] > 0, "{:?} is dark", c);
+        }
+
+        let mut rng = util::game_rng(0);
+        let coord = Coordinate::new(2, 1);
+        let pos = Position::new(coord, towards(coord, brazier));
+        let id = loc.spawn(Actor::new(Race::Human, pos), &mut rng);
+        assert!(loc.act(id, Action::Interact, &mut rng));
+
+        assert_eq!(loc.map[brazier].feature(), Some(tile::Brazier(false)));
+        for &c in &corridor {
+            assert_eq!(loc.light_map[c], 0, "{:?} is still lit", c);
+        }
+    }
+
+    const ANGLES: [Angle; 6] = [Angle::Forward,
+                                Angle::Right,
+                                Angle::RightBack,
+                                Angle::Back,
+                                Angle::LeftBack,
+                                Angle::Left];
+
+    #[test]
+    fn noise_crosses_one_closed_door_but_not_two() {
+        let loc = Location::from_rows(&["##########",
+                                        "#..+.+...#",
+                                        "##########"]);
+        let spread = loc.noise_spread(Coordinate::new(1, 1), 7);
+
+        // 7, less 1 a floor tile and 4 for the door
+        assert_eq!(spread.get(&Coordinate::new(3, 1)), Some(&2));
+        assert_eq!(spread.get(&Coordinate::new(4, 1)), Some(&1));
+        for x in 5..9 {
+            assert_eq!(spread.get(&Coordinate::new(x, 1)), None, "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn noise_crosses_doors_that_are_open() {
+        let loc = Location::from_rows(&["##########",
+                                        "#..'.'...#",
+                                        "##########"]);
+        let spread = loc.noise_spread(Coordinate::new(1, 1), 7);
+
+        assert_eq!(spread.get(&Coordinate::new(8, 1)), Some(&0));
+    }
+
+    #[test]
+    fn noise_never_crosses_a_wall() {
+        let loc = Location::from_rows(&["#########",
+                                        "#...#...#",
+                                        "#...#...#",
+                                        "#...#...#",
+                                        "#########"]);
+        let spread = loc.noise_spread(Coordinate::new(2, 2), 100);
+
+        assert!(spread.contains_key(&Coordinate::new(3, 3)));
+        assert!(spread.keys().all(|c| c.x < 4), "{:?}", spread);
+    }
+
+    #[test]
+    fn dying_in_a_closet_loses_no_items() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["#######",
+                                            "#.#...#",
+                                            "#######"]);
+        let closet = Coordinate::new(1, 1);
+        let id = loc.spawn(Actor::new(Race::Goblin, Position::new(closet, Direction::XZ)),
+                           &mut rng);
+        {
+            let goblin = loc.actors_byid.get_mut(&id).unwrap();
+            let types = [item::Type::Knife, item::Type::Junk, item::Type::Helmet];
+            for i in 0..30 {
+                let item = Arc::new(Item::new(types[i % types.len()], vec![]));
+                assert!(goblin.pick_item(item).is_none());
+            }
+            goblin.hp = 0;
+        }
+        loc.post_any_tick(&mut rng);
+
+        assert!(loc.actors_byid[&id].items_backpack.is_empty());
+        assert_eq!(loc.items.len(), 1);
+        assert_eq!(loc.items.len() + loc.unplaced.len(), 30);
+
+        // once there's room, the rest gets there
+        *loc.tile_mut(Coordinate::new(2, 1)) = tile::Tile::new(tile::Empty);
+        loc.post_any_tick(&mut rng);
+        assert_eq!(loc.items.len(), 5);
+        assert_eq!(loc.items.len() + loc.unplaced.len(), 30);
+    }
+
+    #[test]
+    fn a_thousand_kills_leave_no_bodies_behind() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["#####",
+                                            "#...#",
+                                            "#####"]);
+        let coord = Coordinate::new(2, 1);
+        for turn in 0..1000 {
+            let id = loc.spawn(Actor::new(Race::Rat, Position::new(coord, Direction::XZ)),
+                               &mut rng);
+            loc.actors_byid.get_mut(&id).unwrap().hp = 0;
+            loc.post_any_tick(&mut rng);
+            loc.post_turn(turn + 1, &mut rng);
+
+            // the latest bodies, for whoever saw them die
+            assert!(loc.actors_byid.len() <= 2,
+                    "{} actors on turn {}",
+                    loc.actors_byid.len(),
+                    turn);
+            assert_eq!(loc.actors_byid.len(), loc.actors_dead.len());
+        }
+
+        assert_eq!(loc.kill_log.len(), 1000);
+        assert!(loc.kill_log.iter().all(|r| r.race == Race::Rat && r.killer.is_none()));
+    }
+
+    /// Coordinates in a ring `r` away from `center`
+    fn ring(center: Coordinate, r: i32) -> Vec<Coordinate> {
+        let mut ring = vec![];
+        center.for_each_in_range(r, |c| {
+            if c.distance(center) == r {
+                ring.push(c);
+            }
+        });
+        ring
+    }
+
+    #[test]
+    fn outside_the_map_is_solid_rock() {
+        let loc = Location::from_rows(&["....",
+                                        "...."]);
+        let (min, max) = loc.bounds();
+        assert_eq!((min, max), (Coordinate::new(0, 0), Coordinate::new(3, 1)));
+
+        let player = Actor::new(Race::Human, Position::new(Coordinate::new(0, 0), Direction::XY));
+        for &r in &[1, 10, 100] {
+            for c in ring(Coordinate::new(1, 1), r).into_iter().filter(|&c| !loc.in_bounds(c)) {
+                assert!(loc.at(c).tile_opt().is_none(), "{:?}", c);
+                let tile = loc.at(c).tile();
+                assert_eq!(tile.type_, tile::Wall);
+                assert!(!tile.is_passable());
+                assert!(tile.opaqueness() >= 1000);
+                // what the renderer asks of every cell on the screen
+                assert_eq!(loc.at(c).light_as_seen_by(&player), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn nobody_sees_past_the_edge_of_the_map() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["......",
+                                            "......",
+                                            "......"]);
+        let corner = Coordinate::new(0, 0);
+        for &dir in Direction::all().iter() {
+            let id = loc.spawn(Actor::new(Race::Human, Position::new(corner, dir)), &mut rng);
+            assert!(loc.act(id, Action::Wait, &mut rng));
+            for &c in &loc.actors_byid[&id].perception.visible {
+                assert!(loc.in_bounds(c) || c.neighbors().iter().any(|&n| loc.in_bounds(n)),
+                        "{:?} looking {:?} sees {:?}",
+                        corner,
+                        dir,
+                        c);
+            }
+            loc.remove(id);
+        }
+
+        // noise and dropped items spread by flood fill
+        let spread = loc.noise_spread(corner, 50);
+        assert!(spread.keys().all(|&c| loc.in_bounds(c)), "{:?}", spread);
+        for _ in 0..18 {
+            assert!(loc.at_mut(corner).drop_item(Arc::new(Item::new(item::Type::Junk, vec![])))
+                       .is_none());
+        }
+        assert!(loc.at_mut(corner).drop_item(Arc::new(Item::new(item::Type::Junk, vec![])))
+                   .is_some());
+        assert!(loc.items.keys().all(|&c| loc.in_bounds(c)));
+    }
+
+    #[test]
+    fn grass_burns_out_and_stone_never_catches_fire() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["########",
+                                            "#......#",
+                                            "########"]);
+        let grass: Vec<Coordinate> = (1..4).map(|x| Coordinate::new(x, 1)).collect();
+        for &c in &grass {
+            loc.tile_mut(c).type_ = tile::Grass;
+        }
+        let stone: Vec<Coordinate> = loc.map
+                                        .iter()
+                                        .map(|(&c, _)| c)
+                                        .filter(|c| !grass.contains(c))
+                                        .collect();
+
+        assert!(!loc.ignite(stone[0]));
+        assert!(loc.ignite(grass[0]));
+        for turn in 1..30 {
+            loc.post_turn(turn, &mut rng);
+            for &c in &stone {
+                assert!(!loc.is_burning(c), "{:?} on fire on turn {}", c, turn);
+            }
+        }
+
+        assert!(loc.burning.is_empty());
+        assert_eq!(loc.map[grass[0]].type_, tile::Scorched);
+        for &c in &grass {
+            let type_ = loc.map[c].type_;
+            assert!(type_ == tile::Grass || type_ == tile::Scorched, "{:?}", type_);
+        }
+        // nothing left to burn
+        assert!(!loc.ignite(grass[0]));
+    }
+
+    #[test]
+    fn ambient_light_lets_actors_see_further() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["####################",
+                                            "#..................#",
+                                            "####################"]);
+        loc.set_ambient_light(0);
+        let coord = Coordinate::new(1, 1);
+        let id = loc.spawn(Actor::new(Race::Human, Position::new(coord, Direction::XZ)),
+                           &mut rng);
+        // how far along the corridor the actor sees
+        let sight = |loc: &Location| {
+            loc.actors_byid[&id]
+                .perception
+                .visible
+                .iter()
+                .filter(|&&c| loc.at(c).tile().is_passable())
+                .map(|&c| c.distance(coord))
+                .max()
+                .unwrap_or(0)
+        };
+
+        assert!(loc.act(id, Action::Wait, &mut rng));
+        let dark = sight(&loc);
+        assert_eq!(dark, loc.actors_byid[&id].stats.base.infravision);
+
+        loc.set_ambient_light(generate::SURFACE_AMBIENT_LIGHT);
+        assert!(loc.act(id, Action::Wait, &mut rng));
+        let lit = sight(&loc);
+        assert!(lit > dark, "{} tiles in the light, {} in the dark", lit, dark);
+        assert!(loc.at(Coordinate::new(18, 1)).light() > 0);
+    }
+
+    #[test]
+    fn noises_are_forgotten_once_they_stop() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["##########",
+                                            "#........#",
+                                            "##########"]);
+        let hearer = loc.spawn(Actor::new(Race::Goblin,
+                                          Position::new(Coordinate::new(1, 1), Direction::XZ)),
+                               &mut rng);
+        let alarm = Coordinate::new(6, 1);
+        let source = loc.spawn(Actor::new(Race::Rat, Position::new(alarm, Direction::ZX)),
+                               &mut rng);
+
+        loc.actors_byid.get_mut(&source).unwrap().noise_makes(Noise::Alarm);
+        loc.made_noise(source);
+        loc.recalculate_noise();
+        let heard = loc.actors_byid[&hearer].heard[&alarm];
+        assert_eq!(heard.noise, Noise::Alarm);
+        assert!(heard.loudness > 0);
+
+        assert!(loc.act(hearer, Action::Wait, &mut rng));
+        assert!(!loc.actors_byid[&hearer].heard.contains_key(&alarm));
+    }
+
+    #[test]
+    fn noises_made_in_the_same_tick_are_all_heard() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["###########",
+                                            "#.........#",
+                                            "###########"]);
+        let hearer = loc.spawn(Actor::new(Race::Goblin,
+                                          Position::new(Coordinate::new(5, 1), Direction::XZ)),
+                               &mut rng);
+        let (left, right) = (Coordinate::new(1, 1), Coordinate::new(9, 1));
+        let alarm = loc.spawn(Actor::new(Race::Rat, Position::new(left, Direction::XZ)),
+                              &mut rng);
+        let crash = loc.spawn(Actor::new(Race::Rat, Position::new(right, Direction::ZX)),
+                              &mut rng);
+
+        loc.actors_byid.get_mut(&alarm).unwrap().noise_makes(Noise::Alarm);
+        loc.actors_byid.get_mut(&crash).unwrap().noise_makes(Noise::Crash);
+        loc.made_noise(alarm);
+        loc.made_noise(crash);
+        loc.recalculate_noise();
+
+        let goblin = &loc.actors_byid[&hearer];
+        assert_eq!(goblin.heard[&left].noise, Noise::Alarm);
+        assert_eq!(goblin.heard[&right].noise, Noise::Crash);
+        assert!(goblin.hears(left) && goblin.hears(right));
+    }
+
+    #[test]
+    fn actors_in_range_includes_the_boundary() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["###########",
+                                            "#.........#",
+                                            "###########"]);
+        let ids: Vec<u32> = (1..9)
+                                .map(|x| {
+                                    let pos = Position::new(Coordinate::new(x, 1), Direction::XZ);
+                                    loc.spawn(Actor::new(Race::Rat, pos), &mut rng)
+                                })
+                                .collect();
+        let center = Coordinate::new(1, 1);
+
+        assert!(loc.actors_in_range(center, -1).is_empty());
+        // probing the few hexes around, and going through all the actors
+        // when there are fewer of them, must agree
+        for r in 0..10 {
+            let expected: Vec<u32> = ids.iter().cloned().take(r as usize + 1).collect();
+            assert_eq!(loc.actors_in_range(center, r), expected, "r = {}", r);
+        }
+        assert_eq!(loc.actors_in_range(center, 1000), ids);
+    }
+
+    #[test]
+    fn actors_in_range_is_quick_on_a_crowded_level() {
+        let mut loc = Location::from_rows(&["."]);
+        let mut id = 0;
+        Coordinate::new(0, 0).for_each_in_range(30, |c| {
+            loc.actors_coord_to_id.insert(c, id);
+            id += 1;
+        });
+
+        let started = Instant::now();
+        let mut found = 0;
+        for i in 0..10000 {
+            let center = Coordinate::new(i % 50 - 25, i % 30 - 15);
+            found += loc.actors_in_range(center, 3).len();
+        }
+        assert!(found > 0);
+        assert!(started.elapsed() < Duration::from_secs(2),
+                "10000 queries took {:?}",
+                started.elapsed());
+    }
+
+    #[test]
+    fn the_player_notices_their_own_fight_but_not_one_behind_a_wall() {
+        let mut rng = util::game_rng(0);
+        let mut loc = Location::from_rows(&["#########",
+                                            "#...#...#",
+                                            "#########"]);
+        loc.set_ambient_light(generate::SURFACE_AMBIENT_LIGHT);
+        let tough = |race: Race, x: i32, dir: Direction| {
+            let mut actor = Actor::new(race, Position::new(Coordinate::new(x, 1), dir));
+            actor.hp = 1000;
+            actor
+        };
+
+        let mut player = tough(Race::Human, 1, Direction::XZ);
+        player.set_player();
+        let player = loc.spawn_player(player, &mut rng);
+        let goblin = loc.spawn(Actor::new(Race::Goblin,
+                                          Position::new(Coordinate::new(2, 1), Direction::ZX)),
+                               &mut rng);
+        loc.actors_byid.get_mut(&goblin).unwrap().hp = 3;
+        let left = loc.spawn(tough(Race::Human, 5, Direction::XZ), &mut rng);
+        let right = loc.spawn(tough(Race::Human, 6, Direction::ZX), &mut rng);
+        loc.take_player_events();
+
+        // the player and the goblin go at each other, and so do the two
+        // in the other room
+        let mut noticed = vec![];
+        let mut elsewhere = vec![];
+        for _ in 0..500 {
+            if loc.actors_byid[&goblin].is_dead() {
+                break;
+            }
+            for &id in &[player, goblin, left] {
+                assert!(loc.act(id, Action::Move(Angle::Forward), &mut rng));
+                elsewhere.extend(loc.events_for(right));
+            }
+            noticed.extend(loc.take_player_events());
+        }
+        assert!(loc.actors_byid[&goblin].is_dead());
+
+        let attacked = |events: &[Event], by: actor::Id, on: actor::Id| {
+            events.iter().any(|event| {
+                match *event {
+                    Event::Attack { attacker, target, .. } => attacker == by && target == on,
+                    _ => false,
+                }
+            })
+        };
+        assert!(attacked(&noticed, player, goblin), "{:?}", noticed);
+        assert!(attacked(&noticed, goblin, player), "{:?}", noticed);
+        assert!(noticed.contains(&Event::Died { id: goblin }), "{:?}", noticed);
+
+        assert!(attacked(&elsewhere, left, right), "{:?}", elsewhere);
+        let far = |id| id == left || id == right;
+        assert!(!noticed.iter().any(|event| {
+                    match *event {
+                        Event::Attack { attacker, target, .. } => far(attacker) || far(target),
+                        Event::Died { id } => far(id),
+                        _ => false,
+                    }
+                }),
+                "{:?}",
+                noticed);
+    }
+
+    /// A level some turns into a game, and what it was generated as
+    fn played_level() -> (Location, Location) {
+        let id = LevelId::new(Branch::Main, 2);
+        let mut rng = util::game_rng(1);
+        let mut loc = Location::new(id, &mut util::game_rng(9));
+        let mut free: Vec<Coordinate> = loc.map
+                                           .iter()
+                                           .map(|(&c, _)| c)
+                                           .filter(|&c| {
+                                               loc.at(c).is_passable() && !loc.at(c).is_occupied()
+                                           })
+                                           .collect();
+        free.sort_by_key(|c| (c.y, c.x));
+        let (coord, trap) = (free[0], free[free.len() / 2]);
+        let troll = loc.spawn(Actor::new(Race::Troll, Position::new(coord, Direction::XZ)),
+                              &mut rng);
+        // as if the player had seen it before leaving
+        loc.player_sighted.insert(troll);
+        loc.place_item(coord, Arc::new(Item::new(item::Type::Knife, vec![])));
+        loc.tile_mut(trap).update_feature(tile::Trap(tile::Trap::Dart));
+        loc.set_on_fire(coord, 3);
+        for turn in 1..5 {
+            loc.pre_any_tick();
+            loc.post_any_tick(&mut rng);
+            loc.post_turn(turn, &mut rng);
+        }
+        (loc, Location::new(id, &mut util::game_rng(9)))
+    }
+
+    #[test]
+    fn written_level_reads_back_the_same() {
+        let (loc, generated) = played_level();
+        let mut file = vec![];
+        loc.write_to(&mut file, &generated).unwrap();
+
+        let read = Location::read_from(&file[..], Location::new(loc.id, &mut util::game_rng(9)))
+                       .unwrap();
+
+        let tiles = |l: &Location| {
+            let mut tiles: Vec<(Coordinate, tile::Tile)> = l.map
+                                                            .iter()
+                                                            .map(|(&c, &t)| (c, t))
+                                                            .collect();
+            tiles.sort_by_key(|&(c, _)| (c.y, c.x));
+            tiles
+        };
+        assert!(tiles(&read) == tiles(&loc));
+        assert_eq!(read.actors_ids(), loc.actors_ids());
+        for id in loc.actors_ids() {
+            assert_eq!(read.actors_byid[&id].lines(), loc.actors_byid[&id].lines());
+            assert_eq!(read.actors_byid[&id].stats, loc.actors_byid[&id].stats);
+        }
+        assert_eq!(read.items.len(), loc.items.len());
+        assert_eq!(read.burning, loc.burning);
+        assert_eq!(read.turn, loc.turn);
+        assert_eq!(read.player_sighted, loc.player_sighted);
+
+        // written again, it's the same file
+        let mut again = vec![];
+        read.write_to(&mut again, &generated).unwrap();
+        assert!(again == file);
+    }
+
+    #[test]
+    fn sight_worked_out_at_once_is_as_one_by_one() {
+        let (mut loc, _) = played_level();
+        // a change to the map, for everybody to look again
+        let coord = loc.start;
+        let tile = *loc.at(coord).tile();
+        *loc.tile_mut(coord) = tile;
+        let mut one_by_one = loc.clone();
+
+        loc.update_sight();
+
+        let mut ids = one_by_one.actors_alive_ids();
+        ids.sort();
+        assert!(ids.len() > 1);
+        for &id in &ids {
+            let eye = one_by_one.actors_byid[&id].eye();
+            let mut perception = one_by_one.actors_byid[&id].perception.clone();
+            perception.update(eye, &one_by_one);
+            one_by_one.actors_byid.get_mut(&id).unwrap().perception = perception;
+        }
+
+        for &id in &ids {
+            let (a, b) = (&loc.actors_byid[&id].perception,
+                          &one_by_one.actors_byid[&id].perception);
+            assert_eq!(a.los_updates, b.los_updates, "actor {}", id);
+            assert_eq!(encode_coords(&a.in_los), encode_coords(&b.in_los), "actor {}", id);
+            assert_eq!(encode_coords(&a.visible), encode_coords(&b.visible), "actor {}", id);
+            assert_eq!(encode_coords(&a.known), encode_coords(&b.known), "actor {}", id);
+            assert_eq!(encode_coords(&a.discovered),
+                       encode_coords(&b.discovered),
+                       "actor {}",
+                       id);
+        }
+    }
+
+    #[test]
+    fn level_cut_short_is_refused() {
+        let (loc, generated) = played_level();
+        let mut file = vec![];
+        loc.write_to(&mut file, &generated).unwrap();
+        let text = String::from_utf8(file).unwrap();
+
+        let lines: Vec<&str> = text.lines().collect();
+        let cut = lines[..lines.len() - 1].join("\n");
+        assert!(Location::read_from(cut.as_bytes(), generated.clone()).is_err());
+        assert!(Location::read_from(&text.as_bytes()[..text.len() / 2], generated.clone())
+                    .is_err());
+        let other = Location::new(LevelId::new(Branch::Main, 3), &mut util::game_rng(9));
+        assert!(Location::read_from(text.as_bytes(), other).is_err());
+    }
+
+    #[test]
+    fn the_surface_goes_through_the_day_and_below_there_is_no_telling() {
+        let mut loc = Location::from_rows(&["..."]);
+        let times: Vec<Option<Daytime>> = (0..5)
+                                              .map(|quarter| {
+                                                  loc.turn = quarter * DAY_LENGTH / 2;
+                                                  loc.daytime()
+                                              })
+                                              .collect();
+        assert_eq!(times,
+                   vec![Some(Daytime::Morning),
+                        Some(Daytime::Afternoon),
+                        Some(Daytime::Evening),
+                        Some(Daytime::Night),
+                        Some(Daytime::Morning)]);
+
+        loc.id = LevelId::new(Branch::Main, 1);
+        assert_eq!(loc.daytime(), None);
+        loc.id = LevelId::new(Branch::Warrens, 0);
+        assert_eq!(loc.daytime(), None);
+    }
+
+    #[test]
+    fn places_are_named_for_their_room_or_shop_or_else_a_corridor() {
+        let mut loc = Location::from_rows(&["...........", "...........", "..........."]);
+        let small = area::Area::new(Coordinate::new(1, 1), area::Room(2));
+        let big = area::Area::new(Coordinate::new(8, 1), area::Room(6));
+        loc.tile_mut(Coordinate::new(1, 1)).area = Some(small);
+        loc.tile_mut(Coordinate::new(8, 1)).area = Some(big);
+        loc.tile_mut(Coordinate::new(9, 1)).area = Some(big);
+
+        assert_eq!(loc.place_name(Coordinate::new(1, 1)), "small room");
+        assert_eq!(loc.place_name(Coordinate::new(8, 1)), "big room");
+        assert_eq!(loc.place_name(Coordinate::new(5, 1)), "corridor");
+
+        // the shop goes by what it is, whatever room it's in
+        loc.shop = Some(Shop::new(area::Area::new(Coordinate::new(8, 1), area::Room(1))));
+        assert_eq!(loc.place_name(Coordinate::new(8, 1)), "shop");
+        assert_eq!(loc.place_name(Coordinate::new(9, 1)), "shop");
+        assert_eq!(loc.place_name(Coordinate::new(1, 1)), "small room");
+    }
+
+    #[test]
+    fn nothing_depends_on_the_order_the_map_was_built_in() {
+        let mut loc = Location::new(LevelId::new(Branch::Main, 2), &mut util::game_rng(5));
+        // flickering lights, so the rolls have an order to get wrong
+        let start = loc.start;
+        loc.tile_mut(start).add_feature(tile::Brazier(true)).add_light(tile::BRAZIER_LIGHT);
+
+        let mut reversed = Map::new();
+        for (c, tile) in in_order(&loc.map).into_iter().rev() {
+            reversed[c] = *tile;
+        }
+        let mut other = loc.clone();
+        other.map = Arc::new(reversed);
+        assert_eq!(in_order(&loc.map).len(), in_order(&other.map).len());
+
+        let (mut rng, mut other_rng) = (util::game_rng(7), util::game_rng(7));
+        loc.recalculate_light_map(&mut rng);
+        other.recalculate_light_map(&mut other_rng);
+        assert_eq!(in_order(&loc.light_map), in_order(&other.light_map));
+        assert_eq!(rng.gen::<u32>(), other_rng.gen::<u32>());
+
+        let up = LevelId::new(Branch::Main, 1);
+        assert_eq!(loc.arrival_coord(up), other.arrival_coord(up));
+        assert_eq!(loc.noise_spread(start, 20), other.noise_spread(start, 20));
+        assert_eq!(loc.to_json(), other.to_json());
+    }
+
+    /// Anything an actor could try, whether it makes sense or not:
+    /// items it doesn't have, targets off the map, doors that aren't there
+    fn random_action(loc: &Location, rng: &mut GameRng) -> Action {
+        let angle = *rng.choose(&ANGLES).unwrap();
+        let dir = *rng.choose(Direction::all()).unwrap();
+        let ch = rng.gen_range(b'a', b'h') as char;
+        let (min, max) = loc.bounds;
+        let target = Coordinate::new(rng.gen_range(min.x - 3, max.x + 4),
+                                     rng.gen_range(min.y - 3, max.y + 4));
+        match rng.gen_range(0, 16) {
+            0 => Action::Wait,
+            1 => Action::Turn(angle),
+            2 | 3 | 4 => Action::Move(angle),
+            5 => Action::Charge,
+            6 => Action::Spin(angle),
+            7 => Action::Equip(ch),
+            8 => Action::Drop_(ch),
+            9 => Action::Ranged(target),
+            10 => Action::Throw(ch, target),
+            11 => Action::Pick,
+            12 => Action::Interact,
+            13 => Action::Close(dir),
+            14 => Action::Bash(dir),
+            _ => if rng.gen() { Action::Descend } else { Action::Ascend },
+        }
+    }
+
+    /// Somebody new somewhere one can stand, maybe where somebody is
+    fn spawn_somebody(loc: &mut Location, rng: &mut GameRng) {
+        let free: Vec<Coordinate> = in_order(&loc.map)
+                                        .into_iter()
+                                        .map(|(c, _)| c)
+                                        .filter(|&c| loc.at(c).tile().is_passable())
+                                        .collect();
+        let coord = *rng.choose(&free).unwrap();
+        let race = *rng.choose(&[Race::Rat, Race::Goblin, Race::Troll, Race::Pony]).unwrap();
+        let mut actor = Actor::new(race, util::random_pos(coord.x, coord.y, rng));
+        if rng.gen_weighted_bool(3) {
+            actor.pick_item(Arc::new(Item::new(item::Type::Knife, vec![])));
+        }
+        loc.spawn(actor, rng);
+    }
+
+    #[test]
+    fn invariants_hold_through_thousands_of_random_actions() {
+        for seed in 0..2 {
+            let mut rng = util::game_rng(seed);
+            let mut loc = Location::new(LevelId::new(Branch::Main, seed),
+                                        &mut util::game_rng(seed + 100));
+            let start = loc.start;
+            let mut player = Actor::new(Race::Human, util::random_pos(start.x, start.y, &mut rng));
+            player.set_player();
+            for &type_ in &Race::Human.starting_items() {
+                player.pick_item(Arc::new(Item::new(type_, vec![])));
+            }
+            player.equip_free_slots();
+            loc.spawn_player(player, &mut rng);
+            for _ in 0..10 {
+                spawn_somebody(&mut loc, &mut rng);
+            }
+
+            for turn in 1..1000 {
+                let mut ids = loc.actors_alive_ids();
+                ids.sort();
+                for id in ids {
+                    if loc.actors_byid.get(&id).map_or(true, |a| a.is_dead()) {
+                        continue;
+                    }
+                    let action = random_action(&loc, &mut rng);
+                    loc.act(id, action, &mut rng);
+                    loc.check_invariants();
+                }
+
+                match rng.gen_range(0, 20) {
+                    0 => spawn_somebody(&mut loc, &mut rng),
+                    1 => {
+                        let mut ids = loc.actors_alive_ids();
+                        ids.sort();
+                        let player_id = loc.player_id;
+                        if let Some(&id) = rng.choose(&ids) {
+                            if Some(id) != player_id {
+                                assert!(loc.remove(id).is_some());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                loc.post_turn(turn, &mut rng);
+                loc.check_invariants();
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasherDefault;
+use std::sync::Arc;
+use simplemap::SimpleMap;
+use fnv::FnvHasher;
+
+use hex2d::Coordinate;
+
+use game::item::Item;
+
+pub mod area;
+pub mod actor;
+pub use self::actor::Actor;
+pub mod action;
+pub use self::action::Action;
+pub mod conts;
+pub mod item;
+pub mod engine;
+pub use self::engine::*;
+pub mod event;
+pub use self::event::{Discovery, Event, Rejection};
+pub mod level;
+pub use self::level::{Branch, Daytime, Feeling, LevelId};
+pub mod tile;
+pub use self::tile::Tile;
+pub mod location;
+pub use self::location::Location;
+pub mod path;
+pub use self::path::PathOpts;
+pub mod perception;
+pub use self::perception::Perception;
+pub mod explore;
+pub use self::explore::Explorer;
+pub mod export;
+pub mod format;
+pub mod fov;
+pub mod save;
+pub use self::save::Replay;
+pub mod bones;
+pub use self::bones::Bones;
+pub mod score;
+pub use self::score::HighScores;
+pub mod sim;
+pub use self::sim::Simulation;
+pub mod timings;
+pub use self::timings::Timings;
+pub mod visibility;
+pub use self::visibility::Visibility;
+
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Noise {
+    /// Someone moving around
+    Creature(actor::Race),
+    /// Someone getting hit
+    Combat,
+    /// Door being closed
+    DoorCreak,
+    /// Door being bashed
+    DoorSlam,
+    /// Alarm trap going off
+    Alarm,
+    /// Something being broken or knocked over
+    Crash,
+    /// Someone digging through the rock
+    Digging,
+    /// Boulder being pushed
+    Rumble,
+    Fire,
+}
+
+impl Noise {
+    /// Loudness at the source
+    pub fn loudness(&self) -> i32 {
+        match *self {
+            Noise::Creature(_) => 2,
+            Noise::Combat => 7,
+            Noise::DoorCreak => 3,
+            Noise::DoorSlam => 12,
+            Noise::Alarm => 15,
+            Noise::Crash => 12,
+            Noise::Digging => 9,
+            Noise::Rumble => 6,
+            Noise::Fire => tile::FIRE_NOISE,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match *self {
+            Noise::Creature(cr) => format!("a {}", cr.description()),
+            Noise::Combat => "fighting".to_string(),
+            Noise::DoorCreak => "a door creak".to_string(),
+            Noise::DoorSlam => "a door slam".to_string(),
+            Noise::Alarm => "an alarm".to_string(),
+            Noise::Crash => "a crash".to_string(),
+            Noise::Digging => "digging".to_string(),
+            Noise::Rumble => "a rumble".to_string(),
+            Noise::Fire => "a crackling fire".to_string(),
+        }
+    }
+}
+
+/// Noise as it reached the hearer
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Heard {
+    pub noise: Noise,
+    /// What was left of the emission after the way it traveled
+    pub loudness: i32,
+    /// Location turn it was heard on
+    pub turn: u64,
+}
+
+
+/// Hash containers with a fixed hasher: iteration order only depends on
+/// what was inserted, so it can't make two games with the same seed differ
+pub type FnvHashMap<K, V> = HashMap<K, V, BuildHasherDefault<FnvHasher>>;
+pub type FnvHashSet<K> = HashSet<K, BuildHasherDefault<FnvHasher>>;
+
+/// What most things on the grid are kept in
+pub type CoordMap<V> = FnvHashMap<Coordinate, V>;
+pub type CoordSet = FnvHashSet<Coordinate>;
+
+pub type NoiseMap = CoordMap<Heard>;
+pub type Actors = CoordMap<Actor>;
+pub type Items = CoordMap<Arc<Item>>;
+
+/// Probed far more than walked, and a default for anywhere off the map.
+/// The order `SimpleMap` walks in isn't fixed, so whatever depends on it
+/// goes through `in_order`
+pub type Map = SimpleMap<Coordinate, Tile>;
+pub type LightMap = SimpleMap<Coordinate, u32>;
+
+/// Coordinates as words, row by row; `decode_coords` reads them back
+pub fn encode_coords(coords: &Visibility) -> String {
+    let mut coords: Vec<&Coordinate> = coords.iter().collect();
+    coords.sort_by_key(|c| (c.y, c.x));
+    let words: Vec<String> = coords.iter().map(|c| format!("{} {}", c.x, c.y)).collect();
+    words.join(" ")
+}
+
+/// Reverse of `encode_coords`
+pub fn decode_coords(line: &str) -> Option<Visibility> {
+    let numbers: Vec<i32> = match line.split_whitespace().map(|w| w.parse()).collect() {
+        Ok(numbers) => numbers,
+        Err(_) => return None,
+    };
+    if numbers.len() % 2 != 0 {
+        return None;
+    }
+    Some(numbers.chunks(2).map(|xy| Coordinate::new(xy[0], xy[1])).collect())
+}
+
+/// Everything in `map`, row by row
+pub fn in_order<V>(map: &SimpleMap<Coordinate, V>) -> Vec<(Coordinate, &V)> {
+    let mut entries: Vec<_> = map.iter().map(|(&c, v)| (c, v)).collect();
+    entries.sort_by_key(|&(c, _)| (c.y, c.x));
+    entries
+}
@@ -0,0 +1,246 @@
+use std::i32;
+
+use hex2d::Coordinate;
+
+use super::{tile, CoordMap};
+use super::actor::Actor;
+use super::location::Location;
+
+/// How a path may go
+#[derive(Copy, Clone)]
+pub struct PathOpts<'a> {
+    /// Extra cost of a closed door on the way; `None` if it can't be opened
+    pub door_cost: Option<i32>,
+    /// Stay off fire and the traps one knows about
+    pub avoid_hazards: bool,
+    /// Don't look for paths costing more than that
+    pub max_cost: i32,
+    /// Don't walk through tiles somebody stands on
+    pub occupied_blocks: bool,
+    /// Only walk through tiles this actor knows
+    pub known_by: Option<&'a Actor>,
+}
+
+impl<'a> Default for PathOpts<'a> {
+    fn default() -> Self {
+        PathOpts {
+            door_cost: Some(1),
+            avoid_hazards: false,
+            max_cost: i32::MAX,
+            occupied_blocks: false,
+            known_by: None,
+        }
+    }
+}
+
+impl<'a> PathOpts<'a> {
+    /// Cost of stepping on `coord`, `None` if it can't be walked through
+    fn step_cost(&self, loc: &Location, coord: Coordinate) -> Option<i32> {
+        let tile = loc.at(coord).tile();
+        if !tile.is_passable() {
+            return None;
+        }
+
+        if let Some(actor) = self.known_by {
+            if !actor.knows(coord) {
+                return None;
+            }
+        }
+
+        if self.occupied_blocks && loc.at(coord).is_occupied() {
+            return None;
+        }
+
+        if self.avoid_hazards {
+            let trap = match tile.feature_on(tile::Layer::Trap) {
+                Some(tile::Trap(_)) => self.known_by.map_or(true, |a| a.knows(coord)),
+                _ => false,
+            };
+            if trap || loc.is_burning(coord) {
+                return None;
+            }
+        }
+
+        if tile.door_state() == Some(false) {
+            self.door_cost.map(|cost| 1 + cost)
+        } else {
+            Some(1)
+        }
+    }
+}
+
+/// Cheapest path from `from` to the closest coordinate `is_goal` holds
+/// for, in steps (`from` excluded). The goal only has to be next to a
+/// walkable tile, not walkable itself.
+///
+/// Ties are always broken the same way, so the same location gives the
+/// same path.
+pub fn search<F>(loc: &Location,
+                 from: Coordinate,
+                 is_goal: F,
+                 opts: &PathOpts)
+                 -> Option<Vec<Coordinate>>
+    where F: Fn(Coordinate) -> bool
+{
+    if is_goal(from) {
+        return Some(vec![]);
+    }
+
+    let mut came_from: CoordMap<(Coordinate, i32)> = Default::default();
+    // coordinates to go on from, by the cost of getting there
+    let mut queue: Vec<Vec<Coordinate>> = vec![vec![from]];
+
+    let mut cost = 0;
+    while cost < queue.len() {
+        while let Some(coord) = queue[cost].pop() {
+            if coord != from && came_from[&coord].1 < cost as i32 {
+                // got there cheaper some other way
+                continue;
+            }
+
+            for &n in &coord.neighbors() {
+                if n == from {
+                    continue;
+                }
+
+                if is_goal(n) && cost as i32 + 1 <= opts.max_cost {
+                    came_from.insert(n, (coord, cost as i32 + 1));
+                    return Some(backtrace(&came_from, from, n));
+                }
+
+                let n_cost = match opts.step_cost(loc, n) {
+                    Some(step) => cost as i32 + step,
+                    None => continue,
+                };
+
+                if n_cost > opts.max_cost ||
+                   came_from.get(&n).map_or(false, |&(_, c)| c <= n_cost) {
+                    continue;
+                }
+
+                came_from.insert(n, (coord, n_cost));
+                if queue.len() <= n_cost as usize {
+                    queue.resize(n_cost as usize + 1, vec![]);
+                }
+                queue[n_cost as usize].push(n);
+            }
+        }
+        cost += 1;
+    }
+
+    None
+}
+
+fn backtrace(came_from: &CoordMap<(Coordinate, i32)>,
+             from: Coordinate,
+             to: Coordinate)
+             -> Vec<Coordinate> {
+    let mut path = vec![to];
+    let mut coord = to;
+    while came_from[&coord].0 != from {
+        coord = came_from[&coord].0;
+        path.push(coord);
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::time::{Duration, Instant};
+
+    use hex2d::Coordinate;
+
+    use game::Location;
+    use super::PathOpts;
+
+    /// Straight along the top through a door, or the long way round
+    /// below
+    const DOOR_OR_DETOUR: [&'static str; 5] = ["#######",
+                                               "#..+..#",
+                                               "#.###.#",
+                                               "#.....#",
+                                               "#######"];
+
+    fn with_door_cost(door_cost: Option<i32>) -> PathOpts<'static> {
+        PathOpts { door_cost: door_cost, ..Default::default() }
+    }
+
+    #[test]
+    fn doors_cost_what_they_are_told_to() {
+        let loc = Location::from_rows(&DOOR_OR_DETOUR);
+        let from = Coordinate::new(1, 1);
+        let to = Coordinate::new(5, 1);
+        let door = Coordinate::new(3, 1);
+
+        let cheap = loc.find_path(from, to, &with_door_cost(Some(1))).unwrap();
+        assert_eq!(cheap.len(), 4);
+        assert!(cheap.contains(&door));
+
+        for &cost in &[Some(5), None] {
+            let around = loc.find_path(from, to, &with_door_cost(cost)).unwrap();
+            assert_eq!(around.len(), 7, "{:?}", cost);
+            assert!(!around.contains(&door));
+            assert_eq!(around.last(), Some(&to));
+        }
+    }
+
+    #[test]
+    fn a_door_that_cant_be_opened_is_a_wall() {
+        let loc = Location::from_rows(&["#######", "#..+..#", "#######"]);
+        let path = loc.find_path(Coordinate::new(1, 1),
+                                 Coordinate::new(5, 1),
+                                 &with_door_cost(None));
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn unreachable_is_none_and_quick() {
+        let wall: String = iter::repeat('#').take(62).collect();
+        let floor: String = iter::repeat('.').take(60).collect();
+        let mut rows = vec![wall.clone()];
+        for _ in 0..60 {
+            rows.push(format!("#{}#", floor));
+        }
+        rows.push(wall);
+        // a closet in the corner, walled in all around
+        rows.push("###".to_owned());
+        rows.push("#.#".to_owned());
+        rows.push("###".to_owned());
+        let rows: Vec<&str> = rows.iter().map(|r| &r[..]).collect();
+        let loc = Location::from_rows(&rows);
+        let closet = Coordinate::new(1, 63);
+
+        let started = Instant::now();
+        for i in 0..10 {
+            let from = Coordinate::new(1 + i * 6, 1 + i * 6);
+            assert_eq!(loc.find_path(from, closet, &Default::default()), None);
+        }
+        assert!(started.elapsed() < Duration::from_secs(2),
+                "10 searches took {:?}",
+                started.elapsed());
+    }
+
+    #[test]
+    fn the_same_place_gives_the_same_path() {
+        let rows = ["##########",
+                    "#........#",
+                    "#..#.....#",
+                    "#....#...#",
+                    "#........#",
+                    "##########"];
+        let a = Location::from_rows(&rows);
+        let b = Location::from_rows(&rows);
+        let from = Coordinate::new(1, 1);
+
+        for y in 1..5 {
+            for x in 1..9 {
+                let to = Coordinate::new(x, y);
+                let path = a.find_path(from, to, &Default::default());
+                assert_eq!(path, a.find_path(from, to, &Default::default()));
+                assert_eq!(path, b.find_path(from, to, &Default::default()));
+            }
+        }
+    }
+}
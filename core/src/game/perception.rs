@@ -0,0 +1,184 @@
+//! What an actor sees and knows of a level, kept apart from the rest of
+//! the actor. It's worked out from the actor's `Eye` and the level, and
+//! nothing else, so everybody's can be worked out at once, on threads,
+//! against the same level.
+
+use hex2d::{Coordinate, Position};
+
+use super::fov;
+use super::location::Location;
+use super::Visibility;
+
+/// What a cached LoS was computed from
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct LosKey {
+    pos: Position,
+    vision: i32,
+    infravision: i32,
+    map_generation: u64,
+}
+
+/// All of an actor that what it sees depends on
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Eye {
+    pub pos: Position,
+    pub vision: i32,
+    pub infravision: i32,
+}
+
+impl Eye {
+    fn key(&self, loc: &Location) -> LosKey {
+        LosKey {
+            pos: self.pos,
+            vision: self.vision,
+            infravision: self.infravision,
+            map_generation: loc.map_generation,
+        }
+    }
+
+    /// The coordinate faced
+    fn head(&self) -> Coordinate {
+        self.pos.coord + self.pos.dir
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Perception {
+    /// LoS at the end of the tick
+    pub in_los: Visibility,
+
+    /// Additional LoS that actor could
+    /// experience during the tick (eg. when
+    /// more than one tile was traversed)
+    temporary_los: Visibility,
+
+    /// Currently visible: los + light
+    pub visible: Visibility,
+
+    /// Last LoS computed, reused until the actor or the map changes
+    los_cache: Option<(LosKey, Visibility)>,
+    /// What `visible` was last derived from, with the light generation
+    visible_key: Option<(LosKey, u64)>,
+    /// Times what the actor sees had to be worked out again
+    pub los_updates: u64,
+
+    /// Known coordinates
+    pub known: Visibility,
+    /// Known areas
+    pub known_areas: Visibility,
+
+    /// Discovered in the last LoS
+    pub discovered: Visibility,
+    /// Just discovered areas
+    pub discovered_areas: Visibility,
+}
+
+impl Perception {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn pre_any_tick(&mut self) {
+        self.temporary_los = Default::default();
+        self.discovered = Default::default();
+        self.discovered_areas = Default::default();
+    }
+
+    /// Forget everything specific to the previous level
+    pub fn changed_level(&mut self) {
+        self.known = Default::default();
+        self.known_areas = Default::default();
+        // generations of another level say nothing about this one
+        self.los_cache = None;
+        self.visible_key = None;
+    }
+
+    /// Take in what `eye` has in sight on the way, before `update` at
+    /// the end of the tick
+    pub fn look(&mut self, eye: Eye, loc: &Location) {
+        let key = eye.key(loc);
+        let cached = self.los_cache.as_ref().map_or(false, |&(k, _)| k == key);
+
+        if !cached {
+            let pos = eye.pos;
+            let mut los: Visibility = Default::default();
+            fov::ahead(&|coord| loc.at(coord).tile().opaqueness(),
+                       &mut |coord, _| {
+                           let _ = los.insert(coord);
+                       },
+                       key.vision,
+                       pos.coord,
+                       pos.dir);
+            self.los_cache = Some((key, los));
+        }
+
+        if let Some((_, ref los)) = self.los_cache {
+            self.temporary_los.extend(los.iter().cloned());
+        }
+    }
+
+    fn los_to_visible(&self, eye: Eye, loc: &Location, los: &Visibility) -> Visibility {
+        let mut visible: Visibility = Default::default();
+
+        for &coord in los {
+            if loc.at(coord).light() > 0 {
+                visible.insert(coord);
+            } else if eye.pos.coord.distance(coord) <= eye.infravision {
+                visible.insert(coord);
+            } else if coord == eye.head() {
+                visible.insert(coord);
+            } else if loc.at(coord).tile().opaqueness() > 10 {
+                if loc.at(coord).light_as_seen_from(eye.pos.coord) > 0 {
+                    visible.insert(coord);
+                }
+            }
+        }
+
+        visible
+    }
+
+    /// What `eye` sees at the end of the tick, and what of it is new;
+    /// nothing to do unless it moved, or the map or the light in sight
+    /// changed since last time
+    pub fn update(&mut self, eye: Eye, loc: &Location) {
+        let key = eye.key(loc);
+        if let Some((prev_key, light_generation)) = self.visible_key {
+            if self.temporary_los.is_empty() && prev_key == key &&
+               !loc.light_changed_since(light_generation, &self.in_los) {
+                // nothing could have changed what we see
+                return;
+            }
+        }
+        self.los_updates += 1;
+
+        let total_los = self.temporary_los.clone();
+        let total_visible = self.los_to_visible(eye, loc, &total_los);
+
+        self.temporary_los = Default::default();
+        self.look(eye, loc);
+
+        let visible = self.los_to_visible(eye, loc, &self.temporary_los);
+
+        for &i in total_visible.iter().chain(visible.iter()) {
+            if !self.known.contains(&i) {
+                self.known.insert(i);
+                self.discovered.insert(i);
+            }
+        }
+
+        for &coord in self.discovered.iter() {
+            if let Some(area) = loc.at(coord).tile().area {
+                let area_center = area.center;
+
+                if !self.known_areas.contains(&area_center) {
+                    self.known_areas.insert(area_center);
+                    self.discovered_areas.insert(area_center);
+                }
+            }
+        }
+
+        self.in_los = self.temporary_los.clone();
+        self.visible = visible;
+        self.visible_key = Some((key, loc.light_generation));
+    }
+}
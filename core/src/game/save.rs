@@ -0,0 +1,348 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use hex2d::{Angle, Coordinate, Direction};
+
+use super::{Action, Bones};
+use super::actor::Race;
+use super::format::FileFormat;
+
+pub const FORMAT: FileFormat = FileFormat {
+    kind: "replay",
+    version: 5,
+    migrations: &[],
+};
+
+/// Keys of the lines a replay has, other than the actions
+const KEYS: [&'static str; 6] = ["seed", "race", "name", "bones", "hash", "end"];
+
+/// Races the player can pick
+const PLAYABLE: [Race; 3] = [Race::Human, Race::Elf, Race::Dwarf];
+
+const ANGLES: [Angle; 6] = [Angle::Forward,
+                            Angle::Right,
+                            Angle::RightBack,
+                            Angle::Back,
+                            Angle::LeftBack,
+                            Angle::Left];
+
+/// Everything needed to play a game out again: the engine is
+/// deterministic, so the seed and the player's choices are enough
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u32,
+    /// `None` until the player is spawned
+    pub race: Option<Race>,
+    /// Name the player picked, once spawned
+    pub name: String,
+    /// Bones of other games the levels are haunted by
+    pub bones: Vec<Bones>,
+    /// Every action the player got to do, with the turn it was done on;
+    /// rejected ones change nothing, so they're left out
+    pub actions: Vec<(u64, Action)>,
+    /// State hashes at some of the turns, to tell where a replay diverges
+    pub hashes: Vec<(u64, u64)>,
+    /// Turn and state hash the game was at when written
+    pub end: Option<(u64, u64)>,
+}
+
+impl Replay {
+    pub fn new(seed: u32) -> Replay {
+        Replay {
+            seed: seed,
+            race: None,
+            name: String::new(),
+            bones: vec![],
+            actions: vec![],
+            hashes: vec![],
+            end: None,
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        try!(writeln!(w, "{}", FORMAT.header()));
+        try!(writeln!(w, "seed {}", self.seed));
+        if let Some(race) = self.race {
+            try!(writeln!(w, "race {}", race.description()));
+            try!(writeln!(w, "name {}", self.name));
+        }
+        for bones in &self.bones {
+            for line in bones.lines() {
+                try!(writeln!(w, "bones {}", line));
+            }
+        }
+
+        // in the order they happened
+        let mut next_hash = 0;
+        for &(turn, action) in &self.actions {
+            while next_hash < self.hashes.len() && self.hashes[next_hash].0 <= turn {
+                let (hash_turn, hash) = self.hashes[next_hash];
+                try!(writeln!(w, "hash {} {}", hash_turn, hash));
+                next_hash += 1;
+            }
+            try!(writeln!(w, "{} {}", turn, encode(action)));
+        }
+        for &(turn, hash) in &self.hashes[next_hash..] {
+            try!(writeln!(w, "hash {} {}", turn, hash));
+        }
+
+        if let Some((turn, hash)) = self.end {
+            try!(writeln!(w, "end {} {}", turn, hash));
+        }
+        Ok(())
+    }
+
+    /// Lines with keys of a later version, that this one doesn't know,
+    /// are skipped
+    pub fn read_from<R: BufRead>(r: R) -> io::Result<Replay> {
+        let mut lines = try!(FORMAT.read(r)).into_iter();
+
+        let line = try!(lines.next().ok_or_else(|| invalid("no seed")));
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let seed = match (words.len(), words.get(0).cloned()) {
+            (2, Some("seed")) => try!(words[1].parse().map_err(|_| invalid("bad seed"))),
+            _ => return Err(invalid("no seed")),
+        };
+
+        let mut replay = Replay::new(seed);
+        let mut bones = vec![];
+        for line in lines {
+            if line.starts_with("bones ") {
+                bones.push(line["bones ".len()..].to_string());
+                continue;
+            }
+            if line.starts_with("name ") {
+                replay.name = line["name ".len()..].to_string();
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match (words.len(), words.get(0).cloned()) {
+                (2, Some("race")) => {
+                    let race = PLAYABLE.iter().cloned().find(|r| r.description() == words[1]);
+                    replay.race = Some(try!(race.ok_or_else(|| invalid("bad race"))));
+                }
+                (3, Some("hash")) | (3, Some("end")) => {
+                    let turn_hash = (words[1].parse(), words[2].parse());
+                    let turn_hash = match turn_hash {
+                        (Ok(turn), Ok(hash)) => (turn, hash),
+                        _ => return Err(invalid(&line)),
+                    };
+                    if words[0] == "end" {
+                        replay.end = Some(turn_hash);
+                    } else {
+                        replay.hashes.push(turn_hash);
+                    }
+                }
+                (_, Some(key)) if !KEYS.contains(&key) && key.parse::<u64>().is_err() => {}
+                (n, Some(turn)) if n > 1 => {
+                    let turn = try!(turn.parse().map_err(|_| invalid(&line)));
+                    let action = try!(decode(&words[1..]).ok_or_else(|| invalid(&line)));
+                    replay.actions.push((turn, action));
+                }
+                _ => return Err(invalid(&line)),
+            }
+        }
+
+        let bones = Bones::parse(bones.iter().map(|l| &l[..]));
+        replay.bones = try!(bones.ok_or_else(|| invalid("bad bones")));
+        Ok(replay)
+    }
+
+    /// Write to `path` through a temporary file, so that an interrupted
+    /// write leaves the previous save in place
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.save_through(path, |file| file)
+    }
+
+    /// `save`, writing through whatever `wrap` puts around the file
+    fn save_through<F, W>(&self, path: &Path, wrap: F) -> io::Result<()>
+        where F: FnOnce(fs::File) -> W,
+              W: Write
+    {
+        let tmp = path.with_extension("tmp");
+        let res = fs::File::create(&tmp).and_then(|file| {
+            let mut w = io::BufWriter::new(wrap(try!(file.try_clone())));
+            try!(self.write_to(&mut w));
+            try!(w.flush());
+            file.sync_all()
+        });
+
+        match res {
+            Ok(()) => fs::rename(&tmp, path),
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                Err(e)
+            }
+        }
+    }
+
+    /// Read what `save` wrote; the file is only read, whatever is in it
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let file = try!(fs::File::open(path));
+        let replay = try!(Replay::read_from(io::BufReader::new(file)));
+        // a saved game always ends with where it was left; without it,
+        // the file was cut short
+        if replay.end.is_none() {
+            return Err(invalid("cut short"));
+        }
+        Ok(replay)
+    }
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("corrupted replay: {}", what))
+}
+
+/// `action` as the words a replay line has after the turn
+pub fn encode(action: Action) -> String {
+    let angle = |a: Angle| ANGLES.iter().position(|&x| x == a).unwrap();
+    let dir = |d: Direction| Direction::all().iter().position(|&x| x == d).unwrap();
+
+    match action {
+        Action::Wait => "wait".to_string(),
+        Action::Turn(a) => format!("turn {}", angle(a)),
+        Action::Move(a) => format!("move {}", angle(a)),
+        Action::Charge => "charge".to_string(),
+        Action::Spin(a) => format!("spin {}", angle(a)),
+        Action::Equip(ch) => format!("equip {}", ch),
+        Action::Drop_(ch) => format!("drop {}", ch),
+        Action::Ranged(c) => format!("ranged {} {}", c.x, c.y),
+        Action::Throw(ch, c) => format!("throw {} {} {}", ch, c.x, c.y),
+        Action::Pick => "pick".to_string(),
+        Action::Descend => "descend".to_string(),
+        Action::Ascend => "ascend".to_string(),
+        Action::Interact => "interact".to_string(),
+        Action::Close(d) => format!("close {}", dir(d)),
+        Action::Bash(d) => format!("bash {}", dir(d)),
+    }
+}
+
+/// Reverse of `encode`
+pub fn decode(words: &[&str]) -> Option<Action> {
+    let angle = |s: &str| s.parse::<usize>().ok().and_then(|i| ANGLES.get(i).cloned());
+    let dir = |s: &str| s.parse::<usize>().ok().and_then(|i| Direction::all().get(i).cloned());
+    let ch = |s: &str| if s.chars().count() == 1 { s.chars().next() } else { None };
+
+    if words.len() == 4 && words[0] == "throw" {
+        return match (ch(words[1]), words[2].parse(), words[3].parse()) {
+            (Some(c), Ok(x), Ok(y)) => Some(Action::Throw(c, Coordinate::new(x, y))),
+            _ => None,
+        };
+    }
+    if words.len() > 3 {
+        return None;
+    }
+
+    match (words.get(0).cloned(), words.get(1).cloned(), words.get(2).cloned()) {
+        (Some("wait"), None, None) => Some(Action::Wait),
+        (Some("turn"), Some(a), None) => angle(a).map(Action::Turn),
+        (Some("move"), Some(a), None) => angle(a).map(Action::Move),
+        (Some("charge"), None, None) => Some(Action::Charge),
+        (Some("spin"), Some(a), None) => angle(a).map(Action::Spin),
+        (Some("equip"), Some(c), None) => ch(c).map(Action::Equip),
+        (Some("drop"), Some(c), None) => ch(c).map(Action::Drop_),
+        (Some("ranged"), Some(x), Some(y)) => {
+            match (x.parse(), y.parse()) {
+                (Ok(x), Ok(y)) => Some(Action::Ranged(Coordinate::new(x, y))),
+                _ => None,
+            }
+        }
+        (Some("pick"), None, None) => Some(Action::Pick),
+        (Some("descend"), None, None) => Some(Action::Descend),
+        (Some("ascend"), None, None) => Some(Action::Ascend),
+        (Some("interact"), None, None) => Some(Action::Interact),
+        (Some("close"), Some(d), None) => dir(d).map(Action::Close),
+        (Some("bash"), Some(d), None) => dir(d).map(Action::Bash),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cmp, env, fs};
+    use std::io::{self, Write};
+
+    use hex2d::Angle;
+
+    use game::Action;
+    use game::actor::Race;
+    use super::Replay;
+
+    /// Lets `left` bytes through, then fails like a full disk
+    struct FailAfter<W> {
+        inner: W,
+        left: usize,
+    }
+
+    impl<W: Write> Write for FailAfter<W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.left == 0 {
+                return Err(io::Error::new(io::ErrorKind::Other, "disk full"));
+            }
+            let n = try!(self.inner.write(&buf[..cmp::min(buf.len(), self.left)]));
+            self.left -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    #[test]
+    fn interrupted_save_keeps_the_last_checkpoint() {
+        let path = env::temp_dir().join("rhex-interrupted-save.sav");
+        let mut replay = Replay::new(42);
+        replay.race = Some(Race::Elf);
+        replay.actions = vec![(0, Action::Wait), (1, Action::Move(Angle::Forward))];
+        replay.end = Some((2, 22));
+        replay.save(&path).unwrap();
+
+        let mut longer = replay.clone();
+        longer.actions.extend(vec![(2, Action::Pick); 100]);
+        let res = longer.save_through(&path, |file| {
+            FailAfter {
+                inner: file,
+                left: 50,
+            }
+        });
+        assert!(res.is_err());
+        assert!(!path.with_extension("tmp").exists());
+
+        let loaded = Replay::load(&path).unwrap();
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.race, replay.race);
+        assert_eq!(loaded.actions, replay.actions);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn every_write_error_is_passed_on() {
+        let mut replay = Replay::new(7);
+        replay.race = Some(Race::Dwarf);
+        replay.actions = vec![(0, Action::Wait), (3, Action::Pick)];
+        replay.hashes = vec![(1, 11), (10, 1010)];
+        replay.end = Some((12, 1212));
+
+        let mut whole = vec![];
+        replay.write_to(&mut whole).unwrap();
+        for left in 0..whole.len() {
+            let mut w = FailAfter {
+                inner: vec![],
+                left: left,
+            };
+            assert!(replay.write_to(&mut w).is_err(), "{} bytes in", left);
+            assert_eq!(&w.inner[..], &whole[..left]);
+        }
+    }
+
+    #[test]
+    fn saving_where_it_cant_be_written_leaves_nothing() {
+        let dir = env::temp_dir().join("rhex-no-such-dir");
+        let path = dir.join("rhex.sav");
+        let res = Replay::new(1).save(&path);
+        assert!(res.is_err());
+        assert!(!dir.exists());
+    }
+}
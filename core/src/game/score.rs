@@ -0,0 +1,277 @@
+use std::cmp;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use super::actor::Race;
+use super::format::FileFormat;
+
+pub const FORMAT: FileFormat = FileFormat {
+    kind: "scores",
+    version: 1,
+    migrations: &[],
+};
+
+/// Entries kept in the table
+pub const MAX_ENTRIES: usize = 20;
+
+/// Attempts to get the lock on the table before deciding it's stale
+const LOCK_TRIES: u32 = 50;
+/// Pause between attempts to get the lock
+const LOCK_WAIT_MS: u64 = 20;
+
+/// Points for what a finished game achieved
+pub fn score(deepest: u32, turns: u64, kills: &[(Race, u32)], gold: u32) -> u64 {
+    // the deeper a monster lives, the harder it is
+    let kills = kills.iter()
+                     .map(|&(race, n)| (race.native_depth() as u64 + 1) * n as u64)
+                     .fold(0, |sum, points| sum + points);
+
+    1000 * deepest as u64 + 50 * kills + gold as u64 + cmp::min(turns / 10, 500)
+}
+
+/// One game in the table
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Entry {
+    pub name: String,
+    pub score: u64,
+    /// How it ended, e.g. "killed by a goblin on level main:3, turn 812"
+    pub result: String,
+    pub seed: u32,
+    pub date: String,
+}
+
+/// Best games played, best first
+#[derive(Clone, Default, Debug)]
+pub struct HighScores {
+    pub entries: Vec<Entry>,
+}
+
+impl HighScores {
+    /// Add `entry` where it belongs, dropping whatever falls off the end
+    ///
+    /// Returns: the entry's place, if it made it into the table.
+    pub fn merge(&mut self, entry: Entry) -> Option<usize> {
+        // later entries go below earlier ones with the same score
+        let place = self.entries.iter().position(|e| e.score < entry.score);
+        let place = place.unwrap_or(self.entries.len());
+        self.entries.insert(place, entry);
+        self.entries.truncate(MAX_ENTRIES);
+
+        if place < self.entries.len() {
+            Some(place)
+        } else {
+            None
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        try!(writeln!(w, "{}", FORMAT.header()));
+        for e in &self.entries {
+            try!(writeln!(w,
+                          "{}\t{}\t{}\t{}\t{}",
+                          e.score,
+                          e.seed,
+                          clean(&e.date),
+                          clean(&e.name),
+                          clean(&e.result)));
+        }
+        Ok(())
+    }
+
+    /// Lines that don't parse are skipped
+    pub fn read_from<R: BufRead>(r: R) -> io::Result<HighScores> {
+        let mut scores = HighScores::default();
+        for line in try!(FORMAT.read(r)) {
+            match parse_entry(&line) {
+                Some(entry) => {
+                    scores.merge(entry);
+                }
+                None => warn!("Skipping corrupted score entry: {}", line),
+            }
+        }
+        Ok(scores)
+    }
+
+    /// The table in `path`, empty if there's none yet
+    fn read(path: &Path) -> io::Result<HighScores> {
+        match fs::File::open(path) {
+            Ok(file) => HighScores::read_from(io::BufReader::new(file)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(HighScores::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The table in `path`; empty if there's none yet or it can't be read
+    pub fn load(path: &Path) -> HighScores {
+        HighScores::read(path).unwrap_or_else(|e| {
+            warn!("Can't read scores from {}: {}", path.display(), e);
+            HighScores::default()
+        })
+    }
+
+    /// Add `entry` to the table in `path`. Other games may be doing the
+    /// same, so the read and write happen under a lock file, and the
+    /// write replaces the table in one go. A table that can't be read,
+    /// like one from a newer rhex, is left as it is.
+    ///
+    /// Returns: the entry's place, if it made it into the table.
+    pub fn add(path: &Path, entry: Entry) -> io::Result<Option<usize>> {
+        let _lock = try!(Lock::acquire(path));
+
+        let mut scores = try!(HighScores::read(path));
+        let place = scores.merge(entry);
+
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = io::BufWriter::new(try!(fs::File::create(&tmp)));
+            try!(scores.write_to(&mut file));
+            try!(file.flush());
+            try!(file.get_ref().sync_all());
+        }
+        try!(fs::rename(&tmp, path));
+        Ok(place)
+    }
+}
+
+/// `s` without anything that would break a line of the table
+fn clean(s: &str) -> String {
+    s.replace(|c: char| c == '\t' || c == '\n' || c == '\r', " ")
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let fields: Vec<&str> = line.splitn(5, '\t').collect();
+    if fields.len() != 5 {
+        return None;
+    }
+
+    match (fields[0].parse(), fields[1].parse()) {
+        (Ok(score), Ok(seed)) => {
+            Some(Entry {
+                score: score,
+                seed: seed,
+                date: fields[2].to_string(),
+                name: fields[3].to_string(),
+                result: fields[4].to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Exclusive right to update the table, held while alive
+struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    fn acquire(table: &Path) -> io::Result<Lock> {
+        let path = table.with_extension("lock");
+        for _ in 0..LOCK_TRIES {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Lock { path: path }),
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    thread::sleep(Duration::from_millis(LOCK_WAIT_MS));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        // nobody holds a lock that long; left over from a crash
+        warn!("Taking over stale lock {}", path.display());
+        Ok(Lock { path: path })
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use game::actor::Race;
+    use super::{score, Entry, HighScores, MAX_ENTRIES};
+
+    fn entry(name: &str, score: u64) -> Entry {
+        Entry {
+            name: name.to_string(),
+            score: score,
+            result: "died on level main:1, turn 10".to_string(),
+            seed: 1,
+            date: "2016-01-01".to_string(),
+        }
+    }
+
+    fn names(scores: &HighScores) -> Vec<&str> {
+        scores.entries.iter().map(|e| &e.name[..]).collect()
+    }
+
+    #[test]
+    fn nothing_done_scores_nothing() {
+        assert_eq!(score(0, 0, &[], 0), 0);
+    }
+
+    #[test]
+    fn score_adds_depth_kills_gold_and_turns() {
+        // rats live at the top and are worth 1 each, trolls four levels
+        // down and are worth 5
+        let kills = [(Race::Rat, 2), (Race::Troll, 1)];
+        assert_eq!(score(3, 100, &kills, 25), 3000 + 50 * (2 + 5) + 25 + 10);
+    }
+
+    #[test]
+    fn turns_count_for_little() {
+        assert_eq!(score(0, 1000000, &[], 0), 500);
+        assert_eq!(score(1, 1000000, &[], 0), 1500);
+    }
+
+    #[test]
+    fn merge_keeps_best_first() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.merge(entry("b", 200)), Some(0));
+        assert_eq!(scores.merge(entry("a", 300)), Some(0));
+        assert_eq!(scores.merge(entry("c", 100)), Some(2));
+        assert_eq!(names(&scores), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn merge_puts_later_ties_below() {
+        let mut scores = HighScores::default();
+        scores.merge(entry("first", 100));
+        assert_eq!(scores.merge(entry("second", 100)), Some(1));
+        assert_eq!(names(&scores), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn merge_drops_what_falls_off() {
+        let mut scores = HighScores::default();
+        for i in 0..MAX_ENTRIES {
+            scores.merge(entry("old", 100 + i as u64));
+        }
+        assert_eq!(scores.merge(entry("worst", 1)), None);
+        assert_eq!(scores.entries.len(), MAX_ENTRIES);
+
+        assert_eq!(scores.merge(entry("best", 1000)), Some(0));
+        assert_eq!(scores.entries.len(), MAX_ENTRIES);
+        assert_eq!(scores.entries.last().unwrap().score, 101);
+    }
+
+    #[test]
+    fn table_reads_back_what_was_written() {
+        let mut scores = HighScores::default();
+        scores.merge(entry("tab\there", 20));
+        scores.merge(entry("x", 10));
+        let mut buf = vec![];
+        scores.write_to(&mut buf).unwrap();
+
+        let read = HighScores::read_from(io::Cursor::new(buf)).unwrap();
+        assert_eq!(names(&read), vec!["tab here", "x"]);
+        assert_eq!(read.entries[1], scores.entries[1]);
+    }
+}
@@ -0,0 +1,122 @@
+use super::{Action, Engine, Event, Explorer, LevelId};
+use super::explore::{Step, Stop};
+use super::actor::{self, Actor, Race};
+
+/// A game driven by code instead of a player at the terminal; for
+/// tests and tools
+pub struct Simulation {
+    engine: Engine,
+    /// What the player noticed so far, oldest first
+    log: Vec<Event>,
+}
+
+impl Simulation {
+    /// A new game with the player already spawned
+    pub fn new(seed: u32, race: Race) -> Self {
+        let mut engine = Engine::new(seed);
+        engine.initial_spawn(race, "sim");
+        Simulation {
+            engine: engine,
+            log: vec![],
+        }
+    }
+
+    /// Let the player try `action`, then everybody else act until it's
+    /// the player's turn again
+    ///
+    /// Returns: false if the action was rejected and no time passed.
+    pub fn act(&mut self, action: Action) -> bool {
+        if !self.engine.needs_player_input() {
+            return false;
+        }
+
+        let acted = self.engine.player_act(action);
+        self.log_events();
+        while !self.engine.needs_player_input() && !self.player().is_dead() {
+            self.engine.one_actor_tick();
+            self.log_events();
+        }
+        acted
+    }
+
+    /// Stand still for `turns` turns
+    pub fn wait(&mut self, turns: u64) {
+        for _ in 0..turns {
+            if !self.act(Action::Wait) {
+                break;
+            }
+        }
+    }
+
+    /// Explore for at most `steps` actions, the way the player's
+    /// autoexplore does
+    ///
+    /// Returns: why it stopped, `None` if it ran out of steps.
+    pub fn explore(&mut self, steps: usize) -> Option<Stop> {
+        let mut explorer = Explorer::new(self.engine.current_location(), self.player());
+        for _ in 0..steps {
+            let step = explorer.step(self.engine.current_location(), self.player());
+            match step {
+                Step::Act(action) => {
+                    self.act(action);
+                }
+                Step::Stop(stop) => return Some(stop),
+            }
+        }
+        None
+    }
+
+    /// Rest for at most `turns` turns, the way the player's rest
+    /// command does
+    ///
+    /// Returns: why it stopped, `None` if it ran out of turns.
+    pub fn rest(&mut self, turns: u64) -> Option<Stop> {
+        let mut explorer = Explorer::new(self.engine.current_location(), self.player());
+        for _ in 0..turns {
+            let step = explorer.rest(self.engine.current_location(), self.player());
+            match step {
+                Step::Act(action) => {
+                    self.act(action);
+                }
+                Step::Stop(stop) => return Some(stop),
+            }
+        }
+        None
+    }
+
+    fn log_events(&mut self) {
+        let events = self.engine.take_events_for_player();
+        self.log.extend(events);
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    pub fn player(&self) -> &Actor {
+        self.engine.player()
+    }
+
+    pub fn turn(&self) -> u64 {
+        self.engine.turn()
+    }
+
+    pub fn level(&self) -> LevelId {
+        self.engine.current_location().id
+    }
+
+    /// Everything the player noticed so far, oldest first
+    pub fn log(&self) -> &[Event] {
+        &self.log
+    }
+
+    /// Other actors the player can see now
+    pub fn visible_actors(&self) -> Vec<actor::Id> {
+        let loc = self.engine.current_location();
+        loc.actors_visible_by(loc.player_id())
+    }
+}
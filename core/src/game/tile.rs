@@ -0,0 +1,589 @@
+use std::option::Option;
+pub use self::Type::*;
+pub use self::Feature::*;
+pub use super::area;
+use super::level::{Branch, LevelId};
+
+use std::fmt;
+use rand::Rng;
+use hex2d::Coordinate;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Type {
+    Wall,
+    Empty,
+    Water,
+    /// Walkway over water
+    Bridge,
+    /// Floor overgrown with dry grass; burns easily
+    Grass,
+    /// What is left after a fire
+    Scorched,
+}
+
+impl Type {
+    pub fn description(&self) -> &str {
+        match *self {
+            Wall => "wall",
+            Empty => "nothing",
+            Water => "water",
+            Bridge => "bridge",
+            Grass => "grass",
+            Scorched => "scorched floor",
+        }
+    }
+}
+
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Trap {
+    Dart,
+    Alarm,
+    Needle,
+}
+
+impl Trap {
+    pub fn description(&self) -> &str {
+        match *self {
+            Trap::Dart => "dart trap",
+            Trap::Alarm => "alarm trap",
+            Trap::Needle => "needle trap",
+        }
+    }
+}
+
+/// State of a chest; its contents are kept in `Location::containers`
+/// until it's opened
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Chest {
+    pub open: bool,
+    pub locked: bool,
+    pub trap: Option<Trap>,
+}
+
+/// Features on different layers can share a tile; features on the same
+/// layer exclude each other.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Layer {
+    Fixture,
+    Door,
+    Trap,
+}
+
+const LAYERS: usize = 3;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Feature {
+    /// Door; `true` if open, with its remaining hit points
+    Door(bool, i32),
+    /// Bashed-in doorway that can't be closed anymore
+    BrokenDoor,
+    /// Stairs leading down to a given level
+    StairsDown(LevelId),
+    /// Stairs leading up to a given level
+    StairsUp(LevelId),
+    Statue,
+    /// Fountain with a number of remaining uses
+    Fountain(u32),
+    /// Altar; `true` if it was already used
+    Altar(bool),
+    /// Brazier; `true` if lit
+    Brazier(bool),
+    Fungi,
+    /// Portal leading to its twin at a given coordinate
+    Portal(Coordinate),
+    Chest(Chest),
+    /// Heavy rock that can be pushed around
+    Boulder,
+    Trap(Trap),
+}
+
+impl Feature {
+    pub fn layer(&self) -> Layer {
+        match *self {
+            Door(..) | BrokenDoor => Layer::Door,
+            Trap(_) => Layer::Trap,
+            _ => Layer::Fixture,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match *self {
+            Door(true, _) => "open door",
+            Door(false, _) => "closed door",
+            BrokenDoor => "broken door",
+            StairsDown(LevelId { branch: Branch::Main, .. }) => "stairs down",
+            StairsDown(LevelId { branch: Branch::Warrens, .. }) => "stairs to the Warrens",
+            StairsUp(_) => "stairs up",
+            Statue => "statue",
+            Fountain(0) => "dry fountain",
+            Fountain(_) => "fountain",
+            Altar(false) => "altar",
+            Altar(true) => "cold altar",
+            Brazier(true) => "burning brazier",
+            Brazier(false) => "cold brazier",
+            Fungi => "glowing fungi",
+            Portal(_) => "portal",
+            Chest(Chest { open: true, .. }) => "open chest",
+            Chest(Chest { locked: true, .. }) => "locked chest",
+            Chest(_) => "chest",
+            Boulder => "boulder",
+            Trap(ref trap) => trap.description(),
+        }
+    }
+}
+
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Hit points of a freshly generated door
+pub const DOOR_HP: i32 = 15;
+
+/// Light emitted by a lit brazier
+pub const BRAZIER_LIGHT: i32 = 6;
+/// Light emitted by glowing fungi
+pub const FUNGI_LIGHT: i32 = 2;
+
+/// What lies beyond the edges of the map
+pub static ROCK: Tile = Tile {
+    type_: Wall,
+    features: [None; LAYERS],
+    area: None,
+    light: 0,
+};
+
+/// Light given off by a burning tile
+pub const FIRE_LIGHT: i32 = 4;
+/// Loudness of a burning tile
+pub const FIRE_NOISE: i32 = 3;
+/// Turns a knocked over brazier burns for
+pub const BRAZIER_FIRE_TURNS: u32 = 4;
+
+/// Loudness lost by a noise going through a closed door
+pub const DOOR_NOISE_COST: i32 = 4;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Tile {
+    pub type_: Type,
+    /// Indexed by `Layer`
+    features: [Option<Feature>; LAYERS],
+    pub area: Option<area::Area>,
+    pub light: i32,
+}
+
+impl Tile {
+    pub fn new(t: Type) -> Tile {
+        Tile {
+            type_: t,
+            features: [None; LAYERS],
+            area: None,
+            light: 0,
+        }
+    }
+
+    /// Add a new feature; there must be no other on its layer
+    pub fn add_feature(&mut self, f: Feature) -> &mut Tile {
+        let layer = f.layer() as usize;
+        debug_assert!(self.features[layer].is_none(),
+                      "{} can't share a tile with {}",
+                      f,
+                      self.features[layer].unwrap());
+        self.features[layer] = Some(f);
+        self
+    }
+
+    /// Replace whatever is on the layer of `f` (eg. to open a door)
+    pub fn update_feature(&mut self, f: Feature) -> &mut Tile {
+        self.features[f.layer() as usize] = Some(f);
+        self
+    }
+
+    pub fn remove_feature(&mut self, layer: Layer) -> Option<Feature> {
+        let f = self.features[layer as usize];
+        self.features[layer as usize] = None;
+        f
+    }
+
+    pub fn feature_on(&self, layer: Layer) -> Option<Feature> {
+        self.features[layer as usize]
+    }
+
+    /// The most noticeable feature
+    pub fn feature(&self) -> Option<Feature> {
+        self.features.iter().filter_map(|&f| f).next()
+    }
+
+    /// All features, most noticeable first
+    pub fn features(&self) -> Vec<Feature> {
+        self.features.iter().filter_map(|&f| f).collect()
+    }
+
+    pub fn has_features(&self) -> bool {
+        self.feature().is_some()
+    }
+
+    pub fn has_feature(&self, f: Feature) -> bool {
+        self.features[f.layer() as usize] == Some(f)
+    }
+
+    pub fn has_door(&self) -> bool {
+        self.door_state().is_some()
+    }
+
+    /// `Some(true)` for an open (or broken) door
+    pub fn door_state(&self) -> Option<bool> {
+        match self.feature_on(Layer::Door) {
+            Some(Door(open, _)) => Some(open),
+            Some(BrokenDoor) => Some(true),
+            _ => None,
+        }
+    }
+
+    pub fn is_door_broken(&self) -> bool {
+        self.feature_on(Layer::Door) == Some(BrokenDoor)
+    }
+
+    /// Open or close an intact door
+    pub fn set_door(&mut self, open: bool) {
+        if let Some(Door(_, hp)) = self.feature_on(Layer::Door) {
+            self.update_feature(Door(open, hp));
+        }
+    }
+
+    /// Damage the door; returns true if it broke
+    pub fn bash_door(&mut self, dmg: i32) -> bool {
+        if let Some(Door(open, hp)) = self.feature_on(Layer::Door) {
+            if hp - dmg <= 0 {
+                self.update_feature(BrokenDoor);
+                return true;
+            }
+            self.update_feature(Door(open, hp - dmg));
+        }
+        false
+    }
+
+    pub fn has_stairs(&self) -> bool {
+        self.stairs_down().is_some() || self.stairs_up().is_some()
+    }
+
+    pub fn stairs_down(&self) -> Option<LevelId> {
+        match self.feature_on(Layer::Fixture) {
+            Some(StairsDown(to)) => Some(to),
+            _ => None,
+        }
+    }
+
+    pub fn stairs_up(&self) -> Option<LevelId> {
+        match self.feature_on(Layer::Fixture) {
+            Some(StairsUp(to)) => Some(to),
+            _ => None,
+        }
+    }
+
+    /// Where the portal on this tile leads
+    pub fn portal(&self) -> Option<Coordinate> {
+        match self.feature_on(Layer::Fixture) {
+            Some(Portal(twin)) => Some(twin),
+            _ => None,
+        }
+    }
+
+    pub fn closed_chest(&self) -> Option<Chest> {
+        match self.feature_on(Layer::Fixture) {
+            Some(Chest(chest)) if !chest.open => Some(chest),
+            _ => None,
+        }
+    }
+
+    pub fn trap(&self) -> Option<Trap> {
+        match self.feature_on(Layer::Trap) {
+            Some(Trap(trap)) => Some(trap),
+            _ => None,
+        }
+    }
+
+    pub fn add_light(&mut self, light: i32) -> &mut Tile {
+        self.light = light;
+        self
+    }
+
+    pub fn add_area(&mut self, area: area::Area) -> &mut Tile {
+        self.area = Some(area);
+        self
+    }
+
+    /// One line of words standing for the tile, to keep it in a file:
+    /// its type, light and features. The area isn't kept; the level it
+    /// is read back into has it.
+    pub fn encode(&self) -> String {
+        let level = |id: LevelId| format!("{}:{}", id.branch.name(), id.depth);
+        let trap = |trap: Option<Trap>| {
+            trap.map_or("-".to_string(), |t| t.description().replace(' ', "_"))
+        };
+
+        let mut words = vec![self.type_.description().replace(' ', "_"), self.light.to_string()];
+        words.extend(self.features().into_iter().map(|f| {
+            match f {
+                Door(open, hp) => format!("door:{}:{}", open as u8, hp),
+                BrokenDoor => "broken_door".to_string(),
+                StairsDown(to) => format!("down:{}", level(to)),
+                StairsUp(to) => format!("up:{}", level(to)),
+                Statue => "statue".to_string(),
+                Fountain(uses) => format!("fountain:{}", uses),
+                Altar(used) => format!("altar:{}", used as u8),
+                Brazier(lit) => format!("brazier:{}", lit as u8),
+                Fungi => "fungi".to_string(),
+                Portal(to) => format!("portal:{}:{}", to.x, to.y),
+                Chest(c) => format!("chest:{}:{}:{}", c.open as u8, c.locked as u8, trap(c.trap)),
+                Boulder => "boulder".to_string(),
+                Trap(t) => format!("trap:{}", trap(Some(t))),
+            }
+        }));
+        words.join(" ")
+    }
+
+    /// Reverse of `encode`, with no area
+    pub fn decode(line: &str) -> Option<Tile> {
+        let mut words = line.split_whitespace();
+        let type_ = match words.next() {
+            Some(word) => {
+                match TYPES.iter().find(|t| t.description().replace(' ', "_") == word) {
+                    Some(&t) => t,
+                    None => return None,
+                }
+            }
+            None => return None,
+        };
+        let mut tile = Tile::new(type_);
+        tile.light = match words.next().map(|w| w.parse()) {
+            Some(Ok(light)) => light,
+            _ => return None,
+        };
+
+        for word in words {
+            match decode_feature(word) {
+                Some(f) if tile.feature_on(f.layer()).is_none() => {
+                    tile.add_feature(f);
+                }
+                _ => return None,
+            }
+        }
+        Some(tile)
+    }
+
+    pub fn is_passable(&self) -> bool {
+        match self.feature_on(Layer::Fixture) {
+            Some(Statue) | Some(Brazier(_)) | Some(Boulder) => return false,
+            _ => {}
+        }
+
+        self.type_.is_passable()
+    }
+
+    /// Loudness a noise loses on its way through this tile, `None` if
+    /// it can't get through at all
+    pub fn noise_cost(&self) -> Option<i32> {
+        if self.door_state() == Some(false) {
+            return Some(DOOR_NOISE_COST);
+        }
+
+        if self.has_feature(Boulder) || self.type_ == Wall {
+            return None;
+        }
+
+        Some(1)
+    }
+
+    pub fn opaqueness(&self) -> i32 {
+        if self.door_state() == Some(false) {
+            return 1000;
+        }
+
+        match self.feature_on(Layer::Fixture) {
+            Some(Boulder) => return 1000,
+            Some(Statue) | Some(Brazier(_)) => return 3,
+            _ => {}
+        }
+
+        self.type_.opaqueness()
+    }
+
+    pub fn ascii_expand(&self) -> i32 {
+        match self.door_state() {
+            Some(open) => {
+                return if open {
+                    1
+                } else {
+                    0
+                }
+            }
+            None => {}
+        }
+
+        match self.feature_on(Layer::Fixture) {
+            Some(Statue) | Some(Brazier(_)) | Some(Boulder) => return 8,
+            _ => {}
+        }
+
+        self.type_.base_ascii_expand()
+    }
+
+    /// Is the light emitted by this tile unsteady
+    pub fn flickers(&self) -> bool {
+        self.has_feature(Brazier(true))
+    }
+
+    pub fn can_dig_through(&self) -> bool {
+        self.type_.can_dig_through()
+    }
+
+    /// Turns the tile burns for once set on fire, `None` if it doesn't
+    pub fn burn_time(&self) -> Option<u32> {
+        match self.feature_on(Layer::Door) {
+            Some(Door(..)) => return Some(6),
+            _ => {}
+        }
+
+        match self.type_ {
+            Grass => Some(3),
+            _ => None,
+        }
+    }
+
+    /// Leave behind what a fire does
+    pub fn burn_out(&mut self) {
+        if let Some(Door(..)) = self.feature_on(Layer::Door) {
+            self.update_feature(BrokenDoor);
+        }
+
+        if self.type_ == Grass {
+            self.type_ = Scorched;
+        }
+    }
+
+    pub fn dig<R: Rng>(&mut self, rng: &mut R) {
+        let r = rng.gen_range(0, 10);
+        match self.type_ {
+            Wall => if r < 5 { self.type_ = Empty },
+            _ => {}
+        }
+    }
+}
+
+const TYPES: [Type; 6] = [Wall, Empty, Water, Bridge, Grass, Scorched];
+
+const TRAPS: [Trap; 3] = [Trap::Dart, Trap::Alarm, Trap::Needle];
+
+/// A feature as `Tile::encode` has it
+fn decode_feature(word: &str) -> Option<Feature> {
+    let parts: Vec<&str> = word.split(':').collect();
+    let flag = |s: &str| {
+        match s {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        }
+    };
+    let level = |branch: &str, depth: &str| {
+        match (Branch::from_name(branch), depth.parse()) {
+            (Some(branch), Ok(depth)) => Some(LevelId::new(branch, depth)),
+            _ => None,
+        }
+    };
+    let trap = |s: &str| TRAPS.iter().cloned().find(|t| t.description().replace(' ', "_") == s);
+
+    let arg = |i: usize| parts.get(i).cloned().unwrap_or("");
+    match (parts[0], parts.len()) {
+        ("door", 3) => {
+            match (flag(arg(1)), arg(2).parse()) {
+                (Some(open), Ok(hp)) => Some(Door(open, hp)),
+                _ => None,
+            }
+        }
+        ("broken_door", 1) => Some(BrokenDoor),
+        ("down", 3) => level(arg(1), arg(2)).map(StairsDown),
+        ("up", 3) => level(arg(1), arg(2)).map(StairsUp),
+        ("statue", 1) => Some(Statue),
+        ("fountain", 2) => arg(1).parse().ok().map(Fountain),
+        ("altar", 2) => flag(arg(1)).map(Altar),
+        ("brazier", 2) => flag(arg(1)).map(Brazier),
+        ("fungi", 1) => Some(Fungi),
+        ("portal", 3) => {
+            match (arg(1).parse(), arg(2).parse()) {
+                (Ok(x), Ok(y)) => Some(Portal(Coordinate::new(x, y))),
+                _ => None,
+            }
+        }
+        ("chest", 4) => {
+            let t = if arg(3) == "-" {
+                Some(None)
+            } else {
+                trap(arg(3)).map(Some)
+            };
+            match (flag(arg(1)), flag(arg(2)), t) {
+                (Some(open), Some(locked), Some(t)) => {
+                    Some(Chest(Chest {
+                        open: open,
+                        locked: locked,
+                        trap: t,
+                    }))
+                }
+                _ => None,
+            }
+        }
+        ("boulder", 1) => Some(Boulder),
+        ("trap", 2) => trap(arg(1)).map(Trap),
+        _ => None,
+    }
+}
+
+impl Type {
+    pub fn is_passable(&self) -> bool {
+        match *self {
+            Wall => false,
+            Empty | Bridge | Grass | Scorched => true,
+            Water => false,
+        }
+    }
+
+    pub fn can_dig_through(&self) -> bool {
+        *self == Wall
+    }
+
+    pub fn opaqueness(&self) -> i32 {
+        match *self {
+            Wall => 1000,
+            Empty | Water | Bridge | Grass | Scorched => 1,
+        }
+    }
+
+    pub fn base_ascii_expand(&self) -> i32 {
+        match *self {
+            Water | Bridge => 7,
+            Wall => 9,
+            Empty | Grass | Scorched => 10,
+        }
+    }
+}
+
+impl Default for Tile {
+    fn default() -> Tile {
+        Tile {
+            type_: Wall,
+            features: [None; LAYERS],
+            area: None,
+            light: 0,
+        }
+    }
+}
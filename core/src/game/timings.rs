@@ -0,0 +1,68 @@
+use std::fmt;
+use std::time::Duration;
+#[cfg(feature = "timings")]
+use std::time::Instant;
+
+/// Time spent in each of the costly parts of running a level. Only
+/// measured when built with the `timings` feature; zero otherwise.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Timings {
+    pub light: Duration,
+    pub noise: Duration,
+    /// Line of sight and what's visible in it
+    pub los: Duration,
+    pub ai: Duration,
+    /// Carrying out actions
+    pub act: Duration,
+}
+
+impl Timings {
+    pub fn enabled() -> bool {
+        cfg!(feature = "timings")
+    }
+}
+
+impl fmt::Display for Timings {
+    /// One line of `name_us=N` pairs, microseconds each
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt,
+               "light_us={} noise_us={} los_us={} ai_us={} act_us={}",
+               micros(self.light),
+               micros(self.noise),
+               micros(self.los),
+               micros(self.ai),
+               micros(self.act))
+    }
+}
+
+pub fn micros(d: Duration) -> u64 {
+    d.as_secs() * 1_000_000 + d.subsec_nanos() as u64 / 1_000
+}
+
+/// Measures one piece of work into a `Timings` field; does nothing
+/// without the `timings` feature
+pub struct Stopwatch {
+    #[cfg(feature = "timings")]
+    start: Instant,
+}
+
+impl Stopwatch {
+    #[cfg(feature = "timings")]
+    pub fn start() -> Stopwatch {
+        Stopwatch { start: Instant::now() }
+    }
+
+    #[cfg(not(feature = "timings"))]
+    pub fn start() -> Stopwatch {
+        Stopwatch {}
+    }
+
+    /// Add the time since `start` to `total`
+    #[cfg(feature = "timings")]
+    pub fn stop(self, total: &mut Duration) {
+        *total += self.start.elapsed();
+    }
+
+    #[cfg(not(feature = "timings"))]
+    pub fn stop(self, _total: &mut Duration) {}
+}
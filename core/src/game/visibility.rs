@@ -0,0 +1,208 @@
+//! Sets of coordinates for what actors see and know, tested far more
+//! often than changed
+
+use std::cmp;
+use std::iter::FromIterator;
+use std::mem;
+use std::slice;
+use std::vec;
+
+use hex2d::Coordinate;
+
+/// Hexes each side that a set covers beyond what it holds once it grows
+const MARGIN: i32 = 8;
+
+/// Coordinates on a level: a bit for each in the rectangle of axial
+/// coordinates covered, for `contains` to test, and the coordinates
+/// themselves in the order they came in, to walk. Cloning one copies
+/// two vectors.
+///
+/// The rectangle grows to cover whatever is inserted, a level's worth at
+/// most, so no bounds are needed up front.
+#[derive(Clone, Debug, Default)]
+pub struct Visibility {
+    /// Smallest x and y covered
+    origin: (i32, i32),
+    /// Width and height covered
+    size: (i32, i32),
+    bits: Vec<u64>,
+    coords: Vec<Coordinate>,
+}
+
+impl Visibility {
+    pub fn new() -> Visibility {
+        Default::default()
+    }
+
+    /// Bit of `c`, if it's covered
+    fn index(&self, c: Coordinate) -> Option<usize> {
+        let (x, y) = (c.x - self.origin.0, c.y - self.origin.1);
+        if x < 0 || y < 0 || x >= self.size.0 || y >= self.size.1 {
+            None
+        } else {
+            Some((y * self.size.0 + x) as usize)
+        }
+    }
+
+    fn set(&mut self, i: usize) {
+        self.bits[i / 64] |= 1u64 << (i % 64);
+    }
+
+    pub fn contains(&self, c: &Coordinate) -> bool {
+        self.index(*c).map_or(false, |i| self.bits[i / 64] & (1u64 << (i % 64)) != 0)
+    }
+
+    /// Returns: false if `c` was in already.
+    pub fn insert(&mut self, c: Coordinate) -> bool {
+        if self.contains(&c) {
+            return false;
+        }
+        let i = match self.index(c) {
+            Some(i) => i,
+            None => {
+                self.grow(c);
+                self.index(c).unwrap()
+            }
+        };
+        self.set(i);
+        self.coords.push(c);
+        true
+    }
+
+    /// Cover `c` too, with room to spare on that side so that growing
+    /// again is rare
+    fn grow(&mut self, c: Coordinate) {
+        let (x0, y0) = self.origin;
+        let (x1, y1) = (x0 + self.size.0, y0 + self.size.1);
+        let (x0, x1, y0, y1) = if self.bits.is_empty() {
+            (c.x - MARGIN, c.x + MARGIN + 1, c.y - MARGIN, c.y + MARGIN + 1)
+        } else {
+            let pad_x = cmp::max(MARGIN, self.size.0 / 2);
+            let pad_y = cmp::max(MARGIN, self.size.1 / 2);
+            (cmp::min(x0, c.x - pad_x),
+             cmp::max(x1, c.x + pad_x + 1),
+             cmp::min(y0, c.y - pad_y),
+             cmp::max(y1, c.y + pad_y + 1))
+        };
+
+        self.origin = (x0, y0);
+        self.size = (x1 - x0, y1 - y0);
+        let len = (self.size.0 * self.size.1) as usize;
+        self.bits = vec![0; (len + 63) / 64];
+        let coords = mem::replace(&mut self.coords, vec![]);
+        for &c in &coords {
+            let i = self.index(c).unwrap();
+            self.set(i);
+        }
+        self.coords = coords;
+    }
+
+    pub fn len(&self) -> usize {
+        self.coords.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coords.is_empty()
+    }
+
+    /// In the order they were inserted
+    pub fn iter(&self) -> slice::Iter<Coordinate> {
+        self.coords.iter()
+    }
+
+    /// Those not in `other`
+    pub fn difference<'a>(&'a self,
+                          other: &'a Visibility)
+                          -> Box<Iterator<Item = &'a Coordinate> + 'a> {
+        Box::new(self.iter().filter(move |c| !other.contains(c)))
+    }
+}
+
+impl<'a> IntoIterator for &'a Visibility {
+    type Item = &'a Coordinate;
+    type IntoIter = slice::Iter<'a, Coordinate>;
+
+    fn into_iter(self) -> slice::Iter<'a, Coordinate> {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Visibility {
+    type Item = Coordinate;
+    type IntoIter = vec::IntoIter<Coordinate>;
+
+    fn into_iter(self) -> vec::IntoIter<Coordinate> {
+        self.coords.into_iter()
+    }
+}
+
+impl Extend<Coordinate> for Visibility {
+    fn extend<I: IntoIterator<Item = Coordinate>>(&mut self, iter: I) {
+        for c in iter {
+            self.insert(c);
+        }
+    }
+}
+
+impl FromIterator<Coordinate> for Visibility {
+    fn from_iter<I: IntoIterator<Item = Coordinate>>(iter: I) -> Visibility {
+        let mut set = Visibility::new();
+        set.extend(iter);
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use hex2d::Coordinate;
+    use rand::Rng;
+
+    use game::{decode_coords, encode_coords};
+    use util;
+    use super::Visibility;
+
+    #[test]
+    fn same_as_a_hash_set_on_random_coordinates() {
+        for seed in 0..50 {
+            let mut rng = util::game_rng(seed);
+            // spread over a little or a lot, so that some sets grow often
+            let spread = rng.gen_range(1, 200);
+            let mut set = Visibility::new();
+            let mut hash_set = HashSet::new();
+            let mut order = vec![];
+
+            for _ in 0..rng.gen_range(0, 500) {
+                let c = Coordinate::new(rng.gen_range(-spread, spread),
+                                        rng.gen_range(-spread, spread));
+                let new = hash_set.insert(c);
+                assert_eq!(set.insert(c), new, "{:?}", c);
+                if new {
+                    order.push(c);
+                }
+            }
+
+            assert_eq!(set.len(), hash_set.len());
+            assert_eq!(set.is_empty(), hash_set.is_empty());
+            assert_eq!(set.iter().cloned().collect::<Vec<_>>(), order);
+            for _ in 0..500 {
+                let c = Coordinate::new(rng.gen_range(-spread - 5, spread + 5),
+                                        rng.gen_range(-spread - 5, spread + 5));
+                assert_eq!(set.contains(&c), hash_set.contains(&c), "{:?}", c);
+            }
+            for c in &order {
+                assert!(set.contains(c));
+            }
+
+            let decoded = decode_coords(&encode_coords(&set)).unwrap();
+            assert_eq!(encode_coords(&decoded), encode_coords(&set));
+            let other: Vec<Coordinate> = order.iter().cloned().filter(|_| rng.gen()).collect();
+            let other_set: Visibility = other.iter().cloned().collect();
+            let other_hash_set: HashSet<Coordinate> = other.into_iter().collect();
+            let difference: HashSet<Coordinate> = set.difference(&other_set).cloned().collect();
+            assert_eq!(difference,
+                       hash_set.difference(&other_hash_set).cloned().collect());
+        }
+    }
+}
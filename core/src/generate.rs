@@ -0,0 +1,1053 @@
+
+use rand::Rng;
+use std::collections::VecDeque;
+use std::cmp;
+use std::sync::Arc;
+use simplemap::SimpleMap;
+
+use hex2d as h2d;
+use hex2d::Angle::*;
+use hex2d::{ToCoordinate, Direction, Position, Coordinate};
+use game::tile;
+use game::{Map, Actors, Items, Branch, LevelId, CoordMap, CoordSet, FnvHashSet};
+use game;
+use game::area;
+use game::item;
+use game::actor::{Race, Actor};
+use util::GameRng;
+
+type EndpointQueue = VecDeque<h2d::Position>;
+
+/// Ambient light on the surface, during the day
+pub const SURFACE_AMBIENT_LIGHT: u32 = 4;
+/// Ambient light on the shallowest levels below the surface
+const SHALLOW_AMBIENT_LIGHT: u32 = 1;
+/// Deepest level still getting some light from the surface
+const SHALLOW_DEPTH: u32 = 2;
+
+/// Overall shape of the generated level
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Shape {
+    /// Roughly round cluster of rooms and corridors
+    Blob,
+    /// Level wrapped around an inaccessible core
+    Ring,
+    /// Long, narrow, corridor-heavy band
+    Strip,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct GenerationParams {
+    /// Number of passable tiles to generate (approximately)
+    pub target_tiles: u32,
+    /// No tile will be further than that from the start
+    pub max_radius: i32,
+    pub shape: Shape,
+    /// Light everywhere on the level, regardless of light sources
+    pub ambient_light: u32,
+}
+
+impl GenerationParams {
+    pub fn for_level(level: u32) -> GenerationParams {
+        GenerationParams {
+            target_tiles: 400 + level * 100,
+            max_radius: 40,
+            shape: match level % 5 {
+                3 => Shape::Strip,
+                4 => Shape::Ring,
+                _ => Shape::Blob,
+            },
+            ambient_light: match level {
+                0 => SURFACE_AMBIENT_LIGHT,
+                l if l <= SHALLOW_DEPTH => SHALLOW_AMBIENT_LIGHT,
+                _ => 0,
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ValidationError {
+    UnreachableTile(Coordinate),
+    UnreachableStairs(Coordinate),
+    UnreachableItem(Coordinate),
+    UnreachableActor(Coordinate),
+    /// Spawn point that is a wall or has an actor on it
+    BlockedSpawn(Coordinate),
+}
+
+/// Result of the level generation
+pub struct GeneratedLevel {
+    pub map: Map,
+    pub actors: Actors,
+    pub items: Items,
+    /// Contents of closed chests
+    pub containers: Items,
+    /// Where the player starts (on the up stairs, if any)
+    pub start: Coordinate,
+    /// Free tiles next to the start, for whoever follows the player
+    pub companion_spots: Vec<Coordinate>,
+    pub stairs: Option<Coordinate>,
+    /// Stairs leading to a side branch
+    pub branch_stairs: Option<Coordinate>,
+    /// Room turned into a shop
+    pub shop: Option<area::Area>,
+}
+
+impl GeneratedLevel {
+    /// Check that every passable tile, the stairs, all the items and
+    /// actors can be reached from the start.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let reachable = reachable_from(&self.map, self.start);
+
+        for (&coord, tile) in self.map.iter() {
+            if tile.is_passable() && !reachable.contains(&coord) {
+                return Err(ValidationError::UnreachableTile(coord));
+            }
+        }
+
+        for stairs in self.stairs.iter().chain(self.branch_stairs.iter()) {
+            if !reachable.contains(stairs) {
+                return Err(ValidationError::UnreachableStairs(*stairs));
+            }
+        }
+
+        for (&coord, _) in &self.items {
+            if !reachable.contains(&coord) {
+                return Err(ValidationError::UnreachableItem(coord));
+            }
+        }
+
+        for (&coord, _) in &self.actors {
+            if !reachable.contains(&coord) {
+                return Err(ValidationError::UnreachableActor(coord));
+            }
+        }
+
+        let spawns = Some(self.start)
+                         .into_iter()
+                         .chain(self.stairs)
+                         .chain(self.companion_spots.iter().cloned());
+        for coord in spawns {
+            if !self.map[coord].is_passable() || self.actors.contains_key(&coord) {
+                return Err(ValidationError::BlockedSpawn(coord));
+            }
+            if !reachable.contains(&coord) {
+                return Err(ValidationError::UnreachableTile(coord));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// All coordinates reachable from `start` by walking (closed doors included)
+fn reachable_from(map: &Map, start: Coordinate) -> CoordSet {
+    let mut reachable = CoordSet::default();
+    let mut queue = VecDeque::new();
+
+    reachable.insert(start);
+    queue.push_back(start);
+
+    while let Some(coord) = queue.pop_front() {
+        for &n in &coord.neighbors() {
+            if !reachable.contains(&n) && map[n].is_passable() {
+                reachable.insert(n);
+                queue.push_back(n);
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Carve corridors until every passable tile is reachable from `start`
+fn connect_regions(map: &mut Map, start: Coordinate) {
+    loop {
+        let reachable = reachable_from(map, start);
+
+        let orphan = game::in_order(map)
+                         .into_iter()
+                         .filter(|&(c, t)| t.is_passable() && !reachable.contains(&c))
+                         .map(|(c, _)| c)
+                         .next();
+
+        let orphan = match orphan {
+            Some(orphan) => orphan,
+            None => return,
+        };
+
+        let closest = reachable.iter()
+                               .cloned()
+                               .min_by_key(|c| c.distance(orphan))
+                               .expect("start is always reachable");
+
+        orphan.for_each_in_line_to(closest, |c| {
+            if map[c].type_ == tile::Water {
+                map[c].type_ = tile::Bridge;
+            } else if !map[c].is_passable() {
+                map[c] = tile::Tile::new(tile::Empty);
+            }
+        });
+    }
+}
+
+/// Drop a few boulders where they don't cut anything off
+fn place_boulders(map: &mut Map,
+                  start: Coordinate,
+                  keep_free: &[Coordinate],
+                  items: &Items,
+                  actors: &Actors,
+                  rng: &mut GameRng) {
+    let reachable = reachable_from(map, start);
+    let candidates: Vec<Coordinate> = game::in_order(map)
+                                          .into_iter()
+                                          .filter(|&(c, t)| {
+                                              t.type_ == tile::Empty && !t.has_features() &&
+                                              reachable.contains(&c) &&
+                                              !keep_free.contains(&c) &&
+                                              !items.contains_key(&c) &&
+                                              !actors.contains_key(&c)
+                                          })
+                                          .map(|(c, _)| c)
+                                          .collect();
+
+    for _ in 0..rng.gen_range(0, 4) {
+        let coord = match rng.choose(&candidates) {
+            Some(&c) => c,
+            None => return,
+        };
+
+        if map[coord].has_features() {
+            continue;
+        }
+
+        let before = reachable_from(map, start).len();
+        map[coord].add_feature(tile::Boulder);
+        // boulders count as walls, so only the boulder's own tile may go
+        if reachable_from(map, start).len() + 1 != before {
+            map[coord].remove_feature(tile::Layer::Fixture);
+        }
+    }
+}
+
+pub struct DungeonGenerator {
+    id: LevelId,
+    level: u32,
+    params: GenerationParams,
+    start: Option<Coordinate>,
+    stairs: Option<Coordinate>,
+    tile_count: u32,
+    map: CoordMap<tile::Tile>,
+    endpoints: EndpointQueue,
+    actors: Actors,
+    items: Items,
+    containers: Items,
+    rng: GameRng,
+}
+
+impl DungeonGenerator {
+    pub fn new(id: LevelId, params: GenerationParams, rng: GameRng) -> DungeonGenerator {
+        DungeonGenerator {
+            id: id,
+            level: id.absolute_depth(),
+            params: params,
+            start: None,
+            stairs: None,
+            tile_count: 0,
+            map: Default::default(),
+            endpoints: VecDeque::new(),
+            actors: Default::default(),
+            items: Default::default(),
+            containers: Default::default(),
+            rng: rng,
+        }
+    }
+}
+
+fn tile_is_deadend(map: &Map, coord: Coordinate) -> bool {
+    let neighbors = coord.neighbors();
+
+    let passable: Vec<bool> = neighbors.iter()
+                                       .map(|n_coord| map[*n_coord].is_passable())
+                                       .collect();
+
+    let len = passable.len();
+
+    assert_eq!(len, 6);
+
+    let mut changes = 0;
+    let mut last = passable[len - 1];
+    for i in 0..len {
+        let current = passable[i];
+        if last != current {
+            changes += 1;
+            last = current;
+        }
+    }
+
+    if changes < 4 {
+        true
+    } else {
+        false
+    }
+}
+
+impl DungeonGenerator {
+    /// Can a tile be placed at `coord` given the shape restrictions
+    fn in_bounds(&self, coord: Coordinate) -> bool {
+        let start = self.start.expect("start not set");
+        let max_r = self.params.max_radius;
+
+        if start.distance(coord) > max_r {
+            return false;
+        }
+
+        match self.params.shape {
+            Shape::Blob => true,
+            Shape::Ring => {
+                let core = start + h2d::Direction::XY.to_coordinate().scale(max_r / 2);
+                core.distance(coord) >= max_r / 4
+            }
+            Shape::Strip => (coord.y - start.y).abs() <= cmp::max(3, max_r / 5),
+        }
+    }
+
+    fn range_in_bounds(&self, center: Coordinate, r: i32) -> bool {
+        let mut ok = true;
+        center.for_each_in_range(r, |c| {
+            if !self.in_bounds(c) {
+                ok = false;
+            }
+        });
+        ok
+    }
+
+    // generate_map_feature
+    // fn generate_continue_coridor(&self, map : &mut HashMap<h2d::Coordinate, Tile>,
+    fn generate_continue_coridor(&mut self, pos: h2d::Position) {
+
+        let npos = pos + pos.dir.to_coordinate();
+
+        match self.map.get(&npos.coord).cloned() {
+            Some(tile) => {
+                if tile.type_.is_passable() {
+                    self.endpoint_push(npos);
+                } else {
+                    self.endpoint_push(pos + Right);
+                }
+            }
+            None if !self.in_bounds(npos.coord) => {
+                self.endpoint_push(pos + Right);
+            }
+            None => {
+                let mut t = tile::Tile::new(tile::Empty);
+                if self.rng.gen_weighted_bool(40) {
+                    t.add_feature(tile::Fungi).add_light(tile::FUNGI_LIGHT);
+                }
+                self.map.insert(npos.coord, t);
+                self.endpoint_push(npos);
+                match self.rng.gen_range(0, 19) {
+                    0 => {
+                        let leftwall = pos + (pos.dir + h2d::Angle::Left).to_coordinate();
+                        let rightwall = pos + (pos.dir + h2d::Angle::Right).to_coordinate();
+
+                        if !self.map.contains_key(&leftwall.coord) &&
+                           self.in_bounds(leftwall.coord) {
+                            self.map.insert(leftwall.coord, tile::Tile::new(tile::Wall));
+                        }
+                        if !self.map.contains_key(&rightwall.coord) &&
+                           self.in_bounds(rightwall.coord) {
+                            self.map.insert(rightwall.coord, tile::Tile::new(tile::Wall));
+                        }
+                    }
+                    _ => {}
+                }
+
+                self.tile_count += 1
+            }
+        }
+    }
+
+    // generate_map_feature
+    fn generate_turn(&mut self, pos: h2d::Position, turn: h2d::Angle) {
+        self.generate_continue_coridor(pos + turn)
+    }
+
+    // generate_map_feature
+    fn generate_cross(&mut self, pos: h2d::Position, turn: h2d::Angle) {
+        self.endpoint_push(pos + turn);
+        self.generate_continue_coridor(pos)
+    }
+
+    /// Generate room in front of the iterator `(pos, dir)`
+    fn generate_room(&mut self, pos: h2d::Position, r: u32) {
+
+        self.endpoint_push(pos);
+
+        let center_pos = pos + pos.dir.to_coordinate().scale(r as i32);
+
+        let tile_count_old = self.tile_count;
+        self.generate_room_inplace(center_pos, r);
+
+        if tile_count_old == self.tile_count {
+            match self.rng.gen_range(0, 8) {
+                0 => self.endpoint_push(pos + Left),
+                1 => self.endpoint_push(pos + LeftBack),
+                2 => self.endpoint_push(pos + Right),
+                3 => self.endpoint_push(pos + RightBack),
+                _ => {}
+            }
+        }
+    }
+
+    // generate_map at position `pos`; does not push back the iterator!
+    fn generate_room_inplace(&mut self, center: h2d::Position, r: u32) {
+
+        let coord = center.coord;
+
+        let mut blocked = false;
+        coord.for_each_in_range((r - 1) as i32, |c| {
+            if self.map.contains_key(&c) {
+                blocked = true;
+            }
+        });
+
+        if blocked || !self.range_in_bounds(coord, r as i32) {
+            return;
+        }
+
+        let area = area::Area::new(coord, area::Type::Room(r));
+
+        if Some(coord) != self.start {
+            match self.rng.gen_range(0, 6) {
+                2 => {
+                    if self.needs_stairs() {
+                        let below = self.id.below().unwrap();
+                        self.map.insert(coord,
+                                        *tile::Tile::new(tile::Empty)
+                                             .add_feature(tile::StairsDown(below))
+                                             .add_area(area));
+                        self.stairs = Some(coord);
+                        self.tile_count += 1;
+                    }
+                }
+                3 => {
+                    self.map.insert(coord,
+                                    *tile::Tile::new(tile::Empty)
+                                         .add_feature(tile::Statue)
+                                         .add_area(area));
+                    self.tile_count += 1;
+                }
+                4 => {
+                    let feature = match self.rng.gen_range(0, 6) {
+                        0 => Some((tile::Fountain(self.rng.gen_range(1, 4)), 0)),
+                        1 => Some((tile::Altar(false), 0)),
+                        2 => Some((tile::Brazier(true), tile::BRAZIER_LIGHT)),
+                        3 => Some((tile::Chest(self.random_chest(coord)), 0)),
+                        _ => None,
+                    };
+
+                    if let Some((feature, light)) = feature {
+                        self.map.insert(coord,
+                                        *tile::Tile::new(tile::Empty)
+                                             .add_feature(feature)
+                                             .add_light(light)
+                                             .add_area(area));
+                        self.tile_count += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // some rooms are overgrown
+        let floor = if self.rng.gen_weighted_bool(8) {
+            tile::Grass
+        } else {
+            tile::Empty
+        };
+
+        coord.for_each_in_range((r - 1) as i32, |c| {
+            if !self.map.contains_key(&c) {
+                self.tile_count += 1;
+                self.map.insert(c, *tile::Tile::new(floor).add_area(area));
+            }
+        });
+
+        // TODO: Guarantee that the room is not completely closed
+        coord.for_each_in_ring(r as i32, h2d::Spin::CW(h2d::Direction::XY), |c| {
+            if !self.map.contains_key(&c) {
+                let mut door = tile::Tile::new(tile::Empty);
+                door.add_feature(tile::Door(false, tile::DOOR_HP));
+                // some doorways are trapped
+                if self.rng.gen_weighted_bool(15) {
+                    let trap = if self.rng.gen_weighted_bool(2) {
+                        tile::Trap::Dart
+                    } else {
+                        tile::Trap::Alarm
+                    };
+                    door.add_feature(tile::Trap(trap));
+                }
+                self.map.insert(c, door);
+                self.tile_count += 1;
+            }
+        });
+
+        coord.for_each_in_range(r as i32 - 1, |c| {
+            if self.map.contains_key(&c) {
+                match self.rng.gen_range(0, 15) {
+                    0 => {
+                        self.map.get_mut(&c).unwrap().add_light((r + 4) as i32);
+                    }
+                    _ => {}
+                }
+                self.tile_count += 1;
+            }
+        });
+
+
+        coord.for_each_in_range(r as i32 / 2, |c| {
+            if c != coord && self.map.get(&c).map(|t| t.is_passable()).unwrap_or(false) {
+                let chance = match self.id.branch {
+                    Branch::Warrens => 4,
+                    Branch::Main => 10,
+                };
+                match self.rng.gen_range(0, chance) {
+                    0 => {
+                        let pos = Position::new(c, Direction::XY);
+                        let race = match self.id.branch {
+                            Branch::Warrens => Race::Rat,
+                            Branch::Main => {
+                                // now and then something comes from deeper down
+                                let level = if self.rng.gen_weighted_bool(30) {
+                                    self.level + 4
+                                } else {
+                                    self.level
+                                };
+                                match self.rng.gen_range(0, 1 + level / 2) {
+                                    0 => Race::Rat,
+                                    1 => Race::Goblin,
+                                    _ => Race::Troll,
+                                }
+                            }
+                        };
+                        self.actors.insert(c, Actor::new(race, pos));
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        let center_passable = self.map.get(&coord).map(|t| t.is_passable()).unwrap_or(false);
+        if center_passable && !self.containers.contains_key(&coord) &&
+           self.rng.gen_weighted_bool(2) {
+            self.items.insert(coord, item::random(self.level as i32, &mut self.rng));
+        }
+    }
+
+    fn needs_stairs(&self) -> bool {
+        self.stairs.is_none() && self.id.below().is_some()
+    }
+
+    /// Free tile (without feature, item or actor) furthest from all of `avoid`
+    fn free_tile_away_from(&self, avoid: &[Coordinate]) -> Option<Coordinate> {
+        self.map
+            .iter()
+            .filter(|&(_, t)| t.is_passable() && !t.has_features())
+            .map(|(&c, _)| c)
+            .filter(|c| !self.actors.contains_key(c) && !self.items.contains_key(c))
+            .max_by_key(|c| avoid.iter().map(|a| a.distance(*c)).min().unwrap_or(0))
+    }
+
+    /// Put stairs on the free tile furthest from the start
+    fn place_fallback_stairs(&mut self, start: Coordinate) {
+        let below = self.id.below().unwrap();
+        if let Some(coord) = self.free_tile_away_from(&[start]) {
+            self.map.get_mut(&coord).unwrap().add_feature(tile::StairsDown(below));
+            self.stairs = Some(coord);
+        }
+    }
+
+    /// Up stairs, side branch entrance and the reward at the bottom of a branch
+    fn place_branch_features(&mut self, start: Coordinate) -> Option<Coordinate> {
+        if let Some(above) = self.id.above() {
+            self.map.get_mut(&start).unwrap().add_feature(tile::StairsUp(above));
+        }
+
+        let mut avoid = vec![start];
+        avoid.extend(self.stairs);
+
+        if self.id.branch != Branch::Main && self.id.is_bottom() {
+            if let Some(coord) = self.free_tile_away_from(&avoid) {
+                self.items.insert(coord, item::random(self.level as i32 + 5, &mut self.rng));
+            }
+        }
+
+        match self.id.side_branch() {
+            Some(side) => {
+                let coord = self.free_tile_away_from(&avoid);
+                if let Some(coord) = coord {
+                    self.map.get_mut(&coord).unwrap().add_feature(tile::StairsDown(side));
+                }
+                coord
+            }
+            None => None,
+        }
+    }
+
+    /// New closed chest with something inside; locked ones hold better loot
+    fn random_chest(&mut self, coord: Coordinate) -> tile::Chest {
+        let locked = self.rng.gen_weighted_bool(2);
+        let trap = if self.rng.gen_weighted_bool(3) {
+            Some(if self.rng.gen_weighted_bool(2) {
+                tile::Trap::Needle
+            } else {
+                tile::Trap::Alarm
+            })
+        } else {
+            None
+        };
+
+        let level = self.level as i32 + if locked { 3 } else { 0 };
+        self.containers.insert(coord, item::random(level, &mut self.rng));
+
+        tile::Chest {
+            open: false,
+            locked: locked,
+            trap: trap,
+        }
+    }
+
+    /// Sometimes link two far-apart rooms with a pair of portals
+    fn place_portals(&mut self) {
+        if !self.rng.gen_weighted_bool(3) {
+            return;
+        }
+
+        let centers: Vec<Coordinate> = self.map
+                                           .iter()
+                                           .filter_map(|(_, t)| t.area.map(|a| a.center))
+                                           .collect::<CoordSet>()
+                                           .into_iter()
+                                           .filter(|c| {
+                                               Some(*c) != self.start &&
+                                               self.map[c].is_passable() &&
+                                               !self.map[c].has_features() &&
+                                               !self.actors.contains_key(c) &&
+                                               !self.items.contains_key(c)
+                                           })
+                                           .collect();
+
+        let a = match self.rng.choose(&centers) {
+            Some(&a) => a,
+            None => return,
+        };
+        let b = centers.iter().cloned().max_by_key(|c| c.distance(a)).unwrap();
+
+        if a.distance(b) < 15 {
+            return;
+        }
+
+        self.map.get_mut(&a).unwrap().add_feature(tile::Portal(b));
+        self.map.get_mut(&b).unwrap().add_feature(tile::Portal(a));
+    }
+
+    /// Sometimes turn a room into a shop with priced items and a
+    /// shopkeeper in the middle
+    fn place_shop(&mut self) -> Option<area::Area> {
+        if self.id.branch != Branch::Main || self.level < 1 ||
+           !self.rng.gen_weighted_bool(4) {
+            return None;
+        }
+
+        let areas: Vec<area::Area> = self.map
+                                         .iter()
+                                         .filter_map(|(_, t)| t.area)
+                                         .filter(|a| {
+                                             match a.type_ {
+                                                 area::Room(r) => r >= 3,
+                                             }
+                                         })
+                                         .filter(|a| Some(a.center) != self.start)
+                                         .collect::<FnvHashSet<_>>()
+                                         .into_iter()
+                                         .collect();
+
+        for area in areas {
+            let r = match area.type_ {
+                area::Room(r) => r as i32 - 1,
+            };
+
+            let mut floor = vec![];
+            let mut clear = true;
+            area.center.for_each_in_range(r, |c| {
+                match self.map.get(&c) {
+                    Some(t) if t.area == Some(area) => {
+                        if t.type_ != tile::Empty || t.has_features() {
+                            clear = false;
+                        }
+                        floor.push(c);
+                    }
+                    _ => {}
+                }
+            });
+
+            if !clear {
+                continue;
+            }
+
+            for c in &floor {
+                self.actors.remove(c);
+                self.items.remove(c);
+            }
+
+            let level = self.level as i32 + 2;
+            let wares = self.rng.gen_range(3, 7);
+            for &c in floor.iter().filter(|&&c| c != area.center).take(wares) {
+                let mut item = item::random(level, &mut self.rng);
+                if item.gold().is_some() {
+                    continue;
+                }
+                let price = item.value();
+                Arc::make_mut(&mut item).set_price(Some(price));
+                self.items.insert(c, item);
+            }
+
+            let pos = Position::new(area.center, Direction::XY);
+            self.actors.insert(area.center, Actor::new(Race::Shopkeeper, pos));
+
+            return Some(area);
+        }
+
+        None
+    }
+
+    /// Turn some bigger rooms into ponds with a bridge across. Any door
+    /// left stranded gets its own crossing from `connect_regions`.
+    fn flood_rooms(&mut self) {
+        let areas: Vec<area::Area> = self.map
+                                         .iter()
+                                         .filter_map(|(_, t)| t.area)
+                                         .filter(|a| {
+                                             match a.type_ {
+                                                 area::Room(r) => r >= 3,
+                                             }
+                                         })
+                                         .filter(|a| Some(a.center) != self.start)
+                                         .collect::<FnvHashSet<_>>()
+                                         .into_iter()
+                                         .collect();
+
+        for area in areas {
+            if !self.rng.gen_weighted_bool(6) {
+                continue;
+            }
+
+            let r = match area.type_ {
+                area::Room(r) => r as i32 - 1,
+            };
+
+            let mut pond = vec![];
+            let mut clear = true;
+            area.center.for_each_in_range(r, |c| {
+                match self.map.get(&c) {
+                    Some(t) if t.type_ == tile::Empty && t.area == Some(area) => {
+                        if t.has_features() || self.actors.contains_key(&c) ||
+                           self.items.contains_key(&c) {
+                            clear = false;
+                        }
+                        pond.push(c);
+                    }
+                    _ => {}
+                }
+            });
+
+            if !clear {
+                continue;
+            }
+
+            for c in &pond {
+                self.map.get_mut(c).unwrap().type_ = tile::Water;
+            }
+
+            let dir = Direction::from_int(self.rng.gen_range(0, 3));
+            let from = area.center + dir.to_coordinate().scale(r);
+            let to = area.center - dir.to_coordinate().scale(r);
+            from.for_each_in_line_to(to, |c| {
+                if let Some(t) = self.map.get_mut(&c) {
+                    if t.type_ == tile::Water {
+                        t.type_ = tile::Bridge;
+                    }
+                }
+            });
+        }
+    }
+
+    pub fn endpoint_push(&mut self, pos: h2d::Position) {
+        assert!(self.map.contains_key(&pos.coord));
+        self.endpoints.push_back(pos);
+    }
+
+    pub fn generate_map(mut self, start: h2d::Coordinate) -> GeneratedLevel {
+        let size = self.params.target_tiles;
+        let start_dir = h2d::Direction::XY;
+        let start_pos = Position::new(start, start_dir);
+        let first_room_r = self.rng.gen_range(0, 2) + 2;
+        self.start = Some(start);
+
+        self.generate_room_inplace(start_pos, first_room_r);
+
+        self.endpoint_push(start_pos);
+
+        // Bounded levels might fill up before reaching the target size
+        let max_iterations = size * 50;
+        let mut iterations = 0;
+
+        while (self.tile_count < size || self.needs_stairs()) && iterations < max_iterations {
+            iterations += 1;
+
+            let pos = self.endpoints.pop_front().expect("generator run out of endpoints");
+
+            if self.endpoints.len() > 4 {
+                self.endpoints.pop_front();
+            }
+
+            assert!(self.map
+                        .get(&pos.coord)
+                        .expect("map generator iterator on non-existing tile")
+                        .type_
+                        .is_passable());
+
+            match self.rng.gen_range(0, 10) {
+                0 => {
+                    match self.rng.gen_range(0, 4) {
+                        0 => self.generate_turn(pos, Left),
+                        1 => self.generate_turn(pos, Right),
+                        2 => self.generate_cross(pos, Left),
+                        3 => self.generate_cross(pos, Right),
+                        _ => panic!(),
+                    }
+                }
+                // corridor-heavy levels get fewer rooms
+                1 if self.params.shape != Shape::Strip ||
+                     self.rng.gen_weighted_bool(3) => {
+                    let size = self.rng.gen_range(0, 3) +
+                               self.rng.gen_range(0, 2) +
+                               2;
+                    self.generate_room(pos, size)
+                }
+                _ => self.generate_continue_coridor(pos),
+            }
+        }
+
+        if self.needs_stairs() {
+            self.place_fallback_stairs(start);
+        }
+
+        let branch_stairs = self.place_branch_features(start);
+        let shop = self.place_shop();
+        self.place_portals();
+        self.flood_rooms();
+
+        let mut map = SimpleMap::new();
+
+        for (&coord, tile) in self.map.iter() {
+            map[coord] = tile.clone()
+        }
+
+        // eliminate dead ends
+        for (&coord, tile) in self.map.iter() {
+            if tile.door_state() == Some(false) {
+                if tile_is_deadend(&map, coord) {
+                    map[coord] = tile::Tile::new(tile::Wall);
+                }
+            }
+        }
+
+        connect_regions(&mut map, start);
+
+        // Nobody should be standing where the player arrives
+        self.actors.remove(&start);
+        if let Some(stairs) = self.stairs {
+            self.actors.remove(&stairs);
+        }
+
+        let companion_spots: Vec<Coordinate> = start.neighbors()
+                                                    .iter()
+                                                    .cloned()
+                                                    .filter(|&c| map[c].is_passable())
+                                                    .collect();
+        for c in &companion_spots {
+            self.actors.remove(c);
+        }
+
+        let mut keep_free = companion_spots.clone();
+        keep_free.push(start);
+        keep_free.extend(self.stairs);
+        keep_free.extend(branch_stairs);
+        place_boulders(&mut map,
+                       start,
+                       &keep_free,
+                       &self.items,
+                       &self.actors,
+                       &mut self.rng);
+
+        let level = GeneratedLevel {
+            map: map,
+            actors: self.actors,
+            items: self.items,
+            containers: self.containers,
+            start: start,
+            companion_spots: companion_spots,
+            stairs: self.stairs,
+            branch_stairs: branch_stairs,
+            shop: shop,
+        };
+
+        debug_assert_eq!(level.validate(), Ok(()));
+
+        level
+    }
+}
+
+pub fn gen_level(id: LevelId, params: GenerationParams, rng: &mut GameRng) -> GeneratedLevel {
+    let level = DungeonGenerator::new(id, params, rng.gen()).generate_map(Coordinate::new(0, 0));
+    debug!(target: "generate",
+           "{}: {} tiles, {} actors, {} items and {} in chests; start {:?}, stairs {:?} \
+            and {:?}, shop {:?}",
+           id,
+           level.map.iter().count(),
+           level.actors.len(),
+           level.items.len(),
+           level.containers.len(),
+           level.start,
+           level.stairs,
+           level.branch_stairs,
+           level.shop.map(|a| a.center));
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use hex2d::{Coordinate, Direction, Position};
+
+    use game::{tile, Branch, LevelId};
+    use game::actor::{Actor, Race};
+    use util;
+    use super::{gen_level, GeneratedLevel, GenerationParams, Shape, ValidationError};
+
+    fn generate(target_tiles: u32, shape: Shape, seed: u32) -> GeneratedLevel {
+        let params = GenerationParams {
+            target_tiles: target_tiles,
+            max_radius: 40,
+            shape: shape,
+            ambient_light: 0,
+        };
+        gen_level(LevelId::new(Branch::Main, 1), params, &mut util::game_rng(seed))
+    }
+
+    #[test]
+    fn generated_levels_are_connected() {
+        let levels = (0..5)
+                         .map(|depth| LevelId::new(Branch::Main, depth))
+                         .chain((0..3).map(|depth| LevelId::new(Branch::Warrens, depth)));
+        for id in levels {
+            for seed in 0..20 {
+                let params = GenerationParams::for_level(id.absolute_depth());
+                let level = gen_level(id, params, &mut util::game_rng(seed));
+                assert_eq!(level.validate(), Ok(()), "{:?}, seed {}", id, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn orphaned_floor_is_reported() {
+        let mut level = gen_level(LevelId::new(Branch::Main, 1),
+                                  GenerationParams::for_level(1),
+                                  &mut util::game_rng(0));
+        let far = Coordinate::new(1000, 1000);
+        level.map[far] = tile::Tile::new(tile::Empty);
+
+        assert_eq!(level.validate(), Err(ValidationError::UnreachableTile(far)));
+    }
+
+    #[test]
+    fn nobody_spawns_in_a_wall_or_on_an_actor() {
+        for depth in 0..10 {
+            for seed in 0..20 {
+                let level = gen_level(LevelId::new(Branch::Main, depth),
+                                      GenerationParams::for_level(depth),
+                                      &mut util::game_rng(seed));
+                let spawns = Some(level.start).into_iter().chain(level.stairs);
+                for coord in spawns.chain(level.companion_spots.iter().cloned()) {
+                    assert!(level.map[coord].is_passable(),
+                            "{:?} at depth {}, seed {}",
+                            coord,
+                            depth,
+                            seed);
+                    assert!(!level.actors.contains_key(&coord),
+                            "{:?} at depth {}, seed {}",
+                            coord,
+                            depth,
+                            seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn occupied_start_is_reported() {
+        let mut level = gen_level(LevelId::new(Branch::Main, 1),
+                                  GenerationParams::for_level(1),
+                                  &mut util::game_rng(0));
+        let start = level.start;
+        level.actors.insert(start, Actor::new(Race::Goblin, Position::new(start, Direction::XY)));
+
+        assert_eq!(level.validate(), Err(ValidationError::BlockedSpawn(start)));
+    }
+
+    #[test]
+    fn small_and_large_levels_of_every_shape_are_valid() {
+        for &shape in &[Shape::Blob, Shape::Ring, Shape::Strip] {
+            for &size in &[150, 400, 800, 1200] {
+                for seed in 0..5 {
+                    let level = generate(size, shape, seed);
+                    let what = format!("{} tiles, {:?}, seed {}", size, shape, seed);
+                    assert_eq!(level.validate(), Ok(()), "{}", what);
+                    assert!(level.stairs.is_some(), "{}", what);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn levels_stay_within_max_radius() {
+        for &shape in &[Shape::Blob, Shape::Ring, Shape::Strip] {
+            let params = GenerationParams {
+                target_tiles: 1200,
+                max_radius: 15,
+                shape: shape,
+                ambient_light: 0,
+            };
+            let level = gen_level(LevelId::new(Branch::Main, 1), params, &mut util::game_rng(0));
+            for (&coord, _) in level.map.iter() {
+                assert!(level.start.distance(coord) <= params.max_radius,
+                        "{:?} out of range in a {:?}",
+                        coord,
+                        shape);
+            }
+        }
+    }
+
+    #[test]
+    fn large_level_generates_quickly() {
+        let started = Instant::now();
+        generate(1200, Shape::Blob, 0);
+        assert!(started.elapsed() < Duration::from_secs(5),
+                "1200 tiles took {:?}",
+                started.elapsed());
+    }
+}
@@ -0,0 +1,23 @@
+//! Everything of rhex but the way it's played: the levels and how
+//! they're made, the actors and what they do, and the `Engine` running
+//! it all, deterministically from a seed. Nothing here touches the
+//! terminal, the environment or, but for the save files asked for, the
+//! filesystem.
+
+#![allow(deprecated)]
+
+extern crate simplemap;
+extern crate hex2d;
+extern crate hex2d_dpcext as hex2dext;
+extern crate rand;
+extern crate num;
+#[macro_use]
+extern crate log;
+extern crate core;
+extern crate fnv;
+extern crate rayon;
+
+pub mod ai;
+pub mod game;
+pub mod generate;
+pub mod util;
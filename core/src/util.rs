@@ -0,0 +1,149 @@
+use std::cmp;
+use std::f64::consts::PI;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use hex2d::{Position, Direction, Coordinate, Angle, ToCoordinate, IntegerSpacing};
+
+/// Same layout as the one used for drawing the map
+static SPACING: IntegerSpacing<i32> = IntegerSpacing::PointyTop(2, 1);
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Compass {
+    East,
+    NorthEast,
+    North,
+    NorthWest,
+    West,
+    SouthWest,
+    South,
+    SouthEast,
+}
+
+impl Compass {
+    /// Compass direction from `from` to `to`, as seen on the screen
+    pub fn between(from: Coordinate, to: Coordinate) -> Option<Compass> {
+        if from == to {
+            return None;
+        }
+
+        let (fx, fy) = from.to_pixel_integer(SPACING);
+        let (tx, ty) = to.to_pixel_integer(SPACING);
+
+        // screen `y` grows downwards
+        let angle = (-(ty - fy) as f64).atan2((tx - fx) as f64);
+        let sector = ((angle / (PI / 4.0)).round() as i32 + 8) % 8;
+
+        Some(match sector {
+            0 => Compass::East,
+            1 => Compass::NorthEast,
+            2 => Compass::North,
+            3 => Compass::NorthWest,
+            4 => Compass::West,
+            5 => Compass::SouthWest,
+            6 => Compass::South,
+            _ => Compass::SouthEast,
+        })
+    }
+
+    pub fn description(&self) -> &str {
+        match *self {
+            Compass::East => "east",
+            Compass::NorthEast => "northeast",
+            Compass::North => "north",
+            Compass::NorthWest => "northwest",
+            Compass::West => "west",
+            Compass::SouthWest => "southwest",
+            Compass::South => "south",
+            Compass::SouthEast => "southeast",
+        }
+    }
+}
+
+/// Where `to` is for somebody at `from`, in words: "behind you" if it's
+/// the way they have their back turned, "to the <compass direction>"
+/// otherwise; `None` if it's right there
+pub fn whereabouts(from: Position, to: Coordinate) -> Option<String> {
+    let behind = from.coord.direction_to_cw(to) == Some(from.dir + Angle::Back);
+    if behind {
+        Some("behind you".to_string())
+    } else {
+        Compass::between(from.coord, to).map(|dir| format!("to the {}", dir.description()))
+    }
+}
+
+/// Random number generator behind all of the game logic; the same
+/// seed always gives the same game
+pub type GameRng = XorShiftRng;
+
+pub fn game_rng(seed: u32) -> GameRng {
+    // xorshift must not be seeded with all zeroes
+    XorShiftRng::from_seed([seed, 0x193a_6754, 0xa8a7_d469, 0x9783_0e05])
+}
+
+pub fn random_pos<R: Rng>(x: i32, y: i32, rng: &mut R) -> Position {
+
+    let dir = Direction::from_int(rng.gen_range(0, 6));
+
+    Position::new(Coordinate::new(x, y), dir)
+}
+
+
+/// Roll a against b
+pub fn roll<R: Rng>(a: i32, b: i32, rng: &mut R) -> bool {
+
+    let base = cmp::max(a, b) / 4;
+
+    let a = cmp::max(a - base, 1);
+    let b = cmp::max(b - base, 1);
+
+    rng.gen_range(0, a + b) < a
+}
+
+/// Number of hexes at most `r` away from a given one
+pub fn hexes_in_range(r: i32) -> usize {
+    if r < 0 {
+        0
+    } else {
+        (3 * r * (r + 1) + 1) as usize
+    }
+}
+
+pub fn circular_move(center: Position, cur: Position, angle: Angle) -> Position {
+    let reldir = center.coord.direction_to_cw(cur.coord);
+
+    let reldir = if let Some(reldir) = reldir {
+        reldir
+    } else if (angle == Angle::Left) || (angle == Angle::Right) {
+        return cur + angle;
+    } else {
+        cur.dir
+    };
+
+    match angle {
+        Angle::Forward => cur + reldir.to_coordinate(),
+        Angle::Back => cur - reldir.to_coordinate(),
+        Angle::Left => {
+            let curdist = center.coord.distance(cur.coord);
+            let c = cur.coord + (reldir + Angle::Left);
+            if curdist != center.coord.distance(c) {
+                let c = c - reldir;
+                let newreldir = center.coord.direction_to_cw(c).unwrap_or(reldir);
+                Position::new(c, newreldir)
+            } else {
+                Position::new(c, reldir)
+            }
+        }
+        Angle::Right => {
+            let curdist = center.coord.distance(cur.coord);
+            let c = cur.coord + (reldir + Angle::Right);
+            if curdist != center.coord.distance(c) {
+                let c = c - reldir;
+                let newreldir = center.coord.direction_to_cw(c).unwrap_or(reldir);
+                Position::new(c, newreldir)
+            } else {
+                Position::new(c, reldir)
+            }
+        }
+        _ => panic!(),
+    }
+}
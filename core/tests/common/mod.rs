@@ -0,0 +1,27 @@
+//! What the tests share: places to write files to and read them from
+
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A directory of its own for a test to spill levels to, not there
+/// until something makes it
+pub fn spill_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rhex-test-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// A directory of its own for a test to write files to, empty
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = spill_dir(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A file kept with the tests, as written by some rhex
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
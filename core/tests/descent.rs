@@ -0,0 +1,90 @@
+//! Going down the stairs and back up, with the levels away from the
+//! player spilled to disk
+
+extern crate rhex_core;
+
+mod common;
+
+use std::fs;
+
+use rhex_core::game::{self, Action, Branch, Simulation};
+use rhex_core::game::actor::Race;
+
+use common::spill_dir;
+
+/// Take the stairs down the main branch, or up, and let a few turns go
+/// by on the level they lead to
+fn take_stairs(sim: &mut Simulation, down: bool) {
+    let stairs = game::in_order(&sim.engine().current_location().map)
+                     .into_iter()
+                     .find(|&(_, tile)| {
+                         if down {
+                             tile.stairs_down().map_or(false, |to| to.branch == Branch::Main)
+                         } else {
+                             tile.stairs_up().is_some()
+                         }
+                     })
+                     .map(|(coord, _)| coord)
+                     .expect("no stairs");
+    sim.engine_mut().debug_heal();
+    sim.engine_mut().debug_teleport(stairs);
+    let action = if down { Action::Descend } else { Action::Ascend };
+    assert!(sim.act(action));
+    for _ in 0..3 {
+        sim.engine_mut().debug_heal();
+        assert!(sim.act(Action::Wait));
+    }
+}
+
+#[test]
+fn ten_levels_down_keep_three_in_memory() {
+    let dir = spill_dir("ten-levels-down");
+    let mut spilled = Simulation::new(31, Race::Human);
+    spilled.engine_mut().spill_levels_to(&dir);
+    let mut kept = Simulation::new(31, Race::Human);
+
+    for _ in 0..10 {
+        take_stairs(&mut spilled, true);
+        take_stairs(&mut kept, true);
+        assert!(spilled.engine().levels_loaded() <= 3,
+                "{} levels in memory on {}",
+                spilled.engine().levels_loaded(),
+                spilled.level());
+        assert_eq!(spilled.engine().state_hash(), kept.engine().state_hash());
+    }
+    assert_eq!(spilled.level().absolute_depth(), 10);
+    assert_eq!(kept.engine().levels_loaded(), 11);
+
+    // and back up, to levels read back from disk, as they were left
+    for _ in 0..3 {
+        take_stairs(&mut spilled, false);
+        take_stairs(&mut kept, false);
+        assert!(spilled.engine().levels_loaded() <= 3);
+        assert_eq!(spilled.engine().state_hash(), kept.engine().state_hash());
+        assert_eq!(spilled.player().perception.known.len(),
+                   kept.player().perception.known.len());
+    }
+    assert_eq!(spilled.engine().kills(), kept.engine().kills());
+
+    drop(spilled);
+    let left = fs::read_dir(&dir).map(|entries| entries.count()).unwrap_or(0);
+    assert_eq!(left, 0, "levels left behind in {}", dir.display());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn levels_that_cant_be_written_stay_in_memory() {
+    let dir = spill_dir("unwritable-level");
+    // where the first level would go
+    fs::create_dir_all(dir.join("Dungeon-0")).unwrap();
+    let mut sim = Simulation::new(31, Race::Human);
+    sim.engine_mut().spill_levels_to(&dir);
+
+    take_stairs(&mut sim, true);
+    take_stairs(&mut sim, true);
+    // the first level, and the two last
+    assert_eq!(sim.engine().levels_loaded(), 3);
+
+    drop(sim);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,77 @@
+//! Games started from the same seed and played the same way go the same
+//! way, whatever else is running
+
+extern crate hex2d;
+extern crate rhex_core;
+
+use hex2d::Angle;
+
+use rhex_core::game::{Action, Engine, Simulation};
+use rhex_core::game::actor::Race;
+
+/// Something for the player to do on turn `i`: walking, turning and
+/// waiting, with enough variety to run into walls and monsters
+fn scripted(i: usize) -> Action {
+    const SCRIPT: [Action; 8] = [Action::Move(Angle::Forward),
+                                 Action::Move(Angle::Forward),
+                                 Action::Turn(Angle::Right),
+                                 Action::Move(Angle::Forward),
+                                 Action::Wait,
+                                 Action::Move(Angle::Left),
+                                 Action::Turn(Angle::LeftBack),
+                                 Action::Move(Angle::Forward)];
+    SCRIPT[(i * 7 + i / SCRIPT.len()) % SCRIPT.len()]
+}
+
+#[test]
+fn same_seed_and_actions_give_the_same_game() {
+    let mut a = Simulation::new(1234, Race::Human);
+    let mut b = Simulation::new(1234, Race::Human);
+    assert_eq!(a.engine().state_hash(), b.engine().state_hash());
+
+    for i in 0..200 {
+        let action = scripted(i);
+        assert_eq!(a.act(action), b.act(action), "action {} ({:?})", i, action);
+        assert_eq!(a.engine().state_hash(),
+                   b.engine().state_hash(),
+                   "after action {} ({:?}), turn {}",
+                   i,
+                   action,
+                   a.turn());
+        if a.player().is_dead() {
+            break;
+        }
+    }
+    assert_eq!(a.log(), b.log());
+}
+
+#[test]
+fn different_seeds_give_different_games() {
+    let a = Simulation::new(1, Race::Human);
+    let b = Simulation::new(2, Race::Human);
+    assert!(a.engine().state_hash() != b.engine().state_hash());
+}
+
+#[test]
+fn checkpoints_match_for_500_turns() {
+    let mut a = Simulation::new(4321, Race::Elf);
+    let mut b = Simulation::new(4321, Race::Elf);
+
+    let mut i = 0;
+    while a.turn() < 500 && !a.player().is_dead() {
+        let action = scripted(i);
+        a.act(action);
+        b.act(action);
+        assert_eq!(a.turn(), b.turn());
+        // a checkpoint every few turns
+        assert_eq!(a.engine().replay().hashes, b.engine().replay().hashes);
+        i += 1;
+        assert!(i < 5000, "stuck on turn {}", a.turn());
+    }
+    assert_eq!(a.engine().state_hash(), b.engine().state_hash());
+    assert!(a.engine().replay().hashes.len() as u64 >= a.turn() / 10);
+
+    // and played again from the record
+    let resumed = Engine::resume(a.engine().replay()).unwrap();
+    assert_eq!(resumed.state_hash(), a.engine().state_hash());
+}
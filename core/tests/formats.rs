@@ -0,0 +1,187 @@
+//! Files written by earlier and later rhexes, and files that got damaged
+
+extern crate hex2d;
+extern crate rhex_core;
+
+mod common;
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use hex2d::{Angle, Coordinate};
+
+use rhex_core::game::{Action, Replay, Simulation};
+use rhex_core::game::actor::Race;
+use rhex_core::game::format::{FileFormat, Refusal};
+use rhex_core::game::score::{self, Entry, HighScores};
+
+use common::{fixture, scratch_dir};
+
+/// A format that has been through two versions before this one: `hp`
+/// became `health` in 2, and `pos` lost its comma in 3
+const SAMPLE: FileFormat = FileFormat {
+    kind: "sample",
+    version: 3,
+    migrations: &[hp_to_health, pos_without_comma],
+};
+
+fn hp_to_health(lines: Vec<String>) -> Option<Vec<String>> {
+    Some(lines.into_iter()
+              .map(|l| if l.starts_with("hp ") { format!("health {}", &l[3..]) } else { l })
+              .collect())
+}
+
+fn pos_without_comma(lines: Vec<String>) -> Option<Vec<String>> {
+    lines.into_iter()
+         .map(|l| {
+             if !l.starts_with("pos ") {
+                 Some(l)
+             } else if l.matches(',').count() == 1 {
+                 Some(l.replace(',', " "))
+             } else {
+                 None
+             }
+         })
+         .collect()
+}
+
+fn read_fixture(name: &str) -> io::Result<Vec<String>> {
+    let file = fs::File::open(fixture(name)).unwrap();
+    SAMPLE.read(io::BufReader::new(file))
+}
+
+#[test]
+fn files_from_earlier_versions_are_migrated() {
+    assert_eq!(read_fixture("sample-1.txt").unwrap(), vec!["health 7", "pos 3 4"]);
+    // lines of a later 2 that 2 didn't have are carried along
+    assert_eq!(read_fixture("sample-2.txt").unwrap(),
+               vec!["health 7", "pos 3 4", "note written by a later 2"]);
+}
+
+#[test]
+fn a_failed_migration_is_refused() {
+    let lines = vec!["pos 3,4,5".to_string()];
+    assert_eq!(SAMPLE.migrate(2, lines), Err(Refusal::Unmigrated(2)));
+    let err = SAMPLE.read("rhex sample 1\npos 3,4,5\n".as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn files_from_later_versions_are_refused_saying_why() {
+    let err = read_fixture("sample-4.txt").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let message = err.to_string();
+    assert!(message.contains("format 4") && message.contains("9.9.9"), "{}", message);
+
+    assert_eq!(SAMPLE.check("rhex sample 0"), Err(Refusal::TooOld(0)));
+    assert_eq!(SAMPLE.check("rhex replay 3"), Err(Refusal::Foreign));
+    assert_eq!(SAMPLE.check("rhex sample three"), Err(Refusal::Foreign));
+    assert_eq!(SAMPLE.check(&SAMPLE.header()), Ok(3));
+}
+
+#[test]
+fn replay_fixture_still_loads() {
+    let replay = Replay::load(&fixture("replay-5.txt")).unwrap();
+
+    assert_eq!(replay.seed, 7);
+    assert_eq!(replay.race, Some(Race::Elf));
+    assert_eq!(replay.name, "Fixture");
+    // with the `shrine` line of some later rhex skipped
+    assert_eq!(replay.actions,
+               vec![(0, Action::Move(Angle::Forward)),
+                    (1, Action::Turn(Angle::Right)),
+                    (2, Action::Throw('a', Coordinate::new(3, -4))),
+                    (3, Action::Wait)]);
+    assert_eq!(replay.hashes, vec![(2, 12345)]);
+    assert_eq!(replay.end, Some((4, 999)));
+}
+
+fn write(path: &Path, bytes: &[u8]) {
+    fs::File::create(path).and_then(|mut f| f.write_all(bytes)).unwrap();
+}
+
+fn read(path: &Path) -> Vec<u8> {
+    let mut bytes = vec![];
+    fs::File::open(path).and_then(|mut f| f.read_to_end(&mut bytes)).unwrap();
+    bytes
+}
+
+/// A game saved a few turns in, as it is on disk
+fn saved_game() -> Vec<u8> {
+    let mut sim = Simulation::new(3, Race::Dwarf);
+    for _ in 0..5 {
+        sim.act(Action::Wait);
+    }
+    let mut replay = sim.engine().replay().clone();
+    replay.end = Some((sim.turn(), sim.engine().state_hash()));
+    let mut bytes = vec![];
+    replay.write_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn damaged_saves_are_refused_and_left_alone() {
+    let dir = scratch_dir("damaged-saves");
+    let path = dir.join("rhex.save");
+    let saved = saved_game();
+    write(&path, &saved);
+    assert!(Replay::load(&path).is_ok());
+
+    let last_line = saved[..saved.len() - 1].iter().rposition(|&b| b == b'\n').unwrap() + 1;
+    let mut bad_header = saved.clone();
+    bad_header[2] = b'X';
+    // the first letter of the first action
+    let text = String::from_utf8(saved.clone()).unwrap();
+    let action = text.lines()
+                     .scan(0, |start, line| {
+                         let at = *start;
+                         *start += line.len() + 1;
+                         Some((at, line))
+                     })
+                     .find(|&(_, line)| line.starts_with(|c: char| c.is_digit(10)))
+                     .map(|(at, line)| at + line.find(' ').unwrap() + 1)
+                     .expect("no actions");
+    let mut bad_action = saved.clone();
+    bad_action[action] = b'?';
+    let mut not_text = saved.clone();
+    not_text[action] = 0xff;
+    let damaged = [("empty", vec![]),
+                   ("cut in the middle", saved[..saved.len() / 2].to_vec()),
+                   ("cut before the end", saved[..last_line].to_vec()),
+                   ("cut in the end", saved[..last_line + "end 5".len()].to_vec()),
+                   ("bad header", bad_header),
+                   ("bad action", bad_action),
+                   ("not text", not_text)];
+
+    for &(what, ref bytes) in &damaged {
+        write(&path, bytes);
+        let err = Replay::load(&path).err().expect(what);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}: {}", what, err);
+        assert_eq!(&read(&path), bytes, "{} was changed", what);
+    }
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn unreadable_score_table_is_left_alone() {
+    let dir = scratch_dir("damaged-scores");
+    let path = dir.join("scores");
+    let table = "rhex scores 99 9.9.9\n500\t1\t2099-01-01\tLater\tbetter\n";
+    write(&path, table.as_bytes());
+
+    let entry = Entry {
+        name: "Now".to_string(),
+        score: score::score(1, 100, &[], 0),
+        result: "quit".to_string(),
+        seed: 1,
+        date: "2016-01-01".to_string(),
+    };
+    assert!(HighScores::add(&path, entry).is_err());
+    assert!(HighScores::load(&path).entries.is_empty());
+    assert_eq!(read(&path), table.as_bytes());
+    // no lock or half-written table left behind
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,125 @@
+//! Whole games in small situations, played without the terminal
+
+extern crate hex2d;
+extern crate rhex_core;
+
+use hex2d::{Angle, Coordinate, Position};
+
+use rhex_core::game::{self, tile, Action, Event, Simulation};
+use rhex_core::game::actor::{Actor, Race};
+use rhex_core::game::explore::Stop;
+use rhex_core::util;
+
+const SEED: u32 = 5;
+
+/// Put the player on a free tile next to a wall, facing it
+fn face_a_wall(sim: &mut Simulation) -> Coordinate {
+    let (coord, wall) = {
+        let loc = sim.engine().current_location();
+        let here = sim.player().pos.coord;
+        game::in_order(&loc.map)
+            .into_iter()
+            .filter(|&(c, _)| c == here || loc.at(c).is_passable())
+            .filter_map(|(c, _)| {
+                c.neighbors()
+                 .iter()
+                 .find(|&&n| loc.at(n).tile().type_ == tile::Wall)
+                 .map(|&n| (c, n))
+            })
+            .next()
+            .expect("no wall on the level")
+    };
+    if sim.player().pos.coord != coord {
+        sim.engine_mut().debug_teleport(coord);
+    }
+
+    let loc = sim.engine_mut().current_location_mut();
+    let id = loc.player_id();
+    let player = loc.actors_byid.get_mut(&id).unwrap();
+    assert_eq!(player.pos.coord, coord);
+    player.pos.dir = coord.direction_to_cw(wall).unwrap();
+    coord
+}
+
+#[test]
+fn walking_into_a_wall_takes_no_turn() {
+    let mut sim = Simulation::new(SEED, Race::Human);
+    let coord = face_a_wall(&mut sim);
+    let turn = sim.turn();
+
+    assert!(!sim.act(Action::Move(Angle::Forward)));
+
+    assert_eq!(sim.turn(), turn);
+    assert_eq!(sim.player().pos.coord, coord);
+    assert!(sim.act(Action::Wait));
+    assert_eq!(sim.turn(), turn + 1);
+}
+
+#[test]
+fn adjacent_goblin_attacks_within_two_rounds() {
+    let mut sim = Simulation::new(SEED, Race::Human);
+    let goblin = {
+        let loc = sim.engine_mut().current_location_mut();
+        let target = loc.player().pos.coord;
+        let coord = loc.free_coord_near(target).unwrap();
+        assert_eq!(coord.distance(target), 1);
+        let pos = Position::new(coord, coord.direction_to_cw(target).unwrap());
+        loc.spawn(Actor::new(Race::Goblin, pos), &mut util::game_rng(0))
+    };
+    let player = sim.engine().current_location().player_id();
+
+    sim.wait(2);
+
+    let attacked = sim.log().iter().any(|event| {
+        match *event {
+            Event::Attack { attacker, target, .. } => attacker == goblin && target == player,
+            _ => false,
+        }
+    });
+    assert!(attacked, "{:?}", sim.log());
+}
+
+#[test]
+fn descending_stairs_changes_the_level() {
+    let mut sim = Simulation::new(SEED, Race::Human);
+    let (stairs, below) = game::in_order(&sim.engine().current_location().map)
+                              .into_iter()
+                              .filter_map(|(c, t)| t.stairs_down().map(|to| (c, to)))
+                              .next()
+                              .expect("no stairs down");
+    sim.engine_mut().debug_teleport(stairs);
+    let start = sim.level();
+
+    assert!(sim.act(Action::Descend));
+
+    assert!(sim.level() != start);
+    assert_eq!(sim.level(), below);
+}
+
+#[test]
+fn resting_heals_to_full() {
+    let mut sim = Simulation::new(SEED, Race::Human);
+    {
+        // nobody around to interrupt
+        let loc = sim.engine_mut().current_location_mut();
+        let mut hostile: Vec<_> = loc.actors_byid
+                                     .iter()
+                                     .filter(|&(_, a)| a.is_hostile())
+                                     .map(|(&id, _)| id)
+                                     .collect();
+        hostile.sort();
+        for id in hostile {
+            loc.remove(id);
+        }
+        let id = loc.player_id();
+        loc.actors_byid.get_mut(&id).unwrap().hp -= 10;
+    }
+    let turn = sim.turn();
+
+    assert_eq!(sim.rest(2000), Some(Stop::Done));
+
+    let player = sim.player();
+    assert_eq!(player.hp, player.stats.base.max_hp);
+    assert_eq!(player.sp, player.stats.base.max_sp);
+    assert!(sim.turn() >= turn + 10);
+}
@@ -0,0 +1,61 @@
+//! A bot wandering at random through a game of `rhex --bot`, to show
+//! how the protocol goes:
+//!
+//!     cargo build && cargo run --example random_walker -- target/debug/rhex
+extern crate rand;
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{self, Command, Stdio};
+
+use rand::Rng;
+
+/// Turns it plays at most
+const TURNS: usize = 200;
+
+fn main() {
+    let rhex = env::args().nth(1).unwrap_or_else(|| "rhex".to_string());
+    let mut game = Command::new(&rhex)
+                       .arg("--bot")
+                       .stdin(Stdio::piped())
+                       .stdout(Stdio::piped())
+                       .spawn()
+                       .unwrap_or_else(|e| {
+                           let _ = writeln!(io::stderr(), "{}: {}", rhex, e);
+                           process::exit(1);
+                       });
+
+    let mut to_game = game.stdin.take().unwrap();
+    let from_game = BufReader::new(game.stdout.take().unwrap());
+    let mut rng = rand::thread_rng();
+    let mut turns = 0;
+
+    for line in from_game.lines() {
+        let line = line.unwrap();
+        if line.starts_with("over ") || line == "rhex bot 1" || line.starts_with("seed ") {
+            println!("{}", line);
+        }
+        if line.starts_with("turn ") {
+            print!("\r{}", line);
+            let _ = io::stdout().flush();
+        }
+        if line != "end" {
+            continue;
+        }
+
+        turns += 1;
+        let action = if turns > TURNS {
+            "quit".to_string()
+        } else {
+            match rng.gen_range(0, 4) {
+                0 => format!("turn {}", rng.gen_range(0, 6)),
+                1 => "pick".to_string(),
+                _ => "move 0".to_string(),
+            }
+        };
+        if writeln!(to_game, "{}", action).is_err() {
+            break;
+        }
+    }
+    let _ = game.wait();
+}
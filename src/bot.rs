@@ -0,0 +1,301 @@
+//! Playing by a program at the other end of a pipe instead of a player
+//! at the terminal.
+//!
+//! Lines go both ways, words separated by spaces. The game starts with
+//!
+//! ```text
+//! rhex bot 1
+//! seed SEED
+//! ```
+//!
+//! and then, each time it's the player's turn, tells what the player
+//! knows, ending with `end`:
+//!
+//! ```text
+//! turn TURN
+//! level LEVEL
+//! player X Y DIR HP MAX_HP SP MAX_SP
+//! tile X Y TYPE FEATURE SEEN        for every tile the player knows
+//! actor ID X Y DIR RACE HP HOSTILE  for everybody else in sight
+//! item X Y CATEGORY DESCRIPTION     for the items in sight
+//! inv LETTER EQUIPPED DESCRIPTION   for the items the player has
+//! event WORDS                       for what happened since the last turn
+//! end
+//! ```
+//!
+//! `DIR` is 0 to 5, in the order of `Direction::all`; `FEATURE` is `-`
+//! without one; `SEEN`, `HOSTILE` and `EQUIPPED` are 0 or 1; names with
+//! spaces have them as `_`.
+//!
+//! The answer is one action, written as the replay files do, e.g.
+//! `move 0`, `turn 1`, `ranged 3 -2` or `wait`; `quit` ends the game. What
+//! can't be read is answered with `error WHAT` and taken as `wait`, and
+//! an action that can't be done with `rejected`. The game ends with
+//! `over dead CAUSE`, `over quit`, or `over eof` if the input ends.
+
+use std::io::{self, BufRead, Write};
+
+use hex2d::{Coordinate, Direction};
+
+use game::{self, save, Action, Event, Simulation};
+use game::actor::{Actor, Race};
+
+const HEADER: &'static str = "rhex bot 1";
+
+/// What the player knows on their turn, and nothing more
+pub struct Observation {
+    pub turn: u64,
+    pub level: String,
+    pub player: ActorView,
+    pub tiles: Vec<TileView>,
+    pub actors: Vec<ActorView>,
+    pub items: Vec<ItemView>,
+    pub inventory: Vec<InvView>,
+    pub events: Vec<String>,
+}
+
+pub struct ActorView {
+    pub id: game::actor::Id,
+    pub coord: Coordinate,
+    pub dir: Direction,
+    pub race: Race,
+    pub hp: i32,
+    pub max_hp: i32,
+    pub sp: i32,
+    pub max_sp: i32,
+    pub hostile: bool,
+}
+
+pub struct TileView {
+    pub coord: Coordinate,
+    pub type_: String,
+    pub feature: Option<String>,
+    /// In sight now, not just remembered
+    pub seen: bool,
+}
+
+pub struct ItemView {
+    pub coord: Coordinate,
+    pub category: String,
+    pub description: String,
+}
+
+pub struct InvView {
+    pub letter: char,
+    pub equipped: bool,
+    pub description: String,
+}
+
+impl ActorView {
+    fn new(id: game::actor::Id, actor: &Actor) -> Self {
+        ActorView {
+            id: id,
+            coord: actor.pos.coord,
+            dir: actor.pos.dir,
+            race: actor.race,
+            hp: actor.hp,
+            max_hp: actor.stats.base.max_hp,
+            sp: actor.sp,
+            max_sp: actor.stats.base.max_sp,
+            hostile: actor.is_hostile(),
+        }
+    }
+}
+
+impl Observation {
+    /// What the player of `sim` knows now; `events` are the ones that
+    /// happened since last time
+    pub fn new(sim: &Simulation, events: &[Event]) -> Self {
+        let loc = sim.engine().current_location();
+        let player_id = loc.player_id();
+        let player = sim.player();
+
+        let mut known: Vec<Coordinate> = player.perception.known.iter().cloned().collect();
+        known.sort_by_key(|c| (c.y, c.x));
+        let tiles = known.iter()
+                         .map(|&c| {
+                             let tile = loc.at(c).tile();
+                             TileView {
+                                 coord: c,
+                                 type_: tile.type_.description().to_string(),
+                                 feature: tile.feature().map(|f| f.description().to_string()),
+                                 seen: player.sees(c),
+                             }
+                         })
+                         .collect();
+
+        let actors = loc.actors_visible_by(player_id)
+                        .into_iter()
+                        .map(|id| ActorView::new(id, &loc.actors_byid[&id]))
+                        .collect();
+
+        let items = known.iter()
+                         .filter(|&&c| player.sees(c))
+                         .filter_map(|&c| {
+                             loc.at(c).item().map(|item| {
+                                 ItemView {
+                                     coord: c,
+                                     category: format!("{:?}", item.category()),
+                                     description: item.description(),
+                                 }
+                             })
+                         })
+                         .collect();
+
+        let mut inventory: Vec<InvView> =
+            player.items_equipped
+                  .values()
+                  .map(|&(ch, ref item)| (ch, true, item))
+                  .chain(player.items_backpack.iter().map(|(&ch, item)| (ch, false, item)))
+                  .map(|(ch, equipped, item)| {
+                      InvView {
+                          letter: ch,
+                          equipped: equipped,
+                          description: item.description(),
+                      }
+                  })
+                  .collect();
+        inventory.sort_by_key(|i| i.letter);
+
+        Observation {
+            turn: sim.turn(),
+            level: loc.id.to_string(),
+            player: ActorView::new(player_id, player),
+            tiles: tiles,
+            actors: actors,
+            items: items,
+            inventory: inventory,
+            events: events.iter().map(event_words).collect(),
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let p = &self.player;
+        try!(writeln!(w, "turn {}", self.turn));
+        try!(writeln!(w, "level {}", word(&self.level)));
+        try!(writeln!(w,
+                      "player {} {} {} {} {} {} {}",
+                      p.coord.x,
+                      p.coord.y,
+                      dir_index(p.dir),
+                      p.hp,
+                      p.max_hp,
+                      p.sp,
+                      p.max_sp));
+        for t in &self.tiles {
+            try!(writeln!(w,
+                          "tile {} {} {} {} {}",
+                          t.coord.x,
+                          t.coord.y,
+                          word(&t.type_),
+                          t.feature.as_ref().map_or("-".to_string(), |f| word(f)),
+                          t.seen as u8));
+        }
+        for a in &self.actors {
+            try!(writeln!(w,
+                          "actor {} {} {} {} {} {} {}",
+                          a.id,
+                          a.coord.x,
+                          a.coord.y,
+                          dir_index(a.dir),
+                          word(&a.race.description()),
+                          a.hp,
+                          a.hostile as u8));
+        }
+        for i in &self.items {
+            try!(writeln!(w, "item {} {} {} {}", i.coord.x, i.coord.y, i.category, i.description));
+        }
+        for i in &self.inventory {
+            try!(writeln!(w, "inv {} {} {}", i.letter, i.equipped as u8, i.description));
+        }
+        for e in &self.events {
+            try!(writeln!(w, "event {}", e));
+        }
+        writeln!(w, "end")
+    }
+}
+
+/// Play a new game as a `race` on `seed`, telling `output` what the
+/// player knows and taking actions from `input`, until the player dies
+/// or either side stops
+pub fn run<R, W>(seed: u32, race: Race, input: R, output: &mut W) -> io::Result<()>
+    where R: BufRead,
+          W: Write
+{
+    let mut sim = Simulation::new(seed, race);
+    let mut lines = input.lines();
+    let mut told = 0;
+
+    try!(writeln!(output, "{}", HEADER));
+    try!(writeln!(output, "seed {}", seed));
+    loop {
+        if sim.player().is_dead() {
+            let cause = match sim.engine().result() {
+                Some(&game::GameResult::Died { ref cause }) => word(cause),
+                _ => "unknown".to_string(),
+            };
+            return writeln!(output, "over dead {}", cause);
+        }
+
+        try!(Observation::new(&sim, &sim.log()[told..]).write_to(output));
+        try!(output.flush());
+        told = sim.log().len();
+
+        let line = match lines.next() {
+            Some(line) => try!(line),
+            None => return writeln!(output, "over eof"),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words == ["quit"] {
+            return writeln!(output, "over quit");
+        }
+        let action = match save::decode(&words) {
+            Some(action) => action,
+            None => {
+                try!(writeln!(output, "error unknown action \"{}\"", line.trim()));
+                Action::Wait
+            }
+        };
+        if !sim.act(action) {
+            try!(writeln!(output, "rejected"));
+        }
+    }
+}
+
+/// `s` as one word
+fn word(s: &str) -> String {
+    s.replace(' ', "_")
+}
+
+fn dir_index(dir: Direction) -> usize {
+    Direction::all().iter().position(|&d| d == dir).unwrap()
+}
+
+fn event_words(event: &Event) -> String {
+    match *event {
+        Event::Attack { attacker, target, success, dmg, behind } => {
+            format!("attack {} {} {} {} {}",
+                    attacker,
+                    target,
+                    success as u8,
+                    dmg,
+                    behind as u8)
+        }
+        Event::Died { id } => format!("died {}", id),
+        Event::PickedUp { id, ref item } => format!("picked_up {} {}", id, item),
+        Event::DoorOpened { id, coord } => format!("door_opened {} {} {}", id, coord.x, coord.y),
+        Event::DoorClosed { id, coord } => format!("door_closed {} {} {}", id, coord.x, coord.y),
+        Event::Daybreak => "daybreak".to_string(),
+        Event::Nightfall => "nightfall".to_string(),
+        Event::Discovered(ref d) => format!("discovered {}", word(&format!("{:?}", d))),
+        Event::Interacted { id, .. } => format!("interacted {}", id),
+        Event::Heard { coord, .. } => format!("heard {} {}", coord.x, coord.y),
+        Event::Projectile { id, ref path } => {
+            let end = path.last().cloned().unwrap_or(Coordinate::new(0, 0));
+            format!("projectile {} {} {}", id, end.x, end.y)
+        }
+        Event::Rejected { id, reason } => {
+            format!("rejected {} {}", id, word(&format!("{:?}", reason)))
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::cell::{Cell, RefCell};
+use std::panic;
+use std::rc::Rc;
+
+use super::Result;
+use super::color;
+
+/// How a character is drawn: colors of the 256-color palette, which
+/// the backend brings down to what the terminal can show
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Style {
+    pub fg: u8,
+    pub bg: u8,
+    pub bold: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+    pub fn new(fg: u8, bg: u8) -> Style {
+        Style {
+            fg: fg,
+            bg: bg,
+            bold: false,
+            reverse: false,
+        }
+    }
+
+    pub fn bold(self) -> Style {
+        Style { bold: true, ..self }
+    }
+
+    /// With the colors swapped
+    pub fn reversed(self) -> Style {
+        Style { reverse: true, ..self }
+    }
+
+    /// Escape sequence switching a terminal to the style, with the 256
+    /// colors or the 16 basic ones
+    pub fn sgr(&self, colors256: bool) -> String {
+        let mut sgr = "\x1b[0".to_string();
+        if self.bold {
+            sgr.push_str(";1");
+        }
+        if self.reverse {
+            sgr.push_str(";7");
+        }
+        if colors256 {
+            sgr.push_str(&format!(";38;5;{};48;5;{}m", self.fg, self.bg));
+        } else {
+            let (fg, bg) = (color::basic16(self.fg), color::basic16(self.bg));
+            let fg = if fg < 8 { 30 + fg } else { 90 + fg - 8 };
+            let bg = if bg < 8 { 40 + bg } else { 100 + bg - 8 };
+            sgr.push_str(&format!(";{};{}m", fg, bg));
+        }
+        sgr
+    }
+}
+
+/// Part of the screen, in characters
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+/// A terminal the game is drawn on and takes keys from
+pub trait Backend {
+    /// Width and height of the screen, in characters
+    fn size(&self) -> (i32, i32);
+
+    /// Draw `ch` at `x`, `y`; what's off the screen is left out
+    fn put_glyph(&mut self, x: i32, y: i32, ch: char, style: Style);
+
+    /// Fill `rect` with blanks in `style`
+    fn clear(&mut self, rect: Rect, style: Style);
+
+    /// Show everything drawn since the last time
+    fn flush(&mut self);
+
+    /// The next key pressed, `KEY_RESIZE` if the screen changed size
+    /// instead; `None` without waiting if there's nothing
+    fn read_key(&mut self) -> Option<i32>;
+
+    /// Stop the game as Ctrl-Z does in a shell, with the terminal as it
+    /// was before the game; once continued, the screen has to be drawn
+    /// anew
+    fn suspend(&mut self);
+
+    /// Whether keys can still come; a script of them runs out
+    fn has_input(&self) -> bool {
+        true
+    }
+}
+
+/// The backend, shared by the windows drawing on it
+pub type Screen = Rc<RefCell<Box<Backend>>>;
+
+/// Backends built in, to pick from on the command line
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BackendKind {
+    #[cfg(feature = "ncurses")]
+    Curses,
+    #[cfg(feature = "termion")]
+    Termion,
+}
+
+impl BackendKind {
+    /// `--backend` takes the name
+    pub fn from_name(name: &str) -> Option<BackendKind> {
+        match name {
+            #[cfg(feature = "ncurses")]
+            "curses" => Some(BackendKind::Curses),
+            #[cfg(feature = "termion")]
+            "termion" => Some(BackendKind::Termion),
+            _ => None,
+        }
+    }
+
+    /// Take over the terminal
+    pub fn open(&self) -> Result<Screen> {
+        let backend: Box<Backend> = match *self {
+            #[cfg(feature = "ncurses")]
+            BackendKind::Curses => Box::new(super::ncurses_backend::Curses::new()),
+            #[cfg(feature = "termion")]
+            BackendKind::Termion => Box::new(try!(super::termion_backend::Termion::new())),
+        };
+        Ok(Rc::new(RefCell::new(backend)))
+    }
+}
+
+/// Curses where it's built in; it knows more terminals
+impl Default for BackendKind {
+    #[cfg(feature = "ncurses")]
+    fn default() -> BackendKind {
+        BackendKind::Curses
+    }
+
+    #[cfg(not(feature = "ncurses"))]
+    fn default() -> BackendKind {
+        BackendKind::Termion
+    }
+}
+
+/// Leave the terminal the way it was with `restore` before a panic is
+/// reported, so the message is readable and the shell usable; the
+/// previous hook still prints it. `restore` must be harmless to call
+/// more than once.
+pub fn install_panic_hook<F>(restore: F)
+    where F: Fn() + Send + Sync + 'static
+{
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        previous(info);
+    }));
+}
+
+/// Part of the screen drawn on like a terminal of its own: text goes
+/// where the cursor is, in the style set last, and moves the cursor on
+pub struct Window {
+    screen: Screen,
+    rect: Rect,
+    /// Column and line the next character goes to
+    cursor: Cell<(i32, i32)>,
+    style: Cell<Style>,
+    /// What's blank is drawn in
+    background: Cell<Style>,
+}
+
+impl Window {
+    pub fn new(screen: &Screen, rect: Rect) -> Window {
+        Window {
+            screen: screen.clone(),
+            rect: rect,
+            cursor: Cell::new((0, 0)),
+            style: Cell::new(Style::new(0, 0)),
+            background: Cell::new(Style::new(0, 0)),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.rect.w
+    }
+
+    pub fn height(&self) -> i32 {
+        self.rect.h
+    }
+
+    /// Blank the whole window in `style`, which blanks are drawn in
+    /// from then on, and text until another style is set
+    pub fn fill(&self, style: Style) {
+        self.background.set(style);
+        self.style.set(style);
+        self.screen.borrow_mut().clear(self.rect, style);
+        self.cursor.set((0, 0));
+    }
+
+    pub fn set_style(&self, style: Style) {
+        self.style.set(style);
+    }
+
+    pub fn move_to(&self, x: i32, y: i32) {
+        self.cursor.set((x, y));
+    }
+
+    pub fn cursor_y(&self) -> i32 {
+        self.cursor.get().1
+    }
+
+    /// Write `text` at the cursor; a newline blanks the rest of the
+    /// line and goes on at the start of the next one, and so does text
+    /// reaching the right edge
+    pub fn print(&self, text: &str) {
+        let (mut x, mut y) = self.cursor.get();
+        let style = self.style.get();
+        let mut screen = self.screen.borrow_mut();
+        for ch in text.chars() {
+            if y >= self.rect.h {
+                break;
+            }
+            if ch == '\n' {
+                if x < self.rect.w {
+                    screen.clear(Rect {
+                                     x: self.rect.x + x,
+                                     y: self.rect.y + y,
+                                     w: self.rect.w - x,
+                                     h: 1,
+                                 },
+                                 self.background.get());
+                }
+                x = 0;
+                y += 1;
+                continue;
+            }
+            if x >= 0 && x < self.rect.w && y >= 0 {
+                screen.put_glyph(self.rect.x + x, self.rect.y + y, ch, style);
+            }
+            x += 1;
+            if x >= self.rect.w {
+                x = 0;
+                y += 1;
+            }
+        }
+        self.cursor.set((x, y));
+    }
+
+    pub fn print_at(&self, x: i32, y: i32, text: &str) {
+        self.move_to(x, y);
+        self.print(text);
+    }
+
+    /// Draw `cells` along line `y` from its start, leaving alone where
+    /// there's `None` and the cursor; a map's worth of single characters
+    /// costs far less this way than through `print_at`
+    pub fn put_row(&self, y: i32, cells: &[Option<(char, Style)>]) {
+        if y < 0 || y >= self.rect.h {
+            return;
+        }
+        let mut screen = self.screen.borrow_mut();
+        for (x, cell) in cells.iter().take(self.rect.w as usize).enumerate() {
+            if let Some((ch, style)) = *cell {
+                screen.put_glyph(self.rect.x + x as i32, self.rect.y + y, ch, style);
+            }
+        }
+    }
+}
@@ -1,7 +1,3 @@
-use std::collections::HashMap;
-use std::collections::hash_map::Entry;
-use ncurses as nc;
-
 pub const GRAY: [u8; 26] = [16, 232, 233, 234, 235, 236, 237, 238, 239, 240, 241, 242, 243, 244,
                             245, 246, 247, 248, 249, 250, 251, 252, 253, 254, 255, 15];
 pub const BLACK: u8 = GRAY[0];
@@ -22,55 +18,101 @@ pub const EMPTY_FG: [u8; 4] = [GRAY[17], GRAY[10], NOT_IN_LOS_FG, NOT_IN_LOS_FG]
 pub const EMPTY_BG: [u8; 4] = [GRAY[24], GRAY[16], GRAY[4], NOT_IN_LOS_BG];
 pub const WATER_FG: [u8; 4] = EMPTY_FG;
 pub const WATER_BG: [u8; 4] = [4, 74, 67, NOT_IN_LOS_BG];
+pub const BRIDGE_FG: [u8; 4] = [94, 58, NOT_IN_LOS_FG, NOT_IN_LOS_FG];
+pub const GRASS_FG: [u8; 4] = [28, 22, NOT_IN_LOS_FG, NOT_IN_LOS_FG];
+pub const SCORCHED_FG: [u8; 4] = [GRAY[8], GRAY[5], NOT_IN_LOS_FG, NOT_IN_LOS_FG];
 pub const STONE_FG: [u8; 4] = [BLACK, GRAY[1], NOT_IN_LOS_FG, NOT_IN_LOS_FG];
 pub const WALL_FG: [u8; 4] = STONE_FG;
 pub const WALL_BG: [u8; 4] = EMPTY_BG;
 pub const CHAR_SELF_FG: [u8; 4] = [19, 18, NOT_IN_LOS_FG, NOT_IN_LOS_FG];
-pub const _CHAR_ALLY_FG: [u8; 4] = [28, 22, NOT_IN_LOS_FG, NOT_IN_LOS_FG];
+pub const CHAR_ALLY_FG: [u8; 4] = [28, 22, NOT_IN_LOS_FG, NOT_IN_LOS_FG];
 pub const CHAR_ENEMY_FG: [u8; 4] = [124, 88, NOT_IN_LOS_FG, NOT_IN_LOS_FG];
+pub const CHAR_NEUTRAL_FG: [u8; 4] = [136, 94, NOT_IN_LOS_FG, NOT_IN_LOS_FG];
 pub const CHAR_GRAY_FG: u8 = GRAY[17];
 pub const CHAR_BG: [u8; 4] = EMPTY_BG;
+pub const BRANCH_STAIRS_FG: [u8; 4] = [130, 94, NOT_IN_LOS_FG, NOT_IN_LOS_FG];
 
 pub const BLOCKED_BG: u8 = 124;
+pub const MINIMAP_BG: u8 = GRAY[3];
 
 pub const LABEL_FG: u8 = 94;
 pub const GREEN_FG: u8 = 34;
 pub const RED_FG: u8 = 124;
+/// Status badges, by severity
+pub const STATUS_MILD_FG: u8 = YELLOW;
+pub const STATUS_BAD_FG: u8 = 208;
+pub const STATUS_SEVERE_FG: u8 = RED_FG;
 pub const NOISE_BG: u8 = ORANGE;
+/// Noises heard on the map, by how many turns ago
+pub const NOISE_FG: [u8; 3] = [214, 172, 130];
 pub const TARGET_SELF_FG: u8 = 33;
+/// Path of a shot being aimed
+pub const TARGET_TRAIL_FG: u8 = GRAY[13];
 pub const TARGET_ENEMY_FG: u8 = 196;
+/// Something shot or thrown, in flight
+pub const MISSILE_FG: u8 = GRAY[24];
 pub const LIGHTSOURCE: u8 = YELLOW;
-pub const LOG_1_FG: u8 = GRAY[25];
-pub const LOG_2_FG: u8 = GRAY[21];
-pub const LOG_3_FG: u8 = GRAY[17];
-pub const LOG_4_FG: u8 = GRAY[13];
-pub const LOG_5_FG: u8 = GRAY[9];
+pub const FUNGI_FG: u8 = 82;
+// alternates every turn
+pub const FIRE_FG: [u8; 2] = [202, 196];
+// log entries of each topic, fading with age
+pub const LOG_COMBAT_FG: [u8; 5] = [GRAY[25], GRAY[21], GRAY[17], GRAY[13], GRAY[9]];
+pub const LOG_DISCOVERY_FG: [u8; 5] = [153, 110, 67, 60, GRAY[9]];
+pub const LOG_DANGER_FG: [u8; 5] = [210, 174, 138, 95, GRAY[9]];
+pub const LOG_SYSTEM_FG: [u8; 5] = [229, 187, 144, 101, GRAY[9]];
+pub const LOG_FLAVOR_FG: [u8; 5] = [GRAY[19], GRAY[16], GRAY[13], GRAY[11], GRAY[8]];
 
-pub struct Allocator {
-    map: HashMap<(u8, u8), i16>,
-    cur: i16,
-}
+/// The basic colors in the order curses numbers them, as xterm shows
+/// them: 8 normal, then 8 bright
+const BASIC_RGB: [(i32, i32, i32); 16] = [(0, 0, 0),
+                                          (205, 0, 0),
+                                          (0, 205, 0),
+                                          (205, 205, 0),
+                                          (0, 0, 238),
+                                          (205, 0, 205),
+                                          (0, 205, 205),
+                                          (229, 229, 229),
+                                          (127, 127, 127),
+                                          (255, 0, 0),
+                                          (0, 255, 0),
+                                          (255, 255, 0),
+                                          (92, 92, 255),
+                                          (255, 0, 255),
+                                          (0, 255, 255),
+                                          (255, 255, 255)];
 
-impl Allocator {
-    pub fn new() -> Allocator {
-        Allocator {
-            cur: 1i16, // 0 is reserved for defaults
-            map: HashMap::new(),
+/// Red, green and blue of color `c` of the 256-color palette
+fn rgb(c: u8) -> (i32, i32, i32) {
+    let level = |v: i32| if v == 0 { 0 } else { 55 + 40 * v };
+    match c {
+        0...15 => BASIC_RGB[c as usize],
+        16...231 => {
+            let i = c as i32 - 16;
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        _ => {
+            let v = 8 + 10 * (c as i32 - 232);
+            (v, v, v)
         }
     }
+}
+
+pub fn brightness(c: u8) -> i32 {
+    let (r, g, b) = rgb(c);
+    (2 * r + 3 * g + b) / 6
+}
 
-    pub fn get(&mut self, fg: u8, bg: u8) -> i16 {
-        match self.map.entry((fg, bg)) {
-            Entry::Occupied(i) => *i.get(),
-            Entry::Vacant(i) => {
-                assert!((self.cur as i32) < nc::COLOR_PAIRS,
-                        "curses run out of color pairs!");
-                let ret = self.cur;
-                i.insert(self.cur);
-                nc::init_pair(ret, fg as i16, bg as i16);
-                self.cur += 1;
-                ret
-            }
+/// The basic color closest to `c`
+pub fn basic16(c: u8) -> i16 {
+    let (r, g, b) = rgb(c);
+    let dist = |&(br, bg, bb): &(i32, i32, i32)| {
+        (r - br) * (r - br) + (g - bg) * (g - bg) + (b - bb) * (b - bb)
+    };
+    let mut best = 0;
+    for (i, basic) in BASIC_RGB.iter().enumerate() {
+        if dist(basic) < dist(&BASIC_RGB[best]) {
+            best = i;
         }
     }
+    best as i16
 }
@@ -2,20 +2,89 @@ use hex2d::IntegerSpacing;
 
 pub static SPACING: IntegerSpacing<i32> = IntegerSpacing::PointyTop(2, 1);
 
-pub const WALL_CH: &'static str = "#";
-pub const DOOR_OPEN_CH: &'static str = "_";
-pub const DOOR_CLOSED_CH: &'static str = "×";
-pub const STATUE_CH: &'static str = "&";
-pub const STAIRS_DOWN_CH: &'static str = ">";
-pub const WATER_CH: &'static str = "~";
-pub const NOTHING_CH: &'static str = "~";
-pub const NORMAL_DOT: &'static str = ".";
-pub const UNICODE_DOT: &'static str = "·";
+/// Where the game in progress is kept
+pub const AUTOSAVE_PATH: &'static str = "rhex.autosave";
+/// Directory the levels the player is away from are kept in
+pub const LEVELS_PATH: &'static str = "rhex.levels";
+/// Key bindings overriding the default ones
+pub const KEYS_PATH: &'static str = "rhex.keys";
+/// The player's preferences
+pub const OPTIONS_PATH: &'static str = "rhex.options";
+/// High-score table
+pub const SCORES_PATH: &'static str = "rhex.scores";
+/// Directory of what dead players left for later games
+pub const BONES_PATH: &'static str = "rhex.bones";
+/// Directory of character dumps of finished games
+pub const MORGUE_PATH: &'static str = "rhex.morgue";
+/// Last log entries a morgue file keeps
+pub const MORGUE_LOG: usize = 50;
+/// Directory of the screens the player took pictures of
+pub const SCREENSHOT_PATH: &'static str = "rhex.screenshots";
+/// Directory of the levels written out in wizard mode
+pub const DUMP_PATH: &'static str = "rhex.dumps";
+/// Most places the travel menu offers
+pub const TRAVEL_MAX: usize = 20;
+/// Longest the player rests in one go, in turns
+pub const REST_MAX: u64 = 300;
+/// Turns a noise stays on the map
+pub const NOISE_FADE: u64 = 3;
+/// Narrowest the map gets before the windows next to it go
+pub const MAP_MIN_WIDTH: i32 = 40;
+/// Width of the stats window, as wide as its widest line: two columns of
+/// equipment
+pub const STATS_WIDTH: i32 = 30;
+/// Height of the stats window, one line for each thing it shows
+pub const STATS_HEIGHT: i32 = 16;
+/// Fewest lines the log is worth showing in
+pub const LOG_MIN_HEIGHT: i32 = 3;
+/// How long an attacked actor flashes, in milliseconds
+pub const ANIMATION_FLASH_MS: i32 = 80;
+/// How long a missile takes per tile, in milliseconds
+pub const ANIMATION_STEP_MS: i32 = 25;
+/// Noise left at most this loud is told as faint
+pub const NOISE_FAINT: i32 = 2;
+/// Noise left at least this loud is told as loud
+pub const NOISE_LOUD: i32 = 8;
+/// Log entries kept; older ones are forgotten
+pub const LOG_MAX: usize = 1000;
+/// Longest name the player can pick
+pub const NAME_MAX: usize = 16;
+/// Initial delay between the player's actions when watching a replay
+pub const SPECTATE_DELAY: u32 = 100;
+
+// the keys without a character of their own, numbered as curses does
+pub const KEY_DOWN: i32 = 0o402;
+pub const KEY_UP: i32 = 0o403;
+pub const KEY_LEFT: i32 = 0o404;
+pub const KEY_RIGHT: i32 = 0o405;
+pub const KEY_HOME: i32 = 0o406;
+pub const KEY_BACKSPACE: i32 = 0o407;
+pub const KEY_NPAGE: i32 = 0o522;
+pub const KEY_PPAGE: i32 = 0o523;
+/// Enter on the numpad
+pub const KEY_PADENTER: i32 = 0o527;
+pub const KEY_A1: i32 = 0o534;
+pub const KEY_A3: i32 = 0o535;
+pub const KEY_B2: i32 = 0o536;
+pub const KEY_C1: i32 = 0o537;
+pub const KEY_C3: i32 = 0o540;
+pub const KEY_BTAB: i32 = 0o541;
+pub const KEY_END: i32 = 0o550;
+pub const KEY_SLEFT: i32 = 0o611;
+pub const KEY_SRIGHT: i32 = 0o622;
+/// Not a key: the screen changed size
+pub const KEY_RESIZE: i32 = 0o632;
 
 pub const KEY_ESC: i32 = 0x1b;
 pub const KEY_ENTER: i32 = '\n' as i32;
+pub const KEY_TAB: i32 = '\t' as i32;
+pub const KEY_SPACE: i32 = ' ' as i32;
+/// What terminals send for backspace, besides `KEY_BACKSPACE`
+pub const KEY_DEL: i32 = 0x7f;
+pub const KEY_CTRLH: i32 = 0x08;
 pub const KEY_LOWX: i32 = 'x' as i32;
 pub const KEY_LOWA: i32 = 'a' as i32;
+pub const KEY_LOWZ: i32 = 'z' as i32;
 pub const KEY_LOWB: i32 = 'b' as i32;
 pub const KEY_LOWC: i32 = 'c' as i32;
 pub const KEY_LOWY: i32 = 'y' as i32;
@@ -28,7 +97,9 @@ pub const KEY_LOWO: i32 = 'o' as i32;
 pub const KEY_LOWQ: i32 = 'q' as i32;
 pub const KEY_LOWJ: i32 = 'j' as i32;
 pub const KEY_LOWF: i32 = 'f' as i32;
+pub const KEY_LOWE: i32 = 'e' as i32;
 pub const KEY_CAPY: i32 = 'Y' as i32;
+pub const KEY_CAPC: i32 = 'C' as i32;
 pub const KEY_CAPH: i32 = 'H' as i32;
 pub const KEY_CAPL: i32 = 'L' as i32;
 pub const KEY_CAPE: i32 = 'E' as i32;
@@ -36,8 +107,51 @@ pub const KEY_CAPD: i32 = 'D' as i32;
 pub const KEY_CAPI: i32 = 'I' as i32;
 pub const KEY_CAPK: i32 = 'K' as i32;
 pub const KEY_CAPJ: i32 = 'J' as i32;
+pub const KEY_CAPF: i32 = 'F' as i32;
+pub const KEY_CAPR: i32 = 'R' as i32;
+pub const KEY_CAPN: i32 = 'N' as i32;
+pub const KEY_CAPV: i32 = 'V' as i32;
+pub const KEY_CAPT: i32 = 'T' as i32;
+pub const KEY_CAPO: i32 = 'O' as i32;
 pub const KEY_DOT: i32 = '.' as i32;
 pub const KEY_COMMA: i32 = ',' as i32;
 pub const KEY_HELP: i32 = '?' as i32;
 pub const KEY_GOTO: i32 = 'G' as i32;
 pub const KEY_DESCEND: i32 = '>' as i32;
+pub const KEY_ASCEND: i32 = '<' as i32;
+pub const KEY_PLUS: i32 = '+' as i32;
+pub const KEY_MINUS: i32 = '-' as i32;
+pub const KEY_LOWR: i32 = 'r' as i32;
+pub const KEY_LOWG: i32 = 'g' as i32;
+pub const KEY_LOWT: i32 = 't' as i32;
+pub const KEY_LOWM: i32 = 'm' as i32;
+pub const KEY_CAPM: i32 = 'M' as i32;
+pub const KEY_LOWS: i32 = 's' as i32;
+pub const KEY_LOWN: i32 = 'n' as i32;
+pub const KEY_LOWV: i32 = 'v' as i32;
+pub const KEY_CAPP: i32 = 'P' as i32;
+pub const KEY_CTRLP: i32 = 'p' as i32 & 0x1f;
+pub const KEY_HASH: i32 = '#' as i32;
+
+/// Digits, which is what the numpad sends with Num Lock on
+pub const KEY_NUM2: i32 = '2' as i32;
+pub const KEY_NUM4: i32 = '4' as i32;
+pub const KEY_NUM5: i32 = '5' as i32;
+pub const KEY_NUM6: i32 = '6' as i32;
+pub const KEY_NUM7: i32 = '7' as i32;
+pub const KEY_NUM8: i32 = '8' as i32;
+pub const KEY_NUM9: i32 = '9' as i32;
+
+/// Wizard mode keys, as Ctrl + letter
+pub const KEY_WIZ_REVEAL: i32 = 'f' as i32 & 0x1f;
+pub const KEY_WIZ_HEAL: i32 = 'a' as i32 & 0x1f;
+pub const KEY_WIZ_DESCEND: i32 = 'v' as i32 & 0x1f;
+pub const KEY_WIZ_TELEPORT: i32 = 't' as i32 & 0x1f;
+pub const KEY_WIZ_ITEM: i32 = 'w' as i32 & 0x1f;
+pub const KEY_WIZ_UNDO: i32 = 'u' as i32 & 0x1f;
+pub const KEY_WIZ_PANIC: i32 = 'k' as i32 & 0x1f;
+pub const KEY_WIZ_DUMP: i32 = 'e' as i32 & 0x1f;
+pub const KEY_WIZ_LOG: i32 = 'l' as i32 & 0x1f;
+
+/// Ctrl-Z, which arrives as a key in raw mode
+pub const KEY_SUSPEND: i32 = 'z' as i32 & 0x1f;
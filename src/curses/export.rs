@@ -0,0 +1,87 @@
+use std::cmp;
+
+use hex2d::Coordinate;
+
+use game::Location;
+use game::actor::Race;
+use super::consts::SPACING;
+use super::glyphs::{GlyphSet, Walls};
+
+/// How a level is written out
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Format {
+    /// The map as the game draws it in ASCII
+    Text,
+    /// Every tile, actor and item, as `Location::to_json` has them
+    Json,
+}
+
+impl Format {
+    /// `--dump-levels` takes the name
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    /// What files in the format end with
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Format::Text => "txt",
+            Format::Json => "json",
+        }
+    }
+}
+
+/// All of `loc` in `format`, what's hidden from the player included
+pub fn export(loc: &Location, format: Format) -> String {
+    match format {
+        Format::Text => {
+            let mut text = level_text(loc).join("\n");
+            text.push('\n');
+            text
+        }
+        Format::Json => loc.to_json(),
+    }
+}
+
+/// The whole map of `loc` laid out like `draw_map` does, in ASCII
+fn level_text(loc: &Location) -> Vec<String> {
+    let glyphs = GlyphSet::Ascii;
+    let (mut min, mut max) = ((i32::max_value(), i32::max_value()),
+                              (i32::min_value(), i32::min_value()));
+    for (c, _) in loc.map.iter() {
+        let (px, py) = c.to_pixel_integer(SPACING);
+        min = (cmp::min(min.0, px), cmp::min(min.1, py));
+        max = (cmp::max(max.0, px), cmp::max(max.1, py));
+    }
+
+    let mut lines = vec![];
+    for py in min.1..max.1 + 1 {
+        let mut line = String::new();
+        for px in min.0..max.0 + 1 {
+            let (c, off) = Coordinate::from_pixel_integer(SPACING, (px, py));
+            let glyph = if off == (0, 0) {
+                if loc.at(c).is_occupied() {
+                    glyphs.glyphs().actor(loc.at(c).actor_map_or(Race::Rat, |a| a.race))
+                } else if let Some(item) = loc.at(c).item() {
+                    glyphs.glyphs().item(item.category())
+                } else {
+                    let t = loc.at(c).tile();
+                    glyphs.tile(t.type_, t.feature(), true, Walls::default())
+                }
+            } else {
+                // glue between two tiles, like on the screen
+                let (c2, _) = Coordinate::from_pixel_integer(SPACING, (px + 1, py));
+                let (t1, t2) = (loc.at(c).tile(), loc.at(c2).tile());
+                let t = if t1.ascii_expand() > t2.ascii_expand() { t1 } else { t2 };
+                glyphs.tile(t.type_, None, false, Walls::default())
+            };
+            line.push_str(glyph);
+        }
+        lines.push(line.trim_right().to_string());
+    }
+    lines
+}
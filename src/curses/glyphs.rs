@@ -0,0 +1,260 @@
+use std::env;
+
+use game::{item, tile};
+use game::actor::Race;
+
+/// Which characters the map is drawn with
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GlyphSet {
+    /// Nothing outside of ASCII, for any terminal
+    Ascii,
+    Unicode,
+    /// Unicode, with walls drawn as lines joining their neighbors
+    Lines,
+}
+
+/// Walls next to a wall, as they'd be on the screen; the tiles above
+/// and below are half a tile to the side
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Walls {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+}
+
+/// The characters of one glyph set
+pub struct Glyphs {
+    pub dot: &'static str,
+    pub wall: &'static str,
+    pub door_open: &'static str,
+    pub door_closed: &'static str,
+    pub door_broken: &'static str,
+    pub statue: &'static str,
+    pub stairs_down: &'static str,
+    pub stairs_up: &'static str,
+    pub trap: &'static str,
+    pub fountain: &'static str,
+    pub altar: &'static str,
+    pub brazier: &'static str,
+    pub fungi: &'static str,
+    pub portal: &'static str,
+    pub chest: &'static str,
+    pub boulder: &'static str,
+    pub water: &'static str,
+    pub bridge: &'static str,
+    pub grass: &'static str,
+    pub fire: &'static str,
+    /// Where nothing is known
+    pub nothing: &'static str,
+    /// The end of text cut short
+    pub ellipsis: &'static str,
+}
+
+static ASCII: Glyphs = Glyphs {
+    dot: ".",
+    wall: "#",
+    door_open: "_",
+    door_closed: "+",
+    door_broken: "'",
+    statue: "&",
+    stairs_down: ">",
+    stairs_up: "<",
+    trap: "^",
+    fountain: "{",
+    altar: "_",
+    brazier: "*",
+    fungi: ",",
+    portal: "O",
+    chest: "$",
+    boulder: "0",
+    water: "~",
+    bridge: "=",
+    grass: "\"",
+    fire: "^",
+    nothing: "~",
+    ellipsis: "...",
+};
+
+static UNICODE: Glyphs = Glyphs {
+    dot: "·",
+    wall: "#",
+    door_open: "_",
+    door_closed: "×",
+    door_broken: "'",
+    statue: "&",
+    stairs_down: ">",
+    stairs_up: "<",
+    trap: "^",
+    fountain: "{",
+    altar: "±",
+    brazier: "Ψ",
+    fungi: ",",
+    portal: "Ω",
+    chest: "■",
+    boulder: "0",
+    water: "~",
+    bridge: "=",
+    grass: "\"",
+    fire: "^",
+    nothing: "~",
+    ellipsis: "…",
+};
+
+impl GlyphSet {
+    /// What the terminal can be trusted to show: ASCII unless the
+    /// locale is UTF-8, and not the middle dot putty's default font
+    /// lacks
+    pub fn detect() -> GlyphSet {
+        let putty = env::var("TERM").map(|t| t.starts_with("putty")).unwrap_or(false);
+        if utf8_locale() && !putty {
+            GlyphSet::Unicode
+        } else {
+            GlyphSet::Ascii
+        }
+    }
+
+    /// `--glyphs` takes the name
+    pub fn from_name(name: &str) -> Option<GlyphSet> {
+        match name {
+            "ascii" => Some(GlyphSet::Ascii),
+            "unicode" => Some(GlyphSet::Unicode),
+            "lines" => Some(GlyphSet::Lines),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            GlyphSet::Ascii => "ascii",
+            GlyphSet::Unicode => "unicode",
+            GlyphSet::Lines => "lines",
+        }
+    }
+
+    /// The one after this, to cycle through them
+    pub fn next(&self) -> GlyphSet {
+        match *self {
+            GlyphSet::Ascii => GlyphSet::Unicode,
+            GlyphSet::Unicode => GlyphSet::Lines,
+            GlyphSet::Lines => GlyphSet::Ascii,
+        }
+    }
+
+    pub fn glyphs(&self) -> &'static Glyphs {
+        match *self {
+            GlyphSet::Ascii => &ASCII,
+            GlyphSet::Unicode | GlyphSet::Lines => &UNICODE,
+        }
+    }
+
+    /// Whether walls look any different depending on `Walls`
+    pub fn joins_walls(&self) -> bool {
+        *self == GlyphSet::Lines
+    }
+
+    /// A wall with `walls` around
+    pub fn wall(&self, walls: Walls) -> &'static str {
+        if !self.joins_walls() {
+            return self.glyphs().wall;
+        }
+        match (walls.left, walls.right, walls.up, walls.down) {
+            (false, false, false, false) => self.glyphs().wall,
+            (_, _, false, false) => "─",
+            (false, false, _, _) => "│",
+            (false, true, false, true) => "┌",
+            (true, false, false, true) => "┐",
+            (false, true, true, false) => "└",
+            (true, false, true, false) => "┘",
+            (true, true, false, true) => "┬",
+            (true, true, true, false) => "┴",
+            (false, true, true, true) => "├",
+            (true, false, true, true) => "┤",
+            (true, true, true, true) => "┼",
+        }
+    }
+
+    /// A tile of type `t` with `feature` on it; `proper` for the tile
+    /// itself, not for the glue it spreads over next to it
+    pub fn tile(&self,
+                t: tile::Type,
+                feature: Option<tile::Feature>,
+                proper: bool,
+                walls: Walls)
+                -> &'static str {
+        let glyphs = self.glyphs();
+        match t {
+            tile::Empty if !proper => " ",
+            tile::Empty => {
+                match feature {
+                    None => glyphs.dot,
+                    Some(tile::BrokenDoor) => glyphs.door_broken,
+                    Some(tile::Door(true, _)) => glyphs.door_open,
+                    Some(tile::Door(false, _)) => glyphs.door_closed,
+                    Some(tile::Statue) => glyphs.statue,
+                    Some(tile::StairsDown(_)) => glyphs.stairs_down,
+                    Some(tile::StairsUp(_)) => glyphs.stairs_up,
+                    Some(tile::Fountain(_)) => glyphs.fountain,
+                    Some(tile::Altar(_)) => glyphs.altar,
+                    Some(tile::Brazier(_)) => glyphs.brazier,
+                    Some(tile::Fungi) => glyphs.fungi,
+                    Some(tile::Portal(_)) => glyphs.portal,
+                    Some(tile::Chest(_)) => glyphs.chest,
+                    Some(tile::Boulder) => glyphs.boulder,
+                    Some(tile::Trap(_)) => glyphs.trap,
+                }
+            }
+            tile::Wall if proper => self.wall(walls),
+            tile::Wall => self.wall_glue(walls.left && walls.right),
+            tile::Water => glyphs.water,
+            tile::Bridge => glyphs.bridge,
+            tile::Grass => glyphs.grass,
+            tile::Scorched => glyphs.dot,
+        }
+    }
+
+    /// Between two tiles, where the wall on one side is spread over;
+    /// `joined` if there's a wall on both sides
+    pub fn wall_glue(&self, joined: bool) -> &'static str {
+        match (self.joins_walls(), joined) {
+            (false, _) => self.glyphs().wall,
+            (true, true) => "─",
+            (true, false) => " ",
+        }
+    }
+}
+
+impl Glyphs {
+    pub fn actor(&self, race: Race) -> &'static str {
+        match race {
+            Race::Human | Race::Elf | Race::Dwarf | Race::Shopkeeper => "@",
+            Race::Rat => "r",
+            Race::Goblin => "g",
+            Race::Troll => "T",
+            Race::Pony => "u",
+            Race::Ghost => "G",
+        }
+    }
+
+    pub fn item(&self, t: item::Category) -> &'static str {
+        match t {
+            item::Category::Weapon => ")",
+            item::Category::RangedWeapon => "}",
+            item::Category::Armor => "[",
+            item::Category::Misc => "\"",
+            item::Category::Consumable => "%",
+        }
+    }
+}
+
+/// The locale the terminal was started with is UTF-8
+fn utf8_locale() -> bool {
+    // the first one set wins, like with `setlocale`
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+                     .iter()
+                     .filter_map(|var| env::var(var).ok())
+                     .find(|val| !val.is_empty())
+                     .unwrap_or_default()
+                     .to_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
+}
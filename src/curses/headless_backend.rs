@@ -0,0 +1,75 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use super::backend::{Backend, Rect, Screen, Style};
+
+/// Frames a `Headless` screen showed, oldest first: its lines joined
+/// with newlines, without the blanks they end with
+pub type Frames = Rc<RefCell<Vec<String>>>;
+
+/// A screen kept in memory, taking its keys from a script: to play
+/// without a terminal, and compare what's drawn from one run to another
+pub struct Headless {
+    size: (i32, i32),
+    /// What's drawn, row by row; colors are left out
+    cells: Vec<char>,
+    keys: VecDeque<i32>,
+    frames: Frames,
+}
+
+impl Headless {
+    /// A `width` by `height` screen pressing `keys` in turn, keeping
+    /// every frame in `frames`
+    pub fn open(width: i32, height: i32, keys: Vec<i32>, frames: Frames) -> Screen {
+        let backend: Box<Backend> = Box::new(Headless {
+            size: (width, height),
+            cells: vec![' '; (width * height) as usize],
+            keys: keys.into_iter().collect(),
+            frames: frames,
+        });
+        Rc::new(RefCell::new(backend))
+    }
+}
+
+impl Backend for Headless {
+    fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    fn put_glyph(&mut self, x: i32, y: i32, ch: char, _: Style) {
+        let (w, h) = self.size;
+        if x >= 0 && x < w && y >= 0 && y < h {
+            self.cells[(y * w + x) as usize] = ch;
+        }
+    }
+
+    fn clear(&mut self, rect: Rect, style: Style) {
+        for y in rect.y..rect.y + rect.h {
+            for x in rect.x..rect.x + rect.w {
+                self.put_glyph(x, y, ' ', style);
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        let lines: Vec<String> = self.cells
+                                     .chunks(self.size.0 as usize)
+                                     .map(|row| {
+                                         let row: String = row.iter().cloned().collect();
+                                         row.trim_right().to_string()
+                                     })
+                                     .collect();
+        self.frames.borrow_mut().push(lines.join("\n"));
+    }
+
+    fn read_key(&mut self) -> Option<i32> {
+        self.keys.pop_front()
+    }
+
+    fn suspend(&mut self) {}
+
+    fn has_input(&self) -> bool {
+        !self.keys.is_empty()
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+use super::consts::*;
+
+/// Something the player can ask for with a key; what it does depends
+/// on the mode the UI is in
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Command {
+    TurnLeft,
+    TurnRight,
+    Forward,
+    Back,
+    StrafeLeft,
+    StrafeRight,
+    SpinLeft,
+    SpinRight,
+    Charge,
+    Close,
+    Bash,
+    Wait,
+    /// Wait until healed or disturbed
+    Rest,
+    Pick,
+    Descend,
+    Ascend,
+    Interact,
+    Explore,
+    Walk,
+    GoTo,
+    Examine,
+    Minimap,
+    /// List the actors in sight
+    Monsters,
+    /// The whole known level on the screen
+    Overview,
+    /// Toggle the noises heard on the map
+    Noises,
+    Fire,
+    /// Fire at the last target without picking it again
+    FireAgain,
+    Inventory,
+    Equip,
+    Drop,
+    History,
+    /// Toggle damage numbers in the combat messages
+    Verbose,
+    /// Draw the map with the next glyph set
+    Glyphs,
+    /// The options screen
+    Options,
+    /// Write what's on the screen to a file
+    Screenshot,
+    Help,
+    Quit,
+    /// Move the examine cursor five tiles back
+    FarBack,
+    Confirm,
+    /// Move the examine or target cursor to the next thing of interest
+    NextTarget,
+    PrevTarget,
+    Cancel,
+    WizReveal,
+    WizHeal,
+    WizDescend,
+    WizUndo,
+    WizTeleport,
+    WizItem,
+    WizRat,
+    WizGoblin,
+    WizTroll,
+    WizPanic,
+    /// Write the level out whole
+    WizDump,
+    WizLog,
+}
+
+use self::Command::*;
+
+/// Section of the help screen a command is listed in
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Category {
+    Movement,
+    Interaction,
+    Information,
+    /// Moving the examine and target cursors
+    Cursor,
+    System,
+    Wizard,
+}
+
+impl Category {
+    /// Every category, in the help screen's order
+    pub fn all() -> [Category; 6] {
+        [Category::Movement,
+         Category::Interaction,
+         Category::Information,
+         Category::Cursor,
+         Category::System,
+         Category::Wizard]
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Category::Movement => "Movement",
+            Category::Interaction => "Interaction",
+            Category::Information => "Information",
+            Category::Cursor => "Examining and aiming",
+            Category::System => "System",
+            Category::Wizard => "Wizard mode (the game won't count)",
+        }
+    }
+}
+
+/// Every command with its name in the key file and what the help
+/// screen says about it, in the help screen's order
+const COMMANDS: [(Command, &'static str, &'static str); 54] =
+    [(Forward, "forward", "Move/attack forward"),
+     (Back, "back", "Move/attack back"),
+     (TurnLeft, "turn_left", "Turn left"),
+     (TurnRight, "turn_right", "Turn right"),
+     (StrafeLeft, "strafe_left", "Strafe/attack left"),
+     (StrafeRight, "strafe_right", "Strafe/attack right"),
+     (SpinLeft, "spin_left", "Spin left"),
+     (SpinRight, "spin_right", "Spin right"),
+     (Charge, "charge", "Charge"),
+     (Close, "close", "Close door in front"),
+     (Bash, "bash", "Bash door in front"),
+     (Wait, "wait", "Wait"),
+     (Rest, "rest", "Rest until healed or disturbed"),
+     (Explore, "explore", "Autoexplore"),
+     (Walk, "walk", "Run until something turns up"),
+     (GoTo, "goto", "Travel to stairs, a room or a spot"),
+     (Descend, "descend", "Descend stairs"),
+     (Ascend, "ascend", "Ascend stairs"),
+     (Examine, "examine", "Examine"),
+     (Minimap, "minimap", "Toggle minimap (next target when aiming)"),
+     (Monsters, "monsters", "List who is in sight"),
+     (Overview, "overview", "Overview of the level, to pick where to travel"),
+     (Noises, "noises", "Toggle noises heard on the map"),
+     (Pick, "pick", "Pick item in front"),
+     (Interact, "interact", "Use fountain/altar/chest, pay in shop"),
+     (Inventory, "inventory", "Inventory"),
+     (Equip, "equip", "Equip"),
+     (Drop, "drop", "Drop"),
+     (Fire, "fire", "Fire/Throw: pick a target"),
+     (FireAgain, "fire_again", "Fire/Throw at the last target"),
+     (History, "history", "Message history"),
+     (Verbose, "verbose", "Toggle damage numbers in the log"),
+     (Glyphs, "glyphs", "Switch between ascii, unicode and line-drawn walls"),
+     (Options, "options", "Options, kept for later games"),
+     (Screenshot, "screenshot", "Write the screen to a file, for a bug report"),
+     (Help, "help", "Help"),
+     (Quit, "quit", "Quit"),
+     (FarBack, "far_back", "Examine: jump back"),
+     (Confirm, "confirm", "Confirm target"),
+     (NextTarget, "next_target", "Examine/target: next thing of interest, nearest first"),
+     (PrevTarget, "prev_target", "Examine/target: previous thing of interest"),
+     (Cancel, "cancel", "Leave examine/target mode"),
+     (WizReveal, "wiz_reveal", "Reveal map"),
+     (WizHeal, "wiz_heal", "Heal"),
+     (WizDescend, "wiz_descend", "Descend"),
+     (WizUndo, "wiz_undo", "Undo the last action"),
+     (WizTeleport, "wiz_teleport", "Examine, then teleport"),
+     (WizItem, "wiz_item", "Examine, then spawn item"),
+     (WizRat, "wiz_rat", "Examine, then spawn rat"),
+     (WizGoblin, "wiz_goblin", "Examine, then spawn goblin"),
+     (WizTroll, "wiz_troll", "Examine, then spawn troll"),
+     (WizPanic, "wiz_panic", "Panic mid-draw, to check the terminal comes back"),
+     (WizDump, "wiz_dump", "Write the level to files as text and JSON"),
+     (WizLog, "wiz_log", "Examine, then write all about who's there to debug.log")];
+
+/// The bindings without a key file
+const DEFAULT_KEYS: [(i32, Command); 74] = [(KEY_LOWH, TurnLeft),
+                                           (KEY_LEFT, TurnLeft),
+                                           (KEY_LOWL, TurnRight),
+                                           (KEY_RIGHT, TurnRight),
+                                           (KEY_LOWK, Forward),
+                                           (KEY_UP, Forward),
+                                           (KEY_LOWJ, Back),
+                                           (KEY_DOWN, Back),
+                                           (KEY_CAPH, StrafeLeft),
+                                           (KEY_SLEFT, StrafeLeft),
+                                           (KEY_CAPL, StrafeRight),
+                                           (KEY_SRIGHT, StrafeRight),
+                                           (KEY_NUM8, Forward),
+                                           (KEY_NUM2, Back),
+                                           (KEY_NUM4, TurnLeft),
+                                           (KEY_NUM6, TurnRight),
+                                           (KEY_NUM7, StrafeLeft),
+                                           (KEY_A1, StrafeLeft),
+                                           (KEY_HOME, StrafeLeft),
+                                           (KEY_NUM9, StrafeRight),
+                                           (KEY_A3, StrafeRight),
+                                           (KEY_PPAGE, StrafeRight),
+                                           (KEY_NUM5, Wait),
+                                           (KEY_B2, Wait),
+                                           (KEY_LOWU, SpinLeft),
+                                           (KEY_LOWI, SpinRight),
+                                           (KEY_CAPC, Charge),
+                                           (KEY_LOWC, Close),
+                                           (KEY_LOWB, Bash),
+                                           (KEY_DOT, Wait),
+                                           (KEY_CAPR, Rest),
+                                           (KEY_COMMA, Pick),
+                                           (KEY_DESCEND, Descend),
+                                           (KEY_ASCEND, Ascend),
+                                           (KEY_LOWE, Interact),
+                                           (KEY_LOWO, Explore),
+                                           (KEY_CAPK, Walk),
+                                           (KEY_GOTO, GoTo),
+                                           (KEY_LOWX, Examine),
+                                           (KEY_LOWF, Fire),
+                                           (KEY_CAPF, FireAgain),
+                                           (KEY_CAPI, Inventory),
+                                           (KEY_CAPE, Equip),
+                                           (KEY_CAPD, Drop),
+                                           (KEY_CAPP, History),
+                                           (KEY_CTRLP, History),
+                                           (KEY_CAPV, Verbose),
+                                           (KEY_CAPT, Glyphs),
+                                           (KEY_CAPO, Options),
+                                           (KEY_HASH, Screenshot),
+                                           (KEY_HELP, Help),
+                                           (KEY_LOWQ, Quit),
+                                           (KEY_CAPJ, FarBack),
+                                           (KEY_ENTER, Confirm),
+                                           (KEY_TAB, Minimap),
+                                           (KEY_CAPN, Noises),
+                                           (KEY_LOWM, Monsters),
+                                           (KEY_CAPM, Overview),
+                                           (KEY_LOWN, NextTarget),
+                                           (KEY_BTAB, PrevTarget),
+                                           (KEY_ESC, Cancel),
+                                           (KEY_WIZ_REVEAL, WizReveal),
+                                           (KEY_WIZ_HEAL, WizHeal),
+                                           (KEY_WIZ_DESCEND, WizDescend),
+                                           (KEY_WIZ_UNDO, WizUndo),
+                                           (KEY_WIZ_TELEPORT, WizTeleport),
+                                           (KEY_WIZ_ITEM, WizItem),
+                                           (KEY_LOWR, WizRat),
+                                           (KEY_LOWG, WizGoblin),
+                                           (KEY_LOWT, WizTroll),
+                                           (KEY_WIZ_PANIC, WizPanic),
+                                           (KEY_WIZ_DUMP, WizDump),
+                                           (KEY_WIZ_LOG, WizLog),
+                                           (KEY_PADENTER, Confirm)];
+
+/// Keys with a name of their own in the key file
+const NAMED_KEYS: [(i32, &'static str); 23] = [(KEY_LEFT, "left"),
+                                              (KEY_RIGHT, "right"),
+                                              (KEY_UP, "up"),
+                                              (KEY_DOWN, "down"),
+                                              (KEY_SLEFT, "shift_left"),
+                                              (KEY_SRIGHT, "shift_right"),
+                                              (KEY_HOME, "home"),
+                                              (KEY_END, "end"),
+                                              (KEY_PPAGE, "page_up"),
+                                              (KEY_NPAGE, "page_down"),
+                                              // the numpad's corners and middle
+                                              // with Num Lock off, if the
+                                              // terminal tells them apart
+                                              (KEY_A1, "keypad_7"),
+                                              (KEY_A3, "keypad_9"),
+                                              (KEY_B2, "keypad_5"),
+                                              (KEY_C1, "keypad_1"),
+                                              (KEY_C3, "keypad_3"),
+                                              (KEY_ENTER, "enter"),
+                                              (KEY_PADENTER, "keypad_enter"),
+                                              (KEY_ESC, "esc"),
+                                              (KEY_TAB, "tab"),
+                                              (KEY_BTAB, "shift_tab"),
+                                              (' ' as i32, "space"),
+                                              // as itself, it would start a comment
+                                              (KEY_HASH, "hash"),
+                                              (KEY_DEL, "del")];
+
+impl Command {
+    /// Every command, in the help screen's order
+    pub fn all() -> Vec<Command> {
+        COMMANDS.iter().map(|&(command, _, _)| command).collect()
+    }
+
+    /// How the key file calls it
+    pub fn name(&self) -> &'static str {
+        COMMANDS.iter().find(|&&(c, _, _)| c == *self).unwrap().1
+    }
+
+    pub fn description(&self) -> &'static str {
+        COMMANDS.iter().find(|&&(c, _, _)| c == *self).unwrap().2
+    }
+
+    /// Only does anything in wizard mode
+    pub fn is_wizard(&self) -> bool {
+        self.name().starts_with("wiz_")
+    }
+
+    pub fn category(&self) -> Category {
+        match *self {
+            TurnLeft | TurnRight | Forward | Back | StrafeLeft | StrafeRight | SpinLeft |
+            SpinRight | Charge | Wait | Rest | Descend | Ascend | Explore | Walk | GoTo => {
+                Category::Movement
+            }
+            Close | Bash | Pick | Interact | Inventory | Equip | Drop | Fire | FireAgain => {
+                Category::Interaction
+            }
+            Examine | Minimap | Monsters | Overview | Noises | History | Verbose | Glyphs |
+            Options | Screenshot | Help => Category::Information,
+            FarBack | Confirm | NextTarget | PrevTarget => Category::Cursor,
+            Cancel | Quit => Category::System,
+            WizReveal | WizHeal | WizDescend | WizUndo | WizTeleport | WizItem | WizRat |
+            WizGoblin | WizTroll | WizPanic | WizDump | WizLog => Category::Wizard,
+        }
+    }
+}
+
+/// Which key does what
+#[derive(Clone, Debug)]
+pub struct KeyMap {
+    commands: HashMap<i32, Command>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap { commands: DEFAULT_KEYS.iter().cloned().collect() }
+    }
+}
+
+impl KeyMap {
+    pub fn command(&self, key: i32) -> Option<Command> {
+        self.commands.get(&key).cloned()
+    }
+
+    /// Keys bound to `command`, sorted
+    pub fn keys(&self, command: Command) -> Vec<i32> {
+        let mut keys: Vec<i32> = self.commands
+                                     .iter()
+                                     .filter(|&(_, &c)| c == command)
+                                     .map(|(&k, _)| k)
+                                     .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Keys bound to `command` as the player would type them, e.g. "h, left"
+    pub fn keys_description(&self, command: Command) -> String {
+        let keys: Vec<String> = self.keys(command).into_iter().map(key_name).collect();
+        if keys.is_empty() {
+            "(unbound)".to_string()
+        } else {
+            keys.join(", ")
+        }
+    }
+
+    /// Apply the bindings in `r`, one `key = command` per line, `#`
+    /// starting a comment line; `key = none` unbinds the key. Bad lines
+    /// and keys bound twice are skipped.
+    ///
+    /// Returns: what was wrong with the lines skipped.
+    pub fn read_from<R: BufRead>(&mut self, r: R) -> io::Result<Vec<String>> {
+        let mut problems = vec![];
+        let mut bound: HashMap<i32, Option<Command>> = HashMap::new();
+
+        for (i, line) in r.lines().enumerate() {
+            let line = try!(line);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, name) = match line.rfind('=') {
+                Some(at) => (line[..at].trim(), line[at + 1..].trim()),
+                None => {
+                    problems.push(format!("line {}: no '=' in \"{}\"", i + 1, line));
+                    continue;
+                }
+            };
+            let key = match parse_key(key) {
+                Some(key) => key,
+                None => {
+                    problems.push(format!("line {}: unknown key \"{}\"", i + 1, key));
+                    continue;
+                }
+            };
+            let command = if name == "none" {
+                None
+            } else {
+                match COMMANDS.iter().find(|&&(_, n, _)| n == name) {
+                    Some(&(command, _, _)) => Some(command),
+                    None => {
+                        problems.push(format!("line {}: unknown command \"{}\"", i + 1, name));
+                        continue;
+                    }
+                }
+            };
+
+            match bound.get(&key) {
+                Some(&earlier) if earlier != command => {
+                    problems.push(format!("line {}: {} is already bound to {}",
+                                          i + 1,
+                                          key_name(key),
+                                          earlier.map_or("none", |c| c.name())));
+                    continue;
+                }
+                _ => {}
+            }
+            bound.insert(key, command);
+        }
+
+        for (key, command) in bound {
+            match command {
+                Some(command) => self.commands.insert(key, command),
+                None => self.commands.remove(&key),
+            };
+        }
+        Ok(problems)
+    }
+
+    /// The default bindings with the ones in `path` on top, if there's
+    /// such a file
+    ///
+    /// Returns: the key map, and what was wrong with the file.
+    pub fn load(path: &Path) -> (KeyMap, Vec<String>) {
+        let mut keys = KeyMap::default();
+        let problems = match fs::File::open(path) {
+            Ok(file) => {
+                keys.read_from(io::BufReader::new(file))
+                    .unwrap_or_else(|e| vec![e.to_string()])
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => vec![e.to_string()],
+        };
+        let problems = problems.into_iter()
+                               .map(|p| format!("{}: {}", path.display(), p))
+                               .collect::<Vec<_>>();
+        for problem in &problems {
+            warn!("{}", problem);
+        }
+        (keys, problems)
+    }
+}
+
+/// `key` the way the key file and the help screen write it
+pub fn key_name(key: i32) -> String {
+    if let Some(&(_, name)) = NAMED_KEYS.iter().find(|&&(k, _)| k == key) {
+        return name.to_string();
+    }
+    match key {
+        0x21...0x7e => (key as u8 as char).to_string(),
+        0x01...0x1a => format!("^{}", (key as u8 + b'A' - 1) as char),
+        _ => format!("#{}", key),
+    }
+}
+
+/// Keys named as `key_name` does, separated by whitespace
+///
+/// Returns: the keys, or the first name that isn't one.
+pub fn parse_keys(s: &str) -> ::std::result::Result<Vec<i32>, String> {
+    s.split_whitespace()
+     .map(|name| parse_key(name).ok_or_else(|| format!("unknown key \"{}\"", name)))
+     .collect()
+}
+
+/// Reverse of `key_name`
+fn parse_key(s: &str) -> Option<i32> {
+    if let Some(&(key, _)) = NAMED_KEYS.iter().find(|&&(_, n)| n == s) {
+        return Some(key);
+    }
+    let chars: Vec<char> = s.chars().collect();
+    match (chars.len(), chars.get(0).cloned(), chars.get(1).cloned()) {
+        (1, Some(c), None) if c > ' ' && c <= '~' => Some(c as i32),
+        (2, Some('^'), Some(c @ 'a'...'z')) |
+        (2, Some('^'), Some(c @ 'A'...'Z')) => Some(c as i32 & 0x1f),
+        _ => None,
+    }
+}
@@ -0,0 +1,276 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::collections::vec_deque;
+
+use super::consts::*;
+
+/// How a log entry is colored while it's fresh
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Tone {
+    Plain,
+    /// Something bad happened to the player
+    Bad,
+    /// Something went the player's way
+    Good,
+}
+
+/// What a log entry is about; it picks the color the entry fades
+/// from, how long it stays readable, and whether it's logged at all
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Topic {
+    Combat,
+    /// Places, items and features the player came across
+    Discovery,
+    /// Whatever the player had better not miss
+    Danger,
+    /// Answers to the player's commands
+    System,
+    /// What goes on around, without mattering much
+    Flavor,
+}
+
+/// In the order the options screen lists them
+pub const TOPICS: [Topic; 5] = [Topic::Danger,
+                                Topic::Combat,
+                                Topic::Discovery,
+                                Topic::System,
+                                Topic::Flavor];
+
+impl Topic {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Topic::Combat => "combat",
+            Topic::Discovery => "discovery",
+            Topic::Danger => "danger",
+            Topic::System => "system",
+            Topic::Flavor => "flavor",
+        }
+    }
+
+    /// Least verbose log that still has it
+    pub fn verbosity(&self) -> Verbosity {
+        match *self {
+            Topic::Danger | Topic::System => Verbosity::Terse,
+            Topic::Combat | Topic::Discovery => Verbosity::Normal,
+            Topic::Flavor => Verbosity::Chatty,
+        }
+    }
+
+    /// How long it takes to fade, in quarters of the usual
+    pub fn lasting(&self) -> u64 {
+        match *self {
+            Topic::Flavor => 2,
+            Topic::Combat => 4,
+            Topic::Discovery | Topic::System => 8,
+            Topic::Danger => 16,
+        }
+    }
+}
+
+/// How much goes into the log; each level has everything the one
+/// before has
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum Verbosity {
+    /// Only danger and answers to commands
+    Terse,
+    /// Also combat and discoveries
+    Normal,
+    /// Everything
+    Chatty,
+}
+
+impl Verbosity {
+    pub fn from_name(name: &str) -> Option<Verbosity> {
+        match name {
+            "terse" => Some(Verbosity::Terse),
+            "normal" => Some(Verbosity::Normal),
+            "chatty" => Some(Verbosity::Chatty),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Verbosity::Terse => "terse",
+            Verbosity::Normal => "normal",
+            Verbosity::Chatty => "chatty",
+        }
+    }
+
+    /// The one after this, to cycle through them
+    pub fn next(&self) -> Verbosity {
+        match *self {
+            Verbosity::Terse => Verbosity::Normal,
+            Verbosity::Normal => Verbosity::Chatty,
+            Verbosity::Chatty => Verbosity::Terse,
+        }
+    }
+}
+
+pub struct LogEntry {
+    pub turn: u64,
+    pub text: String,
+    pub tone: Tone,
+    pub topic: Topic,
+    /// Times the same text was logged in a row
+    pub count: u32,
+}
+
+impl LogEntry {
+    /// The text as shown, with how many times it happened
+    pub fn description(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})", self.text, self.count)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// Everything logged for the player, newest first
+pub struct Messages {
+    entries: VecDeque<LogEntry>,
+    /// Newest entries the player hasn't had a chance to read yet
+    unread: usize,
+}
+
+impl Messages {
+    pub fn new() -> Self {
+        Messages {
+            entries: VecDeque::new(),
+            unread: 0,
+        }
+    }
+
+    /// Log `text` on `turn`. Repeating the newest entry within a turn
+    /// only counts it again, so a flurry of bites doesn't scroll
+    /// everything else away.
+    pub fn push(&mut self, text: &str, tone: Tone, topic: Topic, turn: u64) {
+        if let Some(last) = self.entries.front_mut() {
+            if last.text == text && last.tone == tone && last.topic == topic &&
+               turn <= last.turn + 1 {
+                last.count += 1;
+                last.turn = turn;
+                self.unread = cmp::max(self.unread, 1);
+                return;
+            }
+        }
+
+        self.entries.push_front(LogEntry {
+            turn: turn,
+            text: text.to_string(),
+            tone: tone,
+            topic: topic,
+            count: 1,
+        });
+        self.entries.truncate(LOG_MAX);
+        self.unread = cmp::min(self.unread + 1, self.entries.len());
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> vec_deque::Iter<LogEntry> {
+        self.entries.iter()
+    }
+
+    /// Whether more entries are unread than fit in `room` lines, so
+    /// showing the newest would scroll some away unseen
+    pub fn has_more(&self, room: usize) -> bool {
+        self.unread > room
+    }
+
+    /// The oldest `room` unread entries, newest first, to show before
+    /// the rest
+    pub fn oldest_unread(&self, room: usize) -> Vec<&LogEntry> {
+        self.entries.iter().skip(self.unread.saturating_sub(room)).take(room).collect()
+    }
+
+    /// The player read the oldest `room` unread entries
+    pub fn read(&mut self, room: usize) {
+        self.unread = self.unread.saturating_sub(room);
+    }
+
+    /// The player had a look at the log
+    pub fn read_all(&mut self) {
+        self.unread = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Messages, Tone, Topic};
+
+    fn texts(log: &Messages) -> Vec<String> {
+        log.iter().map(|e| e.description()).collect()
+    }
+
+    fn push(log: &mut Messages, text: &str, turn: u64) {
+        log.push(text, Tone::Plain, Topic::Combat, turn);
+    }
+
+    #[test]
+    fn repeats_on_the_same_or_next_turn_are_counted() {
+        let mut log = Messages::new();
+        push(&mut log, "The rat bites you.", 5);
+        push(&mut log, "The rat bites you.", 5);
+        push(&mut log, "The rat bites you.", 6);
+
+        assert_eq!(texts(&log), vec!["The rat bites you. (x3)"]);
+        assert_eq!(log.iter().next().unwrap().turn, 6);
+    }
+
+    #[test]
+    fn repeats_after_a_while_or_after_something_else_are_not() {
+        let mut log = Messages::new();
+        push(&mut log, "The rat bites you.", 5);
+        push(&mut log, "The rat bites you.", 9);
+        push(&mut log, "You miss the rat.", 9);
+        push(&mut log, "The rat bites you.", 9);
+        log.push("The rat bites you.", Tone::Bad, Topic::Combat, 9);
+
+        assert_eq!(texts(&log),
+                   vec!["The rat bites you.",
+                        "The rat bites you.",
+                        "You miss the rat.",
+                        "The rat bites you.",
+                        "The rat bites you."]);
+    }
+
+    #[test]
+    fn overflowing_turn_pauses_page_by_page() {
+        let mut log = Messages::new();
+        push(&mut log, "Old news.", 1);
+        log.read_all();
+        for i in 0..10 {
+            push(&mut log, &format!("Message {}.", i), 2);
+        }
+
+        // a page of 4, with the line for "--more--"
+        assert!(log.has_more(5));
+        let page: Vec<String> = log.oldest_unread(4).iter().map(|e| e.description()).collect();
+        assert_eq!(page, vec!["Message 3.", "Message 2.", "Message 1.", "Message 0."]);
+
+        log.read(4);
+        assert!(log.has_more(5));
+        let page: Vec<String> = log.oldest_unread(4).iter().map(|e| e.description()).collect();
+        assert_eq!(page, vec!["Message 7.", "Message 6.", "Message 5.", "Message 4."]);
+
+        // the last two fit along with the rest of the log
+        log.read(4);
+        assert!(!log.has_more(5));
+    }
+
+    #[test]
+    fn repeats_keep_a_message_unread() {
+        let mut log = Messages::new();
+        push(&mut log, "You hear a noise.", 1);
+        log.read_all();
+        assert!(!log.has_more(0));
+
+        push(&mut log, "You hear a noise.", 2);
+        assert!(log.has_more(0));
+        assert_eq!(log.len(), 1);
+    }
+}
@@ -1,5 +1,19 @@
+use hex2d::Coordinate;
+
 use game;
 
+mod backend;
+pub use self::backend::{BackendKind, Screen};
+
+mod headless_backend;
+pub use self::headless_backend::{Frames, Headless};
+
+#[cfg(feature = "ncurses")]
+mod ncurses_backend;
+
+#[cfg(feature = "termion")]
+mod termion_backend;
+
 mod color;
 
 mod consts;
@@ -7,6 +21,23 @@ mod consts;
 mod error;
 pub use self::error::*;
 
+mod export;
+pub use self::export::{export, Format};
+
+mod glyphs;
+pub use self::glyphs::GlyphSet;
+
+mod keymap;
+pub use self::keymap::parse_keys;
+
+mod messages;
+
+mod options;
+
+mod prompt;
+
+mod screenshot;
+
 
 mod ui;
 pub use self::ui::*;
@@ -14,31 +45,29 @@ pub use self::ui::*;
 pub enum LogEvent {
     AutoExploreDone,
     AutoExploreBlocked,
+    RestDone,
+    AutoMoveStopped(game::explore::Stop),
 }
 
 pub enum Event {
     Log(LogEvent),
-}
-
-pub struct LogEntry {
-    turn: u64,
-    text: String,
+    Feeling(game::Feeling),
 }
 
 pub enum AutoMoveAction {
     Action(game::Action),
     Finish, // Reached destination
     Blocked, // Blocked by something
-}
-
-#[derive(Copy, Clone, PartialEq, Eq)]
-pub enum GoToType {
-    Stairs,
+    Stopped(game::explore::Stop), // Something needs the player's attention
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum AutoMoveType {
     Explore,
-    Walk,
-    GoTo(GoToType),
+    /// Run straight ahead
+    Run,
+    /// Travel to a spot the player picked
+    Travel(Coordinate),
+    /// Wait until healed, or the turn given
+    Rest(u64),
 }
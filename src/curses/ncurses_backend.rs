@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::env;
+
+use libc;
+use ncurses as nc;
+
+use super::backend::{self, Backend, Rect, Style};
+use super::color;
+
+mod locale {
+    use libc::{c_int, c_char};
+    pub const LC_ALL: c_int = 6;
+    extern "C" {
+        pub fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
+    }
+}
+
+/// The terminal through curses, which knows the most of them
+pub struct Curses {
+    calloc: Allocator,
+}
+
+impl Curses {
+    pub fn new() -> Curses {
+        if env::var_os("ESCDELAY").is_none() {
+            env::set_var("ESCDELAY", "25");
+        }
+
+        unsafe {
+            let _ = locale::setlocale(locale::LC_ALL, b"en_US.UTF-8\0".as_ptr() as *const i8);
+        }
+
+        nc::initscr();
+        backend::install_panic_hook(restore);
+        nc::start_color();
+        nc::keypad(nc::stdscr, true);
+        nc::noecho();
+        nc::raw();
+        nc::timeout(0);
+        nc::flushinp();
+        nc::curs_set(nc::CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+
+        Curses { calloc: Allocator::new(Capabilities::detect()) }
+    }
+}
+
+impl Backend for Curses {
+    fn size(&self) -> (i32, i32) {
+        let (mut max_x, mut max_y) = (0, 0);
+        nc::getmaxyx(nc::stdscr, &mut max_y, &mut max_x);
+        (max_x, max_y)
+    }
+
+    fn put_glyph(&mut self, x: i32, y: i32, ch: char, style: Style) {
+        let mut buf = [0; 4];
+        nc::wattrset(nc::stdscr, self.calloc.get(style) as i32);
+        nc::mvwaddstr(nc::stdscr, y, x, ch.encode_utf8(&mut buf));
+    }
+
+    fn clear(&mut self, rect: Rect, style: Style) {
+        let blank = ' ' as nc::chtype | self.calloc.get(style) as nc::chtype;
+        for y in rect.y..rect.y + rect.h {
+            nc::mvwhline(nc::stdscr, y, rect.x, blank, rect.w);
+        }
+    }
+
+    fn flush(&mut self) {
+        nc::refresh();
+    }
+
+    fn read_key(&mut self) -> Option<i32> {
+        match nc::getch() {
+            -1 => None,
+            ch => Some(ch),
+        }
+    }
+
+    // raw mode keeps the terminal from sending SIGTSTP itself
+    fn suspend(&mut self) {
+        nc::endwin();
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGTSTP);
+        }
+        nc::clear();
+        nc::refresh();
+    }
+}
+
+impl Drop for Curses {
+    fn drop(&mut self) {
+        // after a panic, the hook left curses already
+        if !nc::isendwin() {
+            nc::clear();
+            nc::refresh();
+        }
+        restore();
+    }
+}
+
+/// Give the terminal back the way it was before curses; harmless to
+/// call more than once
+fn restore() {
+    if !nc::isendwin() {
+        nc::endwin();
+    }
+}
+
+/// What the terminal can show
+#[derive(Copy, Clone, Debug)]
+struct Capabilities {
+    /// 256, 16 or 8; anything less is treated as no colors at all
+    colors: i32,
+}
+
+impl Capabilities {
+    /// Ask curses; `start_color` must have been called
+    fn detect() -> Capabilities {
+        let colors = if nc::has_colors() {
+            nc::COLORS
+        } else {
+            0
+        };
+        Capabilities { colors: colors }
+    }
+}
+
+/// Hands out curses attributes for the 256-color pairs the UI draws
+/// with, translated to whatever the terminal can actually show
+struct Allocator {
+    caps: Capabilities,
+    map: HashMap<(u8, u8), u64>,
+    /// Pairs by the terminal colors in them
+    pairs: HashMap<(i16, i16), i16>,
+    cur: i16,
+}
+
+impl Allocator {
+    fn new(caps: Capabilities) -> Allocator {
+        Allocator {
+            caps: caps,
+            cur: 1i16, // 0 is reserved for defaults
+            map: HashMap::new(),
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Attributes to draw with `style`
+    fn get(&mut self, style: Style) -> u64 {
+        let mut attrs = self.colors(style.fg, style.bg);
+        if style.bold {
+            attrs |= nc::A_BOLD() as u64;
+        }
+        if style.reverse {
+            attrs |= nc::A_REVERSE() as u64;
+        }
+        attrs
+    }
+
+    /// Attributes to draw with `fg` on `bg`
+    fn colors(&mut self, fg: u8, bg: u8) -> u64 {
+        if let Some(&attrs) = self.map.get(&(fg, bg)) {
+            return attrs;
+        }
+
+        let attrs = if self.caps.colors < 8 {
+            monochrome(fg, bg)
+        } else {
+            let ((fg, fg_attrs), bg) = if self.caps.colors < 16 {
+                (basic8(fg), basic8(bg).0)
+            } else if self.caps.colors < 256 {
+                ((color::basic16(fg), 0), color::basic16(bg))
+            } else {
+                ((fg as i16, 0), bg as i16)
+            };
+            nc::COLOR_PAIR(self.pair(fg, bg)) as u64 | fg_attrs
+        };
+        self.map.insert((fg, bg), attrs);
+        attrs
+    }
+
+    /// Pair for the terminal's own colors `fg` and `bg`; with fewer
+    /// colors, many 256-color pairs end up sharing one
+    fn pair(&mut self, fg: i16, bg: i16) -> i16 {
+        match self.pairs.entry((fg, bg)) {
+            Entry::Occupied(i) => *i.get(),
+            Entry::Vacant(i) => {
+                assert!((self.cur as i32) < nc::COLOR_PAIRS,
+                        "curses run out of color pairs!");
+                let ret = self.cur;
+                i.insert(self.cur);
+                nc::init_pair(ret, fg, bg);
+                self.cur += 1;
+                ret
+            }
+        }
+    }
+}
+
+/// The normal color closest to `c`, with the attributes that brighten
+/// or darken it toward `c`; the attributes only work on a foreground
+fn basic8(c: u8) -> (i16, u64) {
+    match color::basic16(c) {
+        // bold gray is black on too many terminals
+        8 => (7, nc::A_DIM() as u64),
+        c if c > 8 => (c - 8, nc::A_BOLD() as u64),
+        c => (c, 0),
+    }
+}
+
+/// Attributes standing in for `fg` on `bg` without colors
+fn monochrome(fg: u8, bg: u8) -> u64 {
+    let (fg, bg) = (color::brightness(fg), color::brightness(bg));
+    let (fg, reverse) = if bg > fg {
+        (bg, nc::A_REVERSE() as u64)
+    } else {
+        (fg, 0)
+    };
+    let light = if fg > 200 {
+        nc::A_BOLD() as u64
+    } else if fg < 100 {
+        nc::A_DIM() as u64
+    } else {
+        0
+    };
+    reverse | light
+}
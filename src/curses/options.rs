@@ -0,0 +1,355 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use super::glyphs::GlyphSet;
+use super::messages::{Topic, Verbosity, TOPICS};
+
+/// One of the player's preferences
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Opt {
+    Glyphs,
+    Animations,
+    Minimap,
+    Noises,
+    Damage,
+    /// Whether screenshots get a copy in color
+    ScreenshotColors,
+    Verbosity,
+    /// Whether entries on the topic are logged, whatever the verbosity
+    Log(Topic),
+}
+
+/// In the order the options screen lists them
+pub const OPTS: [Opt; 12] = [Opt::Glyphs,
+                             Opt::Animations,
+                             Opt::Minimap,
+                             Opt::Noises,
+                             Opt::Damage,
+                             Opt::ScreenshotColors,
+                             Opt::Verbosity,
+                             Opt::Log(Topic::Danger),
+                             Opt::Log(Topic::Combat),
+                             Opt::Log(Topic::Discovery),
+                             Opt::Log(Topic::System),
+                             Opt::Log(Topic::Flavor)];
+
+impl Opt {
+    /// Key in the options file
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Opt::Glyphs => "glyphs",
+            Opt::Animations => "animations",
+            Opt::Minimap => "minimap",
+            Opt::Noises => "noises",
+            Opt::Damage => "damage_numbers",
+            Opt::ScreenshotColors => "screenshot_colors",
+            Opt::Verbosity => "log",
+            Opt::Log(Topic::Combat) => "log_combat",
+            Opt::Log(Topic::Discovery) => "log_discovery",
+            Opt::Log(Topic::Danger) => "log_danger",
+            Opt::Log(Topic::System) => "log_system",
+            Opt::Log(Topic::Flavor) => "log_flavor",
+        }
+    }
+
+    /// What it's for, on the options screen
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Opt::Glyphs => "Map glyphs",
+            Opt::Animations => "Show attacks and shots as they happen",
+            Opt::Minimap => "Minimap over the map",
+            Opt::Noises => "Noises on the map",
+            Opt::Damage => "Damage numbers in the log",
+            Opt::ScreenshotColors => "Screenshots also with colors, for the terminal",
+            Opt::Verbosity => "What the log has",
+            Opt::Log(Topic::Combat) => "  Combat",
+            Opt::Log(Topic::Discovery) => "  Discoveries",
+            Opt::Log(Topic::Danger) => "  Danger",
+            Opt::Log(Topic::System) => "  Answers to commands",
+            Opt::Log(Topic::Flavor) => "  Flavor, e.g. far off noises",
+        }
+    }
+}
+
+/// The player's preferences, kept between games
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// `None` for what suits the terminal
+    pub glyphs: Option<GlyphSet>,
+    /// Attacks and shots are shown on the map as they happen
+    pub animate: bool,
+    /// The minimap is shown over the map
+    pub minimap: bool,
+    pub show_noises: bool,
+    /// Whether combat messages tell the damage done
+    pub verbose_combat: bool,
+    /// Screenshots are also written with the colors' escape codes
+    pub screenshot_colors: bool,
+    pub verbosity: Verbosity,
+    /// Whether each of `TOPICS` is logged; `None` to go by `verbosity`
+    topics: [Option<bool>; 5],
+    /// Lines of the file with keys this version doesn't know, written
+    /// back as they were
+    unknown: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            glyphs: None,
+            animate: true,
+            minimap: false,
+            show_noises: true,
+            verbose_combat: true,
+            screenshot_colors: true,
+            verbosity: Verbosity::Chatty,
+            topics: [None; 5],
+            unknown: vec![],
+        }
+    }
+}
+
+impl Options {
+    /// Whether log entries on `topic` are kept
+    pub fn logs(&self, topic: Topic) -> bool {
+        self.topics[topic_index(topic)].unwrap_or(topic.verbosity() <= self.verbosity)
+    }
+
+    /// `opt` the way the options file and screen write it
+    pub fn value(&self, opt: Opt) -> &'static str {
+        let flag = |on: bool| if on { "on" } else { "off" };
+        match opt {
+            Opt::Glyphs => self.glyphs.map_or("auto", |g| g.name()),
+            Opt::Animations => flag(self.animate),
+            Opt::Minimap => flag(self.minimap),
+            Opt::Noises => flag(self.show_noises),
+            Opt::Damage => flag(self.verbose_combat),
+            Opt::ScreenshotColors => flag(self.screenshot_colors),
+            Opt::Verbosity => self.verbosity.name(),
+            Opt::Log(topic) => self.topics[topic_index(topic)].map_or("auto", flag),
+        }
+    }
+
+    /// Toggle `opt`, or move on to its next value
+    pub fn cycle(&mut self, opt: Opt) {
+        match opt {
+            Opt::Glyphs => {
+                self.glyphs = match self.glyphs {
+                    None => Some(GlyphSet::Ascii),
+                    Some(GlyphSet::Lines) => None,
+                    Some(g) => Some(g.next()),
+                }
+            }
+            Opt::Animations => self.animate = !self.animate,
+            Opt::Minimap => self.minimap = !self.minimap,
+            Opt::Noises => self.show_noises = !self.show_noises,
+            Opt::Damage => self.verbose_combat = !self.verbose_combat,
+            Opt::ScreenshotColors => self.screenshot_colors = !self.screenshot_colors,
+            Opt::Verbosity => self.verbosity = self.verbosity.next(),
+            Opt::Log(topic) => {
+                let shown = &mut self.topics[topic_index(topic)];
+                *shown = match *shown {
+                    None => Some(true),
+                    Some(true) => Some(false),
+                    Some(false) => None,
+                }
+            }
+        }
+    }
+
+    /// Set `opt` from its value in the file
+    ///
+    /// Returns: false if `value` isn't one it takes; `opt` is left as it
+    /// was.
+    fn set(&mut self, opt: Opt, value: &str) -> bool {
+        let flag = match value {
+            "on" | "yes" | "true" => Some(true),
+            "off" | "no" | "false" => Some(false),
+            _ => None,
+        };
+        match (opt, flag) {
+            (Opt::Glyphs, _) if value == "auto" => self.glyphs = None,
+            (Opt::Glyphs, _) => {
+                match GlyphSet::from_name(value) {
+                    Some(g) => self.glyphs = Some(g),
+                    None => return false,
+                }
+            }
+            (Opt::Verbosity, _) => {
+                match Verbosity::from_name(value) {
+                    Some(v) => self.verbosity = v,
+                    None => return false,
+                }
+            }
+            (Opt::Log(topic), _) if value == "auto" => self.topics[topic_index(topic)] = None,
+            (_, None) => return false,
+            (Opt::Log(topic), on) => self.topics[topic_index(topic)] = on,
+            (Opt::Animations, Some(on)) => self.animate = on,
+            (Opt::Minimap, Some(on)) => self.minimap = on,
+            (Opt::Noises, Some(on)) => self.show_noises = on,
+            (Opt::Damage, Some(on)) => self.verbose_combat = on,
+            (Opt::ScreenshotColors, Some(on)) => self.screenshot_colors = on,
+        }
+        true
+    }
+
+    /// Apply the options in `r`, one `name = value` per line, `#`
+    /// starting a comment line. Unknown names are kept for `write_to`;
+    /// bad values leave the option as it was.
+    ///
+    /// Returns: what was wrong with the lines.
+    pub fn read_from<R: BufRead>(&mut self, r: R) -> io::Result<Vec<String>> {
+        let mut problems = vec![];
+
+        for (i, line) in r.lines().enumerate() {
+            let line = try!(line);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, value) = match line.find('=') {
+                Some(at) => (line[..at].trim(), line[at + 1..].trim()),
+                None => {
+                    problems.push(format!("line {}: no '=' in \"{}\"", i + 1, line));
+                    continue;
+                }
+            };
+            match OPTS.iter().find(|o| o.name() == name) {
+                Some(&opt) => {
+                    if !self.set(opt, value) {
+                        problems.push(format!("line {}: bad value \"{}\" for {}",
+                                              i + 1,
+                                              value,
+                                              name));
+                    }
+                }
+                None => {
+                    problems.push(format!("line {}: unknown option \"{}\"", i + 1, name));
+                    self.unknown.push(line.to_string());
+                }
+            }
+        }
+        Ok(problems)
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        try!(writeln!(w, "# rhex options, rewritten when changed in the game"));
+        for &opt in &OPTS {
+            try!(writeln!(w, "{} = {}", opt.name(), self.value(opt)));
+        }
+        for line in &self.unknown {
+            try!(writeln!(w, "{}", line));
+        }
+        Ok(())
+    }
+
+    /// The defaults with the ones in `path` on top, if there's such a
+    /// file
+    ///
+    /// Returns: the options, and what was wrong with the file.
+    pub fn load(path: &Path) -> (Options, Vec<String>) {
+        let mut options = Options::default();
+        let problems = match fs::File::open(path) {
+            Ok(file) => {
+                options.read_from(io::BufReader::new(file))
+                       .unwrap_or_else(|e| vec![e.to_string()])
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => vec![e.to_string()],
+        };
+        let problems = problems.into_iter()
+                               .map(|p| format!("{}: {}", path.display(), p))
+                               .collect::<Vec<_>>();
+        for problem in &problems {
+            warn!("{}", problem);
+        }
+        (options, problems)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = try!(fs::File::create(path));
+        self.write_to(&mut file)
+    }
+}
+
+/// Where `topic` is in `TOPICS`
+fn topic_index(topic: Topic) -> usize {
+    TOPICS.iter().position(|&t| t == topic).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::messages::{Topic, Verbosity};
+    use super::{Opt, Options};
+
+    fn read(text: &str) -> (Options, Vec<String>) {
+        let mut options = Options::default();
+        let problems = options.read_from(text.as_bytes()).unwrap();
+        (options, problems)
+    }
+
+    #[test]
+    fn verbosity_keeps_its_topics_and_those_before() {
+        let mut options = Options::default();
+        options.verbosity = Verbosity::Terse;
+        assert!(options.logs(Topic::Danger));
+        assert!(options.logs(Topic::System));
+        assert!(!options.logs(Topic::Combat));
+        assert!(!options.logs(Topic::Flavor));
+
+        options.verbosity = Verbosity::Normal;
+        assert!(options.logs(Topic::Combat));
+        assert!(options.logs(Topic::Discovery));
+        assert!(!options.logs(Topic::Flavor));
+
+        options.verbosity = Verbosity::Chatty;
+        assert!(options.logs(Topic::Flavor));
+    }
+
+    #[test]
+    fn a_topic_set_on_or_off_goes_its_own_way() {
+        let (options, problems) = read("log = terse\nlog_flavor = on\nlog_danger = off\n");
+        assert!(problems.is_empty());
+        assert_eq!(options.verbosity, Verbosity::Terse);
+        assert!(options.logs(Topic::Flavor));
+        assert!(!options.logs(Topic::Danger));
+        assert!(options.logs(Topic::System));
+        assert!(!options.logs(Topic::Combat));
+    }
+
+    #[test]
+    fn bad_log_values_leave_it_as_it_was() {
+        let (options, problems) = read("log = everything\nlog_combat = maybe\n");
+        assert_eq!(problems.len(), 2);
+        assert_eq!(options.verbosity, Options::default().verbosity);
+        assert_eq!(options.value(Opt::Log(Topic::Combat)), "auto");
+    }
+
+    #[test]
+    fn a_topic_cycles_through_on_off_and_auto() {
+        let mut options = Options::default();
+        options.verbosity = Verbosity::Terse;
+        let opt = Opt::Log(Topic::Combat);
+
+        let mut seen = vec![];
+        for _ in 0..3 {
+            options.cycle(opt);
+            seen.push((options.value(opt), options.logs(Topic::Combat)));
+        }
+        assert_eq!(seen, vec![("on", true), ("off", false), ("auto", false)]);
+    }
+
+    #[test]
+    fn log_options_are_written_as_they_are_read() {
+        let (options, _) = read("log = normal\nlog_system = off\n");
+        let mut out = vec![];
+        options.write_to(&mut out).unwrap();
+        let (again, problems) = read(&String::from_utf8(out).unwrap());
+        assert!(problems.is_empty());
+        assert_eq!(again.verbosity, Verbosity::Normal);
+        assert_eq!(again.value(Opt::Log(Topic::System)), "off");
+        assert_eq!(again.value(Opt::Log(Topic::Flavor)), "auto");
+    }
+}
@@ -0,0 +1,75 @@
+use hex2d::{Coordinate, Direction};
+
+/// Something the player is asked, over the log window
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Question {
+    Quit,
+    /// What to do about the locked chest in front
+    ForceChest,
+    /// Which of the open doors around to close
+    CloseDoor,
+    /// Wizard: what item to spawn there
+    SpawnItem(Coordinate),
+}
+
+/// How a question is answered
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PromptKind {
+    YesNo,
+    /// One of the keys, each with what it does
+    Choice(&'static [(char, &'static str)]),
+    /// A direction, with the movement keys
+    Direction,
+    /// A short line of text, ended with Enter
+    Text,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Answer {
+    Yes,
+    No,
+    Key(char),
+    Direction(Direction),
+    Text(String),
+}
+
+/// Longest text a prompt takes
+pub const TEXT_MAX: usize = 32;
+
+const FORCE_CHEST: [(char, &'static str); 2] = [('b', "bash it open"), ('l', "leave it")];
+
+impl Question {
+    pub fn text(&self) -> &'static str {
+        match *self {
+            Question::Quit => "Quit? The game is saved, to continue later.",
+            Question::ForceChest => "The chest won't open:",
+            Question::CloseDoor => "Close which door?",
+            Question::SpawnItem(_) => "Spawn what (e.g. \"sword light\")? Nothing for any item:",
+        }
+    }
+
+    pub fn kind(&self) -> PromptKind {
+        match *self {
+            Question::Quit => PromptKind::YesNo,
+            Question::ForceChest => PromptKind::Choice(&FORCE_CHEST),
+            Question::CloseDoor => PromptKind::Direction,
+            Question::SpawnItem(_) => PromptKind::Text,
+        }
+    }
+
+    /// The question as shown, with how to answer it and the text typed
+    /// in so far
+    pub fn line(&self, typed: &str) -> String {
+        match self.kind() {
+            PromptKind::YesNo => format!("{} (y/n)", self.text()),
+            PromptKind::Choice(choices) => {
+                let choices: Vec<String> = choices.iter()
+                                                  .map(|&(key, what)| format!("{}) {}", key, what))
+                                                  .collect();
+                format!("{} {}", self.text(), choices.join(", "))
+            }
+            PromptKind::Direction => format!("{} (a direction to move in)", self.text()),
+            PromptKind::Text => format!("{} {}_", self.text(), typed),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::backend::{Backend, Rect, Style};
+
+/// What a `Capture` screen was drawn with, row by row
+pub type Cells = Rc<RefCell<Vec<(char, Style)>>>;
+
+/// A screen kept in memory, colors and all, to draw a frame on once
+/// more and keep it
+pub struct Capture {
+    size: (i32, i32),
+    cells: Cells,
+}
+
+impl Capture {
+    /// A `width` by `height` screen, and what's drawn on it
+    pub fn open(width: i32, height: i32) -> (Box<Backend>, Cells) {
+        let cells = Rc::new(RefCell::new(vec![(' ', Style::new(0, 0)); (width * height) as usize]));
+        let capture = Capture {
+            size: (width, height),
+            cells: cells.clone(),
+        };
+        (Box::new(capture), cells)
+    }
+}
+
+impl Backend for Capture {
+    fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    fn put_glyph(&mut self, x: i32, y: i32, ch: char, style: Style) {
+        let (w, h) = self.size;
+        if x >= 0 && x < w && y >= 0 && y < h {
+            self.cells.borrow_mut()[(y * w + x) as usize] = (ch, style);
+        }
+    }
+
+    fn clear(&mut self, rect: Rect, style: Style) {
+        for y in rect.y..rect.y + rect.h {
+            for x in rect.x..rect.x + rect.w {
+                self.put_glyph(x, y, ' ', style);
+            }
+        }
+    }
+
+    fn flush(&mut self) {}
+
+    fn read_key(&mut self) -> Option<i32> {
+        None
+    }
+
+    fn suspend(&mut self) {}
+}
+
+/// `cells`, `width` to a line, as plain text without the blanks lines
+/// end with
+pub fn text(cells: &[(char, Style)], width: i32) -> String {
+    let mut text = String::new();
+    for row in cells.chunks(width as usize) {
+        let line: String = row.iter().map(|&(ch, _)| ch).collect();
+        text.push_str(line.trim_right());
+        text.push('\n');
+    }
+    text
+}
+
+/// `cells`, `width` to a line, with escape codes for the 256 colors, for
+/// `cat` to show in a terminal
+pub fn ansi(cells: &[(char, Style)], width: i32) -> String {
+    let mut text = String::new();
+    for row in cells.chunks(width as usize) {
+        let mut style = None;
+        for &(ch, s) in row {
+            if style != Some(s) {
+                text.push_str(&s.sgr(true));
+                style = Some(s);
+            }
+            text.push(ch);
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    text
+}
+
+/// Write `files`, pairs of an extension and what goes in, to `dir` under
+/// a name made of `stem` that none of them has yet, adding a number if
+/// needed
+///
+/// Returns: the paths written to.
+pub fn save(dir: &Path, stem: &str, files: &[(&str, String)]) -> io::Result<Vec<PathBuf>> {
+    try!(fs::create_dir_all(dir));
+    let taken = |name: &str| {
+        files.iter().any(|&(ext, _)| dir.join(format!("{}.{}", name, ext)).exists())
+    };
+    let mut name = stem.to_string();
+    let mut n = 1;
+    while taken(&name) {
+        n += 1;
+        name = format!("{}-{}", stem, n);
+    }
+
+    let mut paths = vec![];
+    for &(ext, ref contents) in files {
+        let path = dir.join(format!("{}.{}", name, ext));
+        let mut file = try!(fs::File::create(&path));
+        try!(file.write_all(contents.as_bytes()));
+        paths.push(path);
+    }
+    Ok(paths)
+}
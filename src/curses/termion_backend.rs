@@ -0,0 +1,218 @@
+use std::env;
+use std::io::{self, Write};
+use std::mem;
+
+use libc;
+use termion;
+use termion::cursor;
+use termion::event::Key;
+use termion::input::{Keys, TermRead};
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::screen::{ToAlternateScreen, ToMainScreen};
+
+use super::backend::{self, Backend, Rect, Style};
+use super::consts::*;
+
+/// What's drawn in one place of the screen
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct Glyph {
+    ch: char,
+    style: Style,
+}
+
+impl Default for Glyph {
+    fn default() -> Self {
+        Glyph {
+            ch: ' ',
+            style: Style::new(0, 0),
+        }
+    }
+}
+
+/// The terminal through escape sequences, without linking any library;
+/// enough for the terminals of today
+pub struct Termion {
+    out: RawTerminal<io::Stdout>,
+    keys: Keys<termion::AsyncReader>,
+    size: (i32, i32),
+    /// What `flush` draws, row by row
+    drawn: Vec<Glyph>,
+    /// What the terminal shows; `None` where it isn't known
+    shown: Vec<Option<Glyph>>,
+    /// Whether to draw with the 256 colors, or the 16 basic ones
+    colors256: bool,
+    /// The second half of an Alt combination, read as two keys like
+    /// curses does
+    pending: Option<i32>,
+}
+
+impl Termion {
+    pub fn new() -> io::Result<Termion> {
+        let mut saved: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDOUT_FILENO, &mut saved) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        backend::install_panic_hook(move || restore(&saved));
+
+        let mut out = try!(io::stdout().into_raw_mode());
+        try!(write!(out, "{}{}", ToAlternateScreen, cursor::Hide));
+        try!(out.flush());
+
+        let colors256 = env::var("TERM").map(|t| t.contains("256color")).unwrap_or(false) ||
+                        env::var_os("COLORTERM").is_some();
+
+        let mut termion = Termion {
+            out: out,
+            keys: termion::async_stdin().keys(),
+            size: (0, 0),
+            drawn: vec![],
+            shown: vec![],
+            colors256: colors256,
+            pending: None,
+        };
+        let size = termion.terminal_size();
+        termion.resize_to(size);
+        Ok(termion)
+    }
+
+    fn terminal_size(&self) -> (i32, i32) {
+        termion::terminal_size().map(|(w, h)| (w as i32, h as i32)).unwrap_or((80, 24))
+    }
+
+    /// Start over with a blank screen of `size`, not knowing what the
+    /// terminal shows
+    fn resize_to(&mut self, size: (i32, i32)) {
+        let len = (size.0 * size.1) as usize;
+        self.size = size;
+        self.drawn = vec![Glyph::default(); len];
+        self.shown = vec![None; len];
+    }
+
+    /// The key code curses would give for `key`; `None` for the ones
+    /// the game has no use for
+    fn code(&mut self, key: Key) -> Option<i32> {
+        let code = match key {
+            Key::Char(ch) => ch as i32,
+            Key::Ctrl(ch) => ch as i32 & 0x1f,
+            Key::Alt(ch) => {
+                self.pending = Some(ch as i32);
+                KEY_ESC
+            }
+            Key::Esc => KEY_ESC,
+            Key::Backspace => KEY_BACKSPACE,
+            Key::Left => KEY_LEFT,
+            Key::Right => KEY_RIGHT,
+            Key::Up => KEY_UP,
+            Key::Down => KEY_DOWN,
+            Key::Home => KEY_HOME,
+            Key::End => KEY_END,
+            Key::PageUp => KEY_PPAGE,
+            Key::PageDown => KEY_NPAGE,
+            Key::BackTab => KEY_BTAB,
+            _ => return None,
+        };
+        Some(code)
+    }
+}
+
+impl Backend for Termion {
+    fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    fn put_glyph(&mut self, x: i32, y: i32, ch: char, style: Style) {
+        let (w, h) = self.size;
+        if x >= 0 && x < w && y >= 0 && y < h {
+            self.drawn[(y * w + x) as usize] = Glyph {
+                ch: ch,
+                style: style,
+            };
+        }
+    }
+
+    fn clear(&mut self, rect: Rect, style: Style) {
+        for y in rect.y..rect.y + rect.h {
+            for x in rect.x..rect.x + rect.w {
+                self.put_glyph(x, y, ' ', style);
+            }
+        }
+    }
+
+    // only what changed since the last time is sent
+    fn flush(&mut self) {
+        let w = self.size.0;
+        let mut buf = String::new();
+        let mut style = None;
+        // where the terminal's cursor is, if known
+        let mut at = None;
+        for i in 0..self.drawn.len() {
+            let glyph = self.drawn[i];
+            if self.shown[i] == Some(glyph) {
+                continue;
+            }
+            let (x, y) = (i as i32 % w, i as i32 / w);
+            if at != Some(i) {
+                buf.push_str(&cursor::Goto(x as u16 + 1, y as u16 + 1).to_string());
+            }
+            if style != Some(glyph.style) {
+                buf.push_str(&glyph.style.sgr(self.colors256));
+                style = Some(glyph.style);
+            }
+            buf.push(glyph.ch);
+            // past the last column it stays put until the next character
+            at = if x + 1 < w { Some(i + 1) } else { None };
+            self.shown[i] = Some(glyph);
+        }
+        let _ = self.out.write_all(buf.as_bytes());
+        let _ = self.out.flush();
+    }
+
+    fn read_key(&mut self) -> Option<i32> {
+        if let Some(code) = self.pending.take() {
+            return Some(code);
+        }
+        let size = self.terminal_size();
+        if size != self.size {
+            self.resize_to(size);
+            return Some(KEY_RESIZE);
+        }
+        while let Some(Ok(key)) = self.keys.next() {
+            if let Some(code) = self.code(key) {
+                return Some(code);
+            }
+        }
+        None
+    }
+
+    fn suspend(&mut self) {
+        let _ = write!(self.out, "\x1b[0m{}{}", ToMainScreen, cursor::Show);
+        let _ = self.out.flush();
+        let _ = self.out.suspend_raw_mode();
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGTSTP);
+        }
+        let _ = self.out.activate_raw_mode();
+        let _ = write!(self.out, "{}{}", ToAlternateScreen, cursor::Hide);
+        let size = self.terminal_size();
+        self.resize_to(size);
+    }
+}
+
+impl Drop for Termion {
+    // `RawTerminal` puts the terminal's mode back after this
+    fn drop(&mut self) {
+        let _ = write!(self.out, "\x1b[0m{}{}", ToMainScreen, cursor::Show);
+        let _ = self.out.flush();
+    }
+}
+
+/// Give the terminal back the way it was before the game, `saved` being
+/// its mode then, while `Termion` can't do it as it goes
+fn restore(saved: &libc::termios) {
+    unsafe {
+        libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSANOW, saved);
+    }
+    let mut out = io::stdout();
+    let _ = write!(out, "\x1b[0m{}{}", ToMainScreen, cursor::Show);
+    let _ = out.flush();
+}
@@ -2,80 +2,98 @@ use std::cell::RefCell;
 use std::collections::{VecDeque, HashMap, HashSet};
 use std::env;
 use std;
-use std::{thread, cmp, fmt};
+use std::{thread, cmp, fmt, mem};
 use std::io::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::fmt::Write as FmtWrite;
 
 use chrono;
+use rand;
 use num::integer::Integer;
 
-use ncurses as nc;
-use hex2d::{Position, Coordinate, Angle, Left, Right, Forward, Back, ToCoordinate};
-
-use hex2dext::algo::bfs;
+use hex2d::{Position, Coordinate, Direction, Angle, Left, Right, Forward, Back, ToCoordinate};
 
+use super::backend::{BackendKind, Rect, Screen, Style, Window};
 use super::consts::*;
+use super::keymap::{Category, Command, KeyMap};
 use super::color;
-use super::{LogEntry, AutoMoveType, AutoMoveAction, LogEvent, Event, GoToType};
+use super::export::{self, Format};
+use super::glyphs::{GlyphSet, Glyphs, Walls};
+use super::messages::{LogEntry, Messages, Tone, Topic};
+use super::options::{self, Opt, Options};
+use super::prompt::{self, Answer, PromptKind, Question};
+use super::screenshot::{self, Capture};
+use super::{AutoMoveType, AutoMoveAction, LogEvent, Event};
 use super::Result;
 
 use game::{actor, Location, Actor, item, area};
 use game;
 use game::actor::{Race, Slot};
+use game::explore::{Step, Stop};
 use game::tile;
 use util;
 
-mod locale {
-    use libc::{c_int, c_char};
-    pub const LC_ALL: c_int = 6;
-    extern "C" {
-        pub fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
-    }
-}
-
-
-pub struct Window {
-    pub window: nc::WINDOW,
+/// Where the windows go on the screen
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct Layout {
+    map: Rect,
+    /// `None` when the screen is too narrow to spare a column
+    stats: Option<Rect>,
+    /// `None` when the screen is too short to spare the lines
+    log: Option<Rect>,
+    full: Rect,
 }
 
-impl Window {
-    pub fn new(w: i32, h: i32, x: i32, y: i32) -> Window {
-        Window { window: nc::subwin(nc::stdscr, h, w, y, x) }
-    }
-}
+impl Layout {
+    /// The layout of a `max_x` by `max_y` screen: the map on the left,
+    /// at least `MAP_MIN_WIDTH` wide; the stats on the right, as wide as
+    /// their widest line; the log under them, with whatever is left.
+    /// What doesn't fit goes, the log first.
+    fn new(max_x: i32, max_y: i32) -> Self {
+        let (max_x, max_y) = (cmp::max(max_x, 1), cmp::max(max_y, 1));
+
+        let side_w = cmp::min(STATS_WIDTH, max_x - cmp::min(max_x, MAP_MIN_WIDTH));
+        let map_w = max_x - side_w;
+        let log_h = max_y - STATS_HEIGHT;
+
+        let (stats, log) = if side_w == 0 {
+            (None, None)
+        } else if log_h < LOG_MIN_HEIGHT {
+            (Some(Rect { x: map_w, y: 0, w: side_w, h: max_y }), None)
+        } else {
+            (Some(Rect { x: map_w, y: 0, w: side_w, h: STATS_HEIGHT }),
+             Some(Rect { x: map_w, y: STATS_HEIGHT, w: side_w, h: log_h }))
+        };
 
-impl Drop for Window {
-    fn drop(&mut self) {
-        nc::delwin(self.window);
+        Layout {
+            map: Rect { x: 0, y: 0, w: map_w, h: max_y },
+            stats: stats,
+            log: log,
+            full: Rect { x: 0, y: 0, w: max_x, h: max_y },
+        }
     }
 }
 
 struct Windows {
     map: Window,
-    log: Window,
-    stats: Window,
+    log: Option<Window>,
+    stats: Option<Window>,
     full: Window,
 }
 
 impl Windows {
-    fn after_resize() -> Self {
-        let mut max_x = 0;
-        let mut max_y = 0;
-        nc::getmaxyx(nc::stdscr, &mut max_y, &mut max_x);
-
-        let mid_x = max_x - 30;
-        let mid_y = 12;
+    /// Windows laid out on the whole of `screen`
+    fn new(screen: &Screen) -> Self {
+        let (max_x, max_y) = screen.borrow().size();
 
-        let map_window = Window::new(mid_x, max_y, 0, 0);
-        let stats_window = Window::new(max_x - mid_x, mid_y, mid_x, 0);
-        let log_window = Window::new(max_x - mid_x, max_y - mid_y, mid_x, mid_y);
-        let fs_window = Window::new(max_x, max_y, 0, 0);
+        let layout = Layout::new(max_x, max_y);
 
         Windows {
-            map: map_window,
-            stats: stats_window,
-            log: log_window,
-            full: fs_window,
+            map: Window::new(screen, layout.map),
+            stats: layout.stats.map(|rect| Window::new(screen, rect)),
+            log: layout.log.map(|rect| Window::new(screen, rect)),
+            full: Window::new(screen, layout.full),
         }
     }
 }
@@ -85,99 +103,375 @@ enum InvMode {
     View,
     Equip,
     Drop_,
+    /// Details of the item with that letter, and what to do with it
+    Item(char),
+}
+
+/// A place the travel menu offers
+struct Destination {
+    name: String,
+    coord: Coordinate,
+    /// Length of the way the player knows there, if any
+    steps: Option<usize>,
+}
+
+/// What is known of a tile, piece by piece
+struct TileDetails {
+    type_: tile::Type,
+    features: Vec<String>,
+    burning: bool,
+    /// How bright it looks, if in sight
+    light: Option<&'static str>,
+    area: Option<area::Type>,
+    item: Option<String>,
+    /// Whoever is in sight there
+    actor: Option<ActorDetails>,
+}
+
+impl TileDetails {
+    /// The tile itself, whatever lies or stands on it
+    fn ground(&self) -> String {
+        if !self.features.is_empty() {
+            let features = self.features.join(", ");
+            return if self.burning {
+                format!("burning {}", features)
+            } else {
+                features
+            };
+        }
+        match self.type_ {
+            tile::Wall => "a wall".to_string(),
+            tile::Empty if self.burning => "fire".to_string(),
+            tile::Empty => {
+                match self.area {
+                    Some(area::Room(_)) => "room".to_string(),
+                    None => "nothing".to_string(),
+                }
+            }
+            _ if self.burning => format!("burning {}", self.type_.description()),
+            _ => self.type_.description().to_string(),
+        }
+    }
+
+    /// Whoever stands there, what lies there and the tile itself, as far
+    /// as worth mentioning; `with_actor` false leaves out whoever stands
+    /// there
+    fn parts(&self, with_actor: bool) -> Vec<String> {
+        let mut parts = vec![];
+        if let (true, Some(actor)) = (with_actor, self.actor.as_ref()) {
+            parts.push(match actor.race {
+                           Race::Pony => "Pony",
+                           Race::Rat => "A rat",
+                           Race::Goblin => "Goblin",
+                           Race::Troll => "Troll",
+                           Race::Human => "Human",
+                           Race::Elf => "Elf",
+                           Race::Dwarf => "Dwarf",
+                           Race::Shopkeeper => "Shopkeeper",
+                           Race::Ghost => "Ghost",
+                       }
+                       .to_string());
+        }
+        if let Some(ref item) = self.item {
+            parts.push(item.clone());
+        }
+        // bare floor goes without saying, unless there's nothing else
+        let bare = self.type_ == tile::Empty && self.features.is_empty() && !self.burning;
+        if !bare || parts.is_empty() {
+            parts.push(self.ground());
+        }
+        parts
+    }
 }
 
+struct ActorDetails {
+    race: Race,
+    name: String,
+    health: &'static str,
+    /// What it has equipped, for all to see
+    equipment: Vec<String>,
+    /// It sees whoever is looking
+    noticed: bool,
+}
+
+/// What the inventory screen can do with an item
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
-enum FSMode {
+enum ItemAction {
+    Drop,
+    Equip,
+    Use,
+    Throw,
+}
+
+const ITEM_ACTIONS: [(char, ItemAction); 4] = [('d', ItemAction::Drop),
+                                               ('e', ItemAction::Equip),
+                                               ('u', ItemAction::Use),
+                                               ('t', ItemAction::Throw)];
+
+/// What the help screen is about: the mode it was asked for in
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum HelpTopic {
+    /// Every command, then how the game works
+    Game,
+    Examine,
+    Inventory,
+}
+
+/// Keys of examine mode, with what they do there
+const EXAMINE_HELP: [(Command, &'static str); 14] =
+    [(Command::Forward, "Cursor forward"),
+     (Command::Back, "Cursor back"),
+     (Command::TurnLeft, "Turn the cursor left"),
+     (Command::TurnRight, "Turn the cursor right"),
+     (Command::StrafeLeft, "Cursor forward left"),
+     (Command::StrafeRight, "Cursor forward right"),
+     (Command::Walk, "Cursor five tiles forward"),
+     (Command::FarBack, "Cursor five tiles back"),
+     (Command::NextTarget, "Next actor in sight, then items in sight, then stairs"),
+     (Command::Minimap, "The same"),
+     (Command::PrevTarget, "The one before that"),
+     (Command::Wait, "Back to yourself"),
+     (Command::Confirm, "Travel there, when picking a spot to travel to"),
+     (Command::Cancel, "Back to the map")];
+
+/// How the game works, for the last page of the help
+const CONCEPTS_HELP: [&'static str; 15] =
+    ["= How things work =",
+     "",
+     "Light: you only see what is lit. Torches and braziers light their",
+     "surroundings; dim tiles are drawn darker.",
+     "",
+     "Noise: moving, fighting, doors and traps make noise. Monsters hear",
+     "it through walls and come to look; what you hear but can't see is",
+     "marked on the map for a few turns.",
+     "",
+     "SP (stamina): attacking with a weapon heavier than your strength",
+     "allows, and charging, cost SP. Without enough SP, blows do half",
+     "the damage and hit half as often. SP comes back slowly over time.",
+     "",
+     "Facing: you attack ahead and to the sides; blows from behind hit",
+     "twice as hard and twice as often, for you as for anybody else."];
+
+/// What the main menu offers
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum MenuEntry {
+    NewGame,
+    /// Resume the autosaved game; only there is one
+    Continue,
+    HighScores,
+    Options,
     Help,
-    Intro,
-    PickRace,
     Quit,
 }
 
+const MENU: [(MenuEntry, &'static str); 6] = [(MenuEntry::NewGame, "New game"),
+                                              (MenuEntry::Continue, "Continue"),
+                                              (MenuEntry::HighScores, "High scores"),
+                                              (MenuEntry::Options, "Options"),
+                                              (MenuEntry::Help, "Help"),
+                                              (MenuEntry::Quit, "Quit")];
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum FSMode {
+    /// The help on the topic, at that page
+    Help(HelpTopic, usize),
+    /// The main menu, with that entry selected
+    Menu(usize),
+    /// Offer to resume the autosaved game
+    Resume,
+    PickRace,
+    /// Type in a name for a player of the race
+    EnterName(Race),
+    /// Summary of the finished game
+    GameOver,
+    HighScores,
+    /// The whole log, newest first, with that many entries scrolled past
+    MessageHistory(usize),
+    /// The options, with that one selected
+    Options(usize),
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 enum TargetMode {
     Ranged,
+    /// Throw the item with that letter
+    Throw(char),
+}
+
+/// What a cell of the minimap shows, least important first
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+enum MinimapCell {
+    Floor,
+    Water,
+    Wall,
+    Door,
+    StairsUp,
+    StairsDown,
+}
+
+/// The known level shrunk to fit a window, a character for each cell
+/// of `scale` by `scale` hexes
+struct Shrunk {
+    min_x: i32,
+    min_y: i32,
+    scale: i32,
+    cols: i32,
+    rows: i32,
+    /// What each cell with anything known shows, and whether any of it
+    /// is in sight
+    cells: HashMap<(i32, i32), (MinimapCell, bool)>,
+}
+
+impl Shrunk {
+    /// The cell `coord` falls in, if it's within the known part
+    fn cell(&self, coord: Coordinate) -> Option<(i32, i32)> {
+        let (px, py) = coord.to_pixel_integer(SPACING);
+        let x = (px.div_floor(&2) - self.min_x).div_floor(&self.scale);
+        let y = (py - self.min_y).div_floor(&self.scale);
+        if x < 0 || y < 0 || x >= self.cols || y >= self.rows {
+            None
+        } else {
+            Some((x, y))
+        }
+    }
+}
+
+/// Something the player saw happen, shown on the map for a moment
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Animation {
+    /// Somebody got attacked there
+    Flash(Coordinate),
+    /// Something flew over the tiles, in order
+    Projectile(Vec<Coordinate>),
+}
+
+/// One frame of an animation, drawn over the map
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Frame {
+    Flash(Coordinate),
+    Missile(Coordinate),
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 enum Mode {
     Normal,
+    /// Waiting for the answer to a question
+    Prompt(Question),
     Examine,
     GoTo,
+    /// The whole known level, with a cursor to pick where to travel to
+    Overview,
+    /// The actors in sight, with that one selected
+    Monsters(usize),
     Target(TargetMode),
     FullScreen(FSMode),
     Inventory(InvMode),
 }
 
 pub struct Ui {
-    calloc: RefCell<color::Allocator>,
-
+    screen: Screen,
     windows: Windows,
 
     mode: Mode,
-    log: RefCell<VecDeque<LogEntry>>,
+    log: RefCell<Messages>,
     target_pos: Option<Position>,
-    dot: &'static str,
+    /// The player's preferences
+    options: Options,
+    /// What the map is drawn with unless the options say otherwise
+    terminal_glyphs: GlyphSet,
 
-    label_color: u64,
-    text_color: u64,
-    text_gray_color: u64,
-    red_color: u64,
-    green_color: u64,
+    label_color: Style,
+    text_color: Style,
+    text_gray_color: Style,
+    red_color: Style,
+    green_color: Style,
 
     engine: game::Engine,
     exit: bool,
     needs_redraw: bool,
     spawned: bool,
+    /// The player heard they're still recovering since they last acted
+    told_recovering: bool,
+    /// Seed of the next game, if not a random one
+    seed: Option<u32>,
+    /// Mode a question was asked in, to go back to
+    prompt_from: Mode,
+    /// Text typed in answer to a question
+    prompt_text: String,
 
     automoving: Option<AutoMoveType>,
     automoving_stopped_turn: u64,
 
     after_action_delay: u32,
     game_action_queue: VecDeque<game::Action>,
+    /// Delay between the player's actions when watching a replay
+    spectating: Option<u32>,
+    /// Wizard commands are allowed
+    wizard: bool,
+    /// Wizard: panic in the middle of the next redraw
+    wiz_panic: bool,
+    /// Place the finished game got in the high scores, if any
+    rank: Option<usize>,
+    /// Name being typed in for the player
+    name: String,
+    keys: KeyMap,
+    /// Whoever the player last shot at
+    last_target: Option<actor::Id>,
+    /// Page of the inventory list shown
+    inv_page: usize,
+    /// Looking around the level the game ended on
+    final_map: bool,
+    /// Where the morgue file of the finished game went, or why it didn't
+    morgue: Option<String>,
+    /// Autoexplore under way
+    explorer: Option<game::Explorer>,
+    /// The examine cursor picks a spot to travel to
+    picking_travel: bool,
+    /// What the examine cursor jumps between, in order; taken when
+    /// examining starts
+    sights: Vec<Coordinate>,
+    /// Statuses of the player last time, to tell which came or went
+    statuses: Vec<&'static str>,
+    /// Noises heard lately, with the level and the turn
+    noises: game::CoordMap<(game::LevelId, game::Noise, u64)>,
+    /// Animations waiting to be played, oldest first
+    animations: Vec<Animation>,
+    /// Animation frame being drawn
+    frame: Option<Frame>,
+    /// Where the files the game keeps are
+    dir: PathBuf,
 }
 
 
 impl Ui {
-    pub fn new() -> Result<Self> {
-
-        if env::var_os("ESCDELAY").is_none() {
-            env::set_var("ESCDELAY", "25");
-        }
-
-        unsafe {
-            let _ = locale::setlocale(locale::LC_ALL, b"en_US.UTF-8\0".as_ptr() as *const i8);
-        }
-
-        nc::initscr();
-        nc::start_color();
-        nc::keypad(nc::stdscr, true);
-        nc::noecho();
-        nc::raw();
-        nc::timeout(0);
-        nc::flushinp();
-
-        assert!(nc::has_colors());
-
-        let mut calloc = color::Allocator::new();
-        let label_color = nc::COLOR_PAIR(calloc.get(color::LABEL_FG, color::BACKGROUND_BG));
-        let text_color = nc::COLOR_PAIR(calloc.get(color::VISIBLE_FG, color::BACKGROUND_BG));
-        let text_gray_color = nc::COLOR_PAIR(calloc.get(color::GRAY[10], color::BACKGROUND_BG));
-        let green_color = nc::COLOR_PAIR(calloc.get(color::GREEN_FG, color::BACKGROUND_BG));
-        let red_color = nc::COLOR_PAIR(calloc.get(color::RED_FG, color::BACKGROUND_BG));
-
-        let mut engine = game::Engine::new();
+    pub fn new(backend: BackendKind) -> Result<Self> {
+        let screen = try!(backend.open());
+        Ok(Ui::with_screen(screen, Path::new(".")))
+    }
 
-        nc::doupdate();
+    /// A UI drawing on `screen`, which it takes keys from too, and
+    /// keeping its files in `dir`
+    pub fn with_screen(screen: Screen, dir: &Path) -> Self {
+        let label_color = Style::new(color::LABEL_FG, color::BACKGROUND_BG);
+        let text_color = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        let text_gray_color = Style::new(color::GRAY[10], color::BACKGROUND_BG);
+        let green_color = Style::new(color::GREEN_FG, color::BACKGROUND_BG);
+        let red_color = Style::new(color::RED_FG, color::BACKGROUND_BG);
+
+        // there's no game until the player starts one; every run gets an
+        // engine of its own
+        let engine = game::Engine::new(rand::random());
+        let (keys, key_problems) = KeyMap::load(&dir.join(KEYS_PATH));
+        let (options, option_problems) = Options::load(&dir.join(OPTIONS_PATH));
 
         let mut ui = Ui {
-            calloc: RefCell::new(calloc),
-            windows: Windows::after_resize(),
-            mode: Mode::FullScreen(FSMode::Intro),
+            windows: Windows::new(&screen),
+            screen: screen,
+            mode: Mode::FullScreen(FSMode::Menu(0)),
             target_pos: None,
-            dot: UNICODE_DOT,
-            log: RefCell::new(VecDeque::new()),
+            options: options,
+            terminal_glyphs: GlyphSet::detect(),
+            log: RefCell::new(Messages::new()),
 
             label_color: label_color,
             text_color: text_color,
@@ -188,6 +482,10 @@ impl Ui {
             exit: false,
             needs_redraw: true,
             spawned: false,
+            told_recovering: false,
+            seed: None,
+            prompt_from: Mode::Normal,
+            prompt_text: String::new(),
 
             engine: engine,
             automoving: None,
@@ -196,31 +494,171 @@ impl Ui {
             after_action_delay: 0,
 
             game_action_queue: VecDeque::new(),
+            spectating: None,
+            wizard: false,
+            wiz_panic: false,
+            rank: None,
+            name: String::new(),
+            keys: keys,
+            last_target: None,
+            inv_page: 0,
+            final_map: false,
+            morgue: None,
+            explorer: None,
+            picking_travel: false,
+            sights: vec![],
+            statuses: vec![],
+            noises: Default::default(),
+            animations: vec![],
+            frame: None,
+            dir: dir.to_path_buf(),
+        };
+        for problem in key_problems.into_iter().chain(option_problems) {
+            ui.log(&format!("Ignored in {}", problem));
+        }
+        ui.display_menu();
+        ui
+    }
+
+    pub fn initial_spawn(&mut self, race: actor::Race, name: &str) {
+        let seed = self.seed.take().unwrap_or_else(rand::random);
+        self.engine = game::Engine::new(seed);
+        for bones in game::Bones::claim(&self.path(BONES_PATH), &mut rand::thread_rng()) {
+            self.engine.add_bones(bones);
+        }
+        self.engine.initial_spawn(race, name);
+        let (autosave, levels) = (self.path(AUTOSAVE_PATH), self.path(LEVELS_PATH));
+        self.engine.set_autosave(&autosave);
+        self.engine.spill_levels_to(&levels);
+        self.log(&format!("Welcome, {} the {}!", name, race.description()));
+        self.started();
+    }
+
+    /// Pick up the autosaved game where it was left
+    ///
+    /// Returns: false if it couldn't be loaded.
+    pub fn resume(&mut self) -> bool {
+        let replay = match game::Replay::load(&self.path(AUTOSAVE_PATH)) {
+            Ok(replay) => replay,
+            Err(e) => {
+                warn!("Can't resume from {}: {}", AUTOSAVE_PATH, e);
+                return false;
+            }
         };
-        ui.display_intro();
-        Ok(ui)
+
+        match game::Engine::resume(&replay) {
+            Ok(ref engine) if replay.race.is_none() || engine.player().is_dead() => false,
+            Ok(engine) => {
+                self.engine = engine;
+                let (autosave, levels) = (self.path(AUTOSAVE_PATH), self.path(LEVELS_PATH));
+                self.engine.set_autosave(&autosave);
+                self.engine.spill_levels_to(&levels);
+                self.started();
+                true
+            }
+            Err(e) => {
+                warn!("Can't resume from {}: {}", AUTOSAVE_PATH, e);
+                false
+            }
+        }
+    }
+
+    /// Watch a recorded game being played
+    pub fn spectate(&mut self, replay: &game::Replay) {
+        self.engine = game::Engine::new(replay.seed);
+        for bones in &replay.bones {
+            self.engine.add_bones(bones.clone());
+        }
+        self.game_action_queue = replay.actions.iter().map(|&(_, action)| action).collect();
+        self.spectating = Some(SPECTATE_DELAY);
+        if let Some(race) = replay.race {
+            self.engine.initial_spawn(race, &replay.name);
+            self.started();
+            self.mode_switch_to(Mode::Normal);
+        }
+    }
+
+    /// Play the game `seed` gives instead of a random one, next time a
+    /// game is started
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = Some(seed);
+    }
+
+    /// Allow the debug commands; any use makes the game not count
+    pub fn set_wizard(&mut self) {
+        self.wizard = true;
     }
 
-    pub fn initial_spawn(&mut self, race: actor::Race) {
-        self.engine.initial_spawn(race);
+    /// Don't show attacks and shots on the map as they happen
+    pub fn disable_animations(&mut self) {
+        self.options.animate = false;
+    }
+
+    /// Draw the map with `glyphs` instead of what suits the terminal
+    pub fn set_glyphs(&mut self, glyphs: GlyphSet) {
+        self.options.glyphs = Some(glyphs);
+    }
+
+    /// Where the file `name` the game keeps is
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// What the map is drawn with
+    fn glyphs(&self) -> GlyphSet {
+        self.options.glyphs.unwrap_or(self.terminal_glyphs)
+    }
+
+    /// Toggle `opt`, or move on to its next value, and keep it for
+    /// later games
+    fn change_option(&mut self, opt: Opt) {
+        self.options.cycle(opt);
+        if let Err(e) = self.options.save(&self.path(OPTIONS_PATH)) {
+            warn!("Can't save the options in {}: {}", OPTIONS_PATH, e);
+        }
+        self.redraw();
+    }
+
+    /// Run wizard command `f`, if wizard mode is on and it's the
+    /// player's turn
+    fn wizard_cmd<F>(&mut self, f: F)
+        where F: FnOnce(&mut game::Engine)
+    {
+        if !self.wizard || !self.engine.needs_player_input() {
+            return;
+        }
+        f(&mut self.engine);
+        let player_id = self.current_location().player_id();
+        self.engine_change(player_id);
+    }
+
+    fn started(&mut self) {
+        if self.wizard {
+            self.engine.enable_undo();
+        }
         let player_id = self.engine.current_location().player_id();
         self.engine_change(player_id);
         self.spawned = true;
     }
 
     pub fn screen_size(&self) -> (i32, i32) {
-        let mut max_x = 0;
-        let mut max_y = 0;
-        nc::getmaxyx(nc::stdscr, &mut max_y, &mut max_x);
-
-        (max_y, max_y)
+        self.screen.borrow().size()
     }
 
+    /// Lay the windows out again for the new screen size; everything is
+    /// drawn anew, whatever the mode
     pub fn resize(&mut self) {
-        self.windows = Windows::after_resize();
+        self.windows = Windows::new(&self.screen);
         self.redraw();
     }
 
+    /// Stop like Ctrl-Z does in a shell, and draw everything anew once
+    /// continued
+    fn suspend(&mut self) {
+        self.screen.borrow_mut().suspend();
+        self.resize();
+    }
+
     /// Mark the screen for redraw
     pub fn redraw(&mut self) {
         self.needs_redraw = true;
@@ -228,162 +666,290 @@ impl Ui {
 
     pub fn redraw_now(&mut self) {
         match self.mode {
-            Mode::Normal | Mode::Examine | Mode::Inventory(_) | Mode::Target(_) | Mode::GoTo => {
+            Mode::Normal | Mode::Examine | Mode::Inventory(_) | Mode::Target(_) | Mode::GoTo |
+            Mode::Monsters(_) | Mode::Prompt(_) => {
                 if let Mode::Inventory(_) = self.mode {
                     self.draw_inventory();
+                } else if let Mode::Monsters(selected) = self.mode {
+                    self.draw_monsters(selected);
+                } else if self.mode == Mode::GoTo {
+                    self.draw_destinations();
                 } else {
                     self.draw_map();
+                    if self.wiz_panic {
+                        panic!("wizard asked for a panic");
+                    }
+                    if self.options.minimap {
+                        self.draw_minimap();
+                    }
                 }
 
                 self.draw_log();
 
                 self.draw_stats();
             }
+            Mode::Overview => self.draw_overview(),
             Mode::FullScreen(fs_mode) => {
                 match fs_mode {
-                    FSMode::Help => {
-                        self.draw_help();
+                    FSMode::Help(topic, page) => {
+                        self.draw_help(topic, page);
                     }
-                    FSMode::Quit => {
-                        self.draw_quit();
+                    FSMode::Menu(selected) => {
+                        self.draw_menu(selected);
                     }
-                    FSMode::Intro => {
-                        self.draw_intro();
+                    FSMode::Resume => {
+                        self.draw_resume();
                     }
                     FSMode::PickRace => {
                         self.draw_pickrace();
                     }
+                    FSMode::EnterName(race) => {
+                        self.draw_enter_name(race);
+                    }
+                    FSMode::GameOver => {
+                        self.draw_game_over();
+                    }
+                    FSMode::HighScores => {
+                        self.draw_high_scores();
+                    }
+                    FSMode::MessageHistory(skip) => {
+                        self.draw_message_history(skip);
+                    }
+                    FSMode::Options(selected) => {
+                        self.draw_options(selected);
+                    }
                 }
             }
         }
 
-        let (max_x, max_y) = self.screen_size();
-
-        nc::mv(max_y - 1, max_x - 1);
-        // TODO: Is this needed?
-        let _ = std::io::stdout().flush();
+        self.screen.borrow_mut().flush();
     }
 
     pub fn is_automoving(&self) -> bool {
         self.automoving.is_some()
     }
 
-    pub fn should_stop_automoving(&self) -> bool {
+    /// Is anything in `events`, or in sight, worth stopping for
+    pub fn should_stop_automoving(&self, events: &[game::Event]) -> bool {
         let player = self.player();
         let cur_loc = self.current_location();
+        let player_id = cur_loc.player_id();
+        // the explorer goes for items and tells what else it stops for;
+        // travel and rest have no use for discoveries
+        let (guided, traveling) = match self.automoving {
+            Some(AutoMoveType::Explore) => (true, false),
+            Some(AutoMoveType::Travel(_)) |
+            Some(AutoMoveType::Rest(_)) => (true, true),
+            _ => (false, false),
+        };
+
+        let alarming = events.iter().any(|event| {
+            match *event {
+                game::Event::Attack { target, .. } => target == player_id,
+                game::Event::Discovered(game::Discovery::Item(_)) => !guided,
+                game::Event::Discovered(game::Discovery::Areas(_)) |
+                game::Event::Discovered(game::Discovery::Stairs) => !traveling,
+                game::Event::Heard { .. } => true,
+                _ => false,
+            }
+        });
 
-        !player.was_attacked_by.is_empty() || player.discovered_areas.iter().any(|_| true) ||
-        player.visible.iter().any(|&coord| {
+        alarming ||
+        !guided &&
+        player.perception.visible.iter().any(|&coord| {
             cur_loc.at(coord)
                    .actor_map_or(false, |a| a.race == actor::Race::Rat)
-        }) ||
-        player.discovered.iter().any(|&coord| {
-            cur_loc.at(coord)
-                   .item_map_or(false, |_| true)
-        }) ||
-        player.heard
-              .iter()
-              .any(|(c, _)| !player.sees(*c)) || player.discovered_stairs(cur_loc)
+        })
     }
 
-    pub fn automove_action(&self, movetype: AutoMoveType) -> AutoMoveAction {
-        let player = self.player();
-        let cur_loc = self.current_location();
+    pub fn automove_action(&mut self, movetype: AutoMoveType) -> AutoMoveAction {
         match movetype {
             AutoMoveType::Explore => self.autoexplore_action(),
-            AutoMoveType::GoTo(gototype) => self.goto_action(gototype),
-            AutoMoveType::Walk => {
-                if cur_loc.at(player.head()).tile().is_passable() {
-                    AutoMoveAction::Action(game::Action::Move(Angle::Forward))
-                } else {
-                    AutoMoveAction::Finish
-                }
-            }
+            AutoMoveType::Travel(dest) => self.travel_action(dest),
+            AutoMoveType::Rest(until) => self.rest_action(until),
+            AutoMoveType::Run => self.run_action(),
         }
     }
 
-    pub fn goto_action(&self, gototype: GoToType) -> AutoMoveAction {
-        let player = self.player();
-        let cur_loc = self.current_location();
+    pub fn run_action(&mut self) -> AutoMoveAction {
+        let mut explorer = match self.explorer.take() {
+            Some(explorer) => explorer,
+            None => game::Explorer::new(self.current_location(), self.player()),
+        };
+        let step = explorer.run(self.current_location(), self.player());
+        self.explorer = Some(explorer);
 
-        let start = player.pos.coord;
+        match step {
+            Step::Act(action) => AutoMoveAction::Action(action),
+            Step::Stop(Stop::Done) | Step::Stop(Stop::Blocked) => AutoMoveAction::Finish,
+            Step::Stop(stop) => AutoMoveAction::Stopped(stop),
+        }
+    }
 
-        let mut bfs = bfs::Traverser::new(|c| {
-                                              c == start ||
-                                              (cur_loc.at(c).tile().is_passable() &&
-                                               player.knows(c))
-                                          },
-                                          |c| {
-                                              cur_loc.at(c).tile().feature ==
-                                              Some(tile::Feature::Stairs)
-                                          },
-                                          start);
-
-        if let Some(dst) = bfs.find() {
-            if let Some(neigh) = bfs.backtrace_last(dst) {
-
-                if let Some(ndir) = player.pos.coord.direction_to_cw(neigh) {
-                    if ndir == player.pos.dir {
-                        if cur_loc.at(neigh).is_occupied() {
-                            AutoMoveAction::Blocked
-                        } else {
-                            AutoMoveAction::Action(game::Action::Move(Angle::Forward))
-                        }
-                    } else {
-                        AutoMoveAction::Action(game::Action::Turn(ndir - player.pos.dir))
-                    }
-                } else {
-                    AutoMoveAction::Finish
-                }
-            } else {
-                AutoMoveAction::Finish
+    pub fn autoexplore_action(&mut self) -> AutoMoveAction {
+        let mut explorer = match self.explorer.take() {
+            Some(explorer) => explorer,
+            None => game::Explorer::new(self.current_location(), self.player()),
+        };
+        let step = explorer.step(self.current_location(), self.player());
+        self.explorer = Some(explorer);
+
+        match step {
+            Step::Act(action) => AutoMoveAction::Action(action),
+            Step::Stop(Stop::Done) => AutoMoveAction::Finish,
+            Step::Stop(Stop::Blocked) => AutoMoveAction::Blocked,
+            Step::Stop(stop) => AutoMoveAction::Stopped(stop),
+        }
+    }
+
+    pub fn travel_action(&mut self, dest: Coordinate) -> AutoMoveAction {
+        let mut explorer = match self.explorer.take() {
+            Some(explorer) => explorer,
+            None => game::Explorer::new(self.current_location(), self.player()),
+        };
+        let step = explorer.travel(self.current_location(), self.player(), dest);
+        self.explorer = Some(explorer);
+
+        match step {
+            Step::Act(action) => AutoMoveAction::Action(action),
+            Step::Stop(Stop::Done) => AutoMoveAction::Finish,
+            Step::Stop(Stop::Blocked) => AutoMoveAction::Blocked,
+            Step::Stop(stop) => AutoMoveAction::Stopped(stop),
+        }
+    }
+
+    pub fn rest_action(&mut self, until: u64) -> AutoMoveAction {
+        if self.engine.turn() >= until {
+            return AutoMoveAction::Finish;
+        }
+        let mut explorer = match self.explorer.take() {
+            Some(explorer) => explorer,
+            None => game::Explorer::new(self.current_location(), self.player()),
+        };
+        let step = explorer.rest(self.current_location(), self.player());
+        self.explorer = Some(explorer);
+
+        match step {
+            Step::Act(action) => AutoMoveAction::Action(action),
+            Step::Stop(Stop::Done) | Step::Stop(Stop::Blocked) => AutoMoveAction::Finish,
+            Step::Stop(stop) => AutoMoveAction::Stopped(stop),
+        }
+    }
+
+    /// Rest for at most `REST_MAX` turns; not with a hostile in view,
+    /// nor when there's nothing to heal
+    fn start_rest(&mut self) {
+        let step = {
+            let (cur_loc, player) = (self.current_location(), self.player());
+            game::Explorer::new(cur_loc, player).rest(cur_loc, player)
+        };
+        match step {
+            Step::Stop(Stop::Hostile(race)) => {
+                self.log(&format!("You can't rest with a {} in view.", race.description()));
             }
-        } else {
-            AutoMoveAction::Blocked
+            Step::Stop(Stop::Blocked) => self.log("Resting won't heal you."),
+            Step::Stop(_) => self.log("You don't need a rest."),
+            Step::Act(_) => {
+                self.automoving = Some(AutoMoveType::Rest(self.engine.turn() + REST_MAX));
+                self.explorer = Some(game::Explorer::new(self.current_location(),
+                                                         self.player()));
+            }
+        }
+    }
+
+    /// Start walking to `dest` on its own
+    fn start_travel(&mut self, dest: Coordinate) {
+        if game::explore::route(self.current_location(), self.player(), dest).is_none() {
+            self.log("You know no way there.");
+            return;
         }
+        self.automoving = Some(AutoMoveType::Travel(dest));
+        self.explorer = Some(game::Explorer::new(self.current_location(), self.player()));
     }
 
-    pub fn autoexplore_action(&self) -> AutoMoveAction {
+    /// Places the travel menu offers: stairs, then rooms, nearest first
+    fn destinations(&self) -> Vec<Destination> {
         let player = self.player();
         let cur_loc = self.current_location();
-
         let start = player.pos.coord;
 
-        let mut bfs = bfs::Traverser::new(|c| c == start || cur_loc.at(c).tile().is_passable(),
-                                          |c| !player.knows(c),
-                                          start);
-
-        if let Some(dst) = bfs.find() {
-            if let Some(neigh) = bfs.backtrace_last(dst) {
-
-                let ndir = player.pos.coord.direction_to_cw(neigh).expect("bfs gave me trash");
-                if ndir == player.pos.dir {
-                    if cur_loc.at(neigh).is_occupied() {
-                        AutoMoveAction::Blocked
-                    } else {
-                        AutoMoveAction::Action(game::Action::Move(Angle::Forward))
-                    }
-                } else {
-                    AutoMoveAction::Action(game::Action::Turn(ndir - player.pos.dir))
-                }
-            } else {
-                AutoMoveAction::Finish
+        let mut stairs: Vec<(Coordinate, String)> = vec![];
+        for &coord in &player.perception.known {
+            let tile = cur_loc.at(coord).tile();
+            if tile.stairs_down().is_some() {
+                stairs.push((coord, "stairs down".to_string()));
+            } else if tile.stairs_up().is_some() {
+                stairs.push((coord, "stairs up".to_string()));
             }
-        } else {
-            AutoMoveAction::Finish
         }
+        stairs.sort_by_key(|&(c, ref name)| (name.clone(), start.distance(c), c.x, c.y));
+
+        let mut rooms: Vec<(Coordinate, String)> = vec![];
+        for &center in &player.perception.known_areas {
+            let name = match cur_loc.at(center).tile().area {
+                Some(area) => area.type_.to_string(),
+                None => continue,
+            };
+            let name = match util::Compass::between(start, center) {
+                Some(dir) => format!("{} to the {}", name, dir.description()),
+                None => format!("{} here", name),
+            };
+            rooms.push((center, name));
+        }
+        rooms.sort_by_key(|&(c, _)| (start.distance(c), c.x, c.y));
+
+        stairs.into_iter()
+              .chain(rooms)
+              .take(TRAVEL_MAX)
+              .map(|(coord, name)| {
+                  Destination {
+                      steps: game::explore::route(cur_loc, player, coord).map(|p| p.len()),
+                      coord: coord,
+                      name: name,
+                  }
+              })
+              .collect()
     }
 
     pub fn automoving_stop(&mut self) {
         self.automoving = None;
+        self.explorer = None;
         self.automoving_stopped_turn = self.engine.turn()
     }
 
     fn engine_change(&mut self, actor_id: actor::Id) {
-        self.update();
+        let events = self.engine.take_events_for_player();
+        self.update(&events);
+        if self.player().can_act() {
+            self.told_recovering = false;
+        }
+        self.log_status_changes();
+        self.play_animations();
+
+        if self.engine.result().is_some() && !self.is_game_over() {
+            self.automoving = None;
+            self.record_score();
+            self.leave_bones();
+            self.write_morgue();
+            self.mode_switch_to(Mode::FullScreen(FSMode::GameOver));
+        }
+
+        let player_id = self.current_location().player_id();
+        let locked = events.contains(&game::Event::Interacted {
+            id: player_id,
+            result: actor::InteractResult::ChestLocked,
+        });
+        if locked && self.spectating.is_none() {
+            self.ask(Question::ForceChest);
+        }
+
+        for feeling in self.engine.take_feelings() {
+            self.event(Event::Feeling(feeling));
+        }
 
         if self.automoving.is_some() {
-            if self.automoving_stopped_turn != self.engine.turn() && self.should_stop_automoving() {
+            if self.automoving_stopped_turn != self.engine.turn() &&
+               self.should_stop_automoving(&events) {
                 self.automoving_stop();
             }
         }
@@ -393,7 +959,12 @@ impl Ui {
             let player_id = cur_loc.player_id();
 
             if actor_id == player_id {
-                if self.is_automoving() {
+                if let Some(delay) = self.spectating {
+                    delay
+                } else if let Some(AutoMoveType::Rest(_)) = self.automoving {
+                    // nothing to watch but the clock
+                    1
+                } else if self.is_automoving() {
                     20
                 } else {
                     0
@@ -406,91 +977,453 @@ impl Ui {
         self.redraw();
     }
 
-    pub fn player(&self) -> &Actor {
-        let player_id = self.engine.current_location().player_id();
-        &self.engine.current_location().actors_byid[&player_id]
-    }
-    pub fn current_location(&self) -> &Location {
-        self.engine.current_location()
-    }
-
-    pub fn run_once(&mut self) {
-        if self.after_action_delay > 0 {
-            self.after_action_delay -= 1;
-        } else if self.spawned {
-            let player_id = self.current_location().player_id();
-
-            if self.engine.needs_player_input() {
-                if let Some(movetype) = self.automoving {
-                    match self.automove_action(movetype) {
-                        AutoMoveAction::Blocked => {
-                            match movetype {
-                                AutoMoveType::Explore | AutoMoveType::GoTo(_) => {
-                                    self.event(Event::Log(LogEvent::AutoExploreBlocked));
-                                }
-                                _ => {}
-                            }
-                            self.automoving_stop();
-                            self.redraw();
-                        }
-                        AutoMoveAction::Action(action) => {
-                            self.engine.player_act(action);
-                            self.engine_change(player_id);
-                        }
-                        AutoMoveAction::Finish => {
-                            match movetype {
-                                AutoMoveType::Explore => {
-                                    self.event(Event::Log(LogEvent::AutoExploreDone));
-                                }
-                                _ => {}
-                            }
-                            self.automoving_stop();
-                            self.redraw();
-                        }
-                    }
-                } else if let Some(action) = self.game_action_queue.pop_front() {
-                    self.engine.player_act(action);
-                    self.engine_change(player_id);
-                }
-            } else {
-                let actor_id = self.engine.one_actor_tick();
-                self.engine_change(actor_id);
+    /// Tell the player of each status that came or went
+    fn log_status_changes(&mut self) {
+        let now: Vec<&'static str> = self.player()
+                                         .active_statuses()
+                                         .iter()
+                                         .map(|&(name, _, _)| name)
+                                         .collect();
+        for name in &now {
+            if !self.statuses.contains(name) {
+                let s = format!("You're {}.", name.to_lowercase());
+                self.log_toned(&s, Tone::Bad, Topic::Danger);
             }
         }
-        {
-            self.input_handle();
-            if self.needs_redraw {
-                self.needs_redraw = false;
-                self.redraw_now();
+        for name in &self.statuses {
+            if !now.contains(name) {
+                self.log_on(&format!("You're no longer {}.", name.to_lowercase()),
+                            Topic::Danger);
             }
         }
+        self.statuses = now;
     }
 
-    pub fn run(&mut self) {
-        while !self.exit {
-            let start = chrono::Local::now();
-            self.run_once();
-            let end = chrono::Local::now();
-            if (end - start) < chrono::Duration::milliseconds(1) {
-                let nanosecs = (chrono::Duration::milliseconds(1) - (end - start))
-                                   .num_nanoseconds()
-                                   .unwrap();
-                assert!(nanosecs > 0);
-                thread::sleep(std::time::Duration::new(0, nanosecs as u32));
-            }
+    fn is_game_over(&self) -> bool {
+        match self.mode {
+            Mode::FullScreen(FSMode::GameOver) |
+            Mode::FullScreen(FSMode::HighScores) => true,
+            _ => false,
         }
     }
 
-    /// Handle input.
+    /// Put the player's game in the high scores, if it was played to
+    /// the end and without wizard commands
+    fn record_score(&mut self) {
+        let cause = match self.engine.result() {
+            Some(&game::GameResult::Died { ref cause }) => cause.clone(),
+            _ => return,
+        };
+        if !self.engine.is_scored() || self.spectating.is_some() {
+            return;
+        }
+
+        let entry = game::score::Entry {
+            name: self.engine.replay().name.clone(),
+            score: self.engine.score(),
+            result: cause,
+            seed: self.engine.replay().seed,
+            date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        };
+        match game::HighScores::add(&self.path(SCORES_PATH), entry) {
+            Ok(rank) => self.rank = rank,
+            Err(e) => warn!("Can't record the score in {}: {}", SCORES_PATH, e),
+        }
+    }
+
+    /// Let a later game be haunted by the player who just died
+    fn leave_bones(&mut self) {
+        if self.spectating.is_some() {
+            return;
+        }
+        if let Some(bones) = self.engine.bones() {
+            if let Err(e) = bones.save(&self.path(BONES_PATH)) {
+                warn!("Can't leave bones in {}: {}", BONES_PATH, e);
+            }
+        }
+    }
+
+    /// Dump the character of a game that ended in death to a file in
+    /// `MORGUE_PATH`, named after the time and the player
+    fn write_morgue(&mut self) {
+        match self.engine.result() {
+            Some(&game::GameResult::Died { .. }) => {}
+            _ => return,
+        }
+        if self.spectating.is_some() {
+            return;
+        }
+
+        let name: String = self.engine
+                               .replay()
+                               .name
+                               .chars()
+                               .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                               .collect();
+        let file_name = format!("{}-{}.txt",
+                                chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                                if name.is_empty() { "player" } else { &name[..] });
+
+        let lines = self.morgue_lines();
+        self.morgue = Some(match save_lines(&self.path(MORGUE_PATH), &file_name, &lines) {
+            Ok(path) => format!("Morgue file: {}", path.display()),
+            Err(e) => {
+                warn!("Can't write the morgue file in {}: {}", MORGUE_PATH, e);
+                format!("Can't write the morgue file: {}", e)
+            }
+        });
+    }
+
+    /// Draw the screen once more into memory and write it to
+    /// `SCREENSHOT_PATH`, as text and, if the options say so, with the
+    /// colors too
+    fn screenshot(&mut self) {
+        let (w, h) = self.screen_size();
+        let (capture, cells) = Capture::open(w, h);
+        let screen = mem::replace(&mut *self.screen.borrow_mut(), capture);
+        self.redraw_now();
+        *self.screen.borrow_mut() = screen;
+
+        let cells = cells.borrow();
+        let mut files = vec![("txt", screenshot::text(&cells, w))];
+        if self.options.screenshot_colors {
+            files.push(("ans", screenshot::ansi(&cells, w)));
+        }
+        let stem = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        match screenshot::save(&self.path(SCREENSHOT_PATH), &stem, &files) {
+            Ok(paths) => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                self.log(&format!("Screenshot written to {}.", paths.join(" and ")));
+            }
+            Err(e) => {
+                self.log(&format!("Can't write the screenshot in {}: {}", SCREENSHOT_PATH, e))
+            }
+        }
+    }
+
+    /// Write the current level out whole to `DUMP_PATH`, once in each
+    /// format, named after the seed, the level and the turn
+    fn wiz_dump(&mut self) {
+        let name = format!("{}-{}-{}",
+                           self.engine.replay().seed,
+                           self.current_location().id.to_string().replace(':', "-"),
+                           self.engine.turn());
+        let mut paths = vec![];
+        for &format in &[Format::Text, Format::Json] {
+            let lines: Vec<String> = export::export(self.current_location(), format)
+                                         .lines()
+                                         .map(|l| l.to_string())
+                                         .collect();
+            let file_name = format!("{}.{}", name, format.extension());
+            match save_lines(&self.path(DUMP_PATH), &file_name, &lines) {
+                Ok(path) => paths.push(path.display().to_string()),
+                Err(e) => {
+                    self.log(&format!("Can't write {} in {}: {}", file_name, DUMP_PATH, e));
+                    return;
+                }
+            }
+        }
+        self.log(&format!("Level written to {}.", paths.join(" and ")));
+    }
+
+    /// Write everything about the actor at `coord` to the debug log, at
+    /// `info` so it's there whatever `RHEX_LOG` says
+    fn wiz_log(&mut self, coord: Coordinate) {
+        let state = {
+            let loc = self.current_location();
+            loc.actors_coord_to_id.get(&coord).map(|&id| {
+                format!("actor {} on turn {}: {:#?}", id, loc.turn, loc.actors_byid[&id])
+            })
+        };
+        match state {
+            Some(state) => {
+                info!(target: "wizard", "{}", state);
+                self.log("Written to debug.log.");
+            }
+            None => self.log("Nobody there."),
+        }
+    }
+
+    /// Where the player is, as the sidebar, overview and morgue head
+    /// it
+    fn location_header(&self) -> String {
+        location_header(self.current_location(), self.player().pos.coord)
+    }
+
+    /// Everything about the finished game worth keeping, as text
+    fn morgue_lines(&self) -> Vec<String> {
+        let player = self.player();
+        let mut lines = vec![];
+
+        lines.push(format!("{} the {}", self.engine.replay().name, player.race.description()));
+        if let Some(&game::GameResult::Died { ref cause }) = self.engine.result() {
+            lines.push(format!("Died: {}", cause));
+        }
+        lines.push(self.location_header());
+        lines.push(format!("Deepest level: {}", self.engine.deepest() + 1));
+        lines.push(format!("Turns: {}", self.engine.turn()));
+        lines.push(format!("Seed: {}", self.engine.replay().seed));
+        if self.engine.is_scored() {
+            lines.push(format!("Score: {}", self.engine.score()));
+        } else {
+            lines.push("Not scored: wizard mode was used".to_string());
+        }
+        lines.push(String::new());
+
+        let stats = &player.stats;
+        lines.push(format!("Str {:2}  DMG {:2}  ACC {:2}",
+                           stats.base.str_,
+                           stats.melee_dmg,
+                           stats.melee_acc));
+        lines.push(format!("Int {:2}   AC {:2}   EV {:2}",
+                           stats.base.int,
+                           stats.base.ac,
+                           stats.base.ev));
+        lines.push(format!("Dex {:2}   Au {}", stats.base.dex, player.gold));
+        lines.push(format!("HP {}/{}  MP {}/{}  SP {}/{}",
+                           player.hp,
+                           stats.base.max_hp,
+                           player.mp,
+                           stats.base.max_mp,
+                           player.sp,
+                           stats.base.max_sp));
+        lines.push(String::new());
+
+        lines.extend(self.inventory_lines());
+        lines.push(String::new());
+
+        lines.push("Kills:".to_string());
+        let kills = self.engine.kills();
+        if kills.is_empty() {
+            lines.push(" none".to_string());
+        }
+        for (race, n) in kills {
+            lines.push(format!(" {:4} {}", n, race.description()));
+        }
+        lines.push(String::new());
+
+        lines.push("Last messages:".to_string());
+        let log = self.log.borrow();
+        for entry in log.iter().take(MORGUE_LOG).collect::<Vec<_>>().into_iter().rev() {
+            lines.push(format!(" {:6} {}", entry.turn, entry.description()));
+        }
+        lines.push(String::new());
+
+        lines.push("Map:".to_string());
+        let map = &self.windows.map;
+        lines.extend(self.map_text(map.width(), map.height()));
+        lines
+    }
+
+    /// The map around the player as the player knows it, `width` by
+    /// `height` characters, laid out like `draw_map` does
+    fn map_text(&self, width: i32, height: i32) -> Vec<String> {
+        let player = self.player();
+        let cur_loc = self.current_location();
+        let (vpx, vpy) = player.pos.coord.to_pixel_integer(SPACING);
+        let glyphs = self.glyphs().glyphs();
+
+        let mut lines = vec![];
+        for vy in 0..height {
+            let mut line = String::new();
+            for vx in 0..width {
+                let (cvx, cvy) = (vx - width / 2 + vpx, vy - height / 2 + vpy);
+                let (c, off) = Coordinate::from_pixel_integer(SPACING, (cvx, cvy));
+
+                let glyph = if off == (0, 0) {
+                    let visible = player.sees(c);
+                    if visible && cur_loc.at(c).is_occupied() {
+                        glyphs.actor(cur_loc.at(c).actor_map_or(Race::Rat, |a| a.race))
+                    } else if visible && cur_loc.at(c).item().is_some() {
+                        glyphs.item(cur_loc.at(c).item().unwrap().category())
+                    } else if visible && cur_loc.is_burning(c) {
+                        glyphs.fire
+                    } else if player.knows(c) {
+                        let t = cur_loc.at(c).tile();
+                        self.tile_glyph(Some(t.type_), t.feature(), true, self.walls_around(c)).2
+                    } else {
+                        " "
+                    }
+                } else {
+                    // glue between two tiles, like on the screen
+                    let (c2, _) = Coordinate::from_pixel_integer(SPACING, (cvx + 1, cvy));
+                    if player.knows(c) && player.knows(c2) {
+                        let (t1, t2) = (cur_loc.at(c).tile(), cur_loc.at(c2).tile());
+                        let t = if t1.ascii_expand() > t2.ascii_expand() { t1 } else { t2 };
+                        self.tile_glyph(Some(t.type_), None, false, self.walls_beside(c, c2)).2
+                    } else {
+                        " "
+                    }
+                };
+                line.push_str(glyph);
+            }
+            lines.push(line.trim_right().to_string());
+        }
+        lines
+    }
+
+    /// Walls the player knows of around the wall at `c`, if the glyph
+    /// set joins them
+    fn walls_around(&self, c: Coordinate) -> Walls {
+        let mut walls = Walls::default();
+        if !self.glyphs().joins_walls() || !self.knows_wall(c) {
+            return walls;
+        }
+
+        let (x, y) = c.to_pixel_integer(SPACING);
+        for &n in &c.neighbors() {
+            if !self.knows_wall(n) {
+                continue;
+            }
+            let (nx, ny) = n.to_pixel_integer(SPACING);
+            if ny < y {
+                walls.up = true;
+            } else if ny > y {
+                walls.down = true;
+            } else if nx < x {
+                walls.left = true;
+            } else {
+                walls.right = true;
+            }
+        }
+        walls
+    }
+
+    /// Walls the player knows of on either side of the glue between
+    /// `left` and `right`
+    fn walls_beside(&self, left: Coordinate, right: Coordinate) -> Walls {
+        Walls {
+            left: self.knows_wall(left),
+            right: self.knows_wall(right),
+            ..Default::default()
+        }
+    }
+
+    fn knows_wall(&self, c: Coordinate) -> bool {
+        (self.final_map || self.player().knows(c)) &&
+        self.current_location().at(c).tile().type_ == tile::Wall
+    }
+
+    pub fn player(&self) -> &Actor {
+        let player_id = self.engine.current_location().player_id();
+        &self.engine.current_location().actors_byid[&player_id]
+    }
+    pub fn current_location(&self) -> &Location {
+        self.engine.current_location()
+    }
+
+    pub fn run_once(&mut self) {
+        if self.after_action_delay > 0 {
+            self.after_action_delay -= 1;
+        } else if self.spawned && !self.log_more() {
+            let player_id = self.current_location().player_id();
+
+            if self.engine.needs_player_input() {
+                if let Some(movetype) = self.automoving {
+                    match self.automove_action(movetype) {
+                        AutoMoveAction::Blocked => {
+                            match movetype {
+                                AutoMoveType::Explore | AutoMoveType::Travel(_) => {
+                                    self.event(Event::Log(LogEvent::AutoExploreBlocked));
+                                }
+                                _ => {}
+                            }
+                            self.automoving_stop();
+                            self.redraw();
+                        }
+                        AutoMoveAction::Action(action) => {
+                            self.engine.player_act(action);
+                            self.engine_change(player_id);
+                        }
+                        AutoMoveAction::Stopped(stop) => {
+                            self.event(Event::Log(LogEvent::AutoMoveStopped(stop)));
+                            self.automoving_stop();
+                            self.redraw();
+                        }
+                        AutoMoveAction::Finish => {
+                            match movetype {
+                                AutoMoveType::Explore => {
+                                    self.event(Event::Log(LogEvent::AutoExploreDone));
+                                }
+                                AutoMoveType::Rest(_) => self.event(Event::Log(LogEvent::RestDone)),
+                                _ => {}
+                            }
+                            self.automoving_stop();
+                            self.redraw();
+                        }
+                    }
+                } else if let Some(action) = self.game_action_queue.pop_front() {
+                    self.engine.player_act(action);
+                    self.engine_change(player_id);
+                }
+            } else if !self.player().is_dead() {
+                let actor_id = self.engine.one_actor_tick();
+                self.engine_change(actor_id);
+            }
+        }
+        {
+            self.input_handle();
+            if self.needs_redraw {
+                self.needs_redraw = false;
+                self.redraw_now();
+            }
+        }
+    }
+
+    /// Play until the player quits, or there are no keys left to
+    /// press and nothing goes on without them
+    pub fn run(&mut self) {
+        while !self.exit {
+            if !self.screen.borrow().has_input() && self.waits_for_key() {
+                break;
+            }
+            let start = chrono::Local::now();
+            self.run_once();
+            let end = chrono::Local::now();
+            if (end - start) < chrono::Duration::milliseconds(1) {
+                let nanosecs = (chrono::Duration::milliseconds(1) - (end - start))
+                                   .num_nanoseconds()
+                                   .unwrap();
+                assert!(nanosecs > 0);
+                thread::sleep(std::time::Duration::new(0, nanosecs as u32));
+            }
+        }
+    }
+
+    /// Nothing happens until a key is pressed
+    fn waits_for_key(&self) -> bool {
+        if self.needs_redraw || self.after_action_delay > 0 {
+            return false;
+        }
+        !self.spawned || self.log_more() || self.player().is_dead() ||
+        (self.engine.needs_player_input() && self.automoving.is_none() &&
+         self.game_action_queue.is_empty())
+    }
+
+    fn read_key(&self) -> Option<i32> {
+        self.screen.borrow_mut().read_key()
+    }
+
+    /// Handle input.
     pub fn input_handle(&mut self) {
         loop {
-            let ch = nc::getch();
-            if ch == nc::KEY_RESIZE {
+            let ch = match self.read_key() {
+                Some(ch) => ch,
+                None => return,
+            };
+            if ch == KEY_RESIZE {
                 self.resize();
-                self.redraw();
             }
-            if ch == -1 {
-                return;
+            if ch == KEY_SUSPEND {
+                self.suspend();
+                continue;
+            }
+            // the backend already waited for the rest of a sequence it
+            // knows; more input right behind ESC is one it doesn't (e.g.
+            // Alt + key), which must not count as a bare ESC
+            if ch == KEY_ESC && self.read_key().is_some() {
+                while self.read_key().is_some() {}
+                continue;
             }
             if self.automoving.is_some() {
                 self.automoving_stop();
@@ -506,6 +1439,9 @@ impl Ui {
     }
 
     fn mode_switch_to(&mut self, mode: Mode) {
+        if mode == Mode::Examine && self.mode != Mode::Examine {
+            self.sights = self.worth_a_look();
+        }
         self.mode = mode;
         self.redraw();
     }
@@ -526,6 +1462,77 @@ impl Ui {
         self.action_push(game::Action::Charge)
     }
 
+    /// Ask `question` over the log window; the mode it's asked in comes
+    /// back once it's answered, or on Escape
+    fn ask(&mut self, question: Question) {
+        match self.mode {
+            Mode::Prompt(_) => {}
+            mode => self.prompt_from = mode,
+        }
+        self.prompt_text.clear();
+        self.mode_switch_to(Mode::Prompt(question));
+    }
+
+    /// Do what the player answered to `question`
+    fn answer(&mut self, question: Question, answer: Answer) {
+        match (question, answer) {
+            (Question::Quit, Answer::Yes) => {
+                self.engine.quit();
+                self.mode_switch_to(Mode::FullScreen(FSMode::GameOver));
+            }
+            (Question::ForceChest, Answer::Key('b')) => self.queue_bash(),
+            (Question::CloseDoor, Answer::Direction(dir)) => {
+                self.action_push(game::Action::Close(dir))
+            }
+            (Question::SpawnItem(coord), Answer::Text(text)) => {
+                let item = if text.is_empty() {
+                    None
+                } else {
+                    match item::Item::decode(&text) {
+                        Some(item) => Some(item),
+                        None => {
+                            self.log(&format!("No such item: {}.", text));
+                            return;
+                        }
+                    }
+                };
+                self.wizard_cmd(|e| e.debug_spawn_item(coord, item));
+            }
+            _ => {}
+        }
+    }
+
+    /// Close the only open door around, or ask which one
+    fn close_door(&mut self) {
+        let doors: Vec<Direction> = {
+            let loc = self.current_location();
+            let center = self.player().pos.coord;
+            Direction::all()
+                .iter()
+                .cloned()
+                .filter(|&dir| {
+                    let t = loc.at(center + dir).tile();
+                    t.door_state() == Some(true) && !t.is_door_broken()
+                })
+                .collect()
+        };
+        match doors.len() {
+            0 => self.queue_close(),
+            1 => self.action_push(game::Action::Close(doors[0])),
+            _ => self.ask(Question::CloseDoor),
+        }
+    }
+
+    pub fn queue_close(&mut self) {
+        let dir = self.player().pos.dir;
+        self.action_push(game::Action::Close(dir))
+    }
+
+    pub fn queue_bash(&mut self) {
+        let dir = self.player().pos.dir;
+        self.action_push(game::Action::Bash(dir))
+    }
+
     pub fn queue_pick(&mut self) {
         self.action_push(game::Action::Pick)
     }
@@ -534,6 +1541,173 @@ impl Ui {
         self.action_push(game::Action::Ranged(coord))
     }
 
+    /// Hostile actors the player sees, nearest first
+    fn visible_hostiles(&self) -> Vec<actor::Id> {
+        let loc = self.current_location();
+        self.visible_actors().into_iter().filter(|id| loc.actors_byid[id].is_hostile()).collect()
+    }
+
+    /// Everybody the player sees, nearest first
+    fn visible_actors(&self) -> Vec<actor::Id> {
+        let loc = self.current_location();
+        let center = self.player().pos.coord;
+        let mut ids = loc.actors_visible_by(loc.player_id());
+        ids.sort_by_key(|id| (loc.actors_byid[id].pos.coord.distance(center), *id));
+        ids
+    }
+
+    /// What's worth a look: the actors in sight, then the items in
+    /// sight, then the stairs known, each nearest first
+    fn worth_a_look(&self) -> Vec<Coordinate> {
+        let loc = self.current_location();
+        let player = self.player();
+        let center = player.pos.coord;
+        let nearest_first = |mut coords: Vec<Coordinate>| {
+            coords.sort_by_key(|c| (c.distance(center), c.x, c.y));
+            coords
+        };
+
+        let mut sights: Vec<Coordinate> = self.visible_actors()
+                                              .iter()
+                                              .map(|id| loc.actors_byid[id].pos.coord)
+                                              .collect();
+        sights.extend(nearest_first(player.perception.visible
+                                          .iter()
+                                          .cloned()
+                                          .filter(|&c| loc.at(c).item().is_some())
+                                          .collect()));
+        sights.extend(nearest_first(player.perception.known
+                                          .iter()
+                                          .cloned()
+                                          .filter(|&c| {
+                                              let t = loc.at(c).tile();
+                                              t.stairs_down().is_some() || t.stairs_up().is_some()
+                                          })
+                                          .collect()));
+
+        let mut seen = HashSet::new();
+        sights.retain(|&c| c != center && seen.insert(c));
+        sights
+    }
+
+    /// The examine cursor with its head on `coord`, looking the way the
+    /// player does
+    fn examining(&self, coord: Coordinate) -> Position {
+        let dir = self.player().pos.dir;
+        Position::new(coord, dir) + (dir + Angle::Back).to_coordinate()
+    }
+
+    /// Where the last target stands, if the player still sees it
+    fn last_target_coord(&self) -> Option<Coordinate> {
+        let loc = self.current_location();
+        match self.last_target.and_then(|id| loc.actors_byid.get(&id)) {
+            Some(a) if !a.is_dead() && self.player().sees(a.pos.coord) => Some(a.pos.coord),
+            _ => None,
+        }
+    }
+
+    /// Aim at the last target if it's still in sight, else at the
+    /// nearest hostile, else in front of the player
+    fn start_targeting(&mut self, mode: TargetMode) {
+        let player = self.player().pos;
+        let coord = self.last_target_coord().or_else(|| {
+            let loc = self.current_location();
+            self.visible_hostiles().first().map(|id| loc.actors_byid[id].pos.coord)
+        });
+        self.target_pos = Some(match coord {
+            Some(coord) => Position::new(coord, player.dir),
+            None => player + player.dir.to_coordinate(),
+        });
+        self.mode_switch_to(Mode::Target(mode));
+    }
+
+    fn inventory(&mut self, mode: InvMode) {
+        self.inv_page = 0;
+        self.mode_switch_to(Mode::Inventory(mode));
+    }
+
+    /// Item with letter `ch` the player has, and whether it's equipped
+    fn inventory_item(&self, ch: char) -> Option<(&item::Item, bool)> {
+        let player = self.player();
+        if let Some(item) = player.items_backpack.get(&ch) {
+            return Some((&**item, false));
+        }
+        player.items_equipped
+              .values()
+              .find(|&&(item_ch, _)| item_ch == ch)
+              .map(|&(_, ref item)| (&**item, true))
+    }
+
+    fn item_action_allowed(&self, ch: char, action: ItemAction) -> bool {
+        let (item, equipped) = match self.inventory_item(ch) {
+            Some(found) => found,
+            None => return false,
+        };
+        let stuck = equipped && item.is_cursed();
+        match action {
+            ItemAction::Drop | ItemAction::Throw => !stuck,
+            ItemAction::Equip => item.slot().is_some() && !stuck,
+            ItemAction::Use => item.is_usable(),
+        }
+    }
+
+    /// Do `action` with item `ch`, unless it makes no sense for it
+    fn item_action(&mut self, ch: char, action: ItemAction) {
+        if !self.item_action_allowed(ch, action) {
+            self.log(match action {
+                ItemAction::Drop | ItemAction::Throw => "You can't let go of that.",
+                ItemAction::Equip => "You can't equip that.",
+                ItemAction::Use => "You can't use that.",
+            });
+            return;
+        }
+        match action {
+            ItemAction::Drop => self.queue_drop(ch),
+            // using is what equipping does to consumables
+            ItemAction::Equip | ItemAction::Use => self.queue_equip(ch),
+            ItemAction::Throw => {
+                self.start_targeting(TargetMode::Throw(ch));
+                return;
+            }
+        }
+        self.mode_switch_to(Mode::Normal);
+    }
+
+    /// Lines of the inventory list, equipped items first, sorted by letter
+    fn inventory_lines(&self) -> Vec<String> {
+        let player = self.player();
+        let mut lines = vec![];
+
+        if !player.items_equipped.is_empty() {
+            let mut equipped: Vec<_> = player.items_equipped.iter().collect();
+            equipped.sort_by_key(|&(_, &(ch, _))| ch);
+            lines.push("Equipped:".to_string());
+            for (slot, &(ch, ref i)) in equipped {
+                lines.push(format!(" {} - {} [{:?}]", ch, i.description(), slot));
+            }
+            lines.push(String::new());
+        }
+
+        lines.push("Inventory:".to_string());
+        let mut backpack: Vec<_> = player.items_backpack.iter().collect();
+        backpack.sort_by_key(|&(&ch, _)| ch);
+        for (ch, i) in backpack {
+            lines.push(format!(" {} - {}", ch, i.description()));
+        }
+        lines
+    }
+
+    /// Lines of the inventory list that fit on a page
+    fn inventory_page_len(&self) -> usize {
+        // the last line tells the page
+        cmp::max(1, self.windows.map.height() - 2) as usize
+    }
+
+    fn inventory_pages(&self) -> usize {
+        let (lines, page_len) = (self.inventory_lines().len(), self.inventory_page_len());
+        cmp::max(1, (lines + page_len - 1) / page_len)
+    }
+
     pub fn queue_wait(&mut self) {
         self.action_push(game::Action::Wait)
     }
@@ -542,6 +1716,14 @@ impl Ui {
         self.action_push(game::Action::Descend)
     }
 
+    pub fn queue_ascend(&mut self) {
+        self.action_push(game::Action::Ascend)
+    }
+
+    pub fn queue_interact(&mut self) {
+        self.action_push(game::Action::Interact)
+    }
+
     pub fn queue_equip(&mut self, ch: char) {
         self.action_push(game::Action::Equip(ch))
     }
@@ -551,34 +1733,205 @@ impl Ui {
     }
 
     pub fn input_handle_key(&mut self, ch: i32) {
+        if let Some(delay) = self.spectating {
+            match ch {
+                KEY_PLUS => self.spectating = Some(delay / 2),
+                KEY_MINUS => self.spectating = Some(cmp::max(1, delay * 2)),
+                KEY_LOWQ => self.exit = true,
+                _ => {}
+            }
+            return;
+        }
+
+        if self.log_more() {
+            let page = self.log_page();
+            self.log.borrow_mut().read(page);
+            self.redraw();
+            return;
+        }
+        self.log.borrow_mut().read_all();
+
+        // a finished game only takes keys on its own screens and the
+        // final map
+        let finished = self.engine.result().is_some();
         match self.mode {
-            Mode::FullScreen(fs_mode) => {
+            Mode::FullScreen(_) => {}
+            Mode::Examine if self.final_map => {}
+            _ if finished => return,
+            _ => {}
+        }
+
+        match self.mode {
+            Mode::FullScreen(fs_mode) => {
                 match fs_mode {
-                    FSMode::Quit => {
-                        match ch {
-                            KEY_LOWY | KEY_CAPY => self.exit = true,
-                            _ => self.mode_switch_to(Mode::Normal),
+                    FSMode::Menu(selected) => {
+                        let entry = match self.keys.command(ch) {
+                            Some(Command::Forward) => {
+                                let selected = (selected + MENU.len() - 1) % MENU.len();
+                                self.mode_switch_to(Mode::FullScreen(FSMode::Menu(selected)));
+                                return;
+                            }
+                            Some(Command::Back) => {
+                                let selected = (selected + 1) % MENU.len();
+                                self.mode_switch_to(Mode::FullScreen(FSMode::Menu(selected)));
+                                return;
+                            }
+                            Some(Command::Confirm) => MENU[selected].0,
+                            _ => return,
+                        };
+                        match entry {
+                            MenuEntry::NewGame if self.path(AUTOSAVE_PATH).exists() => {
+                                self.mode_switch_to(Mode::FullScreen(FSMode::Resume))
+                            }
+                            MenuEntry::NewGame => {
+                                self.mode_switch_to(Mode::FullScreen(FSMode::PickRace))
+                            }
+                            MenuEntry::Continue => {
+                                if self.path(AUTOSAVE_PATH).exists() && self.resume() {
+                                    self.mode_switch_to(Mode::Normal)
+                                }
+                            }
+                            MenuEntry::HighScores => {
+                                self.mode_switch_to(Mode::FullScreen(FSMode::HighScores))
+                            }
+                            MenuEntry::Options => {
+                                self.mode_switch_to(Mode::FullScreen(FSMode::Options(0)))
+                            }
+                            MenuEntry::Help => {
+                                let help = FSMode::Help(HelpTopic::Game, 0);
+                                self.mode_switch_to(Mode::FullScreen(help))
+                            }
+                            MenuEntry::Quit => self.exit = true,
                         }
                     }
-                    FSMode::Intro => {
+                    FSMode::Resume => {
                         match ch {
+                            KEY_LOWY | KEY_CAPY if self.resume() => {
+                                self.mode_switch_to(Mode::Normal)
+                            }
                             _ => self.mode_switch_to(Mode::FullScreen(FSMode::PickRace)),
                         }
                     }
                     FSMode::PickRace => {
+                        let race = match ch {
+                            KEY_LOWA => Race::Human,
+                            KEY_LOWB => Race::Elf,
+                            KEY_LOWC => Race::Dwarf,
+                            KEY_ESC => return self.display_menu(),
+                            _ => return,
+                        };
+                        self.name.clear();
+                        self.mode_switch_to(Mode::FullScreen(FSMode::EnterName(race)));
+                    }
+                    FSMode::EnterName(race) => {
                         match ch {
-                            KEY_LOWA => {
-                                self.initial_spawn(Race::Human);
+                            KEY_ENTER | KEY_ESC => {
+                                if ch == KEY_ESC || self.name.trim().is_empty() {
+                                    self.name = player_name();
+                                }
+                                let name = self.name.trim().to_string();
+                                self.initial_spawn(race, &name);
                                 self.mode_switch_to(Mode::Normal);
                             }
-                            KEY_LOWB => {
-                                self.initial_spawn(Race::Elf);
-                                self.mode_switch_to(Mode::Normal);
+                            KEY_BACKSPACE | KEY_DEL | KEY_CTRLH => {
+                                self.name.pop();
                             }
-                            KEY_LOWC => {
-                                self.initial_spawn(Race::Dwarf);
-                                self.mode_switch_to(Mode::Normal);
+                            0x20...0x7e if self.name.len() < NAME_MAX => {
+                                self.name.push(ch as u8 as char);
+                            }
+                            _ => {}
+                        }
+                        self.redraw();
+                    }
+                    FSMode::GameOver => {
+                        // other keys are ignored, not to skip the screen by accident
+                        match ch {
+                            KEY_LOWV if self.player().is_dead() => {
+                                self.final_map = true;
+                                self.target_pos = None;
+                                self.mode_switch_to(Mode::Examine);
+                            }
+                            KEY_LOWS => {
+                                self.mode_switch_to(Mode::FullScreen(FSMode::HighScores))
+                            }
+                            KEY_LOWM => self.to_menu(),
+                            KEY_LOWQ | KEY_ESC => self.exit = true,
+                            _ => {}
+                        }
+                    }
+                    FSMode::MessageHistory(skip) => {
+                        // lines left for messages, between the title and the help
+                        let page = cmp::max(1, self.windows.full.height() - 4);
+                        let page = page as usize;
+                        let last = self.log.borrow().len().saturating_sub(1);
+                        let skip = match (ch, self.keys.command(ch)) {
+                            (KEY_NPAGE, _) => Some(skip + page),
+                            (KEY_PPAGE, _) => Some(skip.saturating_sub(page)),
+                            (_, Some(Command::Back)) => Some(skip + 1),
+                            (_, Some(Command::Forward)) => Some(skip.saturating_sub(1)),
+                            (_, Some(Command::Cancel)) |
+                            (_, Some(Command::Quit)) => None,
+                            _ => Some(skip),
+                        };
+                        match skip {
+                            Some(skip) => {
+                                let skip = cmp::min(skip, last);
+                                let mode = FSMode::MessageHistory(skip);
+                                self.mode_switch_to(Mode::FullScreen(mode));
+                            }
+                            None => self.mode_switch_to(Mode::Normal),
+                        }
+                    }
+                    FSMode::Help(topic, page) => {
+                        let last = self.help_pages(topic).len() - 1;
+                        let page = match (ch, self.keys.command(ch)) {
+                            (KEY_NPAGE, _) | (KEY_SPACE, _) => Some(cmp::min(page + 1, last)),
+                            (KEY_PPAGE, _) => Some(page.saturating_sub(1)),
+                            (_, Some(Command::Cancel)) |
+                            (_, Some(Command::Quit)) |
+                            (_, Some(Command::Help)) => None,
+                            _ => Some(page),
+                        };
+                        match (page, topic) {
+                            (Some(page), _) => {
+                                self.mode_switch_to(Mode::FullScreen(FSMode::Help(topic, page)))
+                            }
+                            (None, HelpTopic::Game) if !self.spawned => self.display_menu(),
+                            (None, HelpTopic::Game) => self.mode_switch_to(Mode::Normal),
+                            (None, HelpTopic::Examine) => self.mode_switch_to(Mode::Examine),
+                            (None, HelpTopic::Inventory) => {
+                                self.mode_switch_to(Mode::Inventory(InvMode::View))
+                            }
+                        }
+                    }
+                    FSMode::HighScores => {
+                        if self.spawned {
+                            self.mode_switch_to(Mode::FullScreen(FSMode::GameOver))
+                        } else {
+                            self.display_menu()
+                        }
+                    }
+                    FSMode::Options(selected) => {
+                        let len = options::OPTS.len();
+                        match (ch, self.keys.command(ch)) {
+                            (_, Some(Command::Forward)) => {
+                                let selected = (selected + len - 1) % len;
+                                self.mode_switch_to(Mode::FullScreen(FSMode::Options(selected)))
+                            }
+                            (_, Some(Command::Back)) => {
+                                let selected = (selected + 1) % len;
+                                self.mode_switch_to(Mode::FullScreen(FSMode::Options(selected)))
+                            }
+                            (KEY_SPACE, _) |
+                            (_, Some(Command::Confirm)) => {
+                                self.change_option(options::OPTS[selected])
+                            }
+                            (_, Some(Command::Cancel)) |
+                            (_, Some(Command::Quit)) if self.spawned => {
+                                self.mode_switch_to(Mode::Normal)
                             }
+                            (_, Some(Command::Cancel)) |
+                            (_, Some(Command::Quit)) => self.display_menu(),
                             _ => {}
                         }
                     }
@@ -590,265 +1943,733 @@ impl Ui {
                 }
             }
             Mode::Normal => {
-                match ch {
-                    KEY_LOWH|nc::KEY_LEFT => self.queue_turn(Left),
-                    KEY_LOWL|nc::KEY_RIGHT => self.queue_turn(Right),
-                    KEY_LOWK|nc::KEY_UP => self.queue_move(Forward),
-                    KEY_LOWC => self.queue_charge(),
-                    KEY_LOWU => self.queue_spin(Left),
-                    KEY_LOWI => self.queue_spin(Right),
-                    KEY_CAPH => self.queue_move(Left),
-                    KEY_CAPL => self.queue_move(Right),
-                    KEY_LOWJ|nc::KEY_DOWN => self.queue_move(Back),
-                    KEY_DOT => self.queue_wait(),
-                    KEY_COMMA => self.queue_pick(),
-                    KEY_DESCEND => self.queue_descend(),
-                    KEY_LOWO => self.automoving = Some(AutoMoveType::Explore),
-                    KEY_CAPK => self.automoving = Some(AutoMoveType::Walk),
-                    KEY_LOWQ => self.mode_switch_to(Mode::FullScreen(FSMode::Quit)),
-                    KEY_CAPI => self.mode_switch_to(Mode::Inventory(InvMode::View)),
-                    KEY_CAPE => self.mode_switch_to(Mode::Inventory(InvMode::Equip)),
-                    KEY_CAPD => self.mode_switch_to(Mode::Inventory(InvMode::Drop_)),
-                    KEY_LOWX => {
+                let command = match self.keys.command(ch) {
+                    Some(command) => command,
+                    None => return,
+                };
+                match command {
+                    Command::TurnLeft => self.queue_turn(Left),
+                    Command::TurnRight => self.queue_turn(Right),
+                    Command::Forward => self.queue_move(Forward),
+                    Command::Charge => self.queue_charge(),
+                    Command::Close => self.close_door(),
+                    Command::Bash => self.queue_bash(),
+                    Command::SpinLeft => self.queue_spin(Left),
+                    Command::SpinRight => self.queue_spin(Right),
+                    Command::StrafeLeft => self.queue_move(Left),
+                    Command::StrafeRight => self.queue_move(Right),
+                    Command::Back => self.queue_move(Back),
+                    Command::Wait => self.queue_wait(),
+                    Command::Pick => self.queue_pick(),
+                    Command::Descend => self.queue_descend(),
+                    Command::Ascend => self.queue_ascend(),
+                    Command::Interact => self.queue_interact(),
+                    Command::Explore => {
+                        self.automoving = Some(AutoMoveType::Explore);
+                        self.explorer = Some(game::Explorer::new(self.current_location(),
+                                                                 self.player()));
+                    }
+                    Command::Walk => self.automoving = Some(AutoMoveType::Run),
+                    Command::Rest => self.start_rest(),
+                    Command::Quit => self.ask(Question::Quit),
+                    Command::Inventory => self.inventory(InvMode::View),
+                    Command::Equip => self.inventory(InvMode::Equip),
+                    Command::Drop => self.inventory(InvMode::Drop_),
+                    Command::Examine => {
                         self.target_pos = None;
                         self.mode_switch_to(Mode::Examine);
                     }
-                    KEY_LOWF => {
+                    Command::Fire => {
                         if self.player().can_attack_ranged() {
-                            self.target_pos = None;
-                            self.mode_switch_to(Mode::Target(TargetMode::Ranged));
+                            self.start_targeting(TargetMode::Ranged);
                         }
                     }
-                    KEY_HELP => {
-                        self.mode_switch_to(Mode::FullScreen(FSMode::Help));
-                    }
-                    KEY_GOTO => self.mode_switch_to(Mode::GoTo),
-                    _ => {}
-                }
-            }
-            Mode::Inventory(InvMode::Equip) => {
-                match ch {
-                    ch => {
-                        match ch as u8 as char {
-                            'a'...'z' | 'A'...'Z' => {
-                                if self.player().item_letter_taken(ch as u8 as char) {
-                                    self.queue_equip(ch as u8 as char)
-                                }
+                    Command::FireAgain => {
+                        if self.player().can_attack_ranged() {
+                            match self.last_target_coord() {
+                                Some(coord) => self.queue_ranged(coord),
+                                None => self.start_targeting(TargetMode::Ranged),
                             }
-                            '\x1b' => self.mode_switch_to(Mode::Normal),
-                            _ => {}
                         }
                     }
+                    Command::Help => {
+                        self.mode_switch_to(Mode::FullScreen(FSMode::Help(HelpTopic::Game, 0)));
+                    }
+                    Command::GoTo => self.mode_switch_to(Mode::GoTo),
+                    Command::Monsters => self.mode_switch_to(Mode::Monsters(0)),
+                    Command::Overview => {
+                        self.target_pos = None;
+                        self.mode_switch_to(Mode::Overview);
+                    }
+                    Command::Minimap => self.change_option(Opt::Minimap),
+                    Command::Noises => self.change_option(Opt::Noises),
+                    Command::Options => {
+                        self.mode_switch_to(Mode::FullScreen(FSMode::Options(0)))
+                    }
+                    Command::History => {
+                        self.mode_switch_to(Mode::FullScreen(FSMode::MessageHistory(0)))
+                    }
+                    Command::Glyphs => {
+                        self.change_option(Opt::Glyphs);
+                        let glyphs = self.glyphs().name();
+                        self.log(&format!("Drawing the map with {} glyphs.", glyphs));
+                    }
+                    Command::Screenshot => self.screenshot(),
+                    Command::Verbose => {
+                        self.change_option(Opt::Damage);
+                        self.log(if self.options.verbose_combat {
+                            "Damage numbers shown."
+                        } else {
+                            "Damage numbers hidden."
+                        });
+                    }
+                    Command::WizReveal => self.wizard_cmd(|e| e.debug_reveal()),
+                    Command::WizHeal => self.wizard_cmd(|e| e.debug_heal()),
+                    Command::WizDescend => self.wizard_cmd(|e| e.debug_descend()),
+                    Command::WizUndo => {
+                        let mut undone = false;
+                        self.wizard_cmd(|e| undone = e.debug_undo());
+                        if self.wizard && !undone {
+                            self.log("Nothing to undo on this level.");
+                        }
+                    }
+                    Command::WizPanic => {
+                        if self.wizard {
+                            self.wiz_panic = true;
+                            self.redraw();
+                        }
+                    }
+                    Command::WizDump => {
+                        if self.wizard {
+                            self.wiz_dump();
+                        }
+                    }
+                    _ => {}
                 }
             }
-            Mode::Inventory(InvMode::View) => {
+            Mode::Inventory(InvMode::Item(item_ch)) => {
                 match ch {
+                    KEY_ESC => self.mode_switch_to(Mode::Inventory(InvMode::View)),
                     ch => {
-                        match ch as u8 as char {
-                            'a'...'z' | 'A'...'Z' => {}
-                            '\x1b' => self.mode_switch_to(Mode::Normal),
-                            _ => {}
+                        let action = ITEM_ACTIONS.iter()
+                                                 .find(|&&(key, _)| key as i32 == ch)
+                                                 .map(|&(_, action)| action);
+                        if let Some(action) = action {
+                            self.item_action(item_ch, action);
                         }
                     }
                 }
             }
-            Mode::Inventory(InvMode::Drop_) => {
+            Mode::Inventory(mode) => {
                 match ch {
-                    ch => {
-                        match ch as u8 as char {
-                            'a'...'z' | 'A'...'Z' => {
-                                if self.player().item_letter_taken(ch as u8 as char) {
-                                    self.queue_drop(ch as u8 as char)
+                    KEY_ESC => self.mode_switch_to(Mode::Normal),
+                    KEY_HELP => {
+                        let help = FSMode::Help(HelpTopic::Inventory, 0);
+                        self.mode_switch_to(Mode::FullScreen(help));
+                    }
+                    KEY_DESCEND => {
+                        self.inv_page = cmp::min(self.inv_page + 1, self.inventory_pages() - 1);
+                        self.redraw();
+                    }
+                    KEY_ASCEND => {
+                        self.inv_page = self.inv_page.saturating_sub(1);
+                        self.redraw();
+                    }
+                    0...0xff => {
+                        let ch = ch as u8 as char;
+                        match ch {
+                            'a'...'z' | 'A'...'Z' if self.player().item_letter_taken(ch) => {
+                                match mode {
+                                    InvMode::View => {
+                                        self.mode_switch_to(Mode::Inventory(InvMode::Item(ch)))
+                                    }
+                                    InvMode::Equip => {
+                                        // equipping uses consumables
+                                        let action = if self.item_action_allowed(ch,
+                                                                                 ItemAction::Use) {
+                                            ItemAction::Use
+                                        } else {
+                                            ItemAction::Equip
+                                        };
+                                        self.item_action(ch, action);
+                                    }
+                                    InvMode::Drop_ => self.item_action(ch, ItemAction::Drop),
+                                    InvMode::Item(_) => unreachable!(),
                                 }
                             }
-                            '\x1b' => self.mode_switch_to(Mode::Normal),
                             _ => {}
                         }
                     }
+                    _ => {}
                 }
             }
             Mode::Examine => {
                 let pos = self.target_pos.unwrap_or(self.player().pos);
 
-                match ch {
-                    KEY_ESC | KEY_LOWX | KEY_LOWQ => {
+                match self.keys.command(ch) {
+                    Some(Command::Cancel) |
+                    Some(Command::Examine) |
+                    Some(Command::Quit) if self.final_map => {
+                        self.final_map = false;
+                        self.target_pos = None;
+                        self.mode = Mode::FullScreen(FSMode::GameOver);
+                    }
+                    Some(command) if self.final_map && command.is_wizard() => {}
+                    Some(Command::Help) => {
+                        self.mode = Mode::FullScreen(FSMode::Help(HelpTopic::Examine, 0));
+                    }
+                    Some(Command::Confirm) if self.picking_travel => {
+                        self.picking_travel = false;
+                        self.target_pos = None;
+                        self.mode = Mode::Normal;
+                        self.start_travel(pos.coord + pos.dir);
+                    }
+                    Some(Command::Cancel) | Some(Command::Examine) | Some(Command::Quit) => {
+                        self.picking_travel = false;
                         self.target_pos = None;
                         self.mode = Mode::Normal;
                     }
-                    KEY_LOWH => {
-                        self.target_pos = Some(pos + Angle::Left);
+                    Some(Command::WizTeleport) => {
+                        self.wizard_cmd(|e| e.debug_teleport(pos.coord))
                     }
-                    KEY_LOWL => {
-                        self.target_pos = Some(pos + Angle::Right);
+                    Some(Command::WizItem) if self.wizard => {
+                        self.ask(Question::SpawnItem(pos.coord))
                     }
-                    KEY_LOWJ => {
-                        self.target_pos = Some(pos + (pos.dir + Angle::Back).to_coordinate());
+                    Some(Command::WizRat) => {
+                        self.wizard_cmd(|e| e.debug_spawn_monster(actor::Race::Rat, pos.coord))
                     }
-                    KEY_LOWK => {
-                        self.target_pos = Some(pos + pos.dir.to_coordinate());
+                    Some(Command::WizGoblin) => {
+                        self.wizard_cmd(|e| e.debug_spawn_monster(actor::Race::Goblin, pos.coord))
                     }
-                    KEY_CAPK => {
-                        self.target_pos = Some(pos + pos.dir.to_coordinate().scale(5));
+                    Some(Command::WizTroll) => {
+                        self.wizard_cmd(|e| e.debug_spawn_monster(actor::Race::Troll, pos.coord))
                     }
-                    KEY_CAPJ => {
-                        self.target_pos = Some(pos +
-                                               (pos.dir + Angle::Back).to_coordinate().scale(5));
+                    Some(Command::WizLog) if self.wizard => self.wiz_log(pos.coord),
+                    // Tab toggles the minimap everywhere else
+                    Some(Command::NextTarget) | Some(Command::Minimap) |
+                    Some(Command::PrevTarget) => {
+                        let back = self.keys.command(ch) == Some(Command::PrevTarget);
+                        let next = cycle(&self.sights, pos.coord + pos.dir, back);
+                        if let Some(coord) = next {
+                            self.target_pos = Some(self.examining(coord));
+                        }
                     }
-                    _ => {}
+                    Some(Command::Wait) => {
+                        let coord = self.player().pos.coord;
+                        self.target_pos = Some(self.examining(coord));
+                    }
+                    Some(command) => {
+                        if let Some(moved) = cursor_move(pos, command, 1) {
+                            self.target_pos = Some(moved);
+                        }
+                    }
+                    None => {}
                 }
                 self.redraw();
             }
+            Mode::Overview => {
+                let pos = self.target_pos.unwrap_or(self.player().pos);
+
+                match self.keys.command(ch) {
+                    Some(Command::Confirm) => {
+                        self.target_pos = None;
+                        self.mode_switch_to(Mode::Normal);
+                        self.start_travel(pos.coord);
+                    }
+                    Some(Command::Cancel) | Some(Command::Overview) | Some(Command::Quit) => {
+                        self.target_pos = None;
+                        self.mode_switch_to(Mode::Normal);
+                    }
+                    Some(command) => {
+                        // a cell at a time, however many hexes it takes
+                        let step = self.overview_scale();
+                        if let Some(moved) = cursor_move(pos, command, step) {
+                            self.target_pos = Some(moved);
+                            self.redraw();
+                        }
+                    }
+                    None => {}
+                }
+            }
             Mode::Target(_) => {
                 let center = self.player().pos;
                 let pos = self.target_pos.unwrap_or(center);
 
-                match ch {
-                    KEY_ESC | KEY_LOWX | KEY_LOWQ => {
+                match self.keys.command(ch) {
+                    Some(Command::Cancel) | Some(Command::Examine) | Some(Command::Quit) => {
                         self.target_pos = None;
                         self.mode_switch_to(Mode::Normal);
                     }
-                    KEY_ENTER | KEY_LOWF => {
-                        let target = self.target_pos.unwrap();
-                        self.target_pos = None;
-                        self.mode_switch_to(Mode::Normal);
-                        self.queue_ranged(target.coord);
+                    Some(Command::Confirm) | Some(Command::Fire) => {
+                        if center.coord.distance(pos.coord) > actor::RANGED_RANGE {
+                            self.log("That's out of range.");
+                        } else if let Mode::Target(TargetMode::Throw(ch)) = self.mode {
+                            self.target_pos = None;
+                            self.mode_switch_to(Mode::Normal);
+                            self.action_push(game::Action::Throw(ch, pos.coord));
+                        } else {
+                            let target = {
+                                let loc = self.current_location();
+                                loc.shot_path(center.coord, pos.coord)
+                                   .last()
+                                   .and_then(|c| loc.actors_coord_to_id.get(c))
+                                   .cloned()
+                            };
+                            self.last_target = target.or(self.last_target);
+                            self.target_pos = None;
+                            self.mode_switch_to(Mode::Normal);
+                            self.queue_ranged(pos.coord);
+                        }
+                    }
+                    // Tab toggles the minimap everywhere else
+                    Some(Command::NextTarget) | Some(Command::Minimap) |
+                    Some(Command::PrevTarget) => {
+                        let back = self.keys.command(ch) == Some(Command::PrevTarget);
+                        let next = {
+                            let loc = self.current_location();
+                            let coords: Vec<Coordinate> =
+                                self.visible_hostiles()
+                                    .iter()
+                                    .map(|id| loc.actors_byid[id].pos.coord)
+                                    .collect();
+                            cycle(&coords, pos.coord, back)
+                        };
+                        if let Some(coord) = next {
+                            self.target_pos = Some(Position::new(coord, center.dir));
+                        }
                     }
-                    KEY_LOWH => {
+                    Some(Command::TurnLeft) => {
                         self.target_pos = Some(util::circular_move(center, pos, Angle::Left));
                     }
-                    KEY_LOWL => {
+                    Some(Command::TurnRight) => {
                         self.target_pos = Some(util::circular_move(center, pos, Angle::Right));
                     }
-                    KEY_LOWJ => {
+                    Some(Command::Back) => {
                         self.target_pos = Some(util::circular_move(center, pos, Angle::Back));
                     }
-                    KEY_LOWK => {
+                    Some(Command::Forward) => {
                         self.target_pos = Some(util::circular_move(center, pos, Angle::Forward));
                     }
                     _ => {}
                 }
                 self.redraw();
             }
-            Mode::GoTo => {
-                match ch {
-                    KEY_DESCEND => self.automoving = Some(AutoMoveType::GoTo(GoToType::Stairs)),
+            Mode::Prompt(question) => {
+                let from = self.prompt_from;
+                if ch == KEY_ESC {
+                    self.mode_switch_to(from);
+                    return;
+                }
+                let answer = match question.kind() {
+                    PromptKind::YesNo => {
+                        match ch {
+                            KEY_LOWY | KEY_CAPY => Some(Answer::Yes),
+                            KEY_LOWN | KEY_CAPN => Some(Answer::No),
+                            _ => None,
+                        }
+                    }
+                    PromptKind::Choice(choices) => {
+                        choices.iter()
+                               .find(|&&(key, _)| key as i32 == ch)
+                               .map(|&(key, _)| Answer::Key(key))
+                    }
+                    PromptKind::Direction => {
+                        let dir = self.player().pos.dir;
+                        let angle = match self.keys.command(ch) {
+                            Some(Command::Forward) => Some(Angle::Forward),
+                            Some(Command::Back) => Some(Angle::Back),
+                            Some(Command::StrafeLeft) => Some(Angle::Left),
+                            Some(Command::StrafeRight) => Some(Angle::Right),
+                            _ => None,
+                        };
+                        angle.map(|angle| Answer::Direction(dir + angle))
+                    }
+                    PromptKind::Text => {
+                        match ch {
+                            KEY_ENTER => Some(Answer::Text(self.prompt_text.trim().to_string())),
+                            KEY_BACKSPACE | KEY_DEL | KEY_CTRLH => {
+                                self.prompt_text.pop();
+                                None
+                            }
+                            0x20...0x7e if self.prompt_text.len() < prompt::TEXT_MAX => {
+                                self.prompt_text.push(ch as u8 as char);
+                                None
+                            }
+                            _ => None,
+                        }
+                    }
+                };
+                match answer {
+                    Some(answer) => {
+                        self.mode_switch_to(from);
+                        self.answer(question, answer);
+                    }
+                    None => self.redraw(),
+                }
+            }
+            Mode::Monsters(selected) => {
+                let ids = self.visible_actors();
+                let last = ids.len().saturating_sub(1);
+                match self.keys.command(ch) {
+                    Some(Command::Forward) => {
+                        self.mode_switch_to(Mode::Monsters(selected.saturating_sub(1)))
+                    }
+                    Some(Command::Back) => {
+                        self.mode_switch_to(Mode::Monsters(cmp::min(selected + 1, last)))
+                    }
+                    Some(Command::Confirm) if !ids.is_empty() => {
+                        let coord = self.current_location().actors_byid[&ids[selected]].pos.coord;
+                        self.target_pos = Some(self.examining(coord));
+                        self.mode_switch_to(Mode::Examine);
+                    }
+                    Some(Command::Cancel) | Some(Command::Monsters) | Some(Command::Quit) => {
+                        self.mode_switch_to(Mode::Normal)
+                    }
                     _ => {}
                 }
+            }
+            Mode::GoTo => {
+                let picked = match ch {
+                    KEY_LOWA...KEY_LOWZ => {
+                        self.destinations().get((ch - KEY_LOWA) as usize).map(|d| d.coord)
+                    }
+                    _ => None,
+                };
                 self.mode_switch_to(Mode::Normal);
+                match (picked, self.keys.command(ch)) {
+                    (Some(coord), _) => self.start_travel(coord),
+                    (None, Some(Command::Examine)) => {
+                        self.target_pos = None;
+                        self.picking_travel = true;
+                        self.mode_switch_to(Mode::Examine);
+                    }
+                    _ => {}
+                }
             }
         }
     }
 
     // TODO: break into smaller stuff?
-    fn update(&mut self) {
+    /// Log what the player noticed
+    fn update(&mut self, events: &[game::Event]) {
+        let player_id = self.current_location().player_id();
+        let (level, turn) = (self.current_location().id, self.engine.turn());
+        self.noises.retain(|_, &mut (_, _, heard)| turn - heard < NOISE_FADE);
+        // whoever the player wounded, to tell their death a kill
+        let mut wounded = HashSet::new();
+
+        for event in events {
+            if let Some(animation) = self.animation(event) {
+                self.animations.push(animation);
+            }
 
-        let cur_loc = self.current_location();
+            let tone = match *event {
+                game::Event::Attack { attacker, target, success: true, .. } => {
+                    if attacker == player_id {
+                        wounded.insert(target);
+                    }
+                    if target == player_id {
+                        Tone::Bad
+                    } else {
+                        Tone::Plain
+                    }
+                }
+                game::Event::Heard { coord, heard } => {
+                    self.noises.insert(coord, (level, heard.noise, turn));
+                    Tone::Plain
+                }
+                game::Event::Died { id } if id == player_id => Tone::Bad,
+                game::Event::Died { id } if wounded.contains(&id) => {
+                    let s = format!("You kill {}.", self.actor_name(id));
+                    self.log_toned(&s, Tone::Good, Topic::Combat);
+                    continue;
+                }
+                // once until the player gets to act again, however many
+                // keys went in meanwhile
+                game::Event::Rejected { id, .. } if id == player_id => {
+                    if self.told_recovering {
+                        continue;
+                    }
+                    self.told_recovering = true;
+                    Tone::Bad
+                }
+                _ => Tone::Plain,
+            };
+            if let Some(s) = self.format_event(event) {
+                self.log_toned(&s, tone, event_topic(event));
+            }
+        }
+    }
+
+    /// What of `event` the player gets to see on the map, if anything
+    fn animation(&self, event: &game::Event) -> Option<Animation> {
+        if !self.options.animate {
+            return None;
+        }
         let player = self.player();
+        match *event {
+            game::Event::Attack { target, .. } => {
+                self.current_location()
+                    .actors_byid
+                    .get(&target)
+                    .map(|a| a.pos.coord)
+                    .and_then(|c| if player.sees(c) { Some(Animation::Flash(c)) } else { None })
+            }
+            game::Event::Projectile { ref path, .. } => {
+                let seen: Vec<Coordinate> =
+                    path.iter().cloned().filter(|&c| player.sees(c)).collect();
+                if seen.is_empty() {
+                    None
+                } else {
+                    Some(Animation::Projectile(seen))
+                }
+            }
+            _ => None,
+        }
+    }
 
-        if player.is_dead() {
+    /// Play the animations queued by the last events, frame by frame,
+    /// while the map is in view
+    fn play_animations(&mut self) {
+        let animations = std::mem::replace(&mut self.animations, vec![]);
+        match self.mode {
+            Mode::Normal | Mode::Target(_) => {}
+            _ => return,
+        }
+        if animations.is_empty() {
             return;
         }
 
-        let discoviered_areas = player.discovered_areas
-                                      .iter()
-                                      .filter_map(|coord| cur_loc.at(*coord).tile().area);
-
-        if let Some(s) = self.format_areas(discoviered_areas.map(|area| area.type_)) {
-            self.log(&s);
+        self.redraw_now();
+        for animation in animations {
+            let (frames, delay) = match animation {
+                Animation::Flash(coord) => (vec![Frame::Flash(coord)], ANIMATION_FLASH_MS),
+                Animation::Projectile(path) => {
+                    (path.into_iter().map(Frame::Missile).collect(), ANIMATION_STEP_MS)
+                }
+            };
+            for frame in frames {
+                self.frame = Some(frame);
+                self.draw_map();
+                self.screen.borrow_mut().flush();
+                thread::sleep(std::time::Duration::from_millis(delay as u64));
+            }
         }
+        self.frame = None;
+        self.redraw();
+    }
 
-        for item_coord in player.discovered
-                                .iter()
-                                .filter(|&coord| cur_loc.at(*coord).item_map_or(false, |_| true)) {
-            let item_descr = cur_loc.at(*item_coord)
-                                    .item_map_or("".to_string(), |i| i.description().to_string());
-            self.log(&format!("You've found {}.", item_descr));
+    /// "you" for the player, "the <race>" for anyone else
+    fn actor_name(&self, id: actor::Id) -> String {
+        let cur_loc = self.current_location();
+        if id == cur_loc.player_id() {
+            "you".to_string()
+        } else {
+            cur_loc.actors_byid
+                   .get(&id)
+                   .map_or("something".to_string(), |a| format!("the {}", a.description()))
         }
+    }
 
-        if player.discovered_stairs(cur_loc) {
-            self.log("You've found stairs.");
-        }
+    fn format_event(&self, event: &game::Event) -> Option<String> {
+        let player_id = self.current_location().player_id();
 
-        for res in &player.was_attacked_by {
-            if res.success {
-                self.log(&format!("{} hit you {}for {} dmg.",
-                                  res.who,
-                                  if res.behind {
-                                      "from behind "
-                                  } else {
-                                      ""
-                                  },
-                                  res.dmg));
-            } else {
-                self.log(&format!("{} missed you.", res.who));
+        let s = match *event {
+            game::Event::Attack { attacker, target, success, dmg, behind } => {
+                let behind = if behind {
+                    " from behind"
+                } else {
+                    ""
+                };
+                let dmg = if self.options.verbose_combat {
+                    format!(" for {}", dmg)
+                } else {
+                    String::new()
+                };
+                let (attacker, target) = (self.actor_name(attacker), self.actor_name(target));
+                match (success, attacker == "you", target == "you") {
+                    (true, true, _) => format!("You hit {}{}{}.", target, behind, dmg),
+                    (true, false, _) => format!("{} hits {}{}{}.", attacker, target, behind, dmg),
+                    (false, true, _) => format!("You miss {}.", target),
+                    (false, false, true) => format!("You evade {}'s blow.", attacker),
+                    (false, false, false) => format!("{} misses {}.", attacker, target),
+                }
             }
-        }
-
-        for res in &player.did_attack {
-            if res.success {
-                self.log(&format!("You hit {} {}for {} dmg.",
-                                  res.who,
-                                  if res.behind {
-                                      "from behind "
-                                  } else {
-                                      ""
-                                  },
-                                  res.dmg));
-            } else {
-                self.log(&format!("You missed {}.", res.who));
+            game::Event::Died { id } => {
+                if id == player_id {
+                    "You die...".to_string()
+                } else {
+                    format!("{} dies.", self.actor_name(id))
+                }
             }
-        }
+            game::Event::PickedUp { id, ref item } => {
+                if id == player_id {
+                    format!("You pick up {}.", item)
+                } else {
+                    format!("{} picks up {}.", self.actor_name(id), item)
+                }
+            }
+            game::Event::DoorOpened { id, .. } if id != player_id => {
+                format!("{} opens a door.", self.actor_name(id))
+            }
+            game::Event::DoorClosed { id, .. } if id != player_id => {
+                format!("{} closes a door.", self.actor_name(id))
+            }
+            game::Event::Daybreak => "The sun rises.".to_string(),
+            game::Event::Nightfall => "The sun sets.".to_string(),
+            game::Event::Discovered(ref discovery) => {
+                match *discovery {
+                    game::Discovery::Areas(ref areas) => {
+                        return self.format_areas(areas.iter());
+                    }
+                    game::Discovery::Item(ref item) => format!("You've found {}.", item),
+                    game::Discovery::Actors(ref actors) => {
+                        let verb = match actors.len() {
+                            1 => "comes",
+                            _ => "come",
+                        };
+                        format!("{} {} into view.", sighted_list(actors), verb)
+                    }
+                    game::Discovery::Underfoot(ref item) => {
+                        format!("You step over the {}.", item)
+                    }
+                    game::Discovery::Stairs => "You've found stairs.".to_string(),
+                    game::Discovery::Fountain => "You've found a fountain.".to_string(),
+                    game::Discovery::Altar => "You've found an altar.".to_string(),
+                }
+            }
+            game::Event::Interacted { id, result } if id == player_id => {
+                interact_message(result).to_string()
+            }
+            game::Event::Interacted { .. } => return None,
+            game::Event::Heard { coord, ref heard } => {
+                noise_message(heard, self.player().pos, coord)
+            }
+            game::Event::Rejected { id, reason: game::Rejection::Recovering(_) }
+                if id == player_id => "You are still recovering.".to_string(),
+            game::Event::DoorOpened { .. } | game::Event::DoorClosed { .. } |
+            game::Event::Projectile { .. } | game::Event::Rejected { .. } => return None,
+        };
 
-        let noises = player.heard
-                           .iter()
-                           .filter(|&(c, _)| *c != player.pos.coord)
-                           .filter(|&(c, _)| !player.sees(*c));
+        Some(capitalize(&s))
+    }
 
-        for (_, &noise) in noises {
-            self.log(&format!("You hear {}.", noise.description()));
-        }
+    /// Log an answer to something the player asked for
+    pub fn log(&self, s: &str) {
+        self.log_on(s, Topic::System);
     }
 
+    fn log_on(&self, s: &str, topic: Topic) {
+        self.log_toned(s, Tone::Plain, topic);
+    }
 
-    pub fn log(&self, s: &str) {
+    /// Log `s`, unless the options leave `topic` out
+    fn log_toned(&self, s: &str, tone: Tone, topic: Topic) {
+        if !self.options.logs(topic) {
+            return;
+        }
         let turn = self.engine.turn();
-        self.log.borrow_mut().push_front(LogEntry {
-            text: s.to_string(),
-            turn: turn,
-        });
+        self.log.borrow_mut().push(s, tone, topic, turn);
+    }
+
+    pub fn display_menu(&mut self) {
+        self.mode_switch_to(Mode::FullScreen(FSMode::Menu(0)));
+    }
+
+    /// Leave the finished game for the main menu, forgetting everything
+    /// about it, to start another
+    fn to_menu(&mut self) {
+        self.spawned = false;
+        *self.log.borrow_mut() = Messages::new();
+        self.target_pos = None;
+        self.automoving = None;
+        self.automoving_stopped_turn = 0;
+        self.after_action_delay = 0;
+        self.game_action_queue.clear();
+        self.rank = None;
+        self.name.clear();
+        self.last_target = None;
+        self.inv_page = 0;
+        self.final_map = false;
+        self.morgue = None;
+        self.explorer = None;
+        self.picking_travel = false;
+        self.told_recovering = false;
+        self.statuses.clear();
+        self.noises.clear();
+        self.animations.clear();
+        self.frame = None;
+        self.display_menu();
     }
 
-    pub fn display_intro(&mut self) {
-        self.mode = Mode::FullScreen(FSMode::Intro);
+    /// Colors, glyph and boldness of a known tile of type `tt`, with
+    /// `walls` around it; glue between two tiles (`proper` false) never
+    /// shows a feature, and its walls are the tiles on either side
+    fn tile_glyph(&self,
+                  tt: Option<tile::Type>,
+                  feature: Option<tile::Feature>,
+                  proper: bool,
+                  walls: Walls)
+                  -> ([u8; 4], [u8; 4], &'static str, bool) {
+        let tt = match tt {
+            Some(tt) => tt,
+            None => return (color::EMPTY_FG, color::EMPTY_BG, "?", false),
+        };
+        let glyph = self.glyphs().tile(tt, feature, proper, walls);
+        match tt {
+            tile::Empty => {
+                let branch = self.current_location().id.branch;
+                let (fg, bg) = match feature {
+                    _ if !proper => (color::STONE_FG, color::EMPTY_BG),
+                    None => (color::EMPTY_FG, color::EMPTY_BG),
+                    Some(tile::Door(false, _)) => (color::STONE_FG, color::WALL_BG),
+                    Some(tile::StairsDown(to)) |
+                    Some(tile::StairsUp(to)) if to.branch != branch => {
+                        (color::BRANCH_STAIRS_FG, color::EMPTY_BG)
+                    }
+                    Some(_) => (color::STONE_FG, color::EMPTY_BG),
+                };
+                (fg, bg, glyph, false)
+            }
+            tile::Wall => (color::WALL_FG, color::WALL_BG, glyph, true),
+            tile::Water | tile::Bridge => {
+                let (fg, bg) = water_colors(tt);
+                (fg, bg, glyph, false)
+            }
+            tile::Grass => (color::GRASS_FG, color::EMPTY_BG, glyph, false),
+            tile::Scorched => (color::SCORCHED_FG, color::EMPTY_BG, glyph, false),
+        }
     }
 
     fn draw_map(&self) {
         let player = self.player();
         let cur_loc = self.current_location();
 
-        let mut calloc = self.calloc.borrow_mut();
 
-        let window = self.windows.map.window;
+        let window = &self.windows.map;
+        let glyphs = self.glyphs().glyphs();
+        let turn = self.engine.turn();
 
-        let actors_aheads: HashMap<Coordinate, Coordinate> =
-            cur_loc.actors_byid
-                   .iter()
-                   .filter(|&(_, a)| !a.is_dead())
-                   .map(|(_, a)| (a.head(), a.pos.coord))
-                   .collect();
+        // where those the player sees are facing
+        let actors_aheads: game::CoordSet = cur_loc.actors_byid
+                                                   .values()
+                                                   .filter(|a| {
+                                                       !a.is_dead() && player.sees(a.pos.coord)
+                                                   })
+                                                   .map(|a| a.head())
+                                                   .collect();
         let player_ahead = player.pos.coord + player.pos.dir;
+        // the whole level, as it was when the game ended
+        let reveal = self.final_map;
 
         // Get the screen bounds.
-        let mut max_x = 0;
-        let mut max_y = 0;
-        nc::getmaxyx(window, &mut max_y, &mut max_x);
+        let (max_x, max_y) = (window.width(), window.height());
 
         let mid_x = max_x / 2;
         let mid_y = max_y / 2;
 
-        let cpair = nc::COLOR_PAIR(calloc.get(color::VISIBLE_FG, color::MAP_BACKGROUND_BG));
-        nc::wbkgd(window, ' ' as nc::chtype | cpair as nc::chtype);
-        nc::werase(window);
+        let cpair = Style::new(color::VISIBLE_FG, color::MAP_BACKGROUND_BG);
+        window.fill(cpair);
 
         let (center, head) = match self.mode {
             Mode::Examine => {
@@ -866,16 +2687,24 @@ impl Ui {
             _ => (player.pos.coord, player.pos.coord + player.pos.dir),
         };
 
-        let mut target_line = HashSet::new();
+        // where a shot would fly, and the rest of the line it can't reach
+        let mut target_line = game::CoordSet::default();
+        let mut shot_path = game::CoordSet::default();
         if let Mode::Target(_) = self.mode {
             center.for_each_in_line_to(head, |c| {
-                target_line.insert(c);
+                if c != center {
+                    target_line.insert(c);
+                }
             });
+            shot_path.extend(cur_loc.shot_path(center, head));
         }
+        let out_of_range = center.distance(head) > actor::RANGED_RANGE;
 
         let (vpx, vpy) = center.to_pixel_integer(SPACING);
 
+        let mut row = Vec::with_capacity(max_x as usize);
         for vy in 0..max_y {
+            row.clear();
             for vx in 0..max_x {
                 let (rvx, rvy) = (vx - mid_x, vy - mid_y);
 
@@ -885,12 +2714,12 @@ impl Ui {
 
                 let is_proper_coord = off == (0, 0);
 
-                let (visible, _in_los, knows, tt, t, light) = if is_proper_coord {
+                let (visible, _in_los, knows, tt, t, light, walls) = if is_proper_coord {
 
-                    let t = cur_loc.map[c].clone();
+                    let t = *cur_loc.at(c).tile();
                     let tt = t.type_;
 
-                    let visible = player.sees(c) || player.is_dead();
+                    let visible = player.sees(c) || reveal;
                     let light = if visible {
                         cur_loc.at(c).light_as_seen_by(player)
                     } else {
@@ -898,11 +2727,12 @@ impl Ui {
                     };
 
                     (visible,
-                     player.in_los(c) || player.is_dead(),
-                     player.knows(c),
+                     player.in_los(c) || reveal,
+                     reveal || player.knows(c),
                      Some(tt),
                      Some(t),
-                     light)
+                     light,
+                     self.walls_around(c))
                 } else {
                     // Paint a glue characters between two real characters
                     let c1 = c;
@@ -911,7 +2741,7 @@ impl Ui {
                     let low_opaq1 = player.sees(c1) && cur_loc.at(c1).tile().opaqueness() <= 1;
                     let low_opaq2 = player.sees(c2) && cur_loc.at(c2).tile().opaqueness() <= 1;
 
-                    let knows = (player.knows(c1) && player.knows(c2)) ||
+                    let knows = reveal || (player.knows(c1) && player.knows(c2)) ||
                                 (player.knows(c1) && low_opaq1) ||
                                 (player.knows(c2) && low_opaq2);
 
@@ -926,11 +2756,11 @@ impl Ui {
 
                     let tt = c.map_or(None, |c| Some(cur_loc.at(c).tile().type_));
 
-                    let visible = player.is_dead() || (player.sees(c1) && player.sees(c2)) ||
+                    let visible = reveal || (player.sees(c1) && player.sees(c2)) ||
                                   (player.sees(c1) && low_opaq1) ||
                                   (player.sees(c2) && low_opaq2);
 
-                    let in_los = player.is_dead() || (player.in_los(c1) && player.in_los(c2)) ||
+                    let in_los = reveal || (player.in_los(c1) && player.in_los(c2)) ||
                                  (player.in_los(c1) && low_opaq1) ||
                                  (player.in_los(c2) && low_opaq2);
 
@@ -939,7 +2769,7 @@ impl Ui {
                                                 cur_loc.at(c2).light_as_seen_by(player));
 
 
-                        if player.is_dead() {
+                        if reveal {
                             (light1 + light2) / 2
                         } else {
                             match (player.sees(c1), player.sees(c2)) {
@@ -953,102 +2783,66 @@ impl Ui {
                         0
                     };
 
-                    (visible, in_los, knows, tt, None, light)
+                    (visible, in_los, knows, tt, None, light, self.walls_beside(c1, c2))
                 };
 
                 let mut draw = knows;
 
                 if visible {
-                    debug_assert!(knows || player.is_dead());
+                    debug_assert!(knows || reveal);
                 }
 
                 let mut bold = false;
                 let occupied = cur_loc.at(c).is_occupied();
                 let (fg, bg, mut glyph) = if is_proper_coord && visible && occupied {
-                    let (fg, glyph) = match cur_loc.at(c).actor_map_or(Race::Rat, |a| a.race) {
-                        Race::Human | Race::Elf | Race::Dwarf => (color::CHAR_SELF_FG, "@"),
-                        Race::Rat => (color::CHAR_ENEMY_FG, "r"),
-                        Race::Goblin => (color::CHAR_ENEMY_FG, "g"),
-                        Race::Troll => (color::CHAR_ENEMY_FG, "T"),
-                    };
-                    (fg, color::CHAR_BG, glyph)
+                    let race = cur_loc.at(c).actor_map_or(Race::Rat, |a| a.race);
+                    (actor_color(race), color::CHAR_BG, glyphs.actor(race))
                 } else if is_proper_coord && visible &&
                                              cur_loc.at(c).item().is_some() {
                     let item = cur_loc.at(c).item().unwrap();
-                    let s = item_to_str(item.category());
-                    if player.discovered.contains(&c) {
+                    let s = glyphs.item(item.category());
+                    if player.perception.discovered.contains(&c) {
                         bold = true;
                     }
                     (color::WALL_FG, color::EMPTY_BG, s)
                 } else if knows {
-                    match tt {
-                        Some(tile::Empty) => {
-                            let mut fg = color::STONE_FG;
-                            let mut bg = color::EMPTY_BG;
-                            let mut glyph = " ";
-
-                            if is_proper_coord {
-                                match t.and_then(|t| t.feature) {
-                                    None => {
-                                        glyph = self.dot;
-                                        fg = color::EMPTY_FG;
-                                        bg = color::EMPTY_BG;
-                                    }
-                                    Some(tile::Door(open)) => {
-                                        if open {
-                                            glyph = DOOR_OPEN_CH;
-                                        } else {
-                                            glyph = DOOR_CLOSED_CH;
-                                            bg = color::WALL_BG;
-                                        }
-                                    }
-                                    Some(tile::Statue) => glyph = STATUE_CH,
-                                    Some(tile::Stairs) => glyph = STAIRS_DOWN_CH,
-                                }
-                            }
-
-                            (fg, bg, glyph)
-                        }
-                        Some(tile::Wall) => {
-                            bold = true;
-                            (color::WALL_FG, color::WALL_BG, WALL_CH)
-                        }
-                        Some(tile::Water) => (color::WATER_FG, color::WATER_BG, WATER_CH),
-                        None => (color::EMPTY_FG, color::EMPTY_BG, "?"),
-                    }
+                    let feature = t.and_then(|t| t.feature());
+                    let (fg, bg, glyph, wall) =
+                        self.tile_glyph(tt, feature, is_proper_coord, walls);
+                    bold = wall;
+                    (fg, bg, glyph)
                 } else {
-                    (color::EMPTY_FG, color::EMPTY_BG, NOTHING_CH)
+                    (color::EMPTY_FG, color::EMPTY_BG, glyphs.nothing)
                 };
 
-
-                let (mut fg, mut bg) = if !visible || light == 0 {
-                    if visible {
-                        (fg[2], bg[2])
-                    } else {
-                        (fg[3], bg[3])
-                    }
-                } else if light < 3 {
-                    (fg[1], bg[1])
-                } else {
-                    (fg[0], bg[0])
-                };
+                let band = light_band(visible, light);
+                let (mut fg, mut bg) = (fg[band], bg[band]);
 
                 if let Some(t) = t {
                     if visible && t.light > 0 {
                         if !occupied {
-                            fg = color::LIGHTSOURCE;
+                            fg = if t.has_feature(tile::Fungi) {
+                                color::FUNGI_FG
+                            } else {
+                                color::LIGHTSOURCE
+                            };
                             bold = true;
                         }
                     }
                 }
 
+                if is_proper_coord && visible && !occupied && cur_loc.is_burning(c) {
+                    glyph = glyphs.fire;
+                    fg = color::FIRE_FG[(turn % 2) as usize];
+                    bold = true;
+                }
+
                 if is_proper_coord && visible &&
                    cur_loc.at(c).actor_map_or(0, |a| a.light_emision()) > 0u32 {
                     bg = color::LIGHTSOURCE;
                 }
 
-                if is_proper_coord && actors_aheads.contains_key(&c) &&
-                   player.sees(*actors_aheads.get(&c).unwrap()) {
+                if is_proper_coord && actors_aheads.contains(&c) {
                     bold = true;
                     let color = if c == player_ahead {
                         color::TARGET_SELF_FG
@@ -1069,12 +2863,45 @@ impl Ui {
                     }
                 }
 
-                if is_proper_coord && c != center && !visible && player.hears(c) {
-                    bg = color::NOISE_BG;
-                    draw = true;
-                }
-
-                if self.mode == Mode::Examine {
+                let noise = if is_proper_coord && self.options.show_noises {
+                    self.noises.get(&c).and_then(|&(level, noise, heard)| {
+                        let age = turn - heard;
+                        if level == cur_loc.id && age < NOISE_FADE {
+                            Some((noise, age))
+                        } else {
+                            None
+                        }
+                    })
+                } else {
+                    None
+                };
+                if let Some((noise, age)) = noise {
+                    if c != center && c != player.pos.coord && !visible {
+                        glyph = noise_glyph(noise, glyphs);
+                        fg = color::NOISE_FG[age as usize];
+                        if player.hears(c) {
+                            bg = color::NOISE_BG;
+                        }
+                        draw = true;
+                    }
+                }
+
+                match self.frame {
+                    Some(Frame::Flash(at)) if is_proper_coord && at == c => {
+                        std::mem::swap(&mut fg, &mut bg);
+                        bold = true;
+                        draw = true;
+                    }
+                    Some(Frame::Missile(at)) if is_proper_coord && at == c => {
+                        glyph = "*";
+                        fg = color::MISSILE_FG;
+                        bold = true;
+                        draw = true;
+                    }
+                    _ => {}
+                }
+
+                if self.mode == Mode::Examine {
                     if is_proper_coord && center == c {
                         glyph = "@";
                         fg = color::CHAR_GRAY_FG;
@@ -1091,497 +2918,2263 @@ impl Ui {
                     }
                 } else if let Mode::Target(_) = self.mode {
                     if is_proper_coord && target_line.contains(&c) {
-                        glyph = "*";
                         draw = true;
+                        if !(visible && occupied) {
+                            glyph = "*";
+                            fg = color::TARGET_TRAIL_FG;
+                        }
                         if c == head {
                             fg = color::TARGET_SELF_FG;
+                            bold = true;
                         }
-                        if !cur_loc.at(c).tile().is_passable() {
+                        if !shot_path.contains(&c) || (c == head && out_of_range) {
                             bg = color::BLOCKED_BG;
                         }
                     }
                 }
 
 
-                if draw {
-                    let cpair = nc::COLOR_PAIR(calloc.get(fg, bg));
+                row.push(if draw {
+                    let style = Style::new(fg, bg);
+                    let ch = glyph.chars().next().unwrap_or(' ');
+                    Some((ch, if bold { style.bold() } else { style }))
+                } else {
+                    None
+                });
+            }
+            window.put_row(vy, &row);
+        }
+    }
+
+    /// The level as far as the player knows it, shrunk to at most
+    /// `avail_w` by `avail_h` characters: hexes share a character when it
+    /// doesn't fit, and the tile most of them are wins; stairs always
+    /// show
+    fn shrink_known(&self, avail_w: i32, avail_h: i32) -> Option<Shrunk> {
+        let player = self.player();
+        let cur_loc = self.current_location();
+        let known: Vec<(i32, i32, MinimapCell, bool)> =
+            player.perception.known
+                  .iter()
+                  .filter(|&&c| cur_loc.in_bounds(c))
+                  .map(|&c| {
+                      let (px, py) = c.to_pixel_integer(SPACING);
+                      (px.div_floor(&2), py, minimap_cell(cur_loc.at(c).tile()), player.sees(c))
+                  })
+                  .collect();
+        shrink(&known, avail_w, avail_h)
+    }
+
+    /// Overlay the whole known level in the corner of the map window,
+    /// shrunk to fit
+    fn draw_minimap(&self) {
+        let cur_loc = self.current_location();
+        let glyphs = self.glyphs().glyphs();
+
+
+        let window = &self.windows.map;
+
+        let (max_x, max_y) = (window.width(), window.height());
+
+        // at most a quarter of the map window, frame included
+        let shrunk = match self.shrink_known(max_x / 2 - 2, max_y / 2 - 2) {
+            Some(shrunk) => shrunk,
+            None => return,
+        };
+
+        let (x0, y0) = (max_x - shrunk.cols - 2, 0);
+        let put = |x: i32, y: i32, fg: u8, glyph: &str| {
+            window.set_style(Style::new(fg, color::MINIMAP_BG));
+            window.print_at(x0 + 1 + x, y0 + 1 + y, glyph);
+        };
+
+        for y in -1..shrunk.rows + 1 {
+            for x in -1..shrunk.cols + 1 {
+                put(x, y, color::MINIMAP_BG, " ");
+            }
+        }
+
+        for (&(x, y), &(kind, _)) in &shrunk.cells {
+            let (fg, glyph) = minimap_glyph(kind, glyphs);
+            put(x, y, fg, glyph);
+        }
+
+        // the player last, on top of any companion sharing the cell
+        let mut actors: Vec<&Actor> = cur_loc.actors_byid
+                                             .values()
+                                             .filter(|a| !a.is_dead() && (a.player || a.companion))
+                                             .collect();
+        actors.sort_by_key(|a| a.player);
+        for a in actors {
+            let (x, y) = match shrunk.cell(a.pos.coord) {
+                Some(xy) => xy,
+                None => continue,
+            };
+            if a.player {
+                put(x, y, color::CHAR_SELF_FG[0], "@");
+            } else {
+                put(x, y, color::CHAR_ALLY_FG[0], glyphs.dot);
+            }
+        }
+
+    }
+
+    /// Hexes a cell of the overview takes across and down
+    fn overview_scale(&self) -> i32 {
+        let full = &self.windows.full;
+        self.shrink_known(full.width(), full.height() - 2).map_or(1, |shrunk| shrunk.scale)
+    }
+
+    /// The whole known level on the screen, shrunk to fit if it has to,
+    /// with the rooms' names and the cursor picking where to travel to
+    fn draw_overview(&self) {
+        let player = self.player();
+        let cur_loc = self.current_location();
+        let glyphs = self.glyphs().glyphs();
+        let cursor = self.target_pos.unwrap_or(player.pos).coord;
+
+        let window = &self.windows.full;
+        let bg = color::MAP_BACKGROUND_BG;
+        let cpair = Style::new(color::VISIBLE_FG, bg);
+        window.fill(cpair);
+
+        let (max_x, max_y) = (window.width(), window.height());
+
+        // a line for the header and one for the status
+        if let Some(shrunk) = self.shrink_known(max_x, max_y - 2) {
+            let (x0, y0) = ((max_x - shrunk.cols) / 2, 1 + (max_y - 2 - shrunk.rows) / 2);
+            let put = |x: i32, y: i32, fg: u8, bg: u8, glyph: &str| {
+                window.set_style(Style::new(fg, bg));
+                window.print_at(x0 + x, y0 + y, glyph);
+            };
+
+            // what's out of sight dimmed, like on the map
+            let mut shown = HashMap::new();
+            for (&xy, &(kind, visible)) in &shrunk.cells {
+                let (fg, glyph) = minimap_glyph(kind, glyphs);
+                let fg = if visible { fg } else { color::NOT_IN_LOS_FG };
+                shown.insert(xy, (fg, glyph));
+            }
+            // items over the floor they lie on, but not over stairs
+            for &c in &player.perception.known {
+                if cur_loc.at(c).item().is_none() {
+                    continue;
+                }
+                if let Some(xy) = shrunk.cell(c) {
+                    if shrunk.cells.get(&xy).map_or(false, |&(k, _)| k < MinimapCell::StairsUp) {
+                        shown.insert(xy, (color::VISIBLE_FG, "*"));
+                    }
+                }
+            }
+            if let Some(xy) = shrunk.cell(player.pos.coord) {
+                shown.insert(xy, (color::CHAR_SELF_FG[0], "@"));
+            }
+
+            for (&(x, y), &(fg, glyph)) in &shown {
+                put(x, y, fg, bg, glyph);
+            }
+
+            // room names over their centers, where they fit between the
+            // others
+            let mut centers: Vec<Coordinate> =
+                player.perception.known_areas.iter().cloned().collect();
+            centers.sort_by_key(|c| (c.y, c.x));
+            let mut labels: HashMap<i32, Vec<(i32, i32)>> = HashMap::new();
+            for center in centers {
+                let name = match cur_loc.at(center).tile().area {
+                    Some(area) => area.type_.to_string(),
+                    None => continue,
+                };
+                let (x, y) = match shrunk.cell(center) {
+                    Some(xy) => xy,
+                    None => continue,
+                };
+                let len = name.chars().count() as i32;
+                let from = x - len / 2;
+                if from < 0 || from + len > shrunk.cols {
+                    continue;
+                }
+                let row = labels.entry(y).or_insert_with(Vec::new);
+                if row.iter().any(|&(a, b)| from <= b && a <= from + len) {
+                    continue;
+                }
+                row.push((from, from + len));
+                put(from, y, color::LABEL_FG, bg, &name);
+            }
+
+            if let Some(xy) = shrunk.cell(cursor) {
+                let glyph = shown.get(&xy).map_or(" ", |&(_, glyph)| glyph);
+                put(xy.0, xy.1, color::VISIBLE_FG, color::TARGET_SELF_FG, glyph);
+            }
+        }
+
+        let header = self.ellipsize(&self.location_header(), max_x);
+        window.set_style(Style::new(color::LABEL_FG, bg));
+        window.print_at(0, 0, &header);
+
+        let cpair = Style::new(color::VISIBLE_FG, bg);
+        window.set_style(cpair);
+        window.print_at(0,
+                        max_y - 1,
+                        &format!("{}; {}: travel there, {}: back",
+                                 self.tile_description(cursor, player, cur_loc),
+                                 self.keys.keys_description(Command::Confirm),
+                                 self.keys.keys_description(Command::Cancel)));
+    }
+
+    /// A bar of `cur` out of `max`, showing the change since `prev`;
+    /// with `flash`, the label stands out when it went down. A `cost`
+    /// above 0 follows, in red if `cur` doesn't cover it.
+    fn draw_stats_bar(&self,
+                      window: &Window,
+                      name: &str,
+                      cur: i32,
+                      prev: i32,
+                      max: i32,
+                      flash: bool,
+                      cost: i32) {
+
+        let (max_x, max_y) = (window.width(), window.height());
+
+        if max_x < 6 || max_y < 1 {
+            // Don't draw anything on too small window
+            return;
+        }
+
+        let cur = cmp::max(cur, 0) as u32;
+        let prev = cmp::max(prev, 0) as u32;
+        let max = cmp::max(max, 1) as u32;
+
+        if flash && cur < prev {
+            window.set_style(self.label_color.reversed());
+        } else {
+            window.set_style(self.label_color);
+        }
+        window.print(name);
+        window.set_style(self.label_color);
+        window.print(": ");
+
+        let numbers = format!(" {}/{}", cur, max);
+        let cost_text = if cost > 0 {
+            format!(" -{}", cost)
+        } else {
+            String::new()
+        };
+        let taken = 4 + name.chars().count() as i32 + numbers.len() as i32 +
+                    cost_text.len() as i32;
+        let width = cmp::max(0, max_x - taken) as u32;
+        let cur_w = cur * width / max;
+        let prev_w = prev * width / max;
+
+        let fill = match bar_level(cur, max) {
+            BarLevel::High => self.green_color,
+            BarLevel::Mid => Style::new(color::YELLOW, color::BACKGROUND_BG),
+            BarLevel::Low => self.red_color,
+        };
+
+        window.set_style(self.text_color);
+        window.print("[");
+        for i in 0..width {
+            let (color, s) = match (i < cur_w, i < prev_w) {
+                (true, true) => (fill, "="),
+                (false, true) => (self.red_color, "-"),
+                (true, false) => (self.green_color, "+"),
+                (false, false) => (self.text_color, " "),
+            };
+            window.set_style(color);
+            window.print(s);
+        }
+        window.set_style(self.text_color);
+        window.print("]");
+        window.print(&numbers);
+        let cost_color = if (cur as i32) < cost {
+            self.red_color
+        } else {
+            self.text_gray_color
+        };
+        window.set_style(cost_color);
+        window.print(&cost_text);
+    }
+
+    fn draw_turn<T>(&self, window: &Window, label: &str, val: T)
+        where T: Integer + fmt::Display
+    {
+        window.set_style(self.label_color);
+        window.print(&format!("{}: ", label));
+
+        window.set_style(self.text_color);
+        window.print(&format!("{:<8}", val));
+    }
+
+    fn draw_val<T>(&self, window: &Window, label: &str, val: T)
+        where T: Integer + fmt::Display
+    {
+        window.set_style(self.label_color);
+        window.print(&format!("{}:", label));
+
+        window.set_style(self.text_color);
+        window.print(&format!("{:>2} ", val));
+    }
+
+    fn draw_label(&self, window: &Window, label: &str) {
+        window.set_style(self.label_color);
+        window.print(&format!("{}:", label));
+    }
+
+    fn draw_item(&self, window: &Window, astate: &Actor, label: &str, slot: Slot) {
+        self.draw_label(window, label);
+
+        if slot == Slot::RHand && !astate.can_attack() {
+            window.set_style(self.text_gray_color);
+        } else {
+            window.set_style(self.text_color);
+        }
+
+        let item = if let Some(&(_, ref item)) = astate.items_equipped.get(&slot) {
+            item.description().to_string()
+        } else {
+            "-".to_string()
+        };
+
+        // let item = item.slice_chars(0, cmp::min(item.char_len(), 13));
+        window.print(&format!("{:^13}", item));
+    }
+
+    /// Badges of the statuses affecting `actor`, on one line of
+    /// `width`; what doesn't fit is counted
+    fn draw_statuses(&self, window: &Window, actor: &Actor, width: i32) {
+        let mut statuses = actor.active_statuses();
+        // not a status of its own, so it isn't logged coming and going
+        if actor.action_cd > 0 {
+            statuses.insert(0, ("Recovering", actor::Severity::Mild, Some(actor.action_cd)));
+        }
+        let mut used = 0;
+        for (i, &(name, severity, turns)) in statuses.iter().enumerate() {
+            let badge = status_badge(name, turns);
+            let left = statuses.len() - i - 1;
+            // keep room for the count of the rest
+            let reserve = if left > 0 { 8 } else { 0 };
+            if used + badge.len() as i32 + reserve > width {
+                window.set_style(self.text_gray_color);
+                window.print(&format!("+{} more", statuses.len() - i));
+                break;
+            }
+
+            let fg = match severity {
+                actor::Severity::Mild => color::STATUS_MILD_FG,
+                actor::Severity::Bad => color::STATUS_BAD_FG,
+                actor::Severity::Severe => color::STATUS_SEVERE_FG,
+            };
+            let cpair = Style::new(fg, color::BACKGROUND_BG);
+            window.set_style(cpair);
+            window.print(&format!("{} ", badge));
+            used += badge.len() as i32 + 1;
+        }
+        window.set_style(self.text_color);
+    }
+
+    fn draw_inventory(&self) {
+        let window = &self.windows.map;
+
+        let cpair = self.text_color;
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        if let Mode::Inventory(InvMode::Item(ch)) = self.mode {
+            self.draw_inventory_item(window, ch);
+            return;
+        }
+
+        let (page_len, pages) = (self.inventory_page_len(), self.inventory_pages());
+        let page = cmp::min(self.inv_page, pages - 1);
+        for line in self.inventory_lines().iter().skip(page * page_len).take(page_len) {
+            window.print(&format!("{}\n", line));
+        }
+
+        if pages > 1 {
+            window.print_at(0,
+                            window.height() - 1,
+                            &format!("Page {} of {}; <, >: turn page", page + 1, pages));
+        }
+
+    }
+
+    fn draw_destinations(&self) {
+        let window = &self.windows.map;
+
+        let cpair = self.text_color;
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        window.print("Travel to:\n");
+        for (i, dest) in self.destinations().iter().enumerate() {
+            let ch = (b'a' + i as u8) as char;
+            match dest.steps {
+                Some(steps) => {
+                    window.set_style(self.text_color);
+                    window.print(&format!(" {} - {}, {} steps\n", ch, dest.name, steps));
+                }
+                None => {
+                    window.set_style(self.text_gray_color);
+                    window.print(&format!(" {} - {} (no known way)\n", ch, dest.name));
+                }
+            }
+        }
+        window.set_style(self.text_color);
+        window.print(&format!(" {} - a spot picked with the cursor\n",
+                              self.keys.keys_description(Command::Examine)));
+
+    }
+
+    /// Everybody in sight, nearest first, the nearest hostile stands out
+    fn draw_monsters(&self, selected: usize) {
+        let window = &self.windows.map;
+        let player = self.player();
+        let cur_loc = self.current_location();
+
+        let cpair = self.text_color;
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        window.print("In sight:\n");
+        let ids = self.visible_actors();
+        if ids.is_empty() {
+            window.print(" nobody\n");
+        }
+        let nearest_hostile = ids.iter().cloned().find(|id| cur_loc.actors_byid[id].is_hostile());
+        for (i, &id) in ids.iter().enumerate() {
+            let a = &cur_loc.actors_byid[&id];
+            let (fg, glyph) = (actor_color(a.race), self.glyphs().glyphs().actor(a.race));
+            let glyph_color = Style::new(fg[0], color::BACKGROUND_BG);
+            let color = if Some(id) == nearest_hostile {
+                self.red_color
+            } else {
+                self.text_color
+            };
+            let distance = player.pos.coord.distance(a.pos.coord);
+            let whereabouts = match util::Compass::between(player.pos.coord, a.pos.coord) {
+                Some(dir) => format!("{} to the {}", distance, dir.description()),
+                None => distance.to_string(),
+            };
+
+            window.set_style(self.text_color);
+            window.print(if i == selected { " > " } else { "   " });
+            window.set_style(glyph_color);
+            window.print(glyph);
+            window.set_style(color);
+            window.print(&format!(" {}, {}, {}\n",
+                                  a.description(),
+                                  health_description(a.hp, a.stats.base.max_hp),
+                                  whereabouts));
+        }
+
+        window.set_style(self.text_color);
+        window.print(&format!("\n{} / {}: select, {}: examine, {}: back\n",
+                              self.keys.keys_description(Command::Forward),
+                              self.keys.keys_description(Command::Back),
+                              self.keys.keys_description(Command::Confirm),
+                              self.keys.keys_description(Command::Cancel)));
+    }
+
+    fn draw_inventory_item(&self, window: &Window, ch: char) {
+        let (item, equipped) = match self.inventory_item(ch) {
+            Some(found) => found,
+            None => return,
+        };
+
+        window.set_style(self.text_color);
+        window.print(&format!("{} - {}", ch, item.description()));
+        if equipped {
+            window.print(" (equipped)");
+        }
+        window.print("\n\n");
+
+        let s = item.known_stats();
+        let mut stats: Vec<String> = [("AC", s.base.ac),
+                                      ("EV", s.base.ev),
+                                      ("DMG", s.melee_dmg),
+                                      ("ACC", s.melee_acc),
+                                      ("Vision", s.base.vision),
+                                      ("Infravision", s.base.infravision)]
+                                         .iter()
+                                         .filter(|&&(_, val)| val != 0)
+                                         .map(|&(name, val)| format!("{} {:+}", name, val))
+                                         .collect();
+        if s.melee_str_req > 0 {
+            stats.push(format!("needs Str {}", s.melee_str_req));
+        }
+        if s.light_emision > 0 {
+            stats.push(format!("light {}", s.light_emision));
+        }
+        self.draw_label(window, "Kind");
+        window.set_style(self.text_color);
+        window.print(&format!(" {:?}\n", item.category()));
+        if !stats.is_empty() {
+            self.draw_label(window, "Stats");
+            window.set_style(self.text_color);
+            window.print(&format!(" {}\n", stats.join(", ")));
+        }
+        if !item.is_identified() {
+            window.print("Not identified yet.\n");
+        }
+        window.print("\n");
+
+        for &(key, action) in &ITEM_ACTIONS {
+            let name = match action {
+                ItemAction::Drop => "drop",
+                ItemAction::Equip if equipped => "unequip",
+                ItemAction::Equip => "equip",
+                ItemAction::Use => "use",
+                ItemAction::Throw => "throw",
+            };
+            let color = if self.item_action_allowed(ch, action) {
+                self.text_color
+            } else {
+                self.text_gray_color
+            };
+            window.set_style(color);
+            window.print(&format!(" {}) {}\n", key, name));
+        }
+        window.set_style(self.text_color);
+        window.print("\nEsc: back to the list");
+    }
+
+    fn draw_stats(&self) {
+        let turn = self.engine.turn();
+        let window = match self.windows.stats {
+            Some(ref stats) => stats,
+            None => return,
+        };
+        let player = self.player();
+        let cur_loc = self.current_location();
+
+        let (ac, ev) = (player.stats.base.ac, player.stats.base.ev);
+        let (dmg, acc) = (player.stats.melee_dmg, player.stats.melee_acc);
+
+        let cpair = self.text_color;
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        let max_x = window.width();
+
+        let mut y = 0;
+        window.move_to(0, y);
+        window.print(&format!("{} the {}", player.description(), player.race.description()));
+
+        y += 1;
+        window.move_to(0, y);
+        window.set_style(self.label_color);
+        window.print(&self.ellipsize(&self.location_header(), max_x));
+        window.set_style(self.text_color);
+
+        y += 1;
+        window.move_to(0, y);
+        self.draw_val(window, "Str", player.stats.base.str_);
+        window.move_to(7, y);
+        self.draw_val(window, "DMG", dmg);
+        window.move_to(15, y);
+        self.draw_val(window, "ACC", acc);
+
+        y += 1;
+        window.move_to(0, y);
+        self.draw_val(window, "Int", player.stats.base.int);
+        window.move_to(7, y);
+        self.draw_val(window, " AC", ac);
+        window.move_to(15, y);
+        self.draw_val(window, "EV", ev);
+
+        y += 1;
+        window.move_to(0, y);
+        self.draw_val(window, "Dex", player.stats.base.dex);
+        window.move_to(7, y);
+        self.draw_val(window, " Au", player.gold);
+
+        y += 1;
+        window.move_to(0, y);
+
+        self.draw_stats_bar(window,
+                            "HP",
+                            player.hp,
+                            player.saved_hp,
+                            player.stats.base.max_hp,
+                            true,
+                            0);
+
+        y += 1;
+        window.move_to(0, y);
+        self.draw_stats_bar(window,
+                            "MP",
+                            player.mp,
+                            player.saved_mp,
+                            player.stats.base.max_mp,
+                            false,
+                            0);
+
+        y += 1;
+        window.move_to(0, y);
+        self.draw_stats_bar(window,
+                            "SP",
+                            player.sp,
+                            player.saved_sp,
+                            player.stats.base.max_sp,
+                            false,
+                            player.melee_sp_cost());
+
+        y += 1;
+        window.move_to(0, y);
+        self.draw_statuses(window, player, max_x);
+
+        let pos = if self.mode == Mode::Examine {
+            self.target_pos.unwrap_or(self.player().pos)
+        } else {
+            player.pos
+        };
+
+        if self.mode == Mode::Examine {
+            self.draw_examine_panel(window, y + 1, pos.coord + pos.dir);
+            return;
+        }
+
+        let slots = [("R", Slot::RHand),
+                     ("L", Slot::LHand),
+                     ("F", Slot::Feet),
+                     ("B", Slot::Body),
+                     ("H", Slot::Head),
+                     ("C", Slot::Cloak),
+                     ("Q", Slot::Quick)];
+
+        for (i, &(string, slot)) in slots.iter().enumerate() {
+            if i & 1 == 0 {
+                y += 1;
+                window.move_to(0, y);
+            } else {
+                window.move_to(14, y);
+            }
+
+            self.draw_item(window, player, string, slot);
+        }
+
+        y += 1;
+        window.move_to(0, y);
+
+        match (self.mode, self.target_pos) {
+            (Mode::Target(_), Some(target)) => {
+                let descr = self.tile_description(target.coord, player, cur_loc);
+                let out_of_range = " (out of range)";
+                let far = player.pos.coord.distance(target.coord) > actor::RANGED_RANGE;
+                let mut room = max_x - "Target".len() as i32 - 2;
+                if far {
+                    room -= out_of_range.len() as i32;
+                }
+                self.draw_label(window, "Target");
+                window.set_style(self.text_color);
+                window.print(&format!(" {}", self.ellipsize(&descr, room)));
+                if far {
+                    window.set_style(self.red_color);
+                    window.print(out_of_range);
+                }
+            }
+            _ => {
+                let head = pos.coord + pos.dir;
+                let descr = self.tile_description(head, player, cur_loc);
+                self.draw_label(window, "In front");
+                window.set_style(self.text_color);
+                let room = max_x - "In front".len() as i32 - 2;
+                window.print(&format!(" {}", self.ellipsize(&descr, room)));
+            }
+        }
+
+        y += 1;
+        window.move_to(0, y);
+        let descr = self.tile_description(player.pos.coord, player, cur_loc);
+        self.draw_label(window, "Underfoot");
+        window.set_style(self.text_color);
+        let room = max_x - "Underfoot".len() as i32 - 2;
+        window.print(&format!(" {}", self.ellipsize(&descr, room)));
+
+        y += 1;
+        window.move_to(0, y);
+        self.draw_turn(window, "Turn", turn);
+        if self.wizard {
+            window.set_style(self.red_color);
+            window.print(&format!(" seed {}", self.engine.replay().seed));
+            if !self.engine.is_scored() {
+                window.print(" (wizard)");
+            }
+        }
+
+    }
 
-                    if bold {
-                        nc::wattron(window, nc::A_BOLD() as i32);
+    // TODO: Consider the distance to the Item to print something
+    // like "you see x in the distance", "you find yourself in x".
+    fn format_areas<I>(&self, mut i: I) -> Option<String>
+        where I: Iterator,
+              <I as Iterator>::Item: fmt::Display
+    {
+        if let Some(descr) = i.next() {
+            let mut s = String::new();
+            write!(&mut s, "{}", "You see: ").unwrap();
+            write!(&mut s, "{}", descr).unwrap();
+
+            for ref descr in i {
+                write!(&mut s, ", ").unwrap();
+                write!(&mut s, "{}", descr).unwrap();
+            }
+
+            write!(&mut s, ".").unwrap();
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    /// Color of an entry on `topic` logged on `turn`, the older the
+    /// darker, for as long as the topic lasts; `None` once faded out
+    fn turn_to_color(&self, turn: u64, topic: Topic) -> Option<Style> {
+        log_shade(topic, self.engine.turn() - turn).map(|fg| Style::new(fg, color::BACKGROUND_BG))
+    }
+
+    /// Color of a log entry: that of its tone while fresh, then fading
+    /// to gray like the rest
+    fn entry_color(&self, entry: &LogEntry) -> Option<Style> {
+        let fresh = self.engine.turn() - entry.turn < 16;
+        match entry.tone {
+            Tone::Bad if fresh => Some(self.red_color),
+            Tone::Good if fresh => Some(self.green_color),
+            _ => self.turn_to_color(entry.turn, entry.topic),
+        }
+    }
+
+    /// What `astate` knows of the tile at `coord`; `None` if nothing
+    fn tile_details(&self,
+                    coord: Coordinate,
+                    astate: &Actor,
+                    gstate: &game::Location)
+                    -> Option<TileDetails> {
+        if !astate.knows(coord) && !self.final_map {
+            return None;
+        }
+
+        let tile = gstate.at(coord).tile();
+        let sees = astate.sees(coord) || self.final_map;
+
+        let actor = if sees {
+            gstate.at(coord).actor_map_or(None, |a| {
+                Some(ActorDetails {
+                    race: a.race,
+                    name: capitalize(&a.description()),
+                    health: health_description(a.hp, a.stats.base.max_hp),
+                    equipment: {
+                        let mut slots: Vec<_> = a.items_equipped.iter().collect();
+                        slots.sort_by_key(|&(_, &(ch, _))| ch);
+                        slots.iter().map(|&(_, &(_, ref i))| i.description()).collect()
+                    },
+                    noticed: a.sees(astate.pos.coord),
+                })
+            })
+        } else {
+            None
+        };
+
+        Some(TileDetails {
+            type_: tile.type_,
+            features: tile.features().iter().map(|f| f.description().to_string()).collect(),
+            burning: astate.sees(coord) && gstate.is_burning(coord),
+            light: if sees {
+                Some(light_description(gstate.at(coord).light_as_seen_by(astate)))
+            } else {
+                None
+            },
+            area: tile.area.map(|a| a.type_),
+            item: gstate.at(coord).item_map_or(None, |i| Some(i.description().to_string())),
+            actor: actor,
+        })
+    }
+
+    /// What's at `coord`, as far as `astate` knows, on one line
+    fn tile_description(&self,
+                        coord: Coordinate,
+                        astate: &Actor,
+                        gstate: &game::Location)
+                        -> String {
+        match self.tile_details(coord, astate, gstate) {
+            Some(details) => details.parts(coord != astate.pos.coord).join(", "),
+            None => "Unknown".to_string(),
+        }
+    }
+
+    /// `text` cut to `room` characters, with an ellipsis if it didn't
+    /// fit; waddstr would wrap it instead
+    fn ellipsize(&self, text: &str, room: i32) -> String {
+        let room = cmp::max(0, room) as usize;
+        if text.chars().count() <= room {
+            return text.to_string();
+        }
+        let ellipsis = self.glyphs().glyphs().ellipsis;
+        let keep = room.saturating_sub(ellipsis.chars().count());
+        let mut text: String = text.chars().take(keep).collect();
+        text.push_str(ellipsis);
+        text.chars().take(room).collect()
+    }
+
+    /// The examine panel: everything known of the tile at `coord`, a line
+    /// for each piece, from line `y` of the stats window down
+    fn draw_examine_panel(&self, window: &Window, mut y: i32, coord: Coordinate) {
+        let player = self.player();
+        let cur_loc = self.current_location();
+
+        let (max_x, max_y) = (window.width(), window.height());
+
+        let mut lines: Vec<(&str, String)> = vec![];
+        match self.tile_details(coord, player, cur_loc) {
+            None => lines.push(("Here", "Unknown".to_string())),
+            Some(details) => {
+                lines.push(("Here", details.ground()));
+                match (details.light, details.area) {
+                    (Some(light), Some(area)) => {
+                        lines.push(("Light", format!("{}, {}", light, area)))
+                    }
+                    (Some(light), None) => lines.push(("Light", light.to_string())),
+                    (None, Some(area)) => lines.push(("Area", area.to_string())),
+                    (None, None) => {}
+                }
+                if let Some(item) = details.item {
+                    lines.push(("Item", item));
+                }
+                if let Some(actor) = details.actor {
+                    lines.push(("Who", format!("{}, {}", actor.name, actor.health)));
+                    if !actor.equipment.is_empty() {
+                        lines.push(("Gear", actor.equipment.join(", ")));
+                    }
+                    if coord == player.pos.coord {
+                        let statuses: Vec<String> =
+                            player.active_statuses()
+                                  .into_iter()
+                                  .map(|(name, _, turns)| status_badge(name, turns))
+                                  .collect();
+                        if !statuses.is_empty() {
+                            lines.push(("Status", statuses.join(", ")));
+                        }
                     }
+                    if coord != player.pos.coord {
+                        lines.push(("Aware",
+                                    if actor.noticed {
+                                        "sees you".to_string()
+                                    } else {
+                                        "hasn't noticed you".to_string()
+                                    }));
+                    }
+                }
+            }
+        }
+
+        for (label, text) in lines {
+            if y >= max_y {
+                break;
+            }
+            window.move_to(0, y);
+            self.draw_label(window, label);
+            window.set_style(self.text_color);
+            let room = max_x - label.chars().count() as i32 - 2;
+            window.print(&format!(" {}", self.ellipsize(&text, room)));
+            y += 1;
+        }
+    }
+
+    /// Unread messages shown at a time while there are more than fit
+    /// the log window; a line goes to "--more--", the last stays blank
+    fn log_page(&self) -> usize {
+        let height = self.windows.log.as_ref().map_or(0, |log| log.height());
+        cmp::max(1, height - 2) as usize
+    }
+
+    /// Whether more messages came than the log window shows, and the
+    /// rest waits for a key
+    fn log_more(&self) -> bool {
+        let shown = match self.mode {
+            Mode::FullScreen(_) | Mode::Overview => false,
+            _ => self.spectating.is_none() && self.windows.log.is_some(),
+        };
+        shown && self.log.borrow().has_more(self.log_page() + 1)
+    }
+
+    fn draw_log(&self) {
+        let window = match self.windows.log {
+            Some(ref log) => log,
+            None => return,
+        };
+
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        match self.mode {
+            Mode::GoTo => {
+                window.print(&format!("Go to where?\n"));
+            }
+            Mode::Examine if self.picking_travel => {
+                window.print(&format!("Go to where? {} to go there.\n",
+                                      self.keys.keys_description(Command::Confirm)));
+            }
+            Mode::Inventory(InvMode::Drop_) => {
+                window.print(&format!("Drop what?\n"));
+            }
+            Mode::Inventory(InvMode::Equip) => {
+                window.print(&format!("Equip/use what?\n"));
+            }
+            Mode::Inventory(InvMode::Item(_)) => {
+                window.print(&format!("Do what with it?\n"));
+            }
+            Mode::Target(TargetMode::Throw(_)) => {
+                window.print(&format!("Throw where?\n"));
+            }
+            Mode::Prompt(question) => {
+                window.print(&format!("{}\n", question.line(&self.prompt_text)));
+            }
+            _ => {}
+        }
+
+        let log = self.log.borrow();
+        let entries = if self.log_more() {
+            window.print("--more--\n");
+            log.oldest_unread(self.log_page())
+        } else {
+            log.iter().collect()
+        };
+        for i in entries {
+            if window.cursor_y() == window.height() - 1 {
+                break;
+            }
+            if let Some(cpair) = self.entry_color(i) {
+                window.set_style(cpair);
+                window.print(&format!("{} ", i.description()));
+            }
+            window.print("\n");
+        }
+
+    }
+
+    fn draw_message_history(&mut self, skip: usize) {
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        let log = self.log.borrow();
+        window.print(&format!("= Messages {}-{} of {} = \n\n",
+                              cmp::min(skip + 1, log.len()),
+                              log.len(),
+                              log.len()));
+
+        let max_y = window.height();
+        for entry in log.iter().skip(skip) {
+            if window.cursor_y() >= max_y - 2 {
+                break;
+            }
+            // old messages stay readable here
+            let color = self.entry_color(entry).unwrap_or_else(|| {
+                let fg = log_shades(entry.topic)[4];
+                Style::new(fg, color::BACKGROUND_BG)
+            });
+            window.set_style(color);
+            window.print(&format!("{:>6} {}\n", entry.turn, entry.description()));
+        }
+
+        window.set_style(cpair);
+        window.print_at(0,
+                        max_y - 1,
+                        &format!("{} / {}: scroll, PgUp/PgDn: page, {} / {}: back",
+                                 self.keys.keys_description(Command::Back),
+                                 self.keys.keys_description(Command::Forward),
+                                 self.keys.keys_description(Command::Cancel),
+                                 self.keys.keys_description(Command::Quit)));
+    }
+
+    fn draw_menu(&mut self, selected: usize) {
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        window.print("A long time ago in a dungeon deep, deep underground...\n\n");
+
+        let saved = self.path(AUTOSAVE_PATH).exists();
+        for (i, &(entry, name)) in MENU.iter().enumerate() {
+            let color = if entry == MenuEntry::Continue && !saved {
+                self.text_gray_color
+            } else {
+                self.text_color
+            };
+            let mark = if i == selected { ">" } else { " " };
+            window.set_style(color);
+            window.print(&format!(" {} {}\n", mark, name));
+        }
+
+        window.set_style(self.text_color);
+        window.print(&format!("\n{} / {}: move, {}: pick. {} in the game for help.",
+                              self.keys.keys_description(Command::Forward),
+                              self.keys.keys_description(Command::Back),
+                              self.keys.keys_description(Command::Confirm),
+                              self.keys.keys_description(Command::Help)));
+    }
+
+    fn draw_pickrace(&mut self) {
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        window.print("Pick your race\n\n");
+        window.print("     Str Int Dex  HP  MP  SP  AC  EV\n");
+        for &(key, race) in &[('a', Race::Human), ('b', Race::Elf), ('c', Race::Dwarf)] {
+            let s = actor::Stats::new(race);
+            let items: Vec<String> = race.starting_items()
+                                         .iter()
+                                         .map(|t| t.description().to_string())
+                                         .collect();
+            let items = if items.is_empty() {
+                String::new()
+            } else {
+                format!("  starts with: {}", items.join(", "))
+            };
+            window.print(&format!("{}) {:<6}{:>3} {:>3} {:>3} {:>3} {:>3} {:>3} {:>3} {:>3}{}\n",
+                                  key,
+                                  capitalize(&race.description()),
+                                  s.str_,
+                                  s.int,
+                                  s.dex,
+                                  s.max_hp,
+                                  s.max_mp,
+                                  s.max_sp,
+                                  s.ac,
+                                  s.ev,
+                                  items));
+        }
 
-                    nc::wattron(window, cpair as i32);
-                    nc::mvwaddstr(window, vy, vx, glyph);
-                    nc::wattroff(window, cpair as i32);
+    }
+
+    fn draw_enter_name(&mut self, race: Race) {
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        window.print(&format!("What is the name of your {}?\n\n", race.description()));
+        window.print(&format!("> {}_\n\n", self.name));
+        window.print(&format!("Enter to begin; leave it empty or press Escape to be {}.\n",
+                              player_name()));
+
+    }
 
-                    if bold {
-                        nc::wattroff(window, nc::A_BOLD() as i32);
+    fn draw_game_over(&mut self) {
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        match self.engine.result() {
+            Some(&game::GameResult::Died { ref cause }) => {
+                window.print(&format!("Game over for {}: {}.\n\n",
+                                      self.engine.replay().name,
+                                      cause));
+            }
+            Some(&game::GameResult::Quit) | None => {
+                window.print("You quit; the game is saved.\n\n");
+            }
+        }
+
+        let kills = self.engine.kills();
+        let kills = if kills.is_empty() {
+            "none".to_string()
+        } else {
+            kills.iter()
+                 .map(|&(race, n)| format!("{} {}", n, race.description()))
+                 .collect::<Vec<_>>()
+                 .join(", ")
+        };
+
+        window.print(&format!("Turns: {}\n", self.engine.turn()));
+        window.print(&format!("Deepest level: {}\n", self.engine.deepest() + 1));
+        window.print(&format!("Kills: {}\n", kills));
+        window.print(&format!("Gold: {}\n", self.player().gold));
+        window.print(&format!("Seed: {}\n", self.engine.replay().seed));
+        if !self.engine.is_scored() {
+            window.print("Not scored: wizard mode was used\n");
+        } else {
+            window.print(&format!("Score: {}\n", self.engine.score()));
+        }
+        if let Some(rank) = self.rank {
+            window.print(&format!("You made it to #{} in the high scores!\n", rank + 1));
+        }
+        if let Some(ref morgue) = self.morgue {
+            window.print(&format!("{}\n", morgue));
+        }
+        window.print("\n");
+        if self.player().is_dead() {
+            window.print("v) view the final map\n");
+        }
+        window.print("s) high scores\nm) main menu\nq) quit\n");
+
+    }
+
+    fn draw_options(&mut self, selected: usize) {
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        window.print("= Options =\n\n");
+        for (i, &opt) in options::OPTS.iter().enumerate() {
+            let mark = if i == selected { ">" } else { " " };
+            window.set_style(self.text_color);
+            window.print(&format!(" {} {:<40}", mark, opt.description()));
+            window.set_style(self.label_color);
+            window.print(&format!("{}\n", self.options.value(opt)));
+        }
+
+        window.set_style(self.text_color);
+        window.print(&format!("\n{} / {}: move, {} or space: change, {}: back. Kept in {}.",
+                              self.keys.keys_description(Command::Forward),
+                              self.keys.keys_description(Command::Back),
+                              self.keys.keys_description(Command::Confirm),
+                              self.keys.keys_description(Command::Cancel),
+                              OPTIONS_PATH));
+    }
+
+    fn draw_high_scores(&mut self) {
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        window.print("= High scores = \n\n");
+        let scores = game::HighScores::load(&self.path(SCORES_PATH));
+        if scores.entries.is_empty() {
+            window.print("Nobody made it here yet.\n");
+        }
+        for (i, e) in scores.entries.iter().enumerate() {
+            let mark = if Some(i) == self.rank { '*' } else { ' ' };
+            window.print(&format!("{}{:2}. {:>7} {} {}, {} (seed {})\n",
+                                  mark,
+                                  i + 1,
+                                  e.score,
+                                  e.date,
+                                  e.name,
+                                  e.result,
+                                  e.seed));
+        }
+        window.print("\nPress any key to go back.\n");
+
+    }
+
+    /// The help on `topic`, cut into pages that fit the screen; the
+    /// keys come from the key map, so only what is bound is listed
+    fn help_pages(&self, topic: HelpTopic) -> Vec<Vec<String>> {
+        let mut sections: Vec<Vec<String>> = vec![];
+
+        match topic {
+            HelpTopic::Game => {
+                let mut keys = vec![];
+                for &category in Category::all().iter() {
+                    if category == Category::Wizard && !self.wizard {
+                        continue;
+                    }
+                    let commands: Vec<Command> = Command::all()
+                                                     .into_iter()
+                                                     .filter(|c| c.category() == category)
+                                                     .filter(|&c| !self.keys.keys(c).is_empty())
+                                                     .collect();
+                    if commands.is_empty() {
+                        continue;
+                    }
+                    keys.push(format!("= {} =", category.description()));
+                    for command in commands {
+                        keys.push(format!("{} ({}): {}",
+                                          command.description(),
+                                          command.name(),
+                                          self.keys.keys_description(command)));
+                    }
+                    keys.push(String::new());
+                }
+                sections.push(keys);
+                sections.push(CONCEPTS_HELP.iter().map(|s| s.to_string()).collect());
+            }
+            HelpTopic::Examine => {
+                let mut keys = vec!["= Examining =".to_string(), String::new()];
+                for &(command, description) in EXAMINE_HELP.iter() {
+                    if !self.keys.keys(command).is_empty() {
+                        keys.push(format!("{}: {}",
+                                          self.keys.keys_description(command),
+                                          description));
                     }
                 }
+                sections.push(keys);
+            }
+            HelpTopic::Inventory => {
+                let actions: Vec<String> = ITEM_ACTIONS.iter()
+                                                       .map(|&(key, action)| {
+                                                           let name = match action {
+                                                               ItemAction::Drop => "drop",
+                                                               ItemAction::Equip => "(un)equip",
+                                                               ItemAction::Use => "use",
+                                                               ItemAction::Throw => "throw",
+                                                           };
+                                                           format!("{}) {}", key, name)
+                                                       })
+                                                       .collect();
+                sections.push(vec!["= Inventory =".to_string(),
+                                   String::new(),
+                                   "a-z, A-Z: pick the item with that letter".to_string(),
+                                   "<, >: previous and next page".to_string(),
+                                   format!("On a picked item: {}", actions.join(", ")),
+                                   "Esc: back".to_string()]);
+            }
+        }
 
+        // a line for the page count and keys
+        let height = cmp::max(1, self.windows.full.height() - 2) as usize;
+        let mut pages = vec![];
+        for section in sections {
+            for page in section.chunks(height) {
+                pages.push(page.to_vec());
             }
         }
+        pages
+    }
+
+    fn draw_help(&mut self, topic: HelpTopic, page: usize) {
+        let pages = self.help_pages(topic);
+        let page = cmp::min(page, pages.len() - 1);
+
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        for line in &pages[page] {
+            window.print(&format!("{}\n", line));
+        }
+
+        let max_y = window.height();
+        window.print_at(0,
+                        max_y - 1,
+                        &format!("Page {}/{}; PgUp/PgDn: page, {}: back",
+                                 page + 1,
+                                 pages.len(),
+                                 self.keys.keys_description(Command::Cancel)));
+    }
+
+    fn draw_resume(&mut self) {
+        let window = &self.windows.full;
+        let cpair = Style::new(color::VISIBLE_FG, color::BACKGROUND_BG);
+        window.fill(cpair);
+        window.move_to(0, 0);
+
+        window.print("An unfinished game was found.\n\n");
+        window.print("Resume it? (y/n)\n");
+
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Log(logev) => {
+                match logev {
+                    LogEvent::AutoExploreDone => self.log("Nothing else to explore."),
+                    LogEvent::AutoExploreBlocked => self.log("Can't get there."),
+                    LogEvent::RestDone => {
+                        let healed = {
+                            let player = self.player();
+                            player.hp >= player.stats.base.max_hp &&
+                            player.sp >= player.stats.base.max_sp
+                        };
+                        self.log(if healed {
+                            "You feel rested."
+                        } else {
+                            "You stop resting."
+                        });
+                    }
+                    LogEvent::AutoMoveStopped(stop) => {
+                        let s = match stop {
+                            Stop::Hostile(race) => {
+                                format!("You stop: a {} is in view.", race.description())
+                            }
+                            Stop::Trap => "You stop: there's a trap ahead.".to_string(),
+                            Stop::Fire => "You stop: there's fire ahead.".to_string(),
+                            Stop::Hurt => "You stop: you've been hurt.".to_string(),
+                            Stop::Done | Stop::Blocked => return,
+                        };
+                        self.log_on(&s, Topic::Danger);
+                    }
+                }
+            }
+            Event::Feeling(feeling) => {
+                match feeling {
+                    game::Feeling::Chittering => {
+                        self.log_on("You hear distant chittering.", Topic::Flavor)
+                    }
+                    game::Feeling::Glow(dir) => {
+                        let s = format!("A faint glow comes from the {}.", dir.description());
+                        self.log_on(&s, Topic::Discovery)
+                    }
+                    game::Feeling::Dangerous => {
+                        self.log_on("This place feels dangerous.", Topic::Danger)
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+/// Name of whoever plays, for the score table and bones
+fn player_name() -> String {
+    env::var("USER").unwrap_or_else(|_| "anonymous".to_string())
+}
+
+//        . . .
+//       . . . .
+//      . . . . .
+//       . . . .
+//        . . .
+/// The level, the part of it at `coord` and the time of day if
+/// there's telling, e.g. "Dungeon:1, big room, morning"
+fn location_header(loc: &game::Location, coord: Coordinate) -> String {
+    let mut header = format!("{}, {}", loc.id, loc.place_name(coord));
+    if let Some(daytime) = loc.daytime() {
+        header.push_str(&format!(", {}", daytime.description()));
+    }
+    header
+}
+
+fn light_description(light: u32) -> &'static str {
+    match light {
+        0 => "dark",
+        1 => "dimly lit",
+        2...3 => "lit",
+        _ => "brightly lit",
+    }
+}
 
-        nc::wnoutrefresh(window);
+fn health_description(hp: i32, max_hp: i32) -> &'static str {
+    if hp >= max_hp {
+        "unhurt"
+    } else if hp * 3 >= max_hp * 2 {
+        "lightly wounded"
+    } else if hp * 3 >= max_hp {
+        "wounded"
+    } else {
+        "badly wounded"
     }
+}
 
-    fn draw_stats_bar(&self, window: nc::WINDOW, name: &str, cur: i32, prev: i32, max: i32) {
+/// How a cell of the minimap looks
+fn minimap_glyph(kind: MinimapCell, glyphs: &Glyphs) -> (u8, &'static str) {
+    match kind {
+        MinimapCell::Floor => (color::EMPTY_FG[1], glyphs.dot),
+        MinimapCell::Water => (color::WATER_BG[0], glyphs.water),
+        MinimapCell::Wall => (color::GRAY[12], glyphs.wall),
+        MinimapCell::Door => (color::BRIDGE_FG[0], glyphs.door_closed),
+        MinimapCell::StairsUp => (color::VISIBLE_FG, glyphs.stairs_up),
+        MinimapCell::StairsDown => (color::VISIBLE_FG, glyphs.stairs_down),
+    }
+}
 
-        let mut max_x = 0;
-        let mut max_y = 0;
-        nc::getmaxyx(window, &mut max_y, &mut max_x);
+/// `known` tiles, each at its column and row on the screen with what it
+/// shows and whether it's in sight, shrunk to fit `avail_w` by `avail_h`
+fn shrink(known: &[(i32, i32, MinimapCell, bool)], avail_w: i32, avail_h: i32) -> Option<Shrunk> {
+    if known.is_empty() || avail_w < 1 || avail_h < 1 {
+        return None;
+    }
 
-        if max_x < 6 || max_y < 1 {
-            // Don't draw anything on too small window
-            return;
+    let min_x = known.iter().map(|k| k.0).min().unwrap();
+    let max_x = known.iter().map(|k| k.0).max().unwrap();
+    let min_y = known.iter().map(|k| k.1).min().unwrap();
+    let max_y = known.iter().map(|k| k.1).max().unwrap();
+    let (w, h) = (max_x - min_x + 1, max_y - min_y + 1);
+
+    let scale = shrink_scale(w, h, avail_w, avail_h);
+    let cell = |x: i32, y: i32| ((x - min_x) / scale, (y - min_y) / scale);
+
+    let mut counts: HashMap<(i32, i32), HashMap<MinimapCell, u32>> = HashMap::new();
+    let mut in_sight = HashSet::new();
+    for &(x, y, kind, visible) in known {
+        *counts.entry(cell(x, y))
+               .or_insert_with(HashMap::new)
+               .entry(kind)
+               .or_insert(0) += 1;
+        if visible {
+            in_sight.insert(cell(x, y));
         }
+    }
 
-        let cur = cmp::max(cur, 0) as u32;
-        let prev = cmp::max(prev, 0) as u32;
-        let max = cmp::max(max, 1) as u32;
+    let cells = counts.into_iter()
+                      .map(|(xy, kinds)| {
+                          let kind = match kinds.keys().cloned().max() {
+                              Some(kind) if kind >= MinimapCell::StairsUp => kind,
+                              _ => {
+                                  *kinds.iter()
+                                        .max_by_key(|&(&kind, &n)| (n, kind))
+                                        .unwrap()
+                                        .0
+                              }
+                          };
+                          (xy, (kind, in_sight.contains(&xy)))
+                      })
+                      .collect();
+
+    Some(Shrunk {
+        min_x: min_x,
+        min_y: min_y,
+        scale: scale,
+        cols: (w + scale - 1) / scale,
+        rows: (h + scale - 1) / scale,
+        cells: cells,
+    })
+}
 
-        nc::wattron(window, self.label_color as i32);
-        nc::waddstr(window, &format!("{}: ", name));
+/// Hexes a character has to take across and down for `w` by `h` to
+/// fit in `avail_w` by `avail_h`
+fn shrink_scale(w: i32, h: i32, avail_w: i32, avail_h: i32) -> i32 {
+    cmp::max(1, cmp::max((w + avail_w - 1) / avail_w, (h + avail_h - 1) / avail_h))
+}
 
-        let width = max_x as u32 - 4 - name.chars().count() as u32;
-        let cur_w = cur * width / max;
-        let prev_w = prev * width / max;
+/// What the entry logged for `event` is about
+fn event_topic(event: &game::Event) -> Topic {
+    match *event {
+        game::Event::Attack { .. } |
+        game::Event::Died { .. } |
+        game::Event::Projectile { .. } => Topic::Combat,
+        game::Event::PickedUp { .. } |
+        game::Event::Discovered(_) |
+        game::Event::Interacted { .. } => Topic::Discovery,
+        game::Event::Rejected { .. } => Topic::Danger,
+        game::Event::DoorOpened { .. } |
+        game::Event::DoorClosed { .. } |
+        game::Event::Daybreak |
+        game::Event::Nightfall |
+        game::Event::Heard { .. } => Topic::Flavor,
+    }
+}
 
-        nc::wattron(window, self.text_color as i32);
-        nc::waddstr(window, "[");
-        for i in 0..width {
-            let (color, s) = match (i < cur_w, i < prev_w) {
-                (true, true) => (self.text_color, "="),
-                (false, true) => (self.red_color, "-"),
-                (true, false) => (self.green_color, "+"),
-                (false, false) => (self.text_color, " "),
-            };
-            nc::wattron(window, color as i32);
-            nc::waddstr(window, s);
-        }
-        nc::wattron(window, self.text_color as i32);
-        nc::waddstr(window, "]");
+/// The colors log entries on `topic` fade through
+fn log_shades(topic: Topic) -> [u8; 5] {
+    match topic {
+        Topic::Combat => color::LOG_COMBAT_FG,
+        Topic::Discovery => color::LOG_DISCOVERY_FG,
+        Topic::Danger => color::LOG_DANGER_FG,
+        Topic::System => color::LOG_SYSTEM_FG,
+        Topic::Flavor => color::LOG_FLAVOR_FG,
     }
+}
 
-    fn draw_turn<T>(&self, window: nc::WINDOW, label: &str, val: T)
-        where T: Integer + fmt::Display
-    {
-        nc::wattron(window, self.label_color as i32);
-        nc::waddstr(window, &format!("{}: ", label));
+/// The shade of an entry on `topic` that's `age` turns old; `None` once
+/// faded out
+fn log_shade(topic: Topic, age: u64) -> Option<u8> {
+    let dturn = age * 4 / topic.lasting();
+    let shades = log_shades(topic);
+
+    if dturn < 1 {
+        Some(shades[0])
+    } else if dturn < 4 {
+        Some(shades[1])
+    } else if dturn < 16 {
+        Some(shades[2])
+    } else if dturn < 32 {
+        Some(shades[3])
+    } else if dturn < 64 {
+        Some(shades[4])
+    } else {
+        None
+    }
+}
 
-        nc::wattron(window, self.text_color as i32);
-        nc::waddstr(window, &format!("{:<8}", val));
+/// The one after `at` in `coords`, or before it if `back`, wrapping
+/// around; the first (or last) one if `at` isn't there
+fn cycle(coords: &[Coordinate], at: Coordinate, back: bool) -> Option<Coordinate> {
+    if coords.is_empty() {
+        return None;
     }
+    let len = coords.len();
+    let i = match (coords.iter().position(|&c| c == at), back) {
+        (Some(i), false) => (i + 1) % len,
+        (Some(i), true) => (i + len - 1) % len,
+        (None, false) => 0,
+        (None, true) => len - 1,
+    };
+    Some(coords[i])
+}
 
-    fn draw_val<T>(&self, window: nc::WINDOW, label: &str, val: T)
-        where T: Integer + fmt::Display
-    {
-        nc::wattron(window, self.label_color as i32);
-        nc::waddstr(window, &format!("{}:", label));
+/// Where the examine cursor at `pos` goes on `command`, `step` tiles at
+/// a time; `None` for anything but moving it
+fn cursor_move(pos: Position, command: Command, step: i32) -> Option<Position> {
+    let ahead = |angle: Angle, n: i32| pos + (pos.dir + angle).to_coordinate().scale(n);
+    Some(match command {
+        Command::TurnLeft => pos + Angle::Left,
+        Command::TurnRight => pos + Angle::Right,
+        Command::Forward => ahead(Angle::Forward, step),
+        Command::Back => ahead(Angle::Back, step),
+        Command::StrafeLeft => ahead(Angle::Left, step),
+        Command::StrafeRight => ahead(Angle::Right, step),
+        Command::Walk => ahead(Angle::Forward, 5 * step),
+        Command::FarBack => ahead(Angle::Back, 5 * step),
+        _ => return None,
+    })
+}
 
-        nc::wattron(window, self.text_color as i32);
-        nc::waddstr(window, &format!("{:>2} ", val));
+fn minimap_cell(t: &tile::Tile) -> MinimapCell {
+    if t.stairs_down().is_some() {
+        return MinimapCell::StairsDown;
     }
-
-    fn draw_label(&self, window: nc::WINDOW, label: &str) {
-        nc::wattron(window, self.label_color as i32);
-        nc::waddstr(window, &format!("{}:", label));
+    if t.stairs_up().is_some() {
+        return MinimapCell::StairsUp;
     }
+    match (t.type_, t.feature()) {
+        (tile::Wall, _) => MinimapCell::Wall,
+        (tile::Water, _) => MinimapCell::Water,
+        (_, Some(tile::Door(..))) |
+        (_, Some(tile::BrokenDoor)) => MinimapCell::Door,
+        _ => MinimapCell::Floor,
+    }
+}
 
-    fn draw_item(&self, window: nc::WINDOW, astate: &Actor, label: &str, slot: Slot) {
-        self.draw_label(window, label);
-
-        if slot == Slot::RHand && !astate.can_attack() {
-            nc::wattron(window, self.text_gray_color as i32);
-        } else {
-            nc::wattron(window, self.text_color as i32);
+/// "a goblin and two rats" for actors sighted together; the named ones
+/// by name
+fn sighted_list(actors: &[(Race, Option<String>)]) -> String {
+    let mut parts: Vec<String> = vec![];
+    let mut races: Vec<(Race, usize)> = vec![];
+    for &(race, ref name) in actors {
+        match *name {
+            Some(ref name) if race == Race::Ghost => parts.push(format!("the ghost of {}", name)),
+            Some(ref name) => parts.push(name.clone()),
+            None => {
+                match races.iter().position(|&(r, _)| r == race) {
+                    Some(i) => races[i].1 += 1,
+                    None => races.push((race, 1)),
+                }
+            }
         }
+    }
+    for (race, n) in races {
+        let noun = race.description();
+        parts.push(match n {
+            1 => format!("{} {}", indefinite_article(&noun), noun),
+            n => format!("{} {}", number_word(n), plural(&noun)),
+        });
+    }
 
-        let item = if let Some(&(_, ref item)) = astate.items_equipped.get(&slot) {
-            item.description().to_string()
-        } else {
-            "-".to_string()
-        };
+    match parts.len() {
+        0 => String::new(),
+        1 => parts.pop().unwrap(),
+        n => format!("{} and {}", parts[..n - 1].join(", "), parts[n - 1]),
+    }
+}
 
-        // let item = item.slice_chars(0, cmp::min(item.char_len(), 13));
-        nc::waddstr(window, &format!("{:^13}", item));
+fn indefinite_article(noun: &str) -> &'static str {
+    match noun.chars().next() {
+        Some('a') | Some('e') | Some('i') | Some('o') | Some('u') => "an",
+        _ => "a",
     }
+}
 
-    fn draw_inventory(&self) {
-        let window = self.windows.map.window;
-        let player = self.player();
+fn plural(noun: &str) -> String {
+    if noun.ends_with('f') {
+        format!("{}ves", &noun[..noun.len() - 1])
+    } else {
+        format!("{}s", noun)
+    }
+}
 
-        let cpair = self.text_color;
-        nc::wbkgd(window, ' ' as nc::chtype | cpair as nc::chtype);
+/// `n` in words while it's short
+fn number_word(n: usize) -> String {
+    const WORDS: [&'static str; 11] = ["no", "one", "two", "three", "four", "five", "six",
+                                       "seven", "eight", "nine", "ten"];
+    WORDS.get(n).map_or(n.to_string(), |w| w.to_string())
+}
 
-        nc::werase(window);
-        nc::wmove(window, 0, 0);
-        if !player.items_equipped.is_empty() {
-            nc::waddstr(window, &format!("Equipped: \n"));
-            for (slot, &(ref ch, ref i)) in &player.items_equipped {
-                nc::waddstr(window,
-                            &format!(" {} - {} [{:?}]\n", ch, i.description(), slot));
-            }
-            nc::waddstr(window, &format!("\n"));
-        }
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
 
-        nc::waddstr(window, &format!("Inventory: \n"));
-        if !player.items_backpack.is_empty() {
-            for (ch, i) in &player.items_backpack {
-                nc::waddstr(window, &format!(" {} - {}\n", ch, i.description()));
-            }
-            nc::waddstr(window, &format!("\n"));
-        }
+/// What the player is told about the outcome of their interaction
+fn interact_message(result: actor::InteractResult) -> &'static str {
+    match result {
+        actor::InteractResult::FountainHeal => "You feel better.",
+        actor::InteractResult::FountainRefresh => "You feel refreshed.",
+        actor::InteractResult::FountainPoison => "The water tastes foul.",
+        actor::InteractResult::FountainDriedUp => "The fountain dries up.",
+        actor::InteractResult::FountainDry => "The fountain is dry.",
+        actor::InteractResult::AltarIdentify => "The altar glows softly.",
+        actor::InteractResult::AltarUncurse => "A malevolent aura fades away.",
+        actor::InteractResult::AltarNoItem => "Nothing happens.",
+        actor::InteractResult::AltarUsed => "The altar is cold.",
+        actor::InteractResult::BrazierLit => "You light the brazier.",
+        actor::InteractResult::BrazierDoused => "You douse the brazier.",
+        actor::InteractResult::BrazierNoFire => "You have nothing to light it with.",
+        actor::InteractResult::BrazierKnockedOver => "The brazier tips over!",
+        actor::InteractResult::TrapDart => "A dart hits you!",
+        actor::InteractResult::TrapAlarm => "A loud alarm goes off!",
+        actor::InteractResult::DoorNotOpen => "There is no open door there.",
+        actor::InteractResult::DoorBlocked => "Something is in the way.",
+        actor::InteractResult::DoorBashed => "You bash the door.",
+        actor::InteractResult::DoorBroken => "The door breaks!",
+        actor::InteractResult::NoDoor => "There is no door there.",
+        actor::InteractResult::Teleported => "You feel disoriented.",
+        actor::InteractResult::PortalBlocked => "The portal flickers and fades.",
+        actor::InteractResult::TrapNeedle => "A needle pricks you!",
+        actor::InteractResult::ChestLocked => "The chest is locked.",
+        actor::InteractResult::ChestUnlocked => "You unlock the chest with a key.",
+        actor::InteractResult::ChestOpened => "You open the chest.",
+        actor::InteractResult::ChestEmpty => "The chest is empty.",
+        actor::InteractResult::ChestDisarmed => "You disarm a trap on the chest.",
+        actor::InteractResult::ChestForced => "You break the lock.",
+        actor::InteractResult::ChestHolds => "The lock holds.",
+        actor::InteractResult::ChestContentsBroken => "Something breaks inside.",
+        actor::InteractResult::BoulderTooHeavy => "The boulder won't budge.",
+        actor::InteractResult::BoulderStuck => "Something blocks the boulder.",
+        actor::InteractResult::ShopPaid => "You pay for your purchases.",
+        actor::InteractResult::ShopNoGold => "You can't afford that.",
+        actor::InteractResult::ShopThief => "The shopkeeper shouts: \"Thief!\"",
+        actor::InteractResult::Burned => "You are burned!",
+    }
+}
 
-        nc::wnoutrefresh(window);
+/// What the player at `hearer` makes of a noise coming from `source`
+fn noise_message(heard: &game::Heard, hearer: Position, source: Coordinate) -> String {
+    let descr = heard.noise.description();
+    let descr = match loudness_word(heard.loudness) {
+        Some(word) => with_adjective(&descr, word),
+        None => descr,
+    };
+    match util::whereabouts(hearer, source) {
+        Some(place) => format!("You hear {} {}.", descr, place),
+        None => format!("You hear {}.", descr),
     }
+}
 
-    fn draw_stats(&self) {
-        let turn = self.engine.turn();
-        let window = self.windows.stats.window;
-        let player = self.player();
-        let cur_loc = self.current_location();
+/// How loud a noise seems by what's left of it; `None` for neither
+/// faint nor loud
+fn loudness_word(loudness: i32) -> Option<&'static str> {
+    if loudness <= NOISE_FAINT {
+        Some("faint")
+    } else if loudness >= NOISE_LOUD {
+        Some("loud")
+    } else {
+        None
+    }
+}
 
-        let (ac, ev) = (player.stats.base.ac, player.stats.base.ev);
-        let (dmg, acc) = (player.stats.melee_dmg, player.stats.melee_acc);
+/// `descr` with `adj` in front of the noun, fixing up the article
+fn with_adjective(descr: &str, adj: &str) -> String {
+    let noun = if descr.starts_with("a ") {
+        &descr[2..]
+    } else if descr.starts_with("an ") {
+        &descr[3..]
+    } else {
+        return format!("{} {}", adj, descr);
+    };
+    format!("{} {} {}", indefinite_article(adj), adj, noun)
+}
 
-        let cpair = self.text_color;
-        nc::wbkgd(window, ' ' as nc::chtype | cpair as nc::chtype);
+/// Colors of a pond tile, or of the bridge across it
+fn water_colors(tt: tile::Type) -> ([u8; 4], [u8; 4]) {
+    match tt {
+        tile::Bridge => (color::BRIDGE_FG, color::WATER_BG),
+        _ => (color::WATER_FG, color::WATER_BG),
+    }
+}
 
-        nc::werase(window);
-        nc::wmove(window, 0, 0);
+/// How full a stats bar is, for its color
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum BarLevel {
+    /// Above two thirds
+    High,
+    /// Above a third
+    Mid,
+    Low,
+}
 
-        let mut max_x = 0;
-        let mut max_y = 0;
-        nc::getmaxyx(window, &mut max_y, &mut max_x);
+fn bar_level(cur: u32, max: u32) -> BarLevel {
+    if cur * 3 > max * 2 {
+        BarLevel::High
+    } else if cur * 3 > max {
+        BarLevel::Mid
+    } else {
+        BarLevel::Low
+    }
+}
 
-        let mut y = 0;
-        nc::wmove(window, y, 0);
-        self.draw_val(window, "Str", player.stats.base.str_);
-        nc::wmove(window, y, 7);
-        self.draw_val(window, "DMG", dmg);
-        nc::wmove(window, y, 15);
-        self.draw_val(window, "ACC", acc);
+/// Which of a tile color's shades a cell is drawn in: lit brightly, lit
+/// dimly, seen in the dark, or only remembered
+fn light_band(visible: bool, light: u32) -> usize {
+    match (visible, light) {
+        (false, _) => 3,
+        (true, 0) => 2,
+        (true, 1...2) => 1,
+        _ => 0,
+    }
+}
 
-        y += 1;
-        nc::wmove(window, y, 0);
-        self.draw_val(window, "Int", player.stats.base.int);
-        nc::wmove(window, y, 7);
-        self.draw_val(window, " AC", ac);
-        nc::wmove(window, y, 15);
-        self.draw_val(window, "EV", ev);
+/// What a noise heard looks like on the map
+fn noise_glyph(noise: game::Noise, glyphs: &Glyphs) -> &'static str {
+    match noise {
+        game::Noise::Creature(_) => "?",
+        game::Noise::Combat | game::Noise::Alarm => "!",
+        game::Noise::DoorCreak | game::Noise::DoorSlam => "+",
+        game::Noise::Crash | game::Noise::Digging | game::Noise::Rumble => "*",
+        game::Noise::Fire => glyphs.fire,
+    }
+}
 
-        y += 1;
-        nc::wmove(window, y, 0);
-        self.draw_val(window, "Dex", player.stats.base.dex);
+/// A status as shown to the player, with the turns it has left
+fn status_badge(name: &str, turns: Option<i32>) -> String {
+    match turns {
+        Some(turns) => format!("{}({})", name, turns),
+        None => name.to_string(),
+    }
+}
 
-        y += 1;
-        nc::wmove(window, y, 0);
+/// Color an actor of `race` is drawn in
+fn actor_color(race: Race) -> [u8; 4] {
+    match race {
+        Race::Human | Race::Elf | Race::Dwarf => color::CHAR_SELF_FG,
+        Race::Shopkeeper => color::CHAR_NEUTRAL_FG,
+        Race::Pony => color::CHAR_ALLY_FG,
+        Race::Rat | Race::Goblin | Race::Troll | Race::Ghost => color::CHAR_ENEMY_FG,
+    }
+}
 
-        self.draw_stats_bar(window,
-                            "HP",
-                            player.hp,
-                            player.saved_hp,
-                            player.stats.base.max_hp);
+/// Write `lines` to `file_name` in `dir`, through a temporary file
+///
+/// Returns: the path written to.
+fn save_lines(dir: &Path, file_name: &str, lines: &[String]) -> std::io::Result<PathBuf> {
+    try!(fs::create_dir_all(dir));
+    let path = dir.join(file_name);
+    let tmp = path.with_extension("tmp");
+    {
+        let mut file = std::io::BufWriter::new(try!(fs::File::create(&tmp)));
+        for line in lines {
+            try!(writeln!(file, "{}", line));
+        }
+        try!(file.flush());
+        try!(file.get_ref().sync_all());
+    }
+    try!(fs::rename(&tmp, &path));
+    Ok(path)
+}
 
-        y += 1;
-        nc::wmove(window, y, 0);
-        self.draw_stats_bar(window,
-                            "MP",
-                            player.mp,
-                            player.saved_mp,
-                            player.stats.base.max_mp);
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::path::Path;
+
+    use hex2d::{Angle, Coordinate, Direction, Position};
+
+    use game::{self, tile, Noise};
+    use game::actor::Race;
+    use util;
+    use super::super::backend::{Rect, Style, Window};
+    use super::super::color;
+    use super::super::glyphs::{GlyphSet, Walls};
+    use super::super::headless_backend::{Frames, Headless};
+    use super::super::keymap::parse_keys;
+    use super::super::messages::{Topic, TOPICS};
+    use super::{bar_level, log_shade, log_shades, noise_message, shrink, shrink_scale};
+    use super::{light_band, location_header, water_colors};
+    use super::{BarLevel, Layout, MinimapCell, Mode, Ui};
+
+    /// Seed of the games the screens are played in
+    const GOLDEN_SEED: u32 = 12345;
+    /// Size of the screen they're drawn on
+    const GOLDEN_SIZE: (i32, i32) = (80, 25);
+
+    /// The frames pressing `keys` shows, in a new game of `seed` on a
+    /// headless screen, with the files it keeps in a directory of its
+    /// own
+    fn play(name: &str, seed: u32, keys: &str) -> Vec<String> {
+        let dir = env::temp_dir().join(format!("rhex-test-ui-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let frames = Frames::default();
+        let (w, h) = GOLDEN_SIZE;
+        let screen = Headless::open(w, h, parse_keys(keys).unwrap(), frames.clone());
+        let mut ui = Ui::with_screen(screen, &dir);
+        ui.set_glyphs(GlyphSet::Ascii);
+        ui.disable_animations();
+        ui.set_seed(seed);
+        ui.initial_spawn(Race::Human, "Tester");
+        ui.mode_switch_to(Mode::Normal);
+        ui.run();
+
+        let frames = frames.borrow().clone();
+        frames
+    }
 
-        y += 1;
-        nc::wmove(window, y, 0);
-        self.draw_stats_bar(window,
-                            "SP",
-                            player.sp,
-                            player.saved_sp,
-                            player.stats.base.max_sp);
+    /// Check `frame` against the one kept as `name`. One that isn't
+    /// there yet, or any with `RHEX_BLESS` set, is written instead, to
+    /// be looked over and committed.
+    fn assert_golden(name: &str, frame: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                       .join("src/curses/golden")
+                       .join(format!("{}.txt", name));
+        if env::var_os("RHEX_BLESS").is_some() || !path.exists() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let mut file = fs::File::create(&path).unwrap();
+            writeln!(file, "{}", frame).unwrap();
+            return;
+        }
 
-        let slots = [("R", Slot::RHand),
-                     ("L", Slot::LHand),
-                     ("F", Slot::Feet),
-                     ("B", Slot::Body),
-                     ("H", Slot::Head),
-                     ("C", Slot::Cloak),
-                     ("Q", Slot::Quick)];
+        let mut kept = String::new();
+        fs::File::open(&path).unwrap().read_to_string(&mut kept).unwrap();
+        assert!(kept.trim_right_matches('\n') == frame,
+                "{} isn't drawn as it was; now it's:\n{}",
+                path.display(),
+                frame);
+    }
 
-        for (i, &(string, slot)) in slots.iter().enumerate() {
-            if i & 1 == 0 {
-                y += 1;
-                nc::wmove(window, y, 0);
-            } else {
-                nc::wmove(window, y, 14);
-            }
+    /// The last frame `keys` shows, checked against the one kept as
+    /// `name`
+    fn golden(name: &str, keys: &str) {
+        let frames = play(name, GOLDEN_SEED, keys);
+        let last = frames.last().expect("nothing was drawn");
+        assert_golden(name, last);
+    }
 
-            self.draw_item(window, player, string, slot);
+    #[test]
+    fn headless_frames_fill_the_screen_and_no_more() {
+        let (w, h) = GOLDEN_SIZE;
+        let frames = play("size", GOLDEN_SEED, "k k l k");
+        assert!(!frames.is_empty());
+        for frame in &frames {
+            let lines: Vec<&str> = frame.split('\n').collect();
+            assert_eq!(lines.len(), h as usize);
+            assert!(lines.iter().all(|l| l.chars().count() <= w as usize));
         }
+    }
 
-        y += 1;
-        nc::wmove(window, y, 0);
+    #[test]
+    fn same_seed_and_keys_draw_the_same_frames() {
+        let keys = "k k l k k h . x n";
+        assert_eq!(play("same-a", GOLDEN_SEED, keys), play("same-b", GOLDEN_SEED, keys));
+        assert!(play("other", GOLDEN_SEED + 1, keys) != play("same-a", GOLDEN_SEED, keys));
+    }
 
-        let pos = if self.mode == Mode::Examine {
-            self.target_pos.unwrap_or(self.player().pos)
-        } else {
-            player.pos
+    /// A row drawn at once shows just what its cells printed one by one
+    /// do, clipped to the window the same way
+    #[test]
+    fn put_row_draws_what_print_at_would() {
+        let rect = Rect { x: 3, y: 2, w: 10, h: 4 };
+        let style = Style::new(color::WALL_FG[0], color::EMPTY_BG[0]);
+        let cells: Vec<Option<(char, Style)>> =
+            "#.@ .~~ #..#.."
+                .chars()
+                .map(|ch| if ch == ' ' { None } else { Some((ch, style)) })
+                .collect();
+        let draw = |by_row: bool| {
+            let frames = Frames::default();
+            let screen = Headless::open(20, 8, vec![], frames.clone());
+            let window = Window::new(&screen, rect);
+            window.fill(style);
+            for y in -1..rect.h + 1 {
+                if by_row {
+                    window.put_row(y, &cells);
+                } else {
+                    for (x, cell) in cells.iter().enumerate().take(rect.w as usize) {
+                        if let Some((ch, _)) = *cell {
+                            window.print_at(x as i32, y, &ch.to_string());
+                        }
+                    }
+                }
+            }
+            screen.borrow_mut().flush();
+            let frame = frames.borrow()[0].clone();
+            frame
         };
+        assert_eq!(draw(true), draw(false));
+    }
 
-        let head = pos.coord + pos.dir;
-        let descr = self.tile_description(head, player, cur_loc);
-        self.draw_label(window, "In front");
-        nc::wattron(window, self.text_color as i32);
-        nc::waddstr(window, &format!(" {}", descr));
+    #[test]
+    fn golden_start() {
+        golden("start", "");
+    }
 
-        y += 1;
-        nc::wmove(window, y, 0);
-        self.draw_turn(window, "Turn", turn);
-        self.draw_turn(window, "Level", cur_loc.level);
+    #[test]
+    fn golden_walk() {
+        golden("walk", "k k l k k h k . .");
+    }
 
-        nc::wnoutrefresh(window);
+    #[test]
+    fn golden_examine() {
+        golden("examine", "x n n");
     }
 
-    // TODO: Consider the distance to the Item to print something
-    // like "you see x in the distance", "you find yourself in x".
-    fn format_areas<I>(&self, mut i: I) -> Option<String>
-        where I: Iterator,
-              <I as Iterator>::Item: fmt::Display
-    {
-        if let Some(descr) = i.next() {
-            let mut s = String::new();
-            write!(&mut s, "{}", "You see: ").unwrap();
-            write!(&mut s, "{}", descr).unwrap();
+    #[test]
+    fn golden_overview() {
+        golden("overview", "M");
+    }
 
-            for ref descr in i {
-                write!(&mut s, ", ").unwrap();
-                write!(&mut s, "{}", descr).unwrap();
-            }
+    #[test]
+    fn golden_inventory() {
+        golden("inventory", "I");
+    }
 
-            write!(&mut s, ".").unwrap();
-            Some(s)
-        } else {
-            None
-        }
+    #[test]
+    fn cells_are_shaded_by_how_well_they_are_seen() {
+        assert_eq!(light_band(true, 10), 0);
+        assert_eq!(light_band(true, 3), 0);
+        assert_eq!(light_band(true, 2), 1);
+        assert_eq!(light_band(true, 1), 1);
+        assert_eq!(light_band(true, 0), 2);
+        // what's remembered looks the same whatever the light
+        assert_eq!(light_band(false, 0), 3);
+        assert_eq!(light_band(false, 5), 3);
     }
 
-    fn turn_to_color(&self, turn: u64, calloc: &RefCell<color::Allocator>) -> Option<i16> {
-        let mut calloc = calloc.borrow_mut();
+    #[test]
+    fn bridge_over_water_draws_the_bridge_glyph() {
+        let glyphs = GlyphSet::Ascii.glyphs();
+        let walls = Walls::default();
+        let glyph = GlyphSet::Ascii.tile(tile::Bridge, None, true, walls);
+        assert_eq!(glyph, glyphs.bridge);
+        let (fg, bg) = water_colors(tile::Bridge);
+        assert_eq!(fg, color::BRIDGE_FG);
+        // still water underneath
+        assert_eq!(bg, color::WATER_BG);
+
+        assert_eq!(GlyphSet::Ascii.tile(tile::Water, None, true, walls), glyphs.water);
+        assert_eq!(water_colors(tile::Water), (color::WATER_FG, color::WATER_BG));
+    }
 
-        let dturn = self.engine.turn() - turn;
+    #[test]
+    fn every_noise_has_a_message() {
+        let noises = [Noise::Creature(Race::Rat),
+                      Noise::Creature(Race::Troll),
+                      Noise::Combat,
+                      Noise::DoorCreak,
+                      Noise::DoorSlam,
+                      Noise::Alarm,
+                      Noise::Crash,
+                      Noise::Digging,
+                      Noise::Rumble,
+                      Noise::Fire];
+        let hearer = Position::new(Coordinate::new(0, 0), Direction::XZ);
+        for &noise in &noises {
+            let heard = game::Heard {
+                noise: noise,
+                loudness: 5,
+                turn: 0,
+            };
+            let descr = noise.description();
+            assert!(!descr.is_empty());
+
+            let ahead = hearer.coord + hearer.dir + hearer.dir;
+            assert!(noise_message(&heard, hearer, ahead)
+                        .starts_with(&format!("You hear {} to the ", descr)));
+            // right where the player stands
+            assert_eq!(noise_message(&heard, hearer, hearer.coord),
+                       format!("You hear {}.", descr));
+        }
+        assert_eq!(Noise::Creature(Race::Rat).description(), "a rat");
+    }
 
-        let fg = if dturn < 1 {
-            Some(color::LOG_1_FG)
-        } else if dturn < 4 {
-            Some(color::LOG_2_FG)
-        } else if dturn < 16 {
-            Some(color::LOG_3_FG)
-        } else if dturn < 32 {
-            Some(color::LOG_4_FG)
-        } else if dturn < 64 {
-            Some(color::LOG_5_FG)
-        } else {
-            None
+    /// What the player at the origin facing `facing` makes of fighting
+    /// as loud as `loudness`, next to them in direction `dir`
+    fn fighting(facing: Direction, dir: Direction, loudness: i32) -> String {
+        let heard = game::Heard {
+            noise: Noise::Combat,
+            loudness: loudness,
+            turn: 0,
         };
+        let origin = Coordinate::new(0, 0);
+        noise_message(&heard, Position::new(origin, facing), origin + dir)
+    }
 
-        fg.map(|fg| calloc.get(fg, color::BACKGROUND_BG))
+    /// The compass word in "You hear fighting to the <word>."
+    fn compass_word(message: &str) -> String {
+        let prefix = "You hear fighting to the ";
+        assert!(message.starts_with(prefix) && message.ends_with('.'),
+                "{}",
+                message);
+        message[prefix.len()..message.len() - 1].to_string()
     }
 
-    fn tile_description(&self,
-                        coord: Coordinate,
-                        astate: &Actor,
-                        gstate: &game::Location)
-                        -> String {
-        if !astate.knows(coord) {
-            return "Unknown".to_string();
+    #[test]
+    fn each_of_the_six_directions_has_its_own_word() {
+        let mut words: Vec<String> = Direction::all()
+                                         .iter()
+                                         .map(|&dir| compass_word(&fighting(dir, dir, 5)))
+                                         .collect();
+        words.sort();
+        assert_eq!(words,
+                   vec!["east", "northeast", "northwest", "southeast", "southwest", "west"]);
+
+        for &dir in Direction::all() {
+            let word = compass_word(&fighting(dir, dir, 5));
+            let back = compass_word(&fighting(dir + Angle::Back, dir + Angle::Back, 5));
+            let opposite = match &word[..] {
+                "east" => "west",
+                "west" => "east",
+                "northeast" => "southwest",
+                "southwest" => "northeast",
+                "northwest" => "southeast",
+                _ => "northwest",
+            };
+            assert_eq!(back, opposite, "{:?}", dir);
         }
+    }
 
-        let tile_type = gstate.at(coord).tile().type_;
-        let tile = gstate.at(coord).tile();
-        let feature_descr = tile.feature.map(|f| f.description().to_string());
-        let item_descr = gstate.at(coord).item_map_or(None, |i| Some(i.description().to_string()));
+    #[test]
+    fn noises_from_behind_are_behind_whichever_way_one_faces() {
+        for &dir in Direction::all() {
+            assert_eq!(fighting(dir + Angle::Back, dir, 5), "You hear fighting behind you.");
+            // off to the side, it's the compass again
+            for &angle in &[Angle::Left, Angle::Right, Angle::LeftBack, Angle::RightBack] {
+                assert_eq!(fighting(dir + angle, dir, 5), fighting(dir, dir, 5));
+            }
+        }
+    }
 
-        let actor_descr = if astate.sees(coord) || astate.is_dead() {
-            gstate.at(coord).actor_map_or(None, |a| {
-                Some(match a.race {
-                         // Race::Pony => "A Pony",
-                         Race::Rat => "A rat",
-                         Race::Goblin => "Goblin",
-                         Race::Troll => "Troll",
-                         Race::Human => "Human",
-                         Race::Elf => "Elf",
-                         Race::Dwarf => "Dwarf",
-                     }
-                     .to_string())
-            })
-        } else {
-            None
+    #[test]
+    fn faint_and_loud_noises_say_so() {
+        let facing = Direction::XZ;
+        let place = compass_word(&fighting(facing, facing, 5));
+        assert_eq!(fighting(facing, facing, 2),
+                   format!("You hear faint fighting to the {}.", place));
+        assert_eq!(fighting(facing, facing, 8),
+                   format!("You hear loud fighting to the {}.", place));
+
+        let heard = game::Heard {
+            noise: Noise::Alarm,
+            loudness: 1,
+            turn: 0,
         };
+        let origin = Position::new(Coordinate::new(0, 0), facing);
+        assert_eq!(noise_message(&heard, origin, origin.coord), "You hear a faint alarm.");
+    }
 
-        match (tile_type, feature_descr, actor_descr, item_descr) {
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rect {
+        Rect { x: x, y: y, w: w, h: h }
+    }
 
-            (_, _, Some(a_descr), _) => a_descr,
-            (_, _, _, Some(i_descr)) => i_descr,
-            (_, Some(f_descr), _, _) => f_descr.to_string(),
-            (tile::Wall, _, _, _) => "a wall".to_string(),
-            (tile::Empty, _, _, _) => {
-                match tile.area.and_then(|a| Some(a.type_)) {
-                    Some(area::Room(_)) => "room".to_string(),
-                    None => "nothing".to_string(),
+    #[test]
+    fn standard_terminal_has_all_panels() {
+        let layout = Layout::new(80, 24);
+        assert_eq!(layout.map, rect(0, 0, 50, 24));
+        assert_eq!(layout.stats, Some(rect(50, 0, 30, 16)));
+        assert_eq!(layout.log, Some(rect(50, 16, 30, 8)));
+        assert_eq!(layout.full, rect(0, 0, 80, 24));
+    }
+
+    #[test]
+    fn big_terminal_gives_the_room_to_the_map_and_log() {
+        let layout = Layout::new(200, 60);
+        assert_eq!(layout.map, rect(0, 0, 170, 60));
+        assert_eq!(layout.stats, Some(rect(170, 0, 30, 16)));
+        assert_eq!(layout.log, Some(rect(170, 16, 30, 44)));
+        assert_eq!(layout.full, rect(0, 0, 200, 60));
+    }
+
+    #[test]
+    fn small_terminal_keeps_the_map_and_drops_the_log() {
+        let layout = Layout::new(50, 15);
+        assert_eq!(layout.map, rect(0, 0, 40, 15));
+        assert_eq!(layout.stats, Some(rect(40, 0, 10, 15)));
+        assert_eq!(layout.log, None);
+        assert_eq!(layout.full, rect(0, 0, 50, 15));
+    }
+
+    #[test]
+    fn tiny_terminal_is_all_map() {
+        let layout = Layout::new(30, 10);
+        assert_eq!(layout.map, rect(0, 0, 30, 10));
+        assert_eq!(layout.stats, None);
+        assert_eq!(layout.log, None);
+
+        assert_eq!(Layout::new(0, -5).map, rect(0, 0, 1, 1));
+    }
+
+    #[test]
+    fn panels_fill_the_screen_without_overlapping() {
+        for &(max_x, max_y) in &[(80, 24), (200, 60), (50, 15), (70, 18), (71, 19), (41, 2)] {
+            let layout = Layout::new(max_x, max_y);
+            assert!(layout.map.w > 0, "{}x{}", max_x, max_y);
+            match layout.stats {
+                Some(stats) => {
+                    assert_eq!(layout.map.w + stats.w, max_x, "{}x{}", max_x, max_y);
+                    let log_h = layout.log.map_or(0, |log| {
+                        assert_eq!((log.x, log.w), (stats.x, stats.w));
+                        assert_eq!(log.y, stats.y + stats.h);
+                        log.h
+                    });
+                    assert_eq!(stats.h + log_h, max_y, "{}x{}", max_x, max_y);
                 }
+                None => assert_eq!(layout.map.w, max_x),
             }
-            _ => tile.type_.description().to_string(),
         }
     }
 
-    fn draw_log(&self) {
-        let window = self.windows.log.window;
+    #[test]
+    fn bar_colors_change_past_the_thirds() {
+        assert_eq!(bar_level(20, 20), BarLevel::High);
+        assert_eq!(bar_level(14, 20), BarLevel::High);
+        assert_eq!(bar_level(13, 20), BarLevel::Mid);
+        assert_eq!(bar_level(7, 20), BarLevel::Mid);
+        assert_eq!(bar_level(6, 20), BarLevel::Low);
+        assert_eq!(bar_level(2, 20), BarLevel::Low);
+        assert_eq!(bar_level(0, 20), BarLevel::Low);
+    }
 
-        let cpair = nc::COLOR_PAIR(self.calloc
-                                       .borrow_mut()
-                                       .get(color::VISIBLE_FG, color::BACKGROUND_BG));
-        nc::wbkgd(window, ' ' as nc::chtype | cpair as nc::chtype);
-        nc::werase(window);
-        nc::wmove(window, 0, 0);
+    #[test]
+    fn exactly_a_third_is_not_above_it() {
+        assert_eq!(bar_level(31, 45), BarLevel::High);
+        assert_eq!(bar_level(30, 45), BarLevel::Mid);
+        assert_eq!(bar_level(16, 45), BarLevel::Mid);
+        assert_eq!(bar_level(15, 45), BarLevel::Low);
+    }
 
-        match self.mode {
-            Mode::GoTo => {
-                nc::waddstr(window, &format!("Go to where?\n"));
-            }
-            Mode::Inventory(InvMode::Drop_) => {
-                nc::waddstr(window, &format!("Drop what?\n"));
-            }
-            Mode::Inventory(InvMode::Equip) => {
-                nc::waddstr(window, &format!("Equip/use what?\n"));
-            }
-            _ => {}
-        }
+    #[test]
+    fn overfull_bars_are_high() {
+        assert_eq!(bar_level(25, 20), BarLevel::High);
+        assert_eq!(bar_level(1, 1), BarLevel::High);
+    }
 
-        for i in self.log.borrow().iter() {
-            if nc::getcury(window) == nc::getmaxy(window) - 1 {
-                break;
-            }
-            if let Some(color) = self.turn_to_color(i.turn, &self.calloc) {
-                let cpair = nc::COLOR_PAIR(color);
-                nc::wattron(window, cpair as i32);
-                nc::waddstr(window, &format!("{} ", i.text));
-            }
-            nc::waddstr(window, "\n");
-        }
+    /// A known tile for `shrink`, out of sight
+    fn known(x: i32, y: i32, kind: MinimapCell) -> (i32, i32, MinimapCell, bool) {
+        (x, y, kind, false)
+    }
 
-        nc::wnoutrefresh(window);
+    #[test]
+    fn as_few_hexes_per_character_as_fit() {
+        assert_eq!(shrink_scale(40, 20, 40, 20), 1);
+        assert_eq!(shrink_scale(10, 5, 40, 20), 1);
+        assert_eq!(shrink_scale(80, 20, 40, 20), 2);
+        assert_eq!(shrink_scale(81, 20, 40, 20), 3);
+        assert_eq!(shrink_scale(40, 61, 40, 20), 4);
     }
 
-    fn draw_intro(&mut self) {
-        let window = self.windows.full.window;
-        let mut calloc = self.calloc.borrow_mut();
-        let cpair = nc::COLOR_PAIR(calloc.get(color::VISIBLE_FG, color::BACKGROUND_BG));
-        nc::wbkgd(window, ' ' as nc::chtype | cpair as nc::chtype);
-        nc::werase(window);
-        nc::wmove(window, 0, 0);
+    #[test]
+    fn a_character_shows_most_of_what_it_covers() {
+        use super::MinimapCell::*;
+        // 4 by 2 into 2 by 1: two characters of 2 by 2
+        let tiles = [known(0, 0, Wall),
+                     known(1, 0, Wall),
+                     known(0, 1, Wall),
+                     known(1, 1, Floor),
+                     known(2, 0, Floor),
+                     known(3, 0, Floor),
+                     known(2, 1, Floor),
+                     (3, 1, Water, true)];
+        let shrunk = shrink(&tiles, 2, 1).unwrap();
+
+        assert_eq!(shrunk.scale, 2);
+        assert_eq!((shrunk.cols, shrunk.rows), (2, 1));
+        assert_eq!(shrunk.cells.len(), 2);
+        assert_eq!(shrunk.cells[&(0, 0)], (Wall, false));
+        // in sight if any of it is
+        assert_eq!(shrunk.cells[&(1, 0)], (Floor, true));
+    }
 
-        nc::waddstr(window,
-                    "A long time ago in a dungeon deep, deep underground...\n\n");
-        nc::waddstr(window,
-                    &format!("You can press {} in the game for help.\n\n",
-                             KEY_HELP as u8 as char));
-        nc::waddstr(window, "Press anything to start.");
-        nc::wnoutrefresh(window);
+    #[test]
+    fn stairs_show_however_outnumbered() {
+        use super::MinimapCell::*;
+        let tiles = [known(0, 0, Floor),
+                     known(1, 0, StairsUp),
+                     known(0, 1, StairsDown),
+                     known(1, 1, Wall),
+                     known(2, 0, Floor),
+                     known(3, 0, Floor),
+                     known(2, 1, Floor),
+                     known(3, 1, Door)];
+        let shrunk = shrink(&tiles, 2, 1).unwrap();
+
+        assert_eq!(shrunk.cells[&(0, 0)].0, StairsDown);
+        // doors don't
+        assert_eq!(shrunk.cells[&(1, 0)].0, Floor);
     }
 
-    fn draw_pickrace(&mut self) {
-        let window = self.windows.full.window;
-        let mut calloc = self.calloc.borrow_mut();
-        let cpair = nc::COLOR_PAIR(calloc.get(color::VISIBLE_FG, color::BACKGROUND_BG));
-        nc::wbkgd(window, ' ' as nc::chtype | cpair as nc::chtype);
-        nc::werase(window);
-        nc::wmove(window, 0, 0);
-
-        nc::waddstr(window, "Pick your race\n\n");
-        nc::waddstr(window, "a) Human\n");
-        nc::waddstr(window, "b) Elf\n");
-        nc::waddstr(window, "c) Dwarf\n");
-
-        nc::wnoutrefresh(window);
-    }
-
-    fn draw_help(&mut self) {
-        let window = self.windows.full.window;
-        let mut calloc = self.calloc.borrow_mut();
-        let cpair = nc::COLOR_PAIR(calloc.get(color::VISIBLE_FG, color::BACKGROUND_BG));
-        nc::wbkgd(window, ' ' as nc::chtype | cpair as nc::chtype);
-        nc::werase(window);
-        nc::wmove(window, 0, 0);
-
-        nc::waddstr(window, "This game is still incomplete. Sorry for that.\n\n");
-        nc::waddstr(window, "= (more or less) Implemented actions = \n\n");
-        nc::waddstr(window, "Move/attack: hjklui\n");
-        nc::waddstr(window, "Strafe/attack: Shift + h/l\n");
-        nc::waddstr(window, "Charge: c\n");
-        nc::waddstr(window, "Wait: .\n");
-        nc::waddstr(window, "Autoexplore: o\n");
-        nc::waddstr(window, "Automove: shift + k\n");
-        nc::waddstr(window, "Go to: G (only '>' follow-up implemented)\n");
-        nc::waddstr(window, "Examine: x\n");
-        nc::waddstr(window, "Pick item in front: ,\n");
-        nc::waddstr(window, "Inventory: I\n");
-        nc::waddstr(window, "Equip: E\n");
-        nc::waddstr(window, "Drop: D\n");
-        nc::waddstr(window, "Ranged/Throw: f (not fully working)\n");
-        nc::waddstr(window, "Quit: q\n");
-        nc::wnoutrefresh(window);
-    }
-
-    fn draw_quit(&mut self) {
-        let window = self.windows.full.window;
-        let mut calloc = self.calloc.borrow_mut();
-        let cpair = nc::COLOR_PAIR(calloc.get(color::VISIBLE_FG, color::BACKGROUND_BG));
-
-        let mut max_x = 0;
-        let mut max_y = 0;
-        nc::getmaxyx(nc::stdscr, &mut max_y, &mut max_x);
-        let text = "Quit. Are you sure?";
-
-        nc::wbkgd(window, ' ' as nc::chtype | cpair as nc::chtype);
-        nc::werase(window);
-        nc::wmove(window, max_y / 2, (max_x - text.chars().count() as i32) / 2);
-
-        nc::waddstr(window, text);
-        nc::wnoutrefresh(window);
+    #[test]
+    fn nothing_to_shrink_or_no_room_is_no_map() {
+        assert!(shrink(&[], 10, 10).is_none());
+        assert!(shrink(&[known(0, 0, MinimapCell::Floor)], 0, 10).is_none());
+        let one = shrink(&[known(5, 7, MinimapCell::Floor)], 1, 1).unwrap();
+        assert_eq!((one.scale, one.cols, one.rows), (1, 1, 1));
     }
 
-    fn event(&mut self, event: Event) {
-        match event {
-            Event::Log(logev) => {
-                match logev {
-                    LogEvent::AutoExploreDone => self.log("Nothing else to explore."),
-                    LogEvent::AutoExploreBlocked => self.log("Can't get there."),
+    #[test]
+    fn combat_entries_fade_as_they_always_have() {
+        let shades: Vec<Option<u8>> = [0, 1, 3, 4, 15, 16, 31, 32, 63, 64]
+                                          .iter()
+                                          .map(|&age| log_shade(Topic::Combat, age))
+                                          .collect();
+        let fg = color::LOG_COMBAT_FG;
+        assert_eq!(shades,
+                   vec![Some(fg[0]),
+                        Some(fg[1]),
+                        Some(fg[1]),
+                        Some(fg[2]),
+                        Some(fg[2]),
+                        Some(fg[3]),
+                        Some(fg[3]),
+                        Some(fg[4]),
+                        Some(fg[4]),
+                        None]);
+    }
+
+    #[test]
+    fn every_topic_starts_bright_and_fades_out_through_its_own_shades() {
+        for &topic in &TOPICS {
+            let shades = log_shades(topic);
+            assert_eq!(log_shade(topic, 0), Some(shades[0]));
+
+            let mut seen = vec![];
+            for age in 0..1000 {
+                match log_shade(topic, age) {
+                    Some(fg) => {
+                        if seen.last() != Some(&fg) {
+                            seen.push(fg);
+                        }
+                    }
+                    None => break,
                 }
             }
+            assert_eq!(seen, shades.to_vec(), "{:?}", topic);
+            assert_eq!(log_shade(topic, 1000), None);
         }
     }
-}
 
+    #[test]
+    fn danger_lasts_longest_and_flavor_shortest() {
+        let gone = |topic| (0..1000).position(|age| log_shade(topic, age).is_none()).unwrap();
+        assert!(gone(Topic::Flavor) < gone(Topic::Combat));
+        assert!(gone(Topic::Combat) < gone(Topic::System));
+        assert_eq!(gone(Topic::System), gone(Topic::Discovery));
+        assert!(gone(Topic::System) < gone(Topic::Danger));
+        assert_eq!(gone(Topic::Combat), 64);
+    }
 
-impl Drop for Ui {
-    fn drop(&mut self) {
-        nc::clear();
-        nc::refresh();
-        nc::endwin();
+    #[test]
+    fn topics_have_shades_of_their_own() {
+        for (i, &a) in TOPICS.iter().enumerate() {
+            for &b in &TOPICS[i + 1..] {
+                assert!(log_shades(a)[0] != log_shades(b)[0], "{:?} {:?}", a, b);
+            }
+        }
     }
-}
 
-//        . . .
-//       . . . .
-//      . . . . .
-//       . . . .
-//        . . .
-pub fn item_to_str(t: item::Category) -> &'static str {
-    match t {
-        item::Category::Weapon => ")",
-        item::Category::RangedWeapon => "}",
-        item::Category::Armor => "[",
-        item::Category::Misc => "\"",
-        item::Category::Consumable => "%",
+    #[test]
+    fn header_has_the_level_place_and_time_of_day_if_any() {
+        let mut loc = game::Location::new(game::LevelId::start(), &mut util::game_rng(0));
+        let coord = loc.start;
+        let place = loc.place_name(coord);
+        assert_eq!(location_header(&loc, coord), format!("Dungeon:1, {}, morning", place));
+
+        loc.id = game::LevelId::new(game::Branch::Main, 4);
+        assert_eq!(location_header(&loc, coord), format!("Dungeon:5, {}", place));
     }
 }
@@ -1,19 +1,169 @@
+//! What goes to `debug.log`, and how much of it.
+//!
+//! `RHEX_LOG` sets the levels, comma separated: a bare level is the one
+//! for everything, and `target=level` the one for a target and those
+//! under it, like `RHEX_LOG=info,ai=trace,game::act=debug`. The game
+//! logs its decisions under these targets:
+//!
+//! * `ai`: what monsters chose to do, and why
+//! * `game::act`: how actions turned out, attack rolls and rejections
+//! * `generate`: what each level was made of
+//! * `perf`: how long each turn took
+//!
+//! Everything else logs under its module. Without `RHEX_LOG` only
+//! `info` and up is written, and what isn't written costs a comparison.
+
+use std::env;
+
+use log::{self, LogLevelFilter};
 use fern;
-use log;
 use chrono;
 
+const PATH: &'static str = "debug.log";
+const VAR: &'static str = "RHEX_LOG";
+
+/// Levels in `spec`, as `RHEX_LOG` has them
+///
+/// Returns: the default level, the ones for targets, and the entries
+/// that didn't parse.
+fn parse(spec: &str) -> (LogLevelFilter, Vec<(String, LogLevelFilter)>, Vec<String>) {
+    let mut default = LogLevelFilter::Info;
+    let mut targets = vec![];
+    let mut bad = vec![];
+    for entry in spec.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        let mut parts = entry.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(level), None) => {
+                match level.parse() {
+                    Ok(level) => default = level,
+                    Err(_) => bad.push(entry.to_string()),
+                }
+            }
+            (Some(target), Some(level)) if !target.is_empty() => {
+                match level.parse() {
+                    Ok(level) => targets.push((target.to_string(), level)),
+                    Err(_) => bad.push(entry.to_string()),
+                }
+            }
+            _ => bad.push(entry.to_string()),
+        }
+    }
+    (default, targets, bad)
+}
+
+/// Lines as `debug.log` has them, let through as `spec` sets the levels,
+/// with the entries of `spec` that didn't parse
+fn dispatch(spec: &str) -> (fern::Dispatch, Vec<String>) {
+    let (default, targets, bad) = parse(spec);
+
+    let mut dispatch = fern::Dispatch::new()
+                           .format(|out, message, record: &log::LogRecord| {
+                               out.finish(format_args!("{} {:<6} {}: {}",
+                                                       chrono::Local::now().to_rfc3339(),
+                                                       record.level(),
+                                                       record.target(),
+                                                       message))
+                           })
+                           .level(default);
+    for (target, level) in targets {
+        dispatch = dispatch.level_for(target, level);
+    }
+    (dispatch, bad)
+}
+
 pub fn init() {
-    let logger_config = fern::DispatchConfig {
-        format: Box::new(|msg: &str, level: &log::LogLevel, _location: &log::LogLocation| {
-            // This is a fairly simple format, though it's possible to do more complicated ones.
-            // This closure can contain any code, as long as it produces a String message.
-            format!("{} {:<6} {}", chrono::Local::now().to_rfc3339(), level, msg)
-        }),
-        output: vec![fern::OutputConfig::file("debug.log")],
-        level: log::LogLevelFilter::Debug,
+    let file = match fern::log_file(PATH) {
+        Ok(file) => file,
+        Err(e) => panic!("Failed to open {}: {}", PATH, e),
     };
-
-    if let Err(e) = fern::init_global_logger(logger_config, log::LogLevelFilter::Trace) {
+    let (dispatch, bad) = dispatch(&env::var(VAR).unwrap_or_default());
+    if let Err(e) = dispatch.chain(file).apply() {
         panic!("Failed to initialize global logger: {}", e);
     }
+
+    for entry in bad {
+        warn!("Ignoring {} entry {:?}", VAR, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use log::LogLevelFilter;
+
+    use super::{dispatch, parse};
+
+    #[test]
+    fn nothing_set_leaves_info() {
+        assert_eq!(parse(""), (LogLevelFilter::Info, vec![], vec![]));
+    }
+
+    #[test]
+    fn bare_level_is_the_default() {
+        let (default, targets, bad) = parse("debug");
+        assert_eq!(default, LogLevelFilter::Debug);
+        assert!(targets.is_empty());
+        assert!(bad.is_empty());
+    }
+
+    #[test]
+    fn targets_get_their_own_level() {
+        let (default, targets, bad) = parse("warn, ai=trace,game::act=debug");
+        assert_eq!(default, LogLevelFilter::Warn);
+        assert_eq!(targets,
+                   vec![("ai".to_string(), LogLevelFilter::Trace),
+                        ("game::act".to_string(), LogLevelFilter::Debug)]);
+        assert!(bad.is_empty());
+    }
+
+    #[test]
+    fn levels_are_case_insensitive() {
+        let (default, targets, _) = parse("DEBUG,perf=Trace");
+        assert_eq!(default, LogLevelFilter::Debug);
+        assert_eq!(targets, vec![("perf".to_string(), LogLevelFilter::Trace)]);
+    }
+
+    #[test]
+    fn bad_entries_are_kept_apart() {
+        let (default, targets, bad) = parse("loud,ai=chatty,=debug,generate=off");
+        assert_eq!(default, LogLevelFilter::Info);
+        assert_eq!(targets, vec![("generate".to_string(), LogLevelFilter::Off)]);
+        assert_eq!(bad,
+                   vec!["loud".to_string(), "ai=chatty".to_string(), "=debug".to_string()]);
+    }
+
+    #[test]
+    fn last_bare_level_wins() {
+        let (default, _, _) = parse("trace,,error");
+        assert_eq!(default, LogLevelFilter::Error);
+    }
+
+    /// The only test to set the logger: every test's lines go to it from
+    /// then on, so only those saying "checked" are looked at
+    #[test]
+    fn each_target_is_written_down_to_its_own_level() {
+        let (tx, rx) = mpsc::channel();
+        let (dispatch, bad) = dispatch("warn,ai=trace,game::act=debug");
+        assert!(bad.is_empty());
+        dispatch.chain(tx).apply().unwrap();
+
+        trace!(target: "ai", "checked: ai trace");
+        debug!(target: "game::act", "checked: act debug");
+        trace!(target: "game::act", "checked: act trace");
+        debug!(target: "game::act::attack", "checked: attack debug");
+        info!(target: "generate", "checked: generate info");
+        warn!(target: "generate", "checked: generate warn");
+        info!(target: "ai_extra", "checked: ai_extra info");
+
+        let written: Vec<String> = rx.try_iter().filter(|l| l.contains("checked: ")).collect();
+        let said: Vec<&str> = written.iter()
+                                     .map(|l| l.trim_right())
+                                     .map(|l| &l[l.find("checked: ").unwrap() + 9..])
+                                     .collect();
+        assert_eq!(said,
+                   vec!["ai trace", "act debug", "attack debug", "generate warn"]);
+        // as debug.log has them: time, level, target and the message
+        assert!(written[0].contains(" TRACE  ai: checked: ai trace"), "{}", written[0]);
+    }
 }
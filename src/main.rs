@@ -1,9 +1,11 @@
 #![allow(deprecated)]
 
-extern crate simplemap;
+extern crate rhex_core;
+#[cfg(feature = "ncurses")]
 extern crate ncurses;
+#[cfg(feature = "termion")]
+extern crate termion;
 extern crate hex2d;
-extern crate hex2d_dpcext as hex2dext;
 extern crate libc;
 extern crate rand;
 extern crate num;
@@ -12,20 +14,250 @@ extern crate chrono;
 #[macro_use]
 extern crate log;
 extern crate fern;
-extern crate core;
-extern crate fnv;
 
-mod ai;
+mod bot;
 mod curses;
-mod game;
-mod generate;
-mod util;
 mod logging;
 
+use rhex_core::{game, util};
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &'static str = "Usage: rhex [--wizard] [--seed SEED] [--no-animations] \
+                              [--glyphs ascii|unicode|lines] \
+                              [--backend curses|termion] [--headless SCRIPT] | \
+                              --bot [--seed SEED] | \
+                              --dump-levels SEED LEVELS [text|json] | \
+                              --replay FILE | --verify FILE | --bench-sim TURNS";
+
+/// Seed of the level `--bench-sim` runs on, so runs compare
+const BENCH_SEED: u32 = 0x5eed;
+
+/// Size of the screen `--headless` draws on
+const HEADLESS_SIZE: (i32, i32) = (80, 25);
+
+fn usage() -> ! {
+    let _ = writeln!(io::stderr(), "{}", USAGE);
+    process::exit(2);
+}
+
 fn main() {
     logging::init();
 
-    let mut ui = curses::Ui::new().unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--bot") {
+        play_bot(&args);
+        return;
+    }
+    if args.first().map_or(false, |a| a == "--dump-levels") {
+        dump_levels(&args[1..]);
+        return;
+    }
+    if args.iter().all(|a| a != "--replay" && a != "--verify" && a != "--bench-sim") {
+        play(&args);
+        return;
+    }
+
+    if args.len() == 2 && args[0] == "--bench-sim" {
+        match args[1].parse() {
+            Ok(turns) => bench_sim(turns),
+            Err(_) => usage(),
+        }
+        return;
+    }
+
+    if args.len() != 2 || (args[0] != "--replay" && args[0] != "--verify") {
+        usage();
+    }
+
+    let replay = match game::Replay::load(Path::new(&args[1])) {
+        Ok(replay) => replay,
+        Err(e) => {
+            let _ = writeln!(io::stderr(), "{}: {}", args[1], e);
+            process::exit(1);
+        }
+    };
+
+    if args[0] == "--verify" {
+        // headless: play it all out and check it went the same way
+        match game::Engine::resume(&replay) {
+            Ok(engine) => println!("turn {}, state {:016x}", engine.turn(), engine.state_hash()),
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        let mut ui = curses::Ui::new(Default::default()).unwrap();
+        ui.spectate(&replay);
+        ui.run();
+    }
+}
+
+/// A game at the terminal: `--seed` makes it the same dungeon as
+/// another game with that seed
+fn play(args: &[String]) {
+    let mut wizard = false;
+    let mut animate = true;
+    let mut seed = None;
+    let mut glyphs = None;
+    let mut backend = curses::BackendKind::default();
+    let mut script = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--wizard" => wizard = true,
+            "--no-animations" => animate = false,
+            "--seed" => {
+                match args.next().map(|s| s.parse()) {
+                    Some(Ok(s)) => seed = Some(s),
+                    _ => usage(),
+                }
+            }
+            "--glyphs" => {
+                match args.next().and_then(|s| curses::GlyphSet::from_name(s)) {
+                    Some(g) => glyphs = Some(g),
+                    None => usage(),
+                }
+            }
+            "--backend" => {
+                match args.next().and_then(|s| curses::BackendKind::from_name(s)) {
+                    Some(b) => backend = b,
+                    None => usage(),
+                }
+            }
+            "--headless" => {
+                match args.next() {
+                    Some(path) => script = Some(path),
+                    None => usage(),
+                }
+            }
+            _ => usage(),
+        }
+    }
 
+    let frames = curses::Frames::default();
+    let mut ui = match script {
+        Some(path) => {
+            let keys = match read_script(Path::new(path)) {
+                Ok(keys) => keys,
+                Err(e) => {
+                    let _ = writeln!(io::stderr(), "{}: {}", path, e);
+                    process::exit(1);
+                }
+            };
+            let (w, h) = HEADLESS_SIZE;
+            curses::Ui::with_screen(curses::Headless::open(w, h, keys, frames.clone()),
+                                    Path::new("."))
+        }
+        None => curses::Ui::new(backend).unwrap(),
+    };
+    if wizard {
+        ui.set_wizard();
+    }
+    if !animate {
+        ui.disable_animations();
+    }
+    if let Some(seed) = seed {
+        ui.set_seed(seed);
+    }
+    if let Some(glyphs) = glyphs {
+        ui.set_glyphs(glyphs);
+    }
     ui.run();
+
+    // with a script, what it led to is the output
+    if let Some(frame) = frames.borrow().last() {
+        println!("{}", frame);
+    }
+}
+
+/// A game played by another program through stdin and stdout, as
+/// `bot` describes
+fn play_bot(args: &[String]) {
+    let seed = match (args.len(), args.iter().position(|a| a == "--seed")) {
+        (1, None) => rand::random(),
+        (3, Some(i)) => {
+            match args.get(i + 1).map(|s| s.parse()) {
+                Some(Ok(seed)) => seed,
+                _ => usage(),
+            }
+        }
+        _ => usage(),
+    };
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = bot::run(seed, game::actor::Race::Human, stdin.lock(), &mut stdout.lock()) {
+        let _ = writeln!(io::stderr(), "{}", e);
+        process::exit(1);
+    }
+}
+
+/// Generate the first levels of the dungeon for a seed and print them
+/// all, as text by default: to see what a change to the generator does
+/// to them
+fn dump_levels(args: &[String]) {
+    let (seed, levels): (u32, u32) = match (args.get(0).map(|s| s.parse()),
+                                            args.get(1).map(|s| s.parse())) {
+        (Some(Ok(seed)), Some(Ok(levels))) => (seed, levels),
+        _ => usage(),
+    };
+    let format = match args.get(2) {
+        None => curses::Format::Text,
+        Some(name) => curses::Format::from_name(name).unwrap_or_else(|| usage()),
+    };
+    if args.len() > 3 {
+        usage();
+    }
+
+    let dumps: Vec<String> = (0..levels)
+                                 .map(|depth| {
+                                     let id = game::LevelId::new(game::Branch::Main, depth);
+                                     let loc = game::generate_level(seed, id);
+                                     let dump = curses::export(&loc, format);
+                                     match format {
+                                         curses::Format::Text => format!("{}\n{}", id, dump),
+                                         curses::Format::Json => dump,
+                                     }
+                                 })
+                                 .collect();
+    match format {
+        curses::Format::Text => print!("{}", dumps.join("\n")),
+        curses::Format::Json => print!("[{}]\n", dumps.join(",")),
+    }
+}
+
+/// Keys for `--headless` to press, named as in the keys file
+fn read_script(path: &Path) -> Result<Vec<i32>, String> {
+    let mut text = String::new();
+    try!(fs::File::open(path)
+             .and_then(|mut f| f.read_to_string(&mut text))
+             .map_err(|e| e.to_string()));
+    curses::parse_keys(&text)
+}
+
+/// Let the AI play `turns` turns with the player standing still, and
+/// print where the time went on one line
+fn bench_sim(turns: u64) {
+    let mut sim = game::Simulation::new(BENCH_SEED, game::actor::Race::Human);
+    let start = Instant::now();
+    sim.wait(turns);
+    let total = start.elapsed();
+
+    let loc = sim.engine().current_location();
+    println!("bench-sim seed={} turns={} actors={} total_us={} {}",
+             BENCH_SEED,
+             sim.turn(),
+             loc.actors_alive_ids().len(),
+             game::timings::micros(total),
+             loc.timings);
+    if !game::Timings::enabled() {
+        let _ = writeln!(io::stderr(),
+                         "built without the `timings` feature; only the total is measured");
+    }
 }
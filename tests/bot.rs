@@ -0,0 +1,101 @@
+//! Playing `rhex --bot` through a pipe, the way another program would
+
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+const SEED: u32 = 17;
+const TURNS: usize = 200;
+
+/// The `rhex` built along with this test
+fn rhex() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    // target/debug/deps/bot-HASH
+    let dir = exe.parent().unwrap().parent().unwrap();
+    dir.join(format!("rhex{}", env::consts::EXE_SUFFIX))
+}
+
+/// Something to answer on the `i`th turn, with a bad line now and then
+fn answer(i: usize) -> &'static str {
+    const ANSWERS: [&'static str; 7] = ["move 0", "move 0", "turn 1", "wait", "pick", "move 5",
+                                        "turn 4"];
+    if i % 50 == 49 {
+        "dance"
+    } else {
+        ANSWERS[i % ANSWERS.len()]
+    }
+}
+
+/// Everything `rhex --bot` writes for `TURNS` turns of the same
+/// answers, and the turns it told of
+fn play() -> (Vec<String>, Vec<u64>) {
+    let mut game = Command::new(rhex())
+                       .args(&["--bot", "--seed", &SEED.to_string()])
+                       .current_dir(env::temp_dir())
+                       .stdin(Stdio::piped())
+                       .stdout(Stdio::piped())
+                       .spawn()
+                       .expect("can't start rhex");
+    let mut to_game = game.stdin.take().unwrap();
+    let from_game = BufReader::new(game.stdout.take().unwrap());
+
+    let mut transcript = vec![];
+    let mut turns = vec![];
+    let mut answered = 0;
+    for line in from_game.lines() {
+        let line = line.unwrap();
+        let first = line.split_whitespace().next().unwrap_or("").to_string();
+        assert!(["rhex", "seed", "turn", "level", "player", "tile", "actor", "item", "inv",
+                 "event", "end", "error", "rejected", "over"]
+                    .contains(&&first[..]),
+                "unexpected line {:?}",
+                line);
+        if first == "turn" {
+            turns.push(line[5..].parse().unwrap());
+        }
+        transcript.push(line.clone());
+
+        if first == "over" {
+            break;
+        }
+        if first != "end" {
+            continue;
+        }
+        let action = if answered < TURNS { answer(answered) } else { "quit" };
+        answered += 1;
+        writeln!(to_game, "{}", action).unwrap();
+    }
+    drop(to_game);
+    assert!(game.wait().unwrap().success());
+    (transcript, turns)
+}
+
+#[test]
+fn bot_plays_200_turns_through_a_pipe() {
+    let (transcript, turns) = play();
+
+    assert_eq!(transcript[0], "rhex bot 1");
+    assert_eq!(transcript[1], format!("seed {}", SEED));
+    let last = transcript.last().unwrap();
+    assert!(last == "over quit" || last.starts_with("over dead "), "{}", last);
+    if last == "over quit" {
+        assert_eq!(turns.len(), TURNS + 1);
+    }
+
+    // time only goes on, and the waits alone make it go
+    assert!(turns.windows(2).all(|w| w[0] <= w[1]), "{:?}", turns);
+    assert!(*turns.last().unwrap() >= (turns.len() / 7) as u64);
+
+    // every observation has the player, and bad lines were answered
+    let observations = transcript.iter().filter(|l| *l == "end").count();
+    let players = transcript.iter().filter(|l| l.starts_with("player ")).count();
+    assert_eq!(observations, players);
+    assert_eq!(transcript.iter().filter(|l| l.starts_with("error ")).count(),
+               (0..observations).filter(|&i| answer(i) == "dance").count());
+}
+
+#[test]
+fn same_seed_and_answers_give_the_same_transcript() {
+    assert_eq!(play().0, play().0);
+}